@@ -9,6 +9,7 @@ use std::sync::{Arc, LazyLock, RwLock};
 use strum_macros::{Display, EnumString};
 use utoipa::{IntoParams, ToSchema};
 
+use crate::utils::edit_utils::FileEdit;
 use crate::utils::file_utils::uri_to_relative_path_string;
 
 static GLOBAL_MOUNT_DIR: LazyLock<Arc<RwLock<PathBuf>>> =
@@ -60,6 +61,39 @@ pub struct HealthResponse {
     pub version: String,
     /// Map of supported languages and whether they are currently available
     pub languages: HashMap<SupportedLanguages, bool>,
+    /// Status of the language server of each started language
+    pub language_servers: HashMap<SupportedLanguages, LanguageServerStatus>,
+    /// Statistics of the workspace document caches, summed over the language servers
+    pub document_cache: DocumentCacheStats,
+}
+
+/// Status of a started language server.
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum LanguageServerStatus {
+    /// The language server is running and done indexing the workspace.
+    Ready,
+    /// The language server is running but still indexing the workspace, so its results may be
+    /// partial.
+    Indexing,
+    /// The language server stopped and is being restarted.
+    Restarting,
+    /// The language server stopped too many times in a row and won't be restarted.
+    Crashed,
+}
+
+/// Statistics of the cache of workspace files and their content
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct DocumentCacheStats {
+    /// Number of workspace files listed
+    pub files: usize,
+    /// Number of files whose content is cached
+    pub cached_documents: usize,
+    /// Number of reads served from the cache
+    pub hits: u64,
+    /// Number of reads that went to disk
+    pub misses: u64,
 }
 
 #[derive(
@@ -220,6 +254,11 @@ pub struct GetDefinitionRequest {
     #[serde(default)]
     #[schema(example = false)]
     pub include_raw_response: bool,
+
+    /// Unsaved contents of workspace files to use for this request only, e.g. to find the
+    /// definition of a symbol in an edited file. The files themselves are left untouched.
+    #[serde(default)]
+    pub overlays: Vec<FileOverlay>,
 }
 
 #[derive(Deserialize, ToSchema, IntoParams)]
@@ -237,6 +276,114 @@ pub struct GetReferencesRequest {
     #[serde(default)]
     #[schema(example = false)]
     pub include_raw_response: bool,
+
+    /// Unsaved contents of workspace files to use for this request only, e.g. to find the
+    /// references to a function after adding calls to it. The files themselves are left
+    /// untouched.
+    #[serde(default)]
+    pub overlays: Vec<FileOverlay>,
+}
+
+/// Request to get hover information (type signature and documentation) for a symbol.
+#[derive(Deserialize, ToSchema, IntoParams)]
+pub struct GetHoverRequest {
+    pub position: FilePosition,
+
+    /// Whether to include the raw response from the langserver in the response.
+    /// Defaults to false.
+    #[serde(default)]
+    #[schema(example = false)]
+    pub include_raw_response: bool,
+
+    /// Unsaved contents of workspace files to use for this request only, e.g. to get the type
+    /// of a variable after changing its assignment. The files themselves are left untouched.
+    #[serde(default)]
+    pub overlays: Vec<FileOverlay>,
+}
+
+/// Request to get the signatures of the function or method called at a position.
+///
+/// The input position can be anywhere inside the call's argument list.
+#[derive(Deserialize, ToSchema, IntoParams)]
+pub struct GetSignatureHelpRequest {
+    pub position: FilePosition,
+
+    /// Whether to include the raw response from the langserver in the response.
+    /// Defaults to false.
+    #[serde(default)]
+    #[schema(example = false)]
+    pub include_raw_response: bool,
+
+    /// Unsaved contents of workspace files to use for this request only, e.g. to get the
+    /// signature of a call that was just typed. The files themselves are left untouched.
+    #[serde(default)]
+    pub overlays: Vec<FileOverlay>,
+}
+
+/// Request to get the completions at a position.
+#[derive(Deserialize, ToSchema, IntoParams)]
+pub struct GetCompletionsRequest {
+    /// The position to complete at, usually right after what's been typed so far, e.g. after
+    /// `user.na` to complete `user.name`.
+    pub position: FilePosition,
+
+    /// The unsaved content of the file to complete in, when it differs from the file in the
+    /// workspace. The file itself is left untouched.
+    #[schema(example = "user = User(\"John\", 30)\nuser.")]
+    pub text: Option<String>,
+
+    /// The maximum number of completions to return. Defaults to 50.
+    #[serde(default = "default_completions_limit")]
+    #[schema(example = 50)]
+    pub limit: usize,
+}
+
+fn default_completions_limit() -> usize {
+    50
+}
+
+/// Request to get the call hierarchy of a function or method.
+///
+/// The input position must point inside the function or method's identifier, either at its
+/// definition or at one of its call sites.
+#[derive(Deserialize, ToSchema, IntoParams)]
+pub struct GetCallHierarchyRequest {
+    pub identifier_position: FilePosition,
+
+    /// How many levels of calls to expand, e.g. 2 also returns the callers of the callers.
+    /// Defaults to 1, capped at 5.
+    #[serde(default = "default_hierarchy_depth")]
+    #[schema(example = 1, minimum = 1, maximum = 5)]
+    pub depth: u32,
+
+    /// Unsaved contents of workspace files to use for this request only, e.g. to find the
+    /// callers of a function after adding calls to it. The files themselves are left untouched.
+    #[serde(default)]
+    pub overlays: Vec<FileOverlay>,
+}
+
+/// Request to get the type hierarchy (supertypes and subtypes) of a class, interface or struct.
+///
+/// The input position must point inside the type's identifier, either at its declaration or at
+/// one of its usages.
+#[derive(Deserialize, ToSchema, IntoParams)]
+pub struct GetTypeHierarchyRequest {
+    pub identifier_position: FilePosition,
+
+    /// How many levels of supertypes and subtypes to expand, e.g. 2 also returns the subtypes of
+    /// the subtypes. Defaults to 1, capped at 5.
+    #[serde(default = "default_hierarchy_depth")]
+    #[schema(example = 1, minimum = 1, maximum = 5)]
+    pub depth: u32,
+
+    /// Unsaved contents of workspace files to use for this request only, e.g. to find the
+    /// subtypes of a class after adding one. The files themselves are left untouched.
+    #[serde(default)]
+    pub overlays: Vec<FileOverlay>,
+}
+
+fn default_hierarchy_depth() -> u32 {
+    1
 }
 
 /// Request to get all symbols that are referenced from a symbol at the given position, either
@@ -257,6 +404,12 @@ pub struct GetReferencedSymbolsRequest {
 
     /// The identifier position of the symbol to find references within
     pub identifier_position: FilePosition,
+
+    /// Unsaved contents of workspace files to use for this request only, e.g. to find the
+    /// symbols referenced from a function after editing it. The files themselves are left
+    /// untouched.
+    #[serde(default)]
+    pub overlays: Vec<FileOverlay>,
 }
 
 /// Request to get the symbols in a file.
@@ -267,19 +420,257 @@ pub struct FileSymbolsRequest {
     pub file_path: String,
 }
 
-/// Request to get the symbols in the workspace.
-#[allow(unused)] // TODO re-implement using textDocument/symbol
+/// Request to rename a symbol across the workspace.
+///
+/// The input position should point inside the symbol's identifier.
+#[derive(Deserialize, ToSchema, IntoParams)]
+pub struct RenameRequest {
+    pub identifier_position: FilePosition,
+
+    /// The new name of the symbol.
+    #[schema(example = "Member")]
+    pub new_name: String,
+
+    /// Whether to write the edits to the workspace files. Defaults to false, which only previews
+    /// the edits.
+    #[serde(default)]
+    #[schema(example = false)]
+    pub apply: bool,
+}
+
+/// Request to list the code actions available for a range of a file.
+#[derive(Deserialize, ToSchema, IntoParams)]
+pub struct CodeActionsRequest {
+    /// The range to get the code actions for. An empty range gets the actions at a position.
+    pub range: FileRange,
+
+    /// Only include actions of these kinds or their sub-kinds, e.g. `refactor` includes
+    /// `refactor.extract`. Defaults to all kinds.
+    #[serde(default)]
+    #[schema(example = json!(["quickfix", "source.organizeImports"]))]
+    pub kinds: Vec<String>,
+}
+
+/// Request to execute one of the code actions available for a range of a file.
+#[derive(Deserialize, ToSchema, IntoParams)]
+pub struct ExecuteCodeActionRequest {
+    /// The range the code action was listed for.
+    pub range: FileRange,
+
+    /// The title of the code action to execute, as listed for the range.
+    #[schema(example = "Organize imports")]
+    pub title: String,
+
+    /// Whether to write the edits to the workspace files. Defaults to false, which only previews
+    /// the edits.
+    #[serde(default)]
+    #[schema(example = false)]
+    pub apply: bool,
+}
+
+/// Request to get the diagnostics of a file.
+#[derive(Deserialize, ToSchema, IntoParams)]
+pub struct FileDiagnosticsRequest {
+    /// The path to the file to get the diagnostics for, relative to the root of the workspace.
+    #[schema(example = "src/main.py")]
+    pub path: String,
+
+    /// Only include diagnostics at least this severe. Defaults to all severities.
+    pub min_severity: Option<DiagnosticSeverity>,
+}
+
+/// Request to get the diagnostics of the workspace.
+#[derive(Deserialize, ToSchema, IntoParams)]
+pub struct WorkspaceDiagnosticsRequest {
+    /// Only include diagnostics at least this severe. Defaults to all severities.
+    pub min_severity: Option<DiagnosticSeverity>,
+}
+
+/// Request to search the symbols in the workspace.
 #[derive(Deserialize, ToSchema, IntoParams)]
 pub struct WorkspaceSymbolsRequest {
-    /// The query to search for.
+    /// The name to search for. Matching is case-insensitive and fuzzy, e.g. `astgr` matches
+    /// `AStarGraph`. An empty query returns every symbol.
     #[schema(example = "User")]
+    #[serde(default)]
     pub query: String,
 
-    /// Whether to include the raw response from the langserver in the response.
-    /// Defaults to false.
+    /// Only include symbols of this kind, e.g. `class` or `function`.
+    #[schema(example = "class")]
+    pub kind: Option<String>,
+}
+
+/// The unsaved content of a workspace file.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FileOverlay {
+    /// The path to the file, relative to the root of the workspace.
+    #[schema(example = "src/main.py")]
+    pub path: String,
+
+    /// The content to use instead of the file on disk.
+    #[schema(example = "class User:\n    pass\n")]
+    pub text: String,
+}
+
+/// Request to open or update the overlays of a session.
+#[derive(Deserialize, ToSchema)]
+pub struct OverlayRequest {
+    /// The session owning the overlays, chosen by the client.
+    #[schema(example = "agent-1")]
+    pub session: String,
+
+    pub files: Vec<FileOverlay>,
+}
+
+/// Request to close the overlays of a session.
+#[derive(Deserialize, ToSchema)]
+pub struct CloseOverlayRequest {
+    #[schema(example = "agent-1")]
+    pub session: String,
+
+    /// The files to close, relative to the root of the workspace. Defaults to all the files the
+    /// session overlays.
+    #[schema(example = json!(["src/main.py"]))]
+    pub paths: Option<Vec<String>>,
+}
+
+/// Response to an overlay request.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct OverlayResponse {
+    pub session: String,
+
+    /// The files the session overlays after the request, or the files it closed for a close
+    /// request.
+    #[schema(example = json!(["src/main.py"]))]
+    pub paths: Vec<String>,
+}
+
+/// Request to search the workspace for code by its structure, with an ast-grep pattern or rule.
+#[derive(Deserialize, ToSchema)]
+pub struct StructuralSearchRequest {
+    /// The code to search for, where metavariables stand in for parts of it: `$NAME` matches a
+    /// single node, `$$$NAME` zero or more nodes and `$_` a node without capturing it.
+    #[schema(example = "$OBJ.execute($$$ARGS)")]
+    pub pattern: Option<String>,
+
+    /// An ast-grep rule config in YAML, for searches a pattern can't express, with a `rule` and
+    /// optionally `constraints` and `utils`. See https://ast-grep.github.io/reference/yaml.html.
+    /// Either this or `pattern` must be given.
+    #[schema(
+        example = "rule:\n  pattern: $OBJ.execute($$$ARGS)\n  inside:\n    kind: function_definition\n    stopBy: end\n"
+    )]
+    pub rule: Option<String>,
+
+    /// The language of the pattern or rule, named like ast-grep names it. Only the files of
+    /// this language are searched.
+    #[schema(example = "python")]
+    pub language: String,
+
+    /// Only search the files matching these globs, relative to the root of the workspace.
+    /// Defaults to all the files of the language.
+    #[schema(example = json!(["src/**/*.py"]))]
+    pub paths: Option<Vec<String>>,
+}
+
+/// Response to a structural search request.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct StructuralSearchResponse {
+    /// The matches, by file and then by position.
+    pub matches: Vec<StructuralMatch>,
+}
+
+/// Code matching a structural search.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct StructuralMatch {
+    pub file_range: FileRange,
+
+    /// The matched code.
+    #[schema(example = "db.execute(query, timeout=5)")]
+    pub text: String,
+
+    /// The code captured by each single metavariable, like `OBJ` for `$OBJ`.
+    pub single: HashMap<String, CapturedCode>,
+
+    /// The code captured by each multiple metavariable, like `ARGS` for `$$$ARGS`, one item per
+    /// node without punctuation like the commas between arguments.
+    pub multi: HashMap<String, Vec<CapturedCode>>,
+}
+
+/// Code captured by a metavariable.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CapturedCode {
+    #[schema(example = "db")]
+    pub text: String,
+    pub range: Range,
+}
+
+/// Request to rewrite the code matching an ast-grep pattern or rule across the workspace.
+#[derive(Deserialize, ToSchema)]
+pub struct StructuralRewriteRequest {
+    /// The code to rewrite, where metavariables stand in for parts of it: `$NAME` matches a
+    /// single node, `$$$NAME` zero or more nodes and `$_` a node without capturing it.
+    #[schema(example = "$OBJ.execute($$$ARGS)")]
+    pub pattern: Option<String>,
+
+    /// An ast-grep rule config in YAML, for code a pattern can't express, with a `rule` and
+    /// optionally `constraints` and `utils`. See https://ast-grep.github.io/reference/yaml.html.
+    /// Either this or `pattern` must be given.
+    #[schema(
+        example = "rule:\n  pattern: $OBJ.execute($$$ARGS)\n  inside:\n    kind: function_definition\n    stopBy: end\n"
+    )]
+    pub rule: Option<String>,
+
+    /// The code replacing each match, like ast-grep's `fix`. Metavariables are replaced with
+    /// the code they captured, or with nothing if they captured nothing. Lines after the first
+    /// are indented like the line of the match.
+    #[schema(example = "$OBJ.run($$$ARGS)")]
+    pub fix: String,
+
+    /// The language of the pattern or rule, named like ast-grep names it. Only the files of
+    /// this language are rewritten.
+    #[schema(example = "python")]
+    pub language: String,
+
+    /// Only rewrite the files matching these globs, relative to the root of the workspace.
+    /// Defaults to all the files of the language.
+    #[schema(example = json!(["src/**/*.py"]))]
+    pub paths: Option<Vec<String>>,
+
+    /// The maximum number of files to rewrite, the first ones by path. Defaults to 100.
+    #[serde(default = "default_rewrite_max_files")]
+    #[schema(example = 100)]
+    pub max_files: usize,
+
+    /// Whether to write the edits to the workspace files. Defaults to false, which only previews
+    /// the edits.
     #[serde(default)]
     #[schema(example = false)]
-    pub include_raw_response: bool,
+    pub apply: bool,
+}
+
+fn default_rewrite_max_files() -> usize {
+    100
+}
+
+/// Response to a structural rewrite request.
+///
+/// Matches inside a match are left as they are, as is the code of files beyond `max_files`.
+/// Repeating the request rewrites them if the fix no longer matches the rule.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, ToSchema)]
+pub struct StructuralRewriteResponse {
+    /// The text edits to each file, sorted by path.
+    pub edits: Vec<FileTextEdits>,
+    /// A unified diff of the edits, with a section per file as produced by `diff -u`.
+    #[schema(
+        example = "--- a/src/main.py\n+++ b/src/main.py\n@@ -1,1 +1,1 @@\n-db.execute(query)\n+db.run(query)\n"
+    )]
+    pub diff: String,
+    /// Whether the edits were written to the workspace.
+    pub applied: bool,
+    /// The number of files the fix changes, including the files beyond `max_files` that were
+    /// left out.
+    #[schema(example = 1)]
+    pub total_files: usize,
 }
 
 /// Response to a definition request.
@@ -348,6 +739,344 @@ pub struct ReferencesResponse {
     pub selected_identifier: Identifier,
 }
 
+/// The format of hover contents returned by the langserver.
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum HoverContentKind {
+    Markdown,
+    Plaintext,
+}
+
+/// Hover contents normalized into a single document.
+///
+/// Langservers may respond with plain strings, language-tagged code blocks or markup content.
+/// Code blocks are rendered as fenced markdown, and multiple sections are joined with blank lines.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, ToSchema)]
+pub struct HoverContent {
+    /// Whether `value` is markdown or plain text.
+    pub kind: HoverContentKind,
+    /// The hover text, usually the type signature followed by the documentation.
+    #[schema(example = "```python\nclass User(name, age)\n```")]
+    pub value: String,
+}
+
+/// Response to a hover request.
+///
+/// e.g. for `User` on line 5 of `src/main.py` with the code:
+/// ```
+/// 0: class User:
+/// 1:     """A registered user of the service."""
+/// ...
+/// 5: user = User("John", 30)
+/// __________^
+/// ```
+/// The contents will contain the signature of `User` and its docstring.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, ToSchema)]
+pub struct HoverResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// The raw response from the langserver.
+    ///
+    /// https://microsoft.github.io/language-server-protocol/specifications/lsp/3.17/specification/#textDocument_hover
+    pub raw_response: Option<Value>,
+    /// The hover contents, or none if the langserver has nothing to show for the position.
+    pub contents: Option<HoverContent>,
+    /// The identifier that was "hovered" to get the contents.
+    pub selected_identifier: Identifier,
+}
+
+/// A parameter of a function or method signature.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ParameterInformation {
+    /// The parameter as written in the signature's label.
+    #[schema(example = "name: str")]
+    pub label: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub documentation: Option<HoverContent>,
+}
+
+/// A signature of the function or method being called, one per overload.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SignatureInformation {
+    /// The signature as the language server renders it.
+    #[schema(example = "User(name: str, age: int)")]
+    pub label: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub documentation: Option<HoverContent>,
+    pub parameters: Vec<ParameterInformation>,
+}
+
+/// Response to a signature help request.
+///
+/// e.g. for the call on line 5 of `src/main.py` with the code:
+/// ```
+/// 0: class User:
+/// 1:     def __init__(self, name: str, age: int):
+/// ...
+/// 5: user = User("John", 30)
+/// input______________^
+/// ```
+/// The signature will be `User(name: str, age: int)` with `age` as the active parameter.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SignatureHelpResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// The raw response from the langserver.
+    ///
+    /// https://microsoft.github.io/language-server-protocol/specifications/lsp/3.17/specification/#textDocument_signatureHelp
+    pub raw_response: Option<Value>,
+    /// The signatures of the function or method being called, empty if the position isn't
+    /// inside a call.
+    pub signatures: Vec<SignatureInformation>,
+    /// The index of the signature matching the call best.
+    pub active_signature: Option<u32>,
+    /// The index of the parameter of the active signature the position is at.
+    pub active_parameter: Option<u32>,
+    /// The identifier at the position, if the position is on one.
+    pub selected_identifier: Option<Identifier>,
+}
+
+/// A completion offered by the language server.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CompletionItem {
+    /// The name shown for the completion.
+    #[schema(example = "name")]
+    pub label: String,
+    /// The kind of the completion (e.g., method, field, keyword).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "field")]
+    pub kind: Option<String>,
+    /// The text to insert for the word being completed.
+    #[schema(example = "name")]
+    pub insert_text: String,
+    /// More about the completion, usually its type or signature.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "str")]
+    pub detail: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub documentation: Option<HoverContent>,
+}
+
+/// Response to a completions request.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CompletionsResponse {
+    /// The completions matching the word being completed, best matches first.
+    pub items: Vec<CompletionItem>,
+    /// Whether there were more completions than the limit, or than the language server listed.
+    pub is_incomplete: bool,
+}
+
+/// A symbol defined in a file, along with the symbols nested in it.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, ToSchema)]
+pub struct DocumentSymbol {
+    pub symbol: Symbol,
+    /// More about the symbol from the language server, e.g. the signature of a function.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    /// The name of the symbol this one is nested in, e.g. the class of a method.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub container_name: Option<String>,
+    /// The symbols nested in this one, e.g. the fields and methods of a class.
+    #[schema(no_recursion)]
+    pub children: Vec<DocumentSymbol>,
+}
+
+/// A function or method in a call hierarchy, along with the calls one level further out.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CallHierarchyNode {
+    pub symbol: Symbol,
+    /// Where the call between this symbol and its parent node happens.
+    /// Empty for the root nodes.
+    pub call_sites: Vec<FileRange>,
+    /// The callers (incoming) or callees (outgoing) of this symbol, if within the requested depth.
+    ///
+    /// Symbols that were already expanded elsewhere in the hierarchy, or that live outside the
+    /// workspace, are not expanded.
+    #[schema(no_recursion)]
+    pub calls: Vec<CallHierarchyNode>,
+}
+
+/// Response to a call hierarchy request.
+///
+/// e.g. for the incoming calls of `distance` on line 1 of `src/graph.py` with the code:
+/// ```
+/// 0: class Graph:
+/// 1:     def distance(self, a, b):
+/// input______^^^^^^^^
+/// ...
+/// 9:     def heuristic(self, a, b):
+/// 10:        return self.distance(a, b)
+/// ```
+/// The root will be `distance`, with a single call from `heuristic` at line 10.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CallHierarchyResponse {
+    /// The function(s) or method(s) at the requested position, usually just one.
+    pub roots: Vec<CallHierarchyNode>,
+    /// The identifier that was used to prepare the call hierarchy.
+    pub selected_identifier: Identifier,
+}
+
+/// A type in a type hierarchy, along with its supertypes and subtypes.
+///
+/// Nodes reached through `supertypes` only list further supertypes, and nodes reached through
+/// `subtypes` only list further subtypes.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TypeHierarchyNode {
+    pub symbol: Symbol,
+    /// The classes or interfaces this type extends or implements.
+    #[schema(no_recursion)]
+    pub supertypes: Vec<TypeHierarchyNode>,
+    /// The classes or interfaces that extend or implement this type.
+    #[schema(no_recursion)]
+    pub subtypes: Vec<TypeHierarchyNode>,
+}
+
+/// Where a type hierarchy was computed.
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TypeHierarchySource {
+    /// The langserver's `textDocument/prepareTypeHierarchy`.
+    Lsp,
+    /// ast-grep heuristics on class declarations, used when the langserver doesn't support type
+    /// hierarchies. Only covers Python, Java, TypeScript/JavaScript and C#.
+    AstGrep,
+}
+
+/// Response to a type hierarchy request.
+///
+/// e.g. for `Animal` on line 0 of `src/zoo.py` with the code:
+/// ```
+/// 0: class Animal(Base):
+/// input____^^^^^^
+/// ...
+/// 9: class Dog(Animal):
+/// ```
+/// The root will be `Animal`, with `Base` as its supertype and `Dog` as its subtype.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TypeHierarchyResponse {
+    /// The type(s) at the requested position, usually just one.
+    pub roots: Vec<TypeHierarchyNode>,
+    pub source: TypeHierarchySource,
+    /// The identifier that was used to prepare the type hierarchy.
+    pub selected_identifier: Identifier,
+}
+
+/// A replacement of the text in a range of a file.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TextEdit {
+    /// The range to replace. Inserts have an empty range.
+    pub range: Range,
+    /// The text to replace the range with. Deletes have an empty text.
+    #[schema(example = "Member")]
+    pub new_text: String,
+}
+
+/// The text edits to a single file.
+///
+/// All ranges refer to the file before any of the edits are made.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FileTextEdits {
+    /// Path to the file, relative to the workspace root
+    #[schema(example = "src/main.py")]
+    pub path: String,
+    pub edits: Vec<TextEdit>,
+}
+
+/// Response to a rename request.
+///
+/// e.g. renaming `User` to `Member` on line 0 of `src/main.py` with the code:
+/// ```
+/// 0: class User:
+/// input____^^^^
+/// ...
+/// 5: user = User("John", 30)
+/// ```
+/// The edits and the diff will contain both line 0 and line 5.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RenameResponse {
+    /// The text edits to each file, sorted by path.
+    pub edits: Vec<FileTextEdits>,
+    /// A unified diff of the edits across all files, as produced by `diff -u`.
+    #[schema(
+        example = "--- a/src/main.py\n+++ b/src/main.py\n@@ -1,1 +1,1 @@\n-class User:\n+class Member:\n"
+    )]
+    pub diff: String,
+    /// Whether the edits were written to the workspace.
+    pub applied: bool,
+    /// The identifier that was renamed.
+    pub selected_identifier: Identifier,
+}
+
+/// A quick fix, refactoring or source action offered by the language server.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CodeAction {
+    /// The title of the action, used to execute it.
+    #[schema(example = "Organize imports")]
+    pub title: String,
+    /// The kind of the action, e.g. `quickfix`, `refactor.extract` or `source.organizeImports`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "source.organizeImports")]
+    pub kind: Option<String>,
+    /// Whether the server recommends this action over the others, e.g. the likeliest quick fix.
+    pub is_preferred: bool,
+    /// Why the action can't be executed, if it can't.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disabled_reason: Option<String>,
+}
+
+/// Response to a code actions request.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CodeActionsResponse {
+    /// The available actions, in the order the language server ranks them.
+    pub actions: Vec<CodeAction>,
+}
+
+/// Response to an execute code action request.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ExecuteCodeActionResponse {
+    /// The text edits to each file, sorted by path.
+    pub edits: Vec<FileTextEdits>,
+    /// A unified diff of the edits across all files, as produced by `diff -u`.
+    pub diff: String,
+    /// Whether the edits were written to the workspace.
+    pub applied: bool,
+    /// The action that was executed.
+    pub action: CodeAction,
+}
+
+/// How severe a diagnostic is, ordered from most to least severe.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum DiagnosticSeverity {
+    Error,
+    Warning,
+    Information,
+    Hint,
+}
+
+/// A problem reported by the langserver, such as a compile error or a lint warning.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Diagnostic {
+    /// Where the problem is.
+    pub range: FileRange,
+    /// Diagnostics without a severity are reported as errors.
+    pub severity: DiagnosticSeverity,
+    /// The langserver specific code of the diagnostic.
+    #[schema(example = "E0308")]
+    pub code: Option<String>,
+    /// The tool that reported the diagnostic.
+    #[schema(example = "rustc")]
+    pub source: Option<String>,
+    #[schema(example = "mismatched types")]
+    pub message: String,
+}
+
+/// Response to a diagnostics request.
+///
+/// Diagnostics are sorted by file and position.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, ToSchema)]
+pub struct DiagnosticsResponse {
+    pub diagnostics: Vec<Diagnostic>,
+}
+
 /// Response containing symbols referenced from the requested position
 ///
 /// The symbols are categorized into:
@@ -387,6 +1116,365 @@ impl From<LocationLink> for FilePosition {
     }
 }
 
+impl From<lsp_types::CallHierarchyItem> for Symbol {
+    fn from(item: lsp_types::CallHierarchyItem) -> Self {
+        lsp_symbol_to_symbol(
+            item.name,
+            item.kind,
+            &item.uri,
+            item.range,
+            item.selection_range,
+        )
+    }
+}
+
+impl From<lsp_types::TypeHierarchyItem> for Symbol {
+    fn from(item: lsp_types::TypeHierarchyItem) -> Self {
+        lsp_symbol_to_symbol(
+            item.name,
+            item.kind,
+            &item.uri,
+            item.range,
+            item.selection_range,
+        )
+    }
+}
+
+/// Builds a symbol from the name, kind and ranges shared by LSP items and symbols.
+pub(crate) fn lsp_symbol_to_symbol(
+    name: String,
+    kind: lsp_types::SymbolKind,
+    uri: &lsp_types::Url,
+    range: lsp_types::Range,
+    selection_range: lsp_types::Range,
+) -> Symbol {
+    let path = uri_to_relative_path_string(uri);
+    Symbol {
+        name,
+        kind: symbol_kind_to_string(kind),
+        identifier_position: FilePosition {
+            path: path.clone(),
+            position: Position::from(selection_range.start),
+        },
+        file_range: FileRange {
+            path,
+            range: Range {
+                start: Position::from(range.start),
+                end: Position::from(range.end),
+            },
+        },
+    }
+}
+
+/// Maps an LSP symbol kind to the lowercase names used for ast-grep symbols, e.g. "function".
+pub(crate) fn symbol_kind_to_string(kind: lsp_types::SymbolKind) -> String {
+    use lsp_types::SymbolKind;
+    match kind {
+        SymbolKind::FILE => "file",
+        SymbolKind::MODULE => "module",
+        SymbolKind::NAMESPACE => "namespace",
+        SymbolKind::PACKAGE => "package",
+        SymbolKind::CLASS => "class",
+        SymbolKind::METHOD => "method",
+        SymbolKind::PROPERTY => "property",
+        SymbolKind::FIELD => "field",
+        SymbolKind::CONSTRUCTOR => "constructor",
+        SymbolKind::ENUM => "enum",
+        SymbolKind::INTERFACE => "interface",
+        SymbolKind::FUNCTION => "function",
+        SymbolKind::VARIABLE => "variable",
+        SymbolKind::CONSTANT => "constant",
+        SymbolKind::STRING => "string",
+        SymbolKind::NUMBER => "number",
+        SymbolKind::BOOLEAN => "boolean",
+        SymbolKind::ARRAY => "array",
+        SymbolKind::OBJECT => "object",
+        SymbolKind::KEY => "key",
+        SymbolKind::NULL => "null",
+        SymbolKind::ENUM_MEMBER => "enum_member",
+        SymbolKind::STRUCT => "struct",
+        SymbolKind::EVENT => "event",
+        SymbolKind::OPERATOR => "operator",
+        SymbolKind::TYPE_PARAMETER => "type_parameter",
+        _ => "unknown",
+    }
+    .to_string()
+}
+
+impl From<lsp_types::MarkedString> for HoverContent {
+    fn from(marked: lsp_types::MarkedString) -> Self {
+        match marked {
+            lsp_types::MarkedString::String(value) => HoverContent {
+                kind: HoverContentKind::Markdown,
+                value,
+            },
+            lsp_types::MarkedString::LanguageString(code) => HoverContent {
+                kind: HoverContentKind::Markdown,
+                value: format!("```{}\n{}\n```", code.language, code.value),
+            },
+        }
+    }
+}
+
+impl From<lsp_types::Documentation> for HoverContent {
+    fn from(documentation: lsp_types::Documentation) -> Self {
+        match documentation {
+            lsp_types::Documentation::String(value) => HoverContent {
+                kind: HoverContentKind::Plaintext,
+                value,
+            },
+            lsp_types::Documentation::MarkupContent(markup) => {
+                lsp_types::HoverContents::Markup(markup).into()
+            }
+        }
+    }
+}
+
+impl From<lsp_types::SignatureInformation> for SignatureInformation {
+    fn from(signature: lsp_types::SignatureInformation) -> Self {
+        let parameters = signature
+            .parameters
+            .unwrap_or_default()
+            .into_iter()
+            .map(|parameter| ParameterInformation {
+                label: match parameter.label {
+                    lsp_types::ParameterLabel::Simple(label) => label,
+                    // Offsets into the signature label, in UTF-16 code units
+                    lsp_types::ParameterLabel::LabelOffsets([start, end]) => {
+                        let units: Vec<u16> = signature.label.encode_utf16().collect();
+                        let end = (end as usize).min(units.len());
+                        let start = (start as usize).min(end);
+                        String::from_utf16_lossy(&units[start..end])
+                    }
+                },
+                documentation: parameter.documentation.map(HoverContent::from),
+            })
+            .collect();
+        SignatureInformation {
+            label: signature.label,
+            documentation: signature.documentation.map(HoverContent::from),
+            parameters,
+        }
+    }
+}
+
+impl From<lsp_types::CompletionItem> for CompletionItem {
+    fn from(item: lsp_types::CompletionItem) -> Self {
+        let insert_text = match item.text_edit {
+            Some(lsp_types::CompletionTextEdit::Edit(edit)) => edit.new_text,
+            Some(lsp_types::CompletionTextEdit::InsertAndReplace(edit)) => edit.new_text,
+            None => item.insert_text.unwrap_or_else(|| item.label.clone()),
+        };
+        CompletionItem {
+            label: item.label,
+            kind: item.kind.map(completion_item_kind_to_string),
+            insert_text,
+            detail: item.detail,
+            documentation: item.documentation.map(HoverContent::from),
+        }
+    }
+}
+
+/// Maps an LSP completion kind to a lowercase name, e.g. "method".
+fn completion_item_kind_to_string(kind: lsp_types::CompletionItemKind) -> String {
+    use lsp_types::CompletionItemKind;
+    match kind {
+        CompletionItemKind::TEXT => "text",
+        CompletionItemKind::METHOD => "method",
+        CompletionItemKind::FUNCTION => "function",
+        CompletionItemKind::CONSTRUCTOR => "constructor",
+        CompletionItemKind::FIELD => "field",
+        CompletionItemKind::VARIABLE => "variable",
+        CompletionItemKind::CLASS => "class",
+        CompletionItemKind::INTERFACE => "interface",
+        CompletionItemKind::MODULE => "module",
+        CompletionItemKind::PROPERTY => "property",
+        CompletionItemKind::UNIT => "unit",
+        CompletionItemKind::VALUE => "value",
+        CompletionItemKind::ENUM => "enum",
+        CompletionItemKind::KEYWORD => "keyword",
+        CompletionItemKind::SNIPPET => "snippet",
+        CompletionItemKind::COLOR => "color",
+        CompletionItemKind::FILE => "file",
+        CompletionItemKind::REFERENCE => "reference",
+        CompletionItemKind::FOLDER => "folder",
+        CompletionItemKind::ENUM_MEMBER => "enum_member",
+        CompletionItemKind::CONSTANT => "constant",
+        CompletionItemKind::STRUCT => "struct",
+        CompletionItemKind::EVENT => "event",
+        CompletionItemKind::OPERATOR => "operator",
+        CompletionItemKind::TYPE_PARAMETER => "type_parameter",
+        _ => "unknown",
+    }
+    .to_string()
+}
+
+impl From<lsp_types::HoverContents> for HoverContent {
+    fn from(contents: lsp_types::HoverContents) -> Self {
+        match contents {
+            lsp_types::HoverContents::Scalar(marked) => marked.into(),
+            lsp_types::HoverContents::Array(sections) => HoverContent {
+                kind: HoverContentKind::Markdown,
+                value: sections
+                    .into_iter()
+                    .map(|section| HoverContent::from(section).value)
+                    .filter(|value| !value.trim().is_empty())
+                    .collect::<Vec<String>>()
+                    .join("\n\n"),
+            },
+            lsp_types::HoverContents::Markup(markup) => HoverContent {
+                kind: match markup.kind {
+                    lsp_types::MarkupKind::Markdown => HoverContentKind::Markdown,
+                    lsp_types::MarkupKind::PlainText => HoverContentKind::Plaintext,
+                },
+                value: markup.value,
+            },
+        }
+    }
+}
+
+impl From<lsp_types::TextEdit> for TextEdit {
+    fn from(edit: lsp_types::TextEdit) -> Self {
+        TextEdit {
+            range: Range {
+                start: Position::from(edit.range.start),
+                end: Position::from(edit.range.end),
+            },
+            new_text: edit.new_text,
+        }
+    }
+}
+
+impl From<FileEdit> for FileTextEdits {
+    fn from(file_edit: FileEdit) -> Self {
+        FileTextEdits {
+            path: file_edit.path,
+            edits: file_edit.edits.into_iter().map(TextEdit::from).collect(),
+        }
+    }
+}
+
+impl From<lsp_types::CodeActionOrCommand> for CodeAction {
+    fn from(action: lsp_types::CodeActionOrCommand) -> Self {
+        match action {
+            lsp_types::CodeActionOrCommand::Command(command) => CodeAction {
+                title: command.title,
+                kind: None,
+                is_preferred: false,
+                disabled_reason: None,
+            },
+            lsp_types::CodeActionOrCommand::CodeAction(action) => CodeAction {
+                title: action.title,
+                kind: action.kind.map(|kind| kind.as_str().to_string()),
+                is_preferred: action.is_preferred.unwrap_or(false),
+                disabled_reason: action.disabled.map(|disabled| disabled.reason),
+            },
+        }
+    }
+}
+
+impl From<lsp_types::DiagnosticSeverity> for DiagnosticSeverity {
+    fn from(severity: lsp_types::DiagnosticSeverity) -> Self {
+        match severity {
+            lsp_types::DiagnosticSeverity::WARNING => DiagnosticSeverity::Warning,
+            lsp_types::DiagnosticSeverity::INFORMATION => DiagnosticSeverity::Information,
+            lsp_types::DiagnosticSeverity::HINT => DiagnosticSeverity::Hint,
+            _ => DiagnosticSeverity::Error,
+        }
+    }
+}
+
+impl DocumentSymbol {
+    /// Converts a hierarchical document symbol from the language server, along with its
+    /// children.
+    pub fn from_lsp(
+        uri: &lsp_types::Url,
+        symbol: lsp_types::DocumentSymbol,
+        container_name: Option<String>,
+    ) -> Self {
+        let children = symbol
+            .children
+            .unwrap_or_default()
+            .into_iter()
+            .map(|child| DocumentSymbol::from_lsp(uri, child, Some(symbol.name.clone())))
+            .collect();
+        DocumentSymbol {
+            symbol: lsp_symbol_to_symbol(
+                symbol.name,
+                symbol.kind,
+                uri,
+                symbol.range,
+                symbol.selection_range,
+            ),
+            detail: symbol.detail,
+            container_name,
+            children,
+        }
+    }
+
+    /// Nests flat symbols into a tree, making each symbol a child of the smallest symbol whose
+    /// range contains it.
+    pub fn nest(mut symbols: Vec<DocumentSymbol>) -> Vec<DocumentSymbol> {
+        let start = |symbol: &DocumentSymbol| {
+            let start = &symbol.symbol.file_range.range.start;
+            (start.line, start.character)
+        };
+        let end = |symbol: &DocumentSymbol| {
+            let end = &symbol.symbol.file_range.range.end;
+            (end.line, end.character)
+        };
+        // Parents come before the symbols they contain
+        symbols.sort_by(|a, b| start(a).cmp(&start(b)).then(end(b).cmp(&end(a))));
+
+        let mut roots = Vec::new();
+        let mut parents: Vec<DocumentSymbol> = Vec::new();
+        let close = |parents: &mut Vec<DocumentSymbol>, roots: &mut Vec<DocumentSymbol>| {
+            let symbol = parents.pop().unwrap();
+            match parents.last_mut() {
+                Some(parent) => parent.children.push(symbol),
+                None => roots.push(symbol),
+            }
+        };
+        for symbol in symbols {
+            while parents
+                .last()
+                .is_some_and(|parent| end(&symbol) > end(parent))
+            {
+                close(&mut parents, &mut roots);
+            }
+            parents.push(symbol);
+        }
+        while !parents.is_empty() {
+            close(&mut parents, &mut roots);
+        }
+        roots
+    }
+}
+
+impl Diagnostic {
+    pub fn from_lsp(path: &str, diagnostic: lsp_types::Diagnostic) -> Self {
+        Diagnostic {
+            range: FileRange {
+                path: path.to_string(),
+                range: Range {
+                    start: Position::from(diagnostic.range.start),
+                    end: Position::from(diagnostic.range.end),
+                },
+            },
+            severity: diagnostic
+                .severity
+                .map(DiagnosticSeverity::from)
+                .unwrap_or(DiagnosticSeverity::Error),
+            code: diagnostic.code.map(|code| match code {
+                lsp_types::NumberOrString::Number(number) => number.to_string(),
+                lsp_types::NumberOrString::String(string) => string,
+            }),
+            source: diagnostic.source,
+            message: diagnostic.message,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct FindIdentifierRequest {
@@ -629,4 +1717,213 @@ mod tests {
             "position after zero-width range should not be contained"
         );
     }
+
+    #[test]
+    fn test_hover_content_from_marked_strings() {
+        let contents = lsp_types::HoverContents::Array(vec![
+            lsp_types::MarkedString::LanguageString(lsp_types::LanguageString {
+                language: "python".to_string(),
+                value: "def plot_path(path)".to_string(),
+            }),
+            lsp_types::MarkedString::String("".to_string()),
+            lsp_types::MarkedString::String("Plots the path.".to_string()),
+        ]);
+
+        assert_eq!(
+            HoverContent::from(contents),
+            HoverContent {
+                kind: HoverContentKind::Markdown,
+                value: "```python\ndef plot_path(path)\n```\n\nPlots the path.".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_hover_content_from_markup() {
+        let contents = lsp_types::HoverContents::Markup(lsp_types::MarkupContent {
+            kind: lsp_types::MarkupKind::PlainText,
+            value: "fn main()".to_string(),
+        });
+
+        assert_eq!(
+            HoverContent::from(contents),
+            HoverContent {
+                kind: HoverContentKind::Plaintext,
+                value: "fn main()".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_symbol_from_call_hierarchy_item() {
+        set_thread_local_mount_dir("/mnt/workspace");
+        let item = lsp_types::CallHierarchyItem {
+            name: "distance".to_string(),
+            kind: lsp_types::SymbolKind::METHOD,
+            tags: None,
+            detail: None,
+            uri: lsp_types::Url::parse("file:///mnt/workspace/src/graph.py").unwrap(),
+            range: lsp_types::Range::new(
+                lsp_types::Position::new(1, 4),
+                lsp_types::Position::new(3, 20),
+            ),
+            selection_range: lsp_types::Range::new(
+                lsp_types::Position::new(1, 8),
+                lsp_types::Position::new(1, 16),
+            ),
+            data: None,
+        };
+
+        let symbol = Symbol::from(item);
+        unset_thread_local_mount_dir();
+
+        assert_eq!(
+            symbol,
+            Symbol {
+                name: "distance".to_string(),
+                kind: "method".to_string(),
+                identifier_position: FilePosition {
+                    path: "src/graph.py".to_string(),
+                    position: Position {
+                        line: 1,
+                        character: 8,
+                    },
+                },
+                file_range: FileRange {
+                    path: "src/graph.py".to_string(),
+                    range: Range {
+                        start: Position {
+                            line: 1,
+                            character: 4,
+                        },
+                        end: Position {
+                            line: 3,
+                            character: 20,
+                        },
+                    },
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn test_diagnostic_from_lsp() {
+        let diagnostic = Diagnostic::from_lsp(
+            "src/main.rs",
+            lsp_types::Diagnostic {
+                range: lsp_types::Range::new(
+                    lsp_types::Position::new(4, 8),
+                    lsp_types::Position::new(4, 12),
+                ),
+                severity: Some(lsp_types::DiagnosticSeverity::WARNING),
+                code: Some(lsp_types::NumberOrString::String(
+                    "unused_variables".to_string(),
+                )),
+                source: Some("rustc".to_string()),
+                message: "unused variable: `path`".to_string(),
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(diagnostic.range.path, "src/main.rs");
+        assert_eq!(
+            diagnostic.range.range.start,
+            Position {
+                line: 4,
+                character: 8
+            }
+        );
+        assert_eq!(diagnostic.severity, DiagnosticSeverity::Warning);
+        assert_eq!(diagnostic.code.as_deref(), Some("unused_variables"));
+        assert_eq!(diagnostic.source.as_deref(), Some("rustc"));
+
+        // Missing severities are treated as errors, which sort before warnings
+        let diagnostic = Diagnostic::from_lsp("src/main.rs", lsp_types::Diagnostic::default());
+        assert_eq!(diagnostic.severity, DiagnosticSeverity::Error);
+        assert!(DiagnosticSeverity::Error < DiagnosticSeverity::Warning);
+    }
+
+    #[test]
+    fn test_signature_information_from_lsp() {
+        let signature = SignatureInformation::from(lsp_types::SignatureInformation {
+            label: "move_cost(a: Tuple[int, int], b: Tuple[int, int])".to_string(),
+            documentation: Some(lsp_types::Documentation::String(
+                "The cost of moving from a to b".to_string(),
+            )),
+            parameters: Some(vec![
+                lsp_types::ParameterInformation {
+                    label: lsp_types::ParameterLabel::LabelOffsets([10, 28]),
+                    documentation: None,
+                },
+                lsp_types::ParameterInformation {
+                    label: lsp_types::ParameterLabel::Simple("b: Tuple[int, int]".to_string()),
+                    documentation: None,
+                },
+            ]),
+            active_parameter: None,
+        });
+
+        let labels: Vec<&str> = signature
+            .parameters
+            .iter()
+            .map(|parameter| parameter.label.as_str())
+            .collect();
+        assert_eq!(labels, vec!["a: Tuple[int, int]", "b: Tuple[int, int]"]);
+        assert_eq!(
+            signature.documentation,
+            Some(HoverContent {
+                kind: HoverContentKind::Plaintext,
+                value: "The cost of moving from a to b".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_document_symbol_nest() {
+        let symbol = |name: &str, start: u32, end: u32| DocumentSymbol {
+            symbol: Symbol {
+                name: name.to_string(),
+                kind: "class".to_string(),
+                identifier_position: FilePosition {
+                    path: "graph.py".to_string(),
+                    position: Position {
+                        line: start,
+                        character: 0,
+                    },
+                },
+                file_range: FileRange {
+                    path: "graph.py".to_string(),
+                    range: Range {
+                        start: Position {
+                            line: start,
+                            character: 0,
+                        },
+                        end: Position {
+                            line: end,
+                            character: 0,
+                        },
+                    },
+                },
+            },
+            detail: None,
+            container_name: None,
+            children: Vec::new(),
+        };
+
+        let roots = DocumentSymbol::nest(vec![
+            symbol("heuristic", 6, 8),
+            symbol("Graph", 0, 10),
+            symbol("__init__", 1, 5),
+            symbol("barriers", 2, 3),
+            symbol("main", 12, 14),
+        ]);
+
+        let names = |symbols: &[DocumentSymbol]| -> Vec<String> {
+            symbols.iter().map(|s| s.symbol.name.clone()).collect()
+        };
+        assert_eq!(names(&roots), vec!["Graph", "main"]);
+        assert_eq!(names(&roots[0].children), vec!["__init__", "heuristic"]);
+        assert_eq!(names(&roots[0].children[0].children), vec!["barriers"]);
+        assert!(roots[1].children.is_empty());
+    }
 }
@@ -1,22 +1,51 @@
+use std::collections::HashMap;
 use std::io::{Error, ErrorKind};
-use tokio::process::Command;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tokio::sync::RwLock;
 
-const SYMBOL_CONFIG_PATH: &str = "/usr/src/ast_grep/symbol/config.yml";
-const IDENTIFIER_CONFIG_PATH: &str = "/usr/src/ast_grep/identifier/config.yml";
-const REFERENCE_CONFIG_PATH: &str = "/usr/src/ast_grep/reference/config.yml";
+use crate::utils::overlays::Overlays;
 
-use super::types::AstGrepMatch;
+const SYMBOL_RULES: &str = "symbol";
+const IDENTIFIER_RULES: &str = "identifier";
+const REFERENCE_RULES: &str = "reference";
+const TYPE_HIERARCHY_RULES: &str = "type_hierarchy";
 
-pub struct AstGrepClient;
+/// How many files to scan on a blocking thread at once, so other tasks don't wait for the
+/// whole workspace to be scanned.
+const SCAN_BATCH_SIZE: usize = 500;
+
+use super::fix::Fix;
+use super::language::AstGrepLanguage;
+use super::rule::RuleConfig;
+use super::scan::{rewrite, search, ParsedFile, RuleSet, TreeCache};
+use super::types::{AstGrepMatch, FileRewrite, SearchMatch};
+
+/// Runs the ast-grep rules over workspace files, scanning the text of overlaid files in place
+/// of their contents on disk.
+#[derive(Default)]
+pub struct AstGrepClient {
+    overlays: Arc<RwLock<Overlays>>,
+    /// The rule sets by name, loaded when first used.
+    rule_sets: Mutex<HashMap<&'static str, Arc<RuleSet>>>,
+    trees: Arc<TreeCache>,
+}
 
 impl AstGrepClient {
+    pub fn new(overlays: Arc<RwLock<Overlays>>) -> Self {
+        Self {
+            overlays,
+            ..Default::default()
+        }
+    }
+
     pub async fn get_symbol_match_from_position(
         &self,
         file_name: &str,
         identifier_position: &lsp_types::Position,
     ) -> Result<AstGrepMatch, Box<dyn std::error::Error>> {
         // Get all symbols in the file
-        let file_symbols = self.scan_file(SYMBOL_CONFIG_PATH, file_name).await?;
+        let file_symbols = self.scan_file(SYMBOL_RULES, file_name).await?;
 
         // Find the symbol that matches our identifier position
         let symbol_result = file_symbols.into_iter().find(|ast_symbol_match| {
@@ -43,14 +72,39 @@ impl AstGrepClient {
         &self,
         file_name: &str,
     ) -> Result<Vec<AstGrepMatch>, Box<dyn std::error::Error>> {
-        self.scan_file(SYMBOL_CONFIG_PATH, file_name).await
+        self.scan_file(SYMBOL_RULES, file_name).await
+    }
+
+    /// Returns the symbols of many files, scanning them in batches.
+    pub async fn get_symbols_in_files(
+        &self,
+        file_names: &[String],
+    ) -> Result<Vec<AstGrepMatch>, Box<dyn std::error::Error>> {
+        let mut symbols = Vec::new();
+        for batch in file_names.chunks(SCAN_BATCH_SIZE) {
+            symbols.extend(self.scan_files(SYMBOL_RULES, batch).await?);
+        }
+        Ok(symbols)
     }
 
     pub async fn get_file_identifiers(
         &self,
         file_name: &str,
     ) -> Result<Vec<AstGrepMatch>, Box<dyn std::error::Error>> {
-        self.scan_file(IDENTIFIER_CONFIG_PATH, file_name).await
+        self.scan_file(IDENTIFIER_RULES, file_name).await
+    }
+
+    /// Returns one match per supertype listed in a class or interface declaration of the files,
+    /// with the declared type as `NAME` and the supertype as `SUPERTYPE`.
+    pub async fn get_type_relations(
+        &self,
+        file_names: &[String],
+    ) -> Result<Vec<AstGrepMatch>, Box<dyn std::error::Error>> {
+        let mut relations = Vec::new();
+        for batch in file_names.chunks(SCAN_BATCH_SIZE) {
+            relations.extend(self.scan_files(TYPE_HIERARCHY_RULES, batch).await?);
+        }
+        Ok(relations)
     }
 
     pub async fn get_symbol_and_references(
@@ -75,7 +129,7 @@ impl AstGrepClient {
         full_scan: bool,
     ) -> Result<Vec<AstGrepMatch>, Box<dyn std::error::Error>> {
         // Get all references
-        let matches = self.scan_file(REFERENCE_CONFIG_PATH, file_name).await?;
+        let matches = self.scan_file(REFERENCE_RULES, file_name).await?;
 
         // Filter matches to those within the symbol's range
         // And if not full_scan, exclude matches with rule_id "non-function"
@@ -97,30 +151,149 @@ impl AstGrepClient {
 
     async fn scan_file(
         &self,
-        config_path: &str,
+        rules: &'static str,
         file_name: &str,
     ) -> Result<Vec<AstGrepMatch>, Box<dyn std::error::Error>> {
-        let command_result = Command::new("ast-grep")
-            .arg("scan")
-            .arg("--config")
-            .arg(config_path)
-            .arg("--json")
-            .arg(file_name)
-            .output()
+        self.scan_files(rules, &[file_name]).await
+    }
+
+    async fn scan_files(
+        &self,
+        rules: &'static str,
+        file_names: &[impl AsRef<str>],
+    ) -> Result<Vec<AstGrepMatch>, Box<dyn std::error::Error>> {
+        let rule_set = self.rule_set(rules)?;
+        let language_rule_set = Arc::clone(&rule_set);
+        let mut symbols = self
+            .parse_and_scan(
+                file_names,
+                move |path| language_rule_set.language(path),
+                move |file_name, parsed| rule_set.scan(file_name, parsed),
+            )
             .await?;
+        symbols.sort_by_key(|s| s.get_identifier_range().start.line);
+        Ok(symbols)
+    }
+
+    /// Returns the matches of a rule in the files of its language, with every metavariable it
+    /// captured, scanning the files in batches.
+    pub async fn search(
+        &self,
+        rule: RuleConfig,
+        file_names: &[String],
+    ) -> Result<Vec<SearchMatch>, Box<dyn std::error::Error>> {
+        let rule = Arc::new(rule);
+        let mut matches = Vec::new();
+        for batch in file_names.chunks(SCAN_BATCH_SIZE) {
+            let language = rule.language;
+            let rule = Arc::clone(&rule);
+            matches.extend(
+                self.parse_and_scan(
+                    batch,
+                    move |path| AstGrepLanguage::from_path(path).filter(|l| *l == language),
+                    move |file_name, parsed| search(&rule, file_name, parsed),
+                )
+                .await?,
+            );
+        }
+        Ok(matches)
+    }
 
-        if !command_result.status.success() {
-            let error = String::from_utf8_lossy(&command_result.stderr);
-            return Err(format!("sg command failed: {}", error).into());
+    /// Returns the edits replacing the matches of a rule with a fix in the files of its
+    /// language, for the files it changes, scanning the files in batches.
+    pub async fn rewrite(
+        &self,
+        rule: RuleConfig,
+        fix: Fix,
+        file_names: &[String],
+    ) -> Result<Vec<FileRewrite>, Box<dyn std::error::Error>> {
+        let rule = Arc::new(rule);
+        let fix = Arc::new(fix);
+        let mut rewrites = Vec::new();
+        for batch in file_names.chunks(SCAN_BATCH_SIZE) {
+            let language = rule.language;
+            let rule = Arc::clone(&rule);
+            let fix = Arc::clone(&fix);
+            rewrites.extend(
+                self.parse_and_scan(
+                    batch,
+                    move |path| AstGrepLanguage::from_path(path).filter(|l| *l == language),
+                    move |file_name, parsed| {
+                        rewrite(&rule, &fix, file_name, parsed)
+                            .into_iter()
+                            .collect()
+                    },
+                )
+                .await?,
+            );
         }
+        Ok(rewrites)
+    }
+
+    /// Parses the files that have a language, or the text of their overlays, on a blocking
+    /// thread and scans them.
+    async fn parse_and_scan<T: Send + 'static>(
+        &self,
+        file_names: &[impl AsRef<str>],
+        language: impl Fn(&Path) -> Option<AstGrepLanguage> + Send + 'static,
+        scan: impl Fn(&str, &ParsedFile) -> Vec<T> + Send + 'static,
+    ) -> Result<Vec<T>, Box<dyn std::error::Error>> {
+        if file_names.is_empty() {
+            return Ok(Vec::new());
+        }
+        let overlays = self.overlays.read().await;
+        let files: Vec<(String, Option<String>)> = file_names
+            .iter()
+            .map(|file_name| {
+                let file_name = file_name.as_ref();
+                let overlay = overlays.text(Path::new(file_name)).map(str::to_string);
+                (file_name.to_string(), overlay)
+            })
+            .collect();
+        drop(overlays);
 
-        let output = String::from_utf8(command_result.stdout)?;
+        let trees = Arc::clone(&self.trees);
+        let matches = tokio::task::spawn_blocking(move || {
+            let mut matches = Vec::new();
+            for (file_name, overlay) in files {
+                let Some(language) = language(Path::new(&file_name)) else {
+                    continue;
+                };
+                let source = match overlay {
+                    Some(text) => text,
+                    None => std::fs::read_to_string(&file_name)
+                        .map_err(|e| format!("Failed to read {}: {}", file_name, e))?,
+                };
+                let parsed = trees.parse(language, source)?;
+                matches.extend(scan(&file_name, &parsed));
+            }
+            Ok::<_, String>(matches)
+        })
+        .await??;
+        Ok(matches)
+    }
 
-        let mut symbols: Vec<AstGrepMatch> =
-            serde_json::from_str(&output).map_err(|e| format!("Failed to parse JSON: {}", e))?;
-        symbols = symbols.into_iter().collect();
-        symbols.sort_by_key(|s| s.get_identifier_range().start.line);
-        Ok(symbols)
+    /// Loads every rule set, so rules that don't compile are found before they are used.
+    pub fn load_rule_sets(&self) -> Result<(), String> {
+        for rules in [
+            SYMBOL_RULES,
+            IDENTIFIER_RULES,
+            REFERENCE_RULES,
+            TYPE_HIERARCHY_RULES,
+        ] {
+            self.rule_set(rules)?;
+        }
+        Ok(())
+    }
+
+    fn rule_set(&self, rules: &'static str) -> Result<Arc<RuleSet>, String> {
+        let mut rule_sets = self.rule_sets.lock().unwrap();
+        if let Some(rule_set) = rule_sets.get(rules) {
+            return Ok(Arc::clone(rule_set));
+        }
+        let rule_set = Arc::new(RuleSet::load(rules)?);
+        rule_sets.insert(rules, Arc::clone(&rule_set));
+        Ok(rule_set)
     }
 }
 
@@ -130,7 +303,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_references() -> Result<(), Box<dyn std::error::Error>> {
-        let client = AstGrepClient {};
+        let client = AstGrepClient::default();
 
         let path = "/mnt/lsproxy_root/sample_project/python/graph.py";
         let position = lsp_types::Position {
@@ -212,9 +385,55 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_php_attribute_references() -> Result<(), Box<dyn std::error::Error>> {
+        let client = AstGrepClient::default();
+
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("routes.php");
+        std::fs::write(
+            &path,
+            "<?php\n\n\
+             #[Route(\"/users\"), Deprecated]\n\
+             function listUsers(): array {\n\
+             \x20   return array_values(loadUsers());\n\
+             }\n",
+        )?;
+
+        let (_, references) = client
+            .get_symbol_and_references(
+                &path.to_string_lossy(),
+                &lsp_types::Position {
+                    line: 3,
+                    character: 9,
+                },
+                false,
+            )
+            .await?;
+        let names_and_kinds: Vec<(&str, &str)> = references
+            .iter()
+            .map(|reference| {
+                (
+                    reference.meta_variables.single.name.text.as_str(),
+                    reference.rule_id.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            names_and_kinds,
+            vec![
+                ("Route", "attribute-usage"),
+                ("Deprecated", "attribute-usage"),
+                ("array_values", "function-call"),
+                ("loadUsers", "function-call"),
+            ]
+        );
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_contained_references() -> Result<(), Box<dyn std::error::Error>> {
-        let client = AstGrepClient {};
+        let client = AstGrepClient::default();
 
         let path = "/mnt/lsproxy_root/sample_project/python/main.py";
         let position = lsp_types::Position {
@@ -0,0 +1,122 @@
+use tree_sitter::Node;
+
+use super::rule::{span_text, MetaVarEnv};
+
+/// The code replacing the matches of a rule, like ast-grep's `fix`.
+///
+/// Metavariables are replaced with the code they captured: `$NAME` and `$$NAME` with the
+/// captured node and `$$$NAME` with everything from the first to the last captured node.
+/// Metavariables that captured nothing are replaced with nothing. Lines after the first are
+/// indented like the line of the match, so a multi-line fix is written as if it was at the
+/// top level.
+pub struct Fix {
+    parts: Vec<FixPart>,
+}
+
+enum FixPart {
+    Text(String),
+    Single(String),
+    Multiple(String),
+}
+
+impl Fix {
+    pub fn new(template: &str) -> Self {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut rest = template;
+        while let Some(dollar) = rest.find('$') {
+            text.push_str(&rest[..dollar]);
+            let after = &rest[dollar..];
+            let dollars = after.chars().take_while(|c| *c == '$').count().min(3);
+            let name_len = after[dollars..]
+                .find(|c: char| !(c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_'))
+                .unwrap_or(after.len() - dollars);
+            let name = &after[dollars..dollars + name_len];
+            if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+                text.push('$');
+                rest = &after[1..];
+                continue;
+            }
+            parts.push(FixPart::Text(std::mem::take(&mut text)));
+            parts.push(match dollars {
+                3 => FixPart::Multiple(name.to_string()),
+                _ => FixPart::Single(name.to_string()),
+            });
+            rest = &after[dollars + name_len..];
+        }
+        text.push_str(rest);
+        parts.push(FixPart::Text(text));
+        Self { parts }
+    }
+
+    /// The code replacing a matched node, given what the rule captured.
+    pub fn replacement(&self, node: Node, env: &MetaVarEnv, source: &str) -> String {
+        let line_start = source[..node.start_byte()].rfind('\n').map_or(0, |i| i + 1);
+        let indent: String = source[line_start..node.start_byte()]
+            .chars()
+            .take_while(|c| c.is_whitespace())
+            .collect();
+        let mut replacement = String::new();
+        for part in &self.parts {
+            match part {
+                FixPart::Text(text) => {
+                    replacement.push_str(&text.replace('\n', &format!("\n{}", indent)))
+                }
+                FixPart::Single(name) => {
+                    if let Some(node) = env.get_single(name) {
+                        replacement.push_str(&source[node.byte_range()]);
+                    }
+                }
+                FixPart::Multiple(name) => {
+                    if let Some(nodes) = env.get_multiple(name) {
+                        replacement.push_str(span_text(nodes, source.as_bytes()));
+                    }
+                }
+            }
+        }
+        replacement
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast_grep::language::AstGrepLanguage;
+    use crate::ast_grep::rule::SerializableRuleConfig;
+    use tree_sitter::Parser;
+
+    #[test]
+    fn test_fix_replacement() -> Result<(), String> {
+        let rule = SerializableRuleConfig::for_search(
+            Some("$LOG.warn($$$ARGS)"),
+            None,
+            AstGrepLanguage::Python,
+        )
+        .and_then(SerializableRuleConfig::compile)?;
+        let source = "def f():\n    log.warn(\"a\", b)\n";
+        let mut parser = Parser::new();
+        parser
+            .set_language(&AstGrepLanguage::Python.tree_sitter_language())
+            .unwrap();
+        let tree = parser.parse(source, None).unwrap();
+        let call = tree
+            .root_node()
+            .descendant_for_byte_range(13, 13)
+            .and_then(|node| node.parent())
+            .and_then(|node| node.parent())
+            .ok_or("no call")?;
+        let env = rule.match_node(call, source.as_bytes()).ok_or("no match")?;
+
+        let fix = Fix::new("$LOG.warning($$$ARGS, $MISSING$1)");
+        assert_eq!(
+            fix.replacement(call, &env, source),
+            "log.warning(\"a\", b, $1)"
+        );
+        let fix = Fix::new("if $LOG:\n    $LOG.warning($$$ARGS)");
+        assert_eq!(
+            fix.replacement(call, &env, source),
+            "if log:\n        log.warning(\"a\", b)"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,108 @@
+use std::path::Path;
+
+use serde::Deserialize;
+use strum_macros::{Display, EnumString};
+
+/// A language the ast-grep rules can be written in, named like ast-grep names it.
+#[derive(Debug, EnumString, Display, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+#[serde(try_from = "String")]
+pub enum AstGrepLanguage {
+    #[strum(serialize = "cpp", serialize = "c++", serialize = "cc")]
+    Cpp,
+    #[strum(serialize = "csharp", serialize = "c#", serialize = "cs")]
+    CSharp,
+    #[strum(serialize = "go", serialize = "golang")]
+    Go,
+    Java,
+    #[strum(serialize = "javascript", serialize = "js", serialize = "jsx")]
+    JavaScript,
+    Php,
+    #[strum(serialize = "python", serialize = "py")]
+    Python,
+    #[strum(serialize = "ruby", serialize = "rb")]
+    Ruby,
+    #[strum(serialize = "rust", serialize = "rs")]
+    Rust,
+    Tsx,
+    #[strum(serialize = "typescript", serialize = "ts")]
+    TypeScript,
+}
+
+impl TryFrom<String> for AstGrepLanguage {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        name.parse()
+            .map_err(|_| format!("unsupported language '{}'", name))
+    }
+}
+
+impl AstGrepLanguage {
+    pub fn tree_sitter_language(&self) -> tree_sitter::Language {
+        match self {
+            AstGrepLanguage::Cpp => tree_sitter_cpp::LANGUAGE.into(),
+            AstGrepLanguage::CSharp => tree_sitter_c_sharp::LANGUAGE.into(),
+            AstGrepLanguage::Go => tree_sitter_go::LANGUAGE.into(),
+            AstGrepLanguage::Java => tree_sitter_java::LANGUAGE.into(),
+            AstGrepLanguage::JavaScript => tree_sitter_javascript::LANGUAGE.into(),
+            AstGrepLanguage::Php => tree_sitter_php::LANGUAGE_PHP.into(),
+            AstGrepLanguage::Python => tree_sitter_python::LANGUAGE.into(),
+            AstGrepLanguage::Ruby => tree_sitter_ruby::LANGUAGE.into(),
+            AstGrepLanguage::Rust => tree_sitter_rust::LANGUAGE.into(),
+            AstGrepLanguage::Tsx => tree_sitter_typescript::LANGUAGE_TSX.into(),
+            AstGrepLanguage::TypeScript => tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
+        }
+    }
+
+    /// The character standing in for `$` in the metavariables of patterns, for languages where
+    /// `$` can't start an identifier. Like ast-grep, `µ` is used since it can.
+    pub fn expando_char(&self) -> char {
+        match self {
+            AstGrepLanguage::Java
+            | AstGrepLanguage::JavaScript
+            | AstGrepLanguage::Tsx
+            | AstGrepLanguage::TypeScript => '$',
+            _ => 'µ',
+        }
+    }
+
+    /// The file extensions ast-grep detects the language from by default.
+    fn extensions(&self) -> &'static [&'static str] {
+        match self {
+            AstGrepLanguage::Cpp => &["cc", "hpp", "cpp", "c++", "hh", "cxx", "cu", "ino"],
+            AstGrepLanguage::CSharp => &["cs"],
+            AstGrepLanguage::Go => &["go"],
+            AstGrepLanguage::Java => &["java"],
+            AstGrepLanguage::JavaScript => &["cjs", "js", "mjs", "jsx"],
+            AstGrepLanguage::Php => &["php"],
+            AstGrepLanguage::Python => &["py", "py3", "pyi", "bzl"],
+            AstGrepLanguage::Ruby => &["rb", "rbw", "gemspec"],
+            AstGrepLanguage::Rust => &["rs"],
+            AstGrepLanguage::Tsx => &["tsx"],
+            AstGrepLanguage::TypeScript => &["ts", "cts", "mts"],
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?;
+        ALL_LANGUAGES
+            .iter()
+            .find(|language| language.extensions().contains(&extension))
+            .copied()
+    }
+}
+
+const ALL_LANGUAGES: [AstGrepLanguage; 11] = [
+    AstGrepLanguage::Cpp,
+    AstGrepLanguage::CSharp,
+    AstGrepLanguage::Go,
+    AstGrepLanguage::Java,
+    AstGrepLanguage::JavaScript,
+    AstGrepLanguage::Php,
+    AstGrepLanguage::Python,
+    AstGrepLanguage::Ruby,
+    AstGrepLanguage::Rust,
+    AstGrepLanguage::Tsx,
+    AstGrepLanguage::TypeScript,
+];
@@ -1,2 +1,7 @@
 pub(crate) mod client;
+pub(crate) mod fix;
+pub(crate) mod language;
+pub(crate) mod pattern;
+pub(crate) mod rule;
+pub(crate) mod scan;
 pub(crate) mod types;
@@ -0,0 +1,341 @@
+use tree_sitter::{Node, Parser};
+
+use super::language::AstGrepLanguage;
+use super::rule::MetaVarEnv;
+
+/// A code snippet matched against the syntax tree, like ast-grep's patterns.
+///
+/// Metavariables stand in for parts of the code: `$NAME` matches a single named node, `$$NAME`
+/// any single node, and `$$$NAME` zero or more nodes. Metavariables starting with `_` match
+/// without capturing, so `$_A` twice can match different nodes. Nodes the pattern doesn't have,
+/// such as punctuation and comments, are skipped in the code matched.
+#[derive(Debug)]
+pub struct Pattern {
+    root: PatternNode,
+}
+
+#[derive(Debug)]
+enum PatternNode {
+    MetaVar(MetaVar),
+    Terminal {
+        kind_id: u16,
+        text: String,
+    },
+    Internal {
+        kind_id: u16,
+        children: Vec<PatternNode>,
+    },
+}
+
+#[derive(Debug)]
+enum MetaVar {
+    /// Matches a single node, capturing it under the name unless it is None.
+    Single {
+        name: Option<String>,
+        named_only: bool,
+    },
+    /// Matches zero or more nodes, capturing them under the name unless it is None.
+    Multiple { name: Option<String> },
+}
+
+impl Pattern {
+    pub fn new(source: &str, language: AstGrepLanguage) -> Result<Self, String> {
+        // A lone metavariable isn't valid code in every language, e.g. at the top level of C++
+        if let Some(meta_var) = parse_meta_var(source.trim(), '$') {
+            return Ok(Self {
+                root: PatternNode::MetaVar(meta_var),
+            });
+        }
+        let (tree, expanded) = parse_pattern(source, language)?;
+        let root = single_node(tree.root_node());
+        Ok(Self {
+            root: convert_node(root, expanded.as_bytes(), language.expando_char()),
+        })
+    }
+
+    /// A pattern for the part of `context` that is the first node of the `selector` kind, for
+    /// code that doesn't parse on its own, like a class field without its class.
+    pub fn contextual(
+        context: &str,
+        selector: &str,
+        language: AstGrepLanguage,
+    ) -> Result<Self, String> {
+        let (tree, expanded) = parse_pattern(context, language)?;
+        let root = find_kind(tree.root_node(), selector)
+            .ok_or_else(|| format!("pattern context has no '{}' node", selector))?;
+        Ok(Self {
+            root: convert_node(root, expanded.as_bytes(), language.expando_char()),
+        })
+    }
+
+    pub fn match_node<'t>(&self, node: Node<'t>, source: &[u8], env: &mut MetaVarEnv<'t>) -> bool {
+        match_pattern(&self.root, node, source, env)
+    }
+}
+
+/// Parses a pattern, returning its tree along with the source it was parsed from.
+fn parse_pattern(
+    source: &str,
+    language: AstGrepLanguage,
+) -> Result<(tree_sitter::Tree, String), String> {
+    let mut parser = Parser::new();
+    parser
+        .set_language(&language.tree_sitter_language())
+        .map_err(|e| e.to_string())?;
+    let expanded = expand_metavariables(source, language);
+    let tree = parser
+        .parse(&expanded, None)
+        .ok_or("failed to parse pattern")?;
+    if contains_error(tree.root_node()) {
+        return Err(format!(
+            "pattern '{}' is not valid {} code",
+            source, language
+        ));
+    }
+    Ok((tree, expanded))
+}
+
+/// Replaces the `$` of metavariables with the language's expando character so the pattern
+/// parses.
+fn expand_metavariables(source: &str, language: AstGrepLanguage) -> String {
+    let expando = language.expando_char();
+    if expando == '$' {
+        return source.to_string();
+    }
+    let mut expanded = String::with_capacity(source.len());
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        let starts_metavariable = chars
+            .peek()
+            .is_some_and(|next| *next == '$' || *next == '_' || next.is_ascii_uppercase());
+        if c == '$' && starts_metavariable {
+            expanded.push(expando);
+        } else if c == '$' && expanded.ends_with(expando) {
+            // The last `$` of `$$` or `$$$` before a name
+            expanded.push(expando);
+        } else {
+            expanded.push(c);
+        }
+    }
+    expanded
+}
+
+fn contains_error(node: Node) -> bool {
+    if node.is_error() {
+        return true;
+    }
+    let mut cursor = node.walk();
+    let has_error_child = node
+        .children(&mut cursor)
+        .any(|child| contains_error(child));
+    has_error_child
+}
+
+/// Skips the wrappers of a pattern like the module and expression statement around an
+/// expression, down to the first node with more than one child.
+fn single_node(mut node: Node) -> Node {
+    loop {
+        let mut cursor = node.walk();
+        let children: Vec<Node> = node
+            .children(&mut cursor)
+            .filter(|child| {
+                !child.is_missing() && !child.is_extra() && !child.byte_range().is_empty()
+            })
+            .collect();
+        match children.as_slice() {
+            [child] => node = *child,
+            _ => return node,
+        }
+    }
+}
+
+fn find_kind<'t>(node: Node<'t>, kind: &str) -> Option<Node<'t>> {
+    if node.is_named() && node.kind() == kind {
+        return Some(node);
+    }
+    let mut cursor = node.walk();
+    let children: Vec<Node> = node.children(&mut cursor).collect();
+    children
+        .into_iter()
+        .find_map(|child| find_kind(child, kind))
+}
+
+fn convert_node(node: Node, source: &[u8], expando: char) -> PatternNode {
+    let text = node.utf8_text(source).unwrap_or_default();
+    if let Some(meta_var) = parse_meta_var(text, expando) {
+        return PatternNode::MetaVar(meta_var);
+    }
+    let mut cursor = node.walk();
+    let children: Vec<PatternNode> = node
+        .children(&mut cursor)
+        .filter(|child| !child.is_missing() && !child.is_extra())
+        .map(|child| convert_node(child, source, expando))
+        .collect();
+    if children.is_empty() {
+        PatternNode::Terminal {
+            kind_id: node.kind_id(),
+            text: text.to_string(),
+        }
+    } else {
+        PatternNode::Internal {
+            kind_id: node.kind_id(),
+            children,
+        }
+    }
+}
+
+/// Parses a metavariable, `$` followed by uppercase letters, digits and underscores, after its
+/// `$` were replaced with the expando character.
+fn parse_meta_var(text: &str, expando: char) -> Option<MetaVar> {
+    let (dollars, name) = if let Some(name) = text.strip_prefix(&expando.to_string().repeat(3)) {
+        (3, name)
+    } else if let Some(name) = text.strip_prefix(&expando.to_string().repeat(2)) {
+        (2, name)
+    } else {
+        (1, text.strip_prefix(expando)?)
+    };
+    let valid_name = name
+        .chars()
+        .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+        && !name.starts_with(|c: char| c.is_ascii_digit());
+    if !valid_name || (name.is_empty() && dollars != 3) {
+        return None;
+    }
+    let name = (!name.is_empty() && !name.starts_with('_')).then(|| name.to_string());
+    Some(match dollars {
+        3 => MetaVar::Multiple { name },
+        2 => MetaVar::Single {
+            name,
+            named_only: false,
+        },
+        _ => MetaVar::Single {
+            name,
+            named_only: true,
+        },
+    })
+}
+
+fn match_pattern<'t>(
+    pattern: &PatternNode,
+    node: Node<'t>,
+    source: &[u8],
+    env: &mut MetaVarEnv<'t>,
+) -> bool {
+    match pattern {
+        PatternNode::MetaVar(MetaVar::Single { name, named_only }) => {
+            if *named_only && !node.is_named() {
+                return false;
+            }
+            match name {
+                Some(name) => env.bind_single(name, node, source),
+                None => true,
+            }
+        }
+        PatternNode::MetaVar(MetaVar::Multiple { name }) => match name {
+            Some(name) => env.bind_multiple(name, vec![node], source),
+            None => true,
+        },
+        PatternNode::Terminal { kind_id, text } => {
+            node.kind_id() == *kind_id && node.utf8_text(source).ok() == Some(text.as_str())
+        }
+        PatternNode::Internal { kind_id, children } => {
+            if node.kind_id() != *kind_id {
+                return false;
+            }
+            let mut cursor = node.walk();
+            let candidates: Vec<Node<'t>> = node
+                .children(&mut cursor)
+                .filter(|child| !child.is_extra())
+                .collect();
+            match_children(children, &candidates, source, env)
+        }
+    }
+}
+
+/// Matches a sequence of pattern nodes against a sequence of nodes, skipping unnamed nodes the
+/// pattern doesn't have and trying the shortest sequence for multiple metavariables first.
+fn match_children<'t>(
+    patterns: &[PatternNode],
+    nodes: &[Node<'t>],
+    source: &[u8],
+    env: &mut MetaVarEnv<'t>,
+) -> bool {
+    let Some((pattern, rest)) = patterns.split_first() else {
+        return nodes.iter().all(|node| !node.is_named());
+    };
+    if let PatternNode::MetaVar(MetaVar::Multiple { name }) = pattern {
+        for end in 0..=nodes.len() {
+            let mut attempt = env.clone();
+            let bound = match name {
+                Some(name) => attempt.bind_multiple(name, nodes[..end].to_vec(), source),
+                None => true,
+            };
+            if bound && match_children(rest, &nodes[end..], source, &mut attempt) {
+                *env = attempt;
+                return true;
+            }
+        }
+        return false;
+    }
+    let Some((node, remaining)) = nodes.split_first() else {
+        return false;
+    };
+    let mut attempt = env.clone();
+    if match_pattern(pattern, *node, source, &mut attempt)
+        && match_children(rest, remaining, source, &mut attempt)
+    {
+        *env = attempt;
+        return true;
+    }
+    !node.is_named() && match_children(patterns, remaining, source, env)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The text of the nodes the pattern matches with the text captured as `A`.
+    fn matches(pattern: &str, code: &str, language: AstGrepLanguage) -> Vec<(String, String)> {
+        let pattern = Pattern::new(pattern, language).unwrap();
+        let mut parser = Parser::new();
+        parser
+            .set_language(&language.tree_sitter_language())
+            .unwrap();
+        let tree = parser.parse(code, None).unwrap();
+        let mut found = Vec::new();
+        let mut stack = vec![tree.root_node()];
+        while let Some(node) = stack.pop() {
+            let mut env = MetaVarEnv::default();
+            if pattern.match_node(node, code.as_bytes(), &mut env) {
+                found.push((
+                    node.utf8_text(code.as_bytes()).unwrap().to_string(),
+                    env.get_single("A")
+                        .map(|node| node.utf8_text(code.as_bytes()).unwrap().to_string())
+                        .unwrap_or_default(),
+                ));
+            }
+            let mut cursor = node.walk();
+            let mut children: Vec<Node> = node.children(&mut cursor).collect();
+            children.reverse();
+            stack.extend(children);
+        }
+        found
+    }
+
+    #[test]
+    fn test_pattern_matching() {
+        let code = "db.execute(query, timeout=5)\ndb.execute()\ncache.get(key)\n";
+        assert_eq!(
+            matches("$A.execute($$$ARGS)", code, AstGrepLanguage::Python),
+            vec![
+                ("db.execute(query, timeout=5)".to_string(), "db".to_string()),
+                ("db.execute()".to_string(), "db".to_string()),
+            ]
+        );
+        assert_eq!(
+            matches("$A.$B($A)", "a.f(a); a.f(b);", AstGrepLanguage::JavaScript),
+            vec![("a.f(a)".to_string(), "a".to_string())]
+        );
+        assert!(Pattern::new("fn (", AstGrepLanguage::Rust).is_err());
+    }
+}
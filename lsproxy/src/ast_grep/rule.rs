@@ -0,0 +1,838 @@
+use std::collections::{HashMap, HashSet};
+
+use regex::Regex;
+use serde::de::{Error as _, IgnoredAny};
+use serde::{Deserialize, Deserializer};
+use tree_sitter::Node;
+
+use super::language::AstGrepLanguage;
+use super::pattern::Pattern;
+
+/// The id of rules given to searches without one.
+const SEARCH_RULE_ID: &str = "structural-search";
+
+/// The metavariables captured while matching a rule, by name.
+#[derive(Clone, Default)]
+pub struct MetaVarEnv<'t> {
+    single: HashMap<String, Node<'t>>,
+    multiple: HashMap<String, Vec<Node<'t>>>,
+}
+
+impl<'t> MetaVarEnv<'t> {
+    /// Captures a node, or checks it has the same text as the node already captured under the
+    /// name.
+    pub fn bind_single(&mut self, name: &str, node: Node<'t>, source: &[u8]) -> bool {
+        match self.single.get(name) {
+            Some(bound) => bound.utf8_text(source).ok() == node.utf8_text(source).ok(),
+            None => {
+                self.single.insert(name.to_string(), node);
+                true
+            }
+        }
+    }
+
+    /// Captures nodes, or checks they have the same text as the nodes already captured under
+    /// the name.
+    pub fn bind_multiple(&mut self, name: &str, nodes: Vec<Node<'t>>, source: &[u8]) -> bool {
+        match self.multiple.get(name) {
+            Some(bound) => span_text(bound, source) == span_text(&nodes, source),
+            None => {
+                self.multiple.insert(name.to_string(), nodes);
+                true
+            }
+        }
+    }
+
+    pub fn get_single(&self, name: &str) -> Option<Node<'t>> {
+        self.single.get(name).copied()
+    }
+
+    pub fn get_multiple(&self, name: &str) -> Option<&[Node<'t>]> {
+        self.multiple.get(name).map(Vec::as_slice)
+    }
+
+    pub fn singles(&self) -> impl Iterator<Item = (&str, Node<'t>)> {
+        self.single
+            .iter()
+            .map(|(name, node)| (name.as_str(), *node))
+    }
+
+    pub fn multiples(&self) -> impl Iterator<Item = (&str, &[Node<'t>])> {
+        self.multiple
+            .iter()
+            .map(|(name, nodes)| (name.as_str(), nodes.as_slice()))
+    }
+}
+
+/// The text from the start of the first node to the end of the last, empty if there are none.
+pub fn span_text<'s>(nodes: &[Node], source: &'s [u8]) -> &'s str {
+    match (nodes.first(), nodes.last()) {
+        (Some(first), Some(last)) => {
+            std::str::from_utf8(&source[first.start_byte()..last.end_byte()]).unwrap_or_default()
+        }
+        _ => "",
+    }
+}
+
+/// A rule as written in YAML, see <https://ast-grep.github.io/reference/rule.html>. Every key
+/// of a rule must match, and they are matched in the order of this struct. Unknown keys are
+/// rejected like ast-grep does, so a misspelled key can't silently widen a rule.
+#[derive(Clone, Deserialize, Default)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SerializableRule {
+    pattern: Option<SerializablePattern>,
+    kind: Option<String>,
+    regex: Option<String>,
+    nth_child: Option<SerializableNthChild>,
+    inside: Option<Box<SerializableRelation>>,
+    has: Option<Box<SerializableRelation>>,
+    precedes: Option<Box<SerializableRelation>>,
+    follows: Option<Box<SerializableRelation>>,
+    all: Option<Vec<SerializableRule>>,
+    any: Option<Vec<SerializableRule>>,
+    not: Option<Box<SerializableRule>>,
+    matches: Option<String>,
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(untagged)]
+enum SerializablePattern {
+    Code(String),
+    Contextual(SerializableContextualPattern),
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SerializableContextualPattern {
+    context: String,
+    selector: String,
+}
+
+/// A relational rule: a rule with the `stopBy` and `field` keys of `inside`, `has`,
+/// `precedes` and `follows`.
+#[derive(Clone)]
+struct SerializableRelation {
+    rule: SerializableRule,
+    stop_by: Option<SerializableStopBy>,
+    field: Option<String>,
+}
+
+impl<'de> Deserialize<'de> for SerializableRelation {
+    // Not derived with a flattened rule, since flattening ignores `deny_unknown_fields`.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        fn take<T: serde::de::DeserializeOwned, E: serde::de::Error>(
+            mapping: &mut serde_yaml::Mapping,
+            key: &str,
+        ) -> Result<Option<T>, E> {
+            mapping
+                .remove(key)
+                .map(serde_yaml::from_value)
+                .transpose()
+                .map_err(E::custom)
+        }
+
+        let mut mapping = serde_yaml::Mapping::deserialize(deserializer)?;
+        let stop_by = take(&mut mapping, "stopBy")?;
+        let field = take(&mut mapping, "field")?;
+        let rule = serde_yaml::from_value(mapping.into()).map_err(D::Error::custom)?;
+        Ok(Self {
+            rule,
+            stop_by,
+            field,
+        })
+    }
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(untagged)]
+enum SerializableStopBy {
+    /// `neighbor` or `end`.
+    Keyword(String),
+    Rule(Box<SerializableRule>),
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(untagged)]
+enum SerializableNthChild {
+    Position(SerializableNthPosition),
+    Object(SerializableNthChildObject),
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct SerializableNthChildObject {
+    position: SerializableNthPosition,
+    #[serde(default)]
+    reverse: bool,
+    of_rule: Option<Box<SerializableRule>>,
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(untagged)]
+enum SerializableNthPosition {
+    Index(i64),
+    /// An `An+B` formula, like `2n+1`.
+    Formula(String),
+}
+
+pub enum Rule {
+    Pattern(Pattern),
+    Kind(String),
+    Regex(Regex),
+    NthChild(NthChild),
+    Inside(Box<Relation>),
+    Has(Box<Relation>),
+    Precedes(Box<Relation>),
+    Follows(Box<Relation>),
+    All(Vec<Rule>),
+    Any(Vec<Rule>),
+    Not(Box<Rule>),
+    Matches(String),
+}
+
+pub struct Relation {
+    rule: Rule,
+    stop_by: StopBy,
+    field: Option<String>,
+}
+
+enum StopBy {
+    /// Only the closest node is tried: the parent, a direct child or the adjacent sibling.
+    Neighbor,
+    /// Every node is tried up to the root, down to the leaves or to the last sibling.
+    End,
+    /// Nodes are tried up to and including the first matching the rule.
+    Rule(Rule),
+}
+
+pub struct NthChild {
+    /// The position as `An+B`, counting from 1.
+    step: i64,
+    offset: i64,
+    reverse: bool,
+    of_rule: Option<Box<Rule>>,
+}
+
+/// What compiling a rule needs besides the rule itself.
+struct CompileContext<'a> {
+    language: AstGrepLanguage,
+    utils: &'a HashSet<String>,
+}
+
+impl SerializableRule {
+    fn compile(self, context: &CompileContext) -> Result<Rule, String> {
+        let mut rules = Vec::new();
+        if let Some(pattern) = self.pattern {
+            rules.push(Rule::Pattern(match pattern {
+                SerializablePattern::Code(code) => Pattern::new(&code, context.language)?,
+                SerializablePattern::Contextual(pattern) => {
+                    Pattern::contextual(&pattern.context, &pattern.selector, context.language)?
+                }
+            }));
+        }
+        if let Some(kind) = self.kind {
+            if context
+                .language
+                .tree_sitter_language()
+                .id_for_node_kind(&kind, true)
+                == 0
+            {
+                return Err(format!("unknown {} kind '{}'", context.language, kind));
+            }
+            rules.push(Rule::Kind(kind));
+        }
+        if let Some(regex) = self.regex {
+            rules.push(Rule::Regex(Regex::new(&regex).map_err(|e| e.to_string())?));
+        }
+        if let Some(nth_child) = self.nth_child {
+            rules.push(Rule::NthChild(nth_child.compile(context)?));
+        }
+        if let Some(inside) = self.inside {
+            rules.push(Rule::Inside(Box::new(inside.compile(context)?)));
+        }
+        if let Some(has) = self.has {
+            rules.push(Rule::Has(Box::new(has.compile(context)?)));
+        }
+        if let Some(precedes) = self.precedes {
+            rules.push(Rule::Precedes(Box::new(precedes.compile(context)?)));
+        }
+        if let Some(follows) = self.follows {
+            rules.push(Rule::Follows(Box::new(follows.compile(context)?)));
+        }
+        if let Some(all) = self.all {
+            rules.push(Rule::All(compile_rules(all, context)?));
+        }
+        if let Some(any) = self.any {
+            rules.push(Rule::Any(compile_rules(any, context)?));
+        }
+        if let Some(not) = self.not {
+            rules.push(Rule::Not(Box::new(not.compile(context)?)));
+        }
+        if let Some(matches) = self.matches {
+            if !context.utils.contains(&matches) {
+                return Err(format!("unknown utility rule '{}'", matches));
+            }
+            rules.push(Rule::Matches(matches));
+        }
+        match rules.len() {
+            0 => Err("rule must have at least one key".to_string()),
+            1 => Ok(rules.remove(0)),
+            _ => Ok(Rule::All(rules)),
+        }
+    }
+}
+
+fn compile_rules(
+    rules: Vec<SerializableRule>,
+    context: &CompileContext,
+) -> Result<Vec<Rule>, String> {
+    rules
+        .into_iter()
+        .map(|rule| rule.compile(context))
+        .collect()
+}
+
+impl SerializableRelation {
+    fn compile(self, context: &CompileContext) -> Result<Relation, String> {
+        let stop_by = match self.stop_by {
+            None => StopBy::Neighbor,
+            Some(SerializableStopBy::Keyword(keyword)) => match keyword.as_str() {
+                "neighbor" => StopBy::Neighbor,
+                "end" => StopBy::End,
+                _ => return Err(format!("unknown stopBy '{}'", keyword)),
+            },
+            Some(SerializableStopBy::Rule(rule)) => StopBy::Rule(rule.compile(context)?),
+        };
+        Ok(Relation {
+            rule: self.rule.compile(context)?,
+            stop_by,
+            field: self.field,
+        })
+    }
+}
+
+impl SerializableNthChild {
+    fn compile(self, context: &CompileContext) -> Result<NthChild, String> {
+        let object = match self {
+            SerializableNthChild::Position(position) => SerializableNthChildObject {
+                position,
+                reverse: false,
+                of_rule: None,
+            },
+            SerializableNthChild::Object(object) => object,
+        };
+        let (step, offset) = match object.position {
+            SerializableNthPosition::Index(index) => (0, index),
+            SerializableNthPosition::Formula(formula) => parse_nth_formula(&formula)
+                .ok_or_else(|| format!("invalid nthChild position '{}'", formula))?,
+        };
+        Ok(NthChild {
+            step,
+            offset,
+            reverse: object.reverse,
+            of_rule: object
+                .of_rule
+                .map(|rule| rule.compile(context).map(Box::new))
+                .transpose()?,
+        })
+    }
+}
+
+/// Parses `An+B` into `(A, B)`, where A or B may be left out.
+fn parse_nth_formula(formula: &str) -> Option<(i64, i64)> {
+    let formula: String = formula.chars().filter(|c| !c.is_whitespace()).collect();
+    let Some((step, offset)) = formula.split_once('n') else {
+        return formula.parse().ok().map(|offset| (0, offset));
+    };
+    let step = match step {
+        "" | "+" => 1,
+        "-" => -1,
+        _ => step.parse().ok()?,
+    };
+    let offset = match offset {
+        "" => 0,
+        _ => offset.parse().ok()?,
+    };
+    Some((step, offset))
+}
+
+/// What matching a rule needs besides the node.
+pub struct MatchContext<'a> {
+    pub source: &'a [u8],
+    pub utils: &'a HashMap<String, Rule>,
+}
+
+impl Rule {
+    /// Matches the rule at the node, capturing metavariables into `env`. `env` is left in an
+    /// unspecified state when the rule doesn't match.
+    pub fn match_node<'t>(
+        &self,
+        node: Node<'t>,
+        env: &mut MetaVarEnv<'t>,
+        context: &MatchContext,
+    ) -> bool {
+        match self {
+            Rule::Pattern(pattern) => pattern.match_node(node, context.source, env),
+            Rule::Kind(kind) => node.is_named() && node.kind() == kind,
+            Rule::Regex(regex) => node
+                .utf8_text(context.source)
+                .is_ok_and(|text| regex.is_match(text)),
+            Rule::NthChild(nth_child) => nth_child.match_node(node, context),
+            Rule::Inside(relation) => relation.match_inside(node, env, context),
+            Rule::Has(relation) => relation.match_has(node, env, context),
+            Rule::Precedes(relation) => {
+                relation.match_siblings(node, |node| node.next_sibling(), env, context)
+            }
+            Rule::Follows(relation) => {
+                relation.match_siblings(node, |node| node.prev_sibling(), env, context)
+            }
+            Rule::All(rules) => rules.iter().all(|rule| rule.match_node(node, env, context)),
+            Rule::Any(rules) => rules.iter().any(|rule| try_match(rule, node, env, context)),
+            Rule::Not(rule) => !rule.match_node(node, &mut env.clone(), context),
+            Rule::Matches(name) => context
+                .utils
+                .get(name)
+                .is_some_and(|rule| rule.match_node(node, env, context)),
+        }
+    }
+}
+
+/// Matches the rule, keeping the metavariables it captured only if it matches.
+fn try_match<'t>(
+    rule: &Rule,
+    node: Node<'t>,
+    env: &mut MetaVarEnv<'t>,
+    context: &MatchContext,
+) -> bool {
+    let mut attempt = env.clone();
+    if rule.match_node(node, &mut attempt, context) {
+        *env = attempt;
+        true
+    } else {
+        false
+    }
+}
+
+impl StopBy {
+    /// Whether no node past this one should be tried.
+    fn stops_at(&self, node: Node, context: &MatchContext) -> bool {
+        match self {
+            StopBy::Neighbor => true,
+            StopBy::End => false,
+            StopBy::Rule(rule) => rule.match_node(node, &mut MetaVarEnv::default(), context),
+        }
+    }
+}
+
+impl Relation {
+    fn match_inside<'t>(
+        &self,
+        node: Node<'t>,
+        env: &mut MetaVarEnv<'t>,
+        context: &MatchContext,
+    ) -> bool {
+        let mut child = node;
+        while let Some(parent) = child.parent() {
+            let in_field = self
+                .field
+                .as_ref()
+                .is_none_or(|field| is_field_child(parent, child, field));
+            if in_field && try_match(&self.rule, parent, env, context) {
+                return true;
+            }
+            if self.stop_by.stops_at(parent, context) {
+                break;
+            }
+            child = parent;
+        }
+        false
+    }
+
+    fn match_has<'t>(
+        &self,
+        node: Node<'t>,
+        env: &mut MetaVarEnv<'t>,
+        context: &MatchContext,
+    ) -> bool {
+        let mut cursor = node.walk();
+        let children: Vec<Node<'t>> = match &self.field {
+            Some(field) => node.children_by_field_name(field, &mut cursor).collect(),
+            None => node.children(&mut cursor).collect(),
+        };
+        children
+            .into_iter()
+            .any(|child| self.match_descendant(child, env, context))
+    }
+
+    /// Matches the rule at the node, then below it unless the node stops the search.
+    fn match_descendant<'t>(
+        &self,
+        node: Node<'t>,
+        env: &mut MetaVarEnv<'t>,
+        context: &MatchContext,
+    ) -> bool {
+        if try_match(&self.rule, node, env, context) {
+            return true;
+        }
+        if self.stop_by.stops_at(node, context) {
+            return false;
+        }
+        let mut cursor = node.walk();
+        let children: Vec<Node<'t>> = node.children(&mut cursor).collect();
+        children
+            .into_iter()
+            .any(|child| self.match_descendant(child, env, context))
+    }
+
+    fn match_siblings<'t>(
+        &self,
+        node: Node<'t>,
+        next: impl Fn(Node<'t>) -> Option<Node<'t>>,
+        env: &mut MetaVarEnv<'t>,
+        context: &MatchContext,
+    ) -> bool {
+        let mut sibling = next(node);
+        while let Some(current) = sibling {
+            if try_match(&self.rule, current, env, context) {
+                return true;
+            }
+            if self.stop_by.stops_at(current, context) {
+                break;
+            }
+            sibling = next(current);
+        }
+        false
+    }
+}
+
+fn is_field_child(parent: Node, child: Node, field: &str) -> bool {
+    let mut cursor = parent.walk();
+    let in_field = parent
+        .children_by_field_name(field, &mut cursor)
+        .any(|field_child| field_child.id() == child.id());
+    in_field
+}
+
+impl NthChild {
+    /// Matches the node if it is at the position among the named children of its parent,
+    /// counting only those matching `of_rule` if there is one.
+    fn match_node(&self, node: Node, context: &MatchContext) -> bool {
+        let Some(parent) = node.parent() else {
+            return false;
+        };
+        let mut cursor = parent.walk();
+        let siblings: Vec<Node> = parent
+            .named_children(&mut cursor)
+            .filter(|sibling| {
+                self.of_rule.as_ref().is_none_or(|rule| {
+                    rule.match_node(*sibling, &mut MetaVarEnv::default(), context)
+                })
+            })
+            .collect();
+        let Some(index) = siblings
+            .iter()
+            .position(|sibling| sibling.id() == node.id())
+        else {
+            return false;
+        };
+        let position = if self.reverse {
+            siblings.len() - index
+        } else {
+            index + 1
+        } as i64;
+        match self.step {
+            0 => position == self.offset,
+            step => (position - self.offset) % step == 0 && (position - self.offset) / step >= 0,
+        }
+    }
+}
+
+/// A rule file: a rule with its id, language and the rules it can refer to.
+///
+/// Keys changing what or how a rule matches that aren't supported, like `transform`, `fix`,
+/// `rewriters` or `files`, are rejected rather than ignored. The keys only describing the rule
+/// to ast-grep's linter are accepted and ignored.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SerializableRuleConfig {
+    pub id: String,
+    pub language: AstGrepLanguage,
+    pub rule: SerializableRule,
+    /// Rules the rule can refer to with `matches`, by name.
+    #[serde(default)]
+    pub utils: HashMap<String, SerializableRule>,
+    /// Rules the captured metavariables must match, by metavariable name.
+    #[serde(default)]
+    pub constraints: HashMap<String, SerializableRule>,
+    #[serde(default, rename = "message")]
+    _message: IgnoredAny,
+    #[serde(default, rename = "note")]
+    _note: IgnoredAny,
+    #[serde(default, rename = "severity")]
+    _severity: IgnoredAny,
+    #[serde(default, rename = "url")]
+    _url: IgnoredAny,
+    #[serde(default, rename = "metadata")]
+    _metadata: IgnoredAny,
+}
+
+/// A utility rule file, which every rule of its language can refer to with `matches` unless
+/// it has a utility rule of the same name, like the files of ast-grep's `utilDirs`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SerializableUtilRule {
+    pub id: String,
+    pub language: AstGrepLanguage,
+    pub rule: SerializableRule,
+}
+
+pub struct RuleConfig {
+    pub id: String,
+    pub language: AstGrepLanguage,
+    rule: Rule,
+    utils: HashMap<String, Rule>,
+    constraints: HashMap<String, Rule>,
+}
+
+impl RuleConfig {
+    /// The metavariables captured by the rule if it matches the node.
+    pub fn match_node<'t>(&self, node: Node<'t>, source: &[u8]) -> Option<MetaVarEnv<'t>> {
+        let context = MatchContext {
+            source,
+            utils: &self.utils,
+        };
+        let mut env = MetaVarEnv::default();
+        if !self.rule.match_node(node, &mut env, &context) {
+            return None;
+        }
+        let constrained = self.constraints.iter().all(|(name, rule)| {
+            env.get_single(name)
+                .is_none_or(|node| rule.match_node(node, &mut MetaVarEnv::default(), &context))
+        });
+        constrained.then_some(env)
+    }
+}
+
+impl SerializableRuleConfig {
+    /// The rule of a search, from either a pattern or an inline rule config in YAML whose `id`
+    /// and `language` can be left out.
+    pub fn for_search(
+        pattern: Option<&str>,
+        rule: Option<&str>,
+        language: AstGrepLanguage,
+    ) -> Result<Self, String> {
+        let rule = match (pattern, rule) {
+            (Some(pattern), None) => {
+                return Ok(Self {
+                    id: SEARCH_RULE_ID.to_string(),
+                    language,
+                    rule: SerializableRule {
+                        pattern: Some(SerializablePattern::Code(pattern.to_string())),
+                        ..Default::default()
+                    },
+                    utils: HashMap::new(),
+                    constraints: HashMap::new(),
+                    _message: IgnoredAny,
+                    _note: IgnoredAny,
+                    _severity: IgnoredAny,
+                    _url: IgnoredAny,
+                    _metadata: IgnoredAny,
+                })
+            }
+            (None, Some(rule)) => rule,
+            _ => return Err("exactly one of pattern and rule must be given".to_string()),
+        };
+        let mut config: serde_yaml::Mapping =
+            serde_yaml::from_str(rule).map_err(|e| e.to_string())?;
+        config.entry("id".into()).or_insert(SEARCH_RULE_ID.into());
+        config
+            .entry("language".into())
+            .or_insert(language.to_string().into());
+        let config: Self = serde_yaml::from_value(config.into()).map_err(|e| e.to_string())?;
+        if config.language != language {
+            return Err(format!(
+                "the rule is for {} but {} was requested",
+                config.language, language
+            ));
+        }
+        Ok(config)
+    }
+
+    /// Adds the utility rules of its language, keeping its own utility rules of the same name.
+    pub fn with_util_rules(mut self, util_rules: &[SerializableUtilRule]) -> Self {
+        for util_rule in util_rules {
+            if util_rule.language == self.language {
+                self.utils
+                    .entry(util_rule.id.clone())
+                    .or_insert_with(|| util_rule.rule.clone());
+            }
+        }
+        self
+    }
+
+    pub fn compile(self) -> Result<RuleConfig, String> {
+        let util_names: HashSet<String> = self.utils.keys().cloned().collect();
+        let context = CompileContext {
+            language: self.language,
+            utils: &util_names,
+        };
+        let compile_map = |rules: HashMap<String, SerializableRule>| {
+            rules
+                .into_iter()
+                .map(|(name, rule)| Ok((name, rule.compile(&context)?)))
+                .collect::<Result<HashMap<String, Rule>, String>>()
+        };
+        let utils = compile_map(self.utils)?;
+        let constraints = compile_map(self.constraints)?;
+        let rule = self
+            .rule
+            .compile(&context)
+            .map_err(|e| format!("rule '{}': {}", self.id, e))?;
+        Ok(RuleConfig {
+            id: self.id,
+            language: self.language,
+            rule,
+            utils,
+            constraints,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tree_sitter::Parser;
+
+    fn rule_config(yaml: &str) -> Result<RuleConfig, String> {
+        serde_yaml::from_str::<SerializableRuleConfig>(yaml)
+            .map_err(|e| e.to_string())?
+            .compile()
+    }
+
+    fn matched_names(config: &RuleConfig, code: &str) -> Vec<String> {
+        let mut parser = Parser::new();
+        parser
+            .set_language(&config.language.tree_sitter_language())
+            .unwrap();
+        let tree = parser.parse(code, None).unwrap();
+        let mut names = Vec::new();
+        let mut stack = vec![tree.root_node()];
+        while let Some(node) = stack.pop() {
+            if let Some(env) = config.match_node(node, code.as_bytes()) {
+                names.push(
+                    env.get_single("NAME")
+                        .unwrap()
+                        .utf8_text(code.as_bytes())
+                        .unwrap()
+                        .to_string(),
+                );
+            }
+            let mut cursor = node.walk();
+            let mut children: Vec<Node> = node.children(&mut cursor).collect();
+            children.reverse();
+            stack.extend(children);
+        }
+        names
+    }
+
+    #[test]
+    fn test_rule_matching() -> Result<(), String> {
+        let code = "class A(Base):\n    x = 1\n    def f(self):\n        y = g(self.x)\n";
+        let variables = rule_config(
+            r#"
+id: variable
+language: python
+utils:
+  top_level:
+    kind: expression_statement
+    any:
+      - inside: { kind: module }
+      - inside: { kind: block, inside: { kind: class_definition } }
+rule:
+  kind: identifier
+  pattern: $NAME
+  inside:
+    kind: assignment
+    field: left
+    inside: { matches: top_level }
+"#,
+        )?;
+        assert_eq!(matched_names(&variables, code), vec!["x"]);
+
+        let supertypes = rule_config(
+            r#"
+id: supertype
+language: python
+rule:
+  kind: identifier
+  pattern: $SUPERTYPE
+  inside: { kind: argument_list }
+  all:
+    - inside:
+        stopBy: end
+        kind: class_definition
+        has: { field: name, pattern: $NAME }
+  not: { inside: { kind: block, stopBy: end } }
+  nthChild: { position: 2n+1, reverse: true }
+"#,
+        )?;
+        assert_eq!(matched_names(&supertypes, code), vec!["A"]);
+
+        let calls = rule_config(
+            r#"
+id: call
+language: python
+rule:
+  pattern: $NAME($$$ARGS)
+constraints:
+  NAME: { regex: "^g" }
+"#,
+        )?;
+        assert_eq!(matched_names(&calls, code), vec!["g"]);
+
+        assert!(rule_config("id: a\nlanguage: python\nrule: { kind: klass }").is_err());
+        assert!(rule_config("id: a\nlanguage: python\nrule: { matches: b }").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_search_rule() -> Result<(), String> {
+        let code = "db.execute(q)\ncache.execute(q)\n";
+        let search = |pattern: Option<&str>, rule: Option<&str>| {
+            SerializableRuleConfig::for_search(pattern, rule, AstGrepLanguage::Python)
+                .and_then(SerializableRuleConfig::compile)
+        };
+
+        let pattern = search(Some("$NAME.execute($$$)"), None)?;
+        assert_eq!(pattern.id, SEARCH_RULE_ID);
+        assert_eq!(matched_names(&pattern, code), vec!["db", "cache"]);
+
+        let rule = search(
+            None,
+            Some("rule: { pattern: $NAME.execute($$$) }\nconstraints: { NAME: { regex: ^c } }"),
+        )?;
+        assert_eq!(matched_names(&rule, code), vec!["cache"]);
+
+        assert!(search(None, Some("language: rust\nrule: { kind: identifier }")).is_err());
+        assert!(search(Some("$A"), Some("rule: { kind: identifier }")).is_err());
+        assert!(search(None, None).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_rule_keys() -> Result<(), String> {
+        let rule = |yaml: &str| rule_config(&format!("id: a\nlanguage: python\n{}", yaml));
+
+        assert!(rule("rule: { kind: call, patern: f($$$) }").is_err());
+        assert!(rule("rule: { inside: { kind: call, stopby: end } }").is_err());
+        assert!(rule("rule: { nthChild: { position: 1, ofrule: { kind: call } } }").is_err());
+        assert!(rule("rule: { pattern: { context: f(x), selectr: call } }").is_err());
+        assert!(rule("rule: { kind: call }\nfix: g()").is_err());
+        assert!(rule("rule: { kind: call }\ntransform: {}").is_err());
+        assert!(rule("rule: { kind: call }\nrewriters: []").is_err());
+
+        rule("rule: { inside: { kind: call, stopBy: end, field: arguments } }")?;
+        rule("rule: { kind: call }\nmessage: a call\nseverity: hint\nmetadata: { a: 1 }")?;
+        Ok(())
+    }
+}
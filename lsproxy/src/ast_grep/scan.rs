@@ -0,0 +1,499 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex, RwLock};
+
+use include_dir::{include_dir, Dir, DirEntry};
+use serde::Deserialize;
+use tree_sitter::{Node, Parser, Tree};
+
+use super::fix::Fix;
+use super::language::AstGrepLanguage;
+use super::rule::{RuleConfig, SerializableRuleConfig, SerializableUtilRule};
+use super::types::{AstGrepMatch, FileRewrite, SearchMatch};
+
+/// The rule sets in `src/ast_grep`, built into the binary so it doesn't need them on disk.
+static BUNDLED_RULE_SETS: [(&str, Dir); 4] = [
+    (
+        "symbol",
+        include_dir!("$CARGO_MANIFEST_DIR/src/ast_grep/symbol"),
+    ),
+    (
+        "identifier",
+        include_dir!("$CARGO_MANIFEST_DIR/src/ast_grep/identifier"),
+    ),
+    (
+        "reference",
+        include_dir!("$CARGO_MANIFEST_DIR/src/ast_grep/reference"),
+    ),
+    (
+        "type_hierarchy",
+        include_dir!("$CARGO_MANIFEST_DIR/src/ast_grep/type_hierarchy"),
+    ),
+];
+
+/// A directory with rule sets replacing the bundled ones of the same name, laid out like
+/// `src/ast_grep`.
+static GLOBAL_RULES_DIR: LazyLock<RwLock<Option<PathBuf>>> = LazyLock::new(|| RwLock::new(None));
+
+const CONFIG_FILE: &str = "config.yml";
+
+/// How many parsed files to keep, so files scanned again without changes aren't parsed again.
+const TREE_CACHE_SIZE: usize = 1000;
+
+pub fn set_global_rules_dir(path: impl AsRef<Path>) {
+    *GLOBAL_RULES_DIR.write().unwrap() = Some(path.as_ref().to_path_buf());
+}
+
+fn get_rules_dir() -> Option<PathBuf> {
+    GLOBAL_RULES_DIR.read().unwrap().clone()
+}
+
+/// An ast-grep project config like `symbol/config.yml`.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ProjectConfig {
+    /// The directories of the rule files, relative to the config.
+    rule_dirs: Vec<String>,
+    /// The directories of the utility rule files every rule of their language can refer to,
+    /// relative to the config.
+    #[serde(default)]
+    util_dirs: Vec<String>,
+    /// The globs of the files each language is scanned in, besides its default extensions.
+    #[serde(default)]
+    language_globs: HashMap<AstGrepLanguage, Vec<String>>,
+}
+
+/// The rules of a project config, like those finding symbols or references.
+pub struct RuleSet {
+    rules: Vec<RuleConfig>,
+    language_globs: Vec<(AstGrepLanguage, glob::Pattern)>,
+}
+
+impl RuleSet {
+    /// Loads the rule set of the given name, like `symbol`, from the rules directory if it has
+    /// one and from the rule sets built into the binary otherwise.
+    pub fn load(name: &str) -> Result<Self, String> {
+        match get_rules_dir().map(|rules_dir| rules_dir.join(name)) {
+            Some(dir) if dir.join(CONFIG_FILE).is_file() => Self::load_dir(&dir),
+            _ => Self::load_bundled(name),
+        }
+    }
+
+    fn load_dir(dir: &Path) -> Result<Self, String> {
+        let read = |path: &Path| {
+            std::fs::read_to_string(path)
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+        };
+        let config_path = dir.join(CONFIG_FILE);
+        let config = parse_config(&read(&config_path)?, &config_path)?;
+        let read_dirs = |dirs: &[String]| {
+            let mut paths = Vec::new();
+            for rule_dir in dirs {
+                collect_rule_paths(&dir.join(rule_dir), &mut paths)
+                    .map_err(|e| format!("Failed to list rules in {}: {}", rule_dir, e))?;
+            }
+            paths
+                .into_iter()
+                .map(|path| Ok((read(&path)?, path)))
+                .collect::<Result<Vec<_>, String>>()
+        };
+        let rule_files = read_dirs(&config.rule_dirs)?;
+        let util_files = read_dirs(&config.util_dirs)?;
+        Self::compile(config, rule_files, util_files)
+    }
+
+    fn load_bundled(name: &str) -> Result<Self, String> {
+        let (_, dir) = BUNDLED_RULE_SETS
+            .iter()
+            .find(|(bundled, _)| *bundled == name)
+            .ok_or_else(|| format!("Unknown rule set '{}'", name))?;
+        let config_path = Path::new(name).join(CONFIG_FILE);
+        let config = dir
+            .get_file(CONFIG_FILE)
+            .and_then(|file| file.contents_utf8())
+            .ok_or_else(|| format!("Missing config {}", config_path.display()))?;
+        let config = parse_config(config, &config_path)?;
+        let read_dirs = |dirs: &[String]| {
+            let mut files = Vec::new();
+            for rule_dir in dirs {
+                let rule_dir = dir
+                    .get_dir(rule_dir)
+                    .ok_or_else(|| format!("Failed to list rules in {}", rule_dir))?;
+                collect_bundled_rules(name, rule_dir, &mut files);
+            }
+            Ok::<_, String>(files)
+        };
+        let rule_files = read_dirs(&config.rule_dirs)?;
+        let util_files = read_dirs(&config.util_dirs)?;
+        Self::compile(config, rule_files, util_files)
+    }
+
+    /// Compiles the rules of a config, given as the contents and path of each rule file and
+    /// utility rule file.
+    fn compile(
+        config: ProjectConfig,
+        mut rule_files: Vec<(impl AsRef<str>, PathBuf)>,
+        util_files: Vec<(impl AsRef<str>, PathBuf)>,
+    ) -> Result<Self, String> {
+        let mut util_rules = Vec::new();
+        for (yaml, util_file) in util_files {
+            for document in serde_yaml::Deserializer::from_str(yaml.as_ref()) {
+                let util_rule = SerializableUtilRule::deserialize(document)
+                    .map_err(|e| format!("Invalid utility rule {}: {}", util_file.display(), e))?;
+                util_rules.push(util_rule);
+            }
+        }
+
+        rule_files.sort_by(|(_, a), (_, b)| a.cmp(b));
+        let mut rules = Vec::new();
+        for (yaml, rule_file) in rule_files {
+            for document in serde_yaml::Deserializer::from_str(yaml.as_ref()) {
+                let rule = SerializableRuleConfig::deserialize(document)
+                    .map(|rule| rule.with_util_rules(&util_rules))
+                    .map_err(|e| e.to_string())
+                    .and_then(SerializableRuleConfig::compile)
+                    .map_err(|e| format!("Invalid rule {}: {}", rule_file.display(), e))?;
+                rules.push(rule);
+            }
+        }
+
+        let mut language_globs = Vec::new();
+        for (language, globs) in config.language_globs {
+            for glob in globs {
+                let pattern = glob::Pattern::new(&glob)
+                    .map_err(|e| format!("Invalid glob '{}': {}", glob, e))?;
+                language_globs.push((language, pattern));
+            }
+        }
+        Ok(Self {
+            rules,
+            language_globs,
+        })
+    }
+
+    /// The language a file is scanned as, from the config's globs or else its extension.
+    pub fn language(&self, path: &Path) -> Option<AstGrepLanguage> {
+        let file_name = path.file_name()?.to_str()?;
+        self.language_globs
+            .iter()
+            .find(|(_, glob)| glob.matches(file_name))
+            .map(|(language, _)| *language)
+            .or_else(|| AstGrepLanguage::from_path(path))
+    }
+
+    /// The matches of every rule of the file's language, in the order of the nodes they match.
+    /// Matches without a `NAME` are left out.
+    pub fn scan(&self, file: &str, parsed: &ParsedFile) -> Vec<AstGrepMatch> {
+        let rules: Vec<&RuleConfig> = self
+            .rules
+            .iter()
+            .filter(|rule| rule.language == parsed.language)
+            .collect();
+        let mut matches = Vec::new();
+        if rules.is_empty() {
+            return matches;
+        }
+        let source = parsed.source.as_str();
+        visit(parsed.tree.root_node(), &mut |node| {
+            for rule in &rules {
+                if let Some(env) = rule.match_node(node, source.as_bytes()) {
+                    matches.extend(AstGrepMatch::new(
+                        &rule.id,
+                        rule.language,
+                        file,
+                        node,
+                        &env,
+                        source,
+                    ));
+                }
+            }
+        });
+        matches
+    }
+}
+
+/// The matches of a rule in a file of its language, in the order of the nodes they match.
+pub fn search(rule: &RuleConfig, file: &str, parsed: &ParsedFile) -> Vec<SearchMatch> {
+    let mut matches = Vec::new();
+    if rule.language != parsed.language {
+        return matches;
+    }
+    let source = parsed.source.as_str();
+    visit(parsed.tree.root_node(), &mut |node| {
+        if let Some(env) = rule.match_node(node, source.as_bytes()) {
+            matches.push(SearchMatch::new(file, node, &env, source));
+        }
+    });
+    matches
+}
+
+/// The edits replacing the matches of a rule in a file of its language with a fix, if any
+/// change the file. Matches inside a match already replaced are left as they are.
+pub fn rewrite(
+    rule: &RuleConfig,
+    fix: &Fix,
+    file: &str,
+    parsed: &ParsedFile,
+) -> Option<FileRewrite> {
+    if rule.language != parsed.language {
+        return None;
+    }
+    let source = parsed.source.as_str();
+    let mut edits: Vec<(std::ops::Range<usize>, String)> = Vec::new();
+    visit(parsed.tree.root_node(), &mut |node| {
+        if edits
+            .last()
+            .is_some_and(|(range, _)| node.start_byte() < range.end)
+        {
+            return;
+        }
+        if let Some(env) = rule.match_node(node, source.as_bytes()) {
+            let replacement = fix.replacement(node, &env, source);
+            if replacement != source[node.byte_range()] {
+                edits.push((node.byte_range(), replacement));
+            }
+        }
+    });
+    (!edits.is_empty()).then(|| FileRewrite {
+        file: file.to_string(),
+        source: parsed.source.clone(),
+        edits,
+    })
+}
+
+fn parse_config(config: &str, config_path: &Path) -> Result<ProjectConfig, String> {
+    serde_yaml::from_str(config)
+        .map_err(|e| format!("Invalid config {}: {}", config_path.display(), e))
+}
+
+fn is_rule_file(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == "yml" || extension == "yaml")
+}
+
+fn collect_rule_paths(dir: &Path, paths: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_rule_paths(&path, paths)?;
+        } else if is_rule_file(&path) {
+            paths.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn collect_bundled_rules(
+    name: &str,
+    dir: &'static Dir<'static>,
+    rule_files: &mut Vec<(&'static str, PathBuf)>,
+) {
+    for entry in dir.entries() {
+        match entry {
+            DirEntry::Dir(dir) => collect_bundled_rules(name, dir, rule_files),
+            DirEntry::File(file) if is_rule_file(file.path()) => {
+                if let Some(yaml) = file.contents_utf8() {
+                    rule_files.push((yaml, Path::new(name).join(file.path())));
+                }
+            }
+            DirEntry::File(_) => {}
+        }
+    }
+}
+
+/// Calls `f` on every node of the tree, parents before their children.
+fn visit<'t>(root: Node<'t>, f: &mut impl FnMut(Node<'t>)) {
+    let mut cursor = root.walk();
+    loop {
+        f(cursor.node());
+        if cursor.goto_first_child() || cursor.goto_next_sibling() {
+            continue;
+        }
+        loop {
+            if !cursor.goto_parent() {
+                return;
+            }
+            if cursor.goto_next_sibling() {
+                break;
+            }
+        }
+    }
+}
+
+pub struct ParsedFile {
+    pub language: AstGrepLanguage,
+    pub source: String,
+    pub tree: Tree,
+}
+
+/// The syntax trees of recently parsed files, by hash of their language and content. A file is
+/// only reused for the same language and content, so a hash collision is parsed again.
+#[derive(Default)]
+pub struct TreeCache {
+    files: Mutex<CachedFiles>,
+}
+
+#[derive(Default)]
+struct CachedFiles {
+    by_hash: HashMap<u64, Arc<ParsedFile>>,
+    /// The hashes in the order their files were first parsed, the oldest evicted first.
+    order: VecDeque<u64>,
+}
+
+impl TreeCache {
+    pub fn parse(
+        &self,
+        language: AstGrepLanguage,
+        source: String,
+    ) -> Result<Arc<ParsedFile>, String> {
+        let mut hasher = DefaultHasher::new();
+        (language, &source).hash(&mut hasher);
+        let hash = hasher.finish();
+        if let Some(parsed) = self.files.lock().unwrap().by_hash.get(&hash) {
+            if parsed.language == language && parsed.source == source {
+                return Ok(Arc::clone(parsed));
+            }
+        }
+
+        let mut parser = Parser::new();
+        parser
+            .set_language(&language.tree_sitter_language())
+            .map_err(|e| e.to_string())?;
+        let tree = parser
+            .parse(&source, None)
+            .ok_or_else(|| format!("Failed to parse {} source", language))?;
+        let parsed = Arc::new(ParsedFile {
+            language,
+            source,
+            tree,
+        });
+
+        let mut files = self.files.lock().unwrap();
+        if files.by_hash.insert(hash, Arc::clone(&parsed)).is_none() {
+            files.order.push_back(hash);
+        }
+        while files.order.len() > TREE_CACHE_SIZE {
+            if let Some(oldest) = files.order.pop_front() {
+                files.by_hash.remove(&oldest);
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_rule_sets() -> Result<(), Box<dyn std::error::Error>> {
+        for (name, _) in &BUNDLED_RULE_SETS {
+            assert!(!RuleSet::load_bundled(name)?.rules.is_empty());
+        }
+
+        let rules_dir = tempfile::tempdir()?;
+        let symbol_dir = rules_dir.path().join("symbol");
+        std::fs::create_dir_all(symbol_dir.join("rules/python"))?;
+        std::fs::create_dir_all(symbol_dir.join("utils/python"))?;
+        std::fs::write(
+            symbol_dir.join(CONFIG_FILE),
+            "ruleDirs:\n  - rules\nutilDirs:\n  - utils\nlanguageGlobs:\n  python: ['*.star']\n",
+        )?;
+        std::fs::write(
+            symbol_dir.join("rules/python/task.yml"),
+            "id: task\nlanguage: python\nrule:\n  matches: task-call\n",
+        )?;
+        std::fs::write(
+            symbol_dir.join("utils/python/task-call.yml"),
+            "id: task-call\nlanguage: python\nrule:\n  pattern: task($NAME)\n",
+        )?;
+        let rule_set = RuleSet::load_dir(&symbol_dir)?;
+
+        let path = "BUILD.star";
+        let language = rule_set.language(Path::new(path)).ok_or("no language")?;
+        let parsed = TreeCache::default().parse(language, "task(build)\n".to_string())?;
+        let matches = rule_set.scan(path, &parsed);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].rule_id, "task");
+        assert_eq!(matches[0].meta_variables.single.name.text, "build");
+        Ok(())
+    }
+
+    /// The rule id, file, range and `NAME` of each match in the JSON output of ast-grep.
+    fn match_keys(matches: &[serde_json::Value]) -> Vec<String> {
+        let mut keys: Vec<String> = matches
+            .iter()
+            .filter_map(|m| {
+                let name = m.pointer("/metaVariables/single/NAME/text")?;
+                Some(format!(
+                    "{} {} {} {} {}",
+                    m["ruleId"], m["file"], m["range"]["start"], m["range"]["end"], name
+                ))
+            })
+            .collect();
+        keys.sort();
+        keys
+    }
+
+    /// Runs where the ast-grep CLI is installed, like the dev image, and is skipped elsewhere.
+    #[test]
+    fn test_scan_matches_ast_grep_cli() -> Result<(), Box<dyn std::error::Error>> {
+        if std::process::Command::new("ast-grep")
+            .arg("--version")
+            .output()
+            .is_err()
+        {
+            eprintln!("Skipping, the ast-grep CLI isn't installed");
+            return Ok(());
+        }
+        let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let sample_project = manifest_dir.join("../sample_project").canonicalize()?;
+        let tree_cache = TreeCache::default();
+        for (name, _) in &BUNDLED_RULE_SETS {
+            let rule_set = RuleSet::load_bundled(name)?;
+            let mut files = Vec::new();
+            let mut matches = Vec::new();
+            for path in glob::glob(&format!("{}/**/*", sample_project.display()))? {
+                let path = path?;
+                let Some(language) = rule_set.language(&path).filter(|_| path.is_file()) else {
+                    continue;
+                };
+                let file = path.to_string_lossy().into_owned();
+                let parsed = tree_cache.parse(language, std::fs::read_to_string(&path)?)?;
+                for m in rule_set.scan(&file, &parsed) {
+                    matches.push(serde_json::to_value(m)?);
+                }
+                files.push(file);
+            }
+
+            let output = std::process::Command::new("ast-grep")
+                .arg("scan")
+                .arg("--config")
+                .arg(
+                    manifest_dir
+                        .join("src/ast_grep")
+                        .join(name)
+                        .join(CONFIG_FILE),
+                )
+                .arg("--json")
+                .args(&files)
+                .output()?;
+            assert!(
+                output.status.success(),
+                "{}",
+                String::from_utf8_lossy(&output.stderr)
+            );
+            let cli_matches: Vec<serde_json::Value> = serde_json::from_slice(&output.stdout)?;
+
+            assert_eq!(
+                match_keys(&matches),
+                match_keys(&cli_matches),
+                "{} rules",
+                name
+            );
+        }
+        Ok(())
+    }
+}
@@ -1,9 +1,16 @@
+use std::collections::HashMap;
+use std::ops::Range as ByteRange;
 use std::path::PathBuf;
 
 use serde::{Deserialize, Serialize};
+use tree_sitter::Node;
 
+use super::language::AstGrepLanguage;
+use super::rule::MetaVarEnv;
 use crate::{
-    api_types::{FilePosition, FileRange, Identifier, Position, Range, Symbol},
+    api_types::{
+        CapturedCode, FilePosition, FileRange, Identifier, Position, Range, StructuralMatch, Symbol,
+    },
     utils::file_utils::absolute_path_to_relative_path_string,
 };
 
@@ -22,6 +29,49 @@ pub struct AstGrepMatch {
 }
 
 impl AstGrepMatch {
+    /// The match of a rule at a node, None if the rule didn't capture a `NAME`.
+    pub fn new(
+        rule_id: &str,
+        language: AstGrepLanguage,
+        file: &str,
+        node: Node,
+        env: &MetaVarEnv,
+        source: &str,
+    ) -> Option<Self> {
+        let meta_variable = |name: &str| {
+            env.get_single(name)
+                .map(|node| MetaVariable::from_node(node, source))
+        };
+        let range = AstGrepRange::from_node(node, source);
+        let line_start = source[..range.byte_offset.start]
+            .rfind('\n')
+            .map_or(0, |i| i + 1);
+        let line_end = source[range.byte_offset.end..]
+            .find('\n')
+            .map_or(source.len(), |i| range.byte_offset.end + i);
+        Some(Self {
+            text: source[node.byte_range()].to_string(),
+            file: file.to_string(),
+            lines: source[line_start..line_end].to_string(),
+            char_count: CharCount {
+                leading: source[line_start..range.byte_offset.start].chars().count(),
+                trailing: source[range.byte_offset.end..line_end].chars().count(),
+            },
+            language: language.to_string(),
+            meta_variables: MetaVariables {
+                single: SingleVariable {
+                    name: meta_variable("NAME")?,
+                    context: meta_variable("CONTEXT"),
+                    supertype: meta_variable("SUPERTYPE"),
+                },
+                multi: MultiVariables { secondary: None },
+            },
+            rule_id: rule_id.to_string(),
+            labels: None,
+            range,
+        })
+    }
+
     pub fn get_source_code(&self) -> String {
         if let Some(context) = &self.meta_variables.single.context {
             context.text.clone()
@@ -53,6 +103,51 @@ impl AstGrepMatch {
     }
 }
 
+/// A match of a search, with every metavariable its rule captured. Multiple metavariables
+/// only keep their named nodes, leaving out punctuation like the commas between arguments.
+#[derive(Debug, Clone)]
+pub struct SearchMatch {
+    pub file: String,
+    pub text: String,
+    pub range: AstGrepRange,
+    pub single: HashMap<String, MetaVariable>,
+    pub multi: HashMap<String, Vec<MetaVariable>>,
+}
+
+impl SearchMatch {
+    pub fn new(file: &str, node: Node, env: &MetaVarEnv, source: &str) -> Self {
+        Self {
+            file: file.to_string(),
+            text: source[node.byte_range()].to_string(),
+            range: AstGrepRange::from_node(node, source),
+            single: env
+                .singles()
+                .map(|(name, node)| (name.to_string(), MetaVariable::from_node(node, source)))
+                .collect(),
+            multi: env
+                .multiples()
+                .map(|(name, nodes)| {
+                    let variables = nodes
+                        .iter()
+                        .filter(|node| node.is_named())
+                        .map(|node| MetaVariable::from_node(*node, source))
+                        .collect();
+                    (name.to_string(), variables)
+                })
+                .collect(),
+        }
+    }
+}
+
+/// The edits rewriting the matches of a rule in a file, along with the text they apply to.
+#[derive(Debug)]
+pub struct FileRewrite {
+    pub file: String,
+    pub source: String,
+    /// The byte range of each match and the code replacing it, in order.
+    pub edits: Vec<(ByteRange<usize>, String)>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct AstGrepRange {
@@ -61,6 +156,27 @@ pub struct AstGrepRange {
     pub end: AstGrepPosition,
 }
 
+impl AstGrepRange {
+    /// The range of a node, with columns counted in characters.
+    fn from_node(node: Node, source: &str) -> Self {
+        let position = |byte: usize, row: usize| {
+            let line_start = source[..byte].rfind('\n').map_or(0, |i| i + 1);
+            AstGrepPosition {
+                line: row as u32,
+                column: source[line_start..byte].chars().count() as u32,
+            }
+        };
+        Self {
+            byte_offset: ByteOffset {
+                start: node.start_byte(),
+                end: node.end_byte(),
+            },
+            start: position(node.start_byte(), node.start_position().row),
+            end: position(node.end_byte(), node.end_position().row),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct ByteOffset {
@@ -95,6 +211,8 @@ pub struct SingleVariable {
     pub name: MetaVariable,
     #[serde(rename = "CONTEXT")]
     pub context: Option<MetaVariable>,
+    #[serde(rename = "SUPERTYPE")]
+    pub supertype: Option<MetaVariable>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -110,6 +228,15 @@ pub struct MetaVariable {
     pub range: AstGrepRange,
 }
 
+impl MetaVariable {
+    fn from_node(node: Node, source: &str) -> Self {
+        Self {
+            text: source[node.byte_range()].to_string(),
+            range: AstGrepRange::from_node(node, source),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Label {
@@ -126,6 +253,69 @@ impl From<&AstGrepMatch> for lsp_types::Position {
     }
 }
 
+impl From<AstGrepRange> for lsp_types::Range {
+    fn from(range: AstGrepRange) -> Self {
+        Self {
+            start: lsp_types::Position {
+                line: range.start.line,
+                character: range.start.column,
+            },
+            end: lsp_types::Position {
+                line: range.end.line,
+                character: range.end.column,
+            },
+        }
+    }
+}
+
+impl From<AstGrepRange> for Range {
+    fn from(range: AstGrepRange) -> Self {
+        Self {
+            start: Position {
+                line: range.start.line,
+                character: range.start.column,
+            },
+            end: Position {
+                line: range.end.line,
+                character: range.end.column,
+            },
+        }
+    }
+}
+
+impl From<MetaVariable> for CapturedCode {
+    fn from(meta_variable: MetaVariable) -> Self {
+        Self {
+            text: meta_variable.text,
+            range: meta_variable.range.into(),
+        }
+    }
+}
+
+impl From<SearchMatch> for StructuralMatch {
+    fn from(search_match: SearchMatch) -> Self {
+        Self {
+            file_range: FileRange {
+                path: absolute_path_to_relative_path_string(&PathBuf::from(&search_match.file)),
+                range: search_match.range.into(),
+            },
+            text: search_match.text,
+            single: search_match
+                .single
+                .into_iter()
+                .map(|(name, meta_variable)| (name, meta_variable.into()))
+                .collect(),
+            multi: search_match
+                .multi
+                .into_iter()
+                .map(|(name, meta_variables)| {
+                    (name, meta_variables.into_iter().map(Into::into).collect())
+                })
+                .collect(),
+        }
+    }
+}
+
 impl From<AstGrepMatch> for Symbol {
     fn from(ast_match: AstGrepMatch) -> Self {
         assert!(ast_match.rule_id != "all-identifiers");
@@ -0,0 +1,401 @@
+use std::collections::HashSet;
+
+use actix_web::web::{Data, Json};
+use actix_web::HttpResponse;
+use log::{error, info};
+use lsp_types::{CallHierarchyItem, Position as LspPosition};
+
+use crate::api_types::{
+    CallHierarchyNode, CallHierarchyResponse, ErrorResponse, FileRange, GetCallHierarchyRequest,
+    Position, Range, Symbol,
+};
+use crate::handlers::error::IntoHttpResponse;
+use crate::handlers::utils;
+use crate::lsp::manager::{LspManagerError, Manager};
+use crate::utils::file_utils::uri_to_relative_path_string;
+use crate::AppState;
+
+const MAX_CALL_HIERARCHY_DEPTH: u32 = 5;
+
+#[derive(Debug, Clone, Copy)]
+enum CallDirection {
+    Incoming,
+    Outgoing,
+}
+
+/// Find the callers of a function or method
+///
+/// The input position should point to the identifier of the function or method, either at its
+/// definition or at one of its call sites.
+///
+/// Returns the function(s) at the position as roots, each with the functions that call it.
+/// Use `depth` to also expand the callers of the callers.
+///
+/// e.g. for `distance` on line 1 of `src/graph.py` with the code:
+/// ```
+/// 0: class Graph:
+/// 1:     def distance(self, a, b):
+/// input______^^^^^^^^
+/// ...
+/// 9:     def heuristic(self, a, b):
+/// 10:        return self.distance(a, b)
+/// ```
+/// The root will be `distance` with a call from `heuristic`, whose call site is line 10.
+#[utoipa::path(
+    post,
+    path = "/symbol/call-hierarchy/incoming",
+    tag = "symbol",
+    request_body = GetCallHierarchyRequest,
+    responses(
+        (status = 200, description = "Incoming calls retrieved successfully", body = CallHierarchyResponse),
+        (status = 400, description = "Bad request"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn incoming_calls(
+    data: Data<AppState>,
+    info: Json<GetCallHierarchyRequest>,
+) -> HttpResponse {
+    call_hierarchy(data, info, CallDirection::Incoming).await
+}
+
+/// Find the functions and methods called by a function or method
+///
+/// The input position should point to the identifier of the function or method, either at its
+/// definition or at one of its call sites.
+///
+/// Returns the function(s) at the position as roots, each with the functions it calls.
+/// Use `depth` to also expand the callees of the callees.
+///
+/// e.g. for `heuristic` on line 9 of `src/graph.py` with the code:
+/// ```
+/// 0: class Graph:
+/// 1:     def distance(self, a, b):
+/// ...
+/// 9:     def heuristic(self, a, b):
+/// input______^^^^^^^^^
+/// 10:        return self.distance(a, b)
+/// ```
+/// The root will be `heuristic` with a call to `distance`, whose call site is line 10.
+#[utoipa::path(
+    post,
+    path = "/symbol/call-hierarchy/outgoing",
+    tag = "symbol",
+    request_body = GetCallHierarchyRequest,
+    responses(
+        (status = 200, description = "Outgoing calls retrieved successfully", body = CallHierarchyResponse),
+        (status = 400, description = "Bad request"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn outgoing_calls(
+    data: Data<AppState>,
+    info: Json<GetCallHierarchyRequest>,
+) -> HttpResponse {
+    call_hierarchy(data, info, CallDirection::Outgoing).await
+}
+
+async fn call_hierarchy(
+    data: Data<AppState>,
+    info: Json<GetCallHierarchyRequest>,
+    direction: CallDirection,
+) -> HttpResponse {
+    info!(
+        "Received {:?} call hierarchy request for file: {}, line: {}, character: {}, depth: {}",
+        direction,
+        info.identifier_position.path,
+        info.identifier_position.position.line,
+        info.identifier_position.position.character,
+        info.depth
+    );
+
+    utils::with_inline_overlays(
+        &data.manager,
+        &info.overlays,
+        respond(&data, &info, direction),
+    )
+    .await
+}
+
+async fn respond(
+    data: &Data<AppState>,
+    info: &GetCallHierarchyRequest,
+    direction: CallDirection,
+) -> HttpResponse {
+    let file_identifiers = match data
+        .manager
+        .get_file_identifiers(&info.identifier_position.path)
+        .await
+    {
+        Ok(identifiers) => identifiers,
+        Err(e) => {
+            error!("Failed to get file identifiers: {:?}", e);
+            return e.into_http_response();
+        }
+    };
+
+    let selected_identifier =
+        match utils::find_identifier_at_position(file_identifiers, &info.identifier_position).await
+        {
+            Ok(identifier) => identifier,
+            Err(e) => {
+                error!("Failed to find call hierarchy from position: {:?}", e);
+                return HttpResponse::BadRequest().json(ErrorResponse {
+                    error: format!("Failed to find call hierarchy from position: {}", e),
+                });
+            }
+        };
+
+    let depth = info.depth.clamp(1, MAX_CALL_HIERARCHY_DEPTH);
+    match build_call_hierarchy(
+        &data.manager,
+        &info.identifier_position.path,
+        LspPosition {
+            line: info.identifier_position.position.line,
+            character: info.identifier_position.position.character,
+        },
+        direction,
+        depth,
+    )
+    .await
+    {
+        Ok(roots) => HttpResponse::Ok().json(CallHierarchyResponse {
+            roots,
+            selected_identifier,
+        }),
+        Err(e) => e.into_http_response(),
+    }
+}
+
+async fn build_call_hierarchy(
+    manager: &Manager,
+    file_path: &str,
+    position: LspPosition,
+    direction: CallDirection,
+    depth: u32,
+) -> Result<Vec<CallHierarchyNode>, LspManagerError> {
+    let mut visited = HashSet::new();
+
+    let mut roots = Vec::new();
+    for item in manager.prepare_call_hierarchy(file_path, position).await? {
+        visited.insert(item_key(&item));
+        let calls = expand_calls(manager, &item, direction, depth, &mut visited).await?;
+        roots.push(CallHierarchyNode {
+            symbol: Symbol::from(item),
+            call_sites: Vec::new(),
+            calls,
+        });
+    }
+    Ok(roots)
+}
+
+/// Fetches the calls of `item` in the given direction, recursing until `depth` levels are
+/// expanded. Each item is expanded at most once so recursive calls terminate.
+async fn expand_calls(
+    manager: &Manager,
+    item: &CallHierarchyItem,
+    direction: CallDirection,
+    depth: u32,
+    visited: &mut HashSet<(String, u32, u32)>,
+) -> Result<Vec<CallHierarchyNode>, LspManagerError> {
+    let mut calls: Vec<(CallHierarchyItem, Vec<FileRange>)> = match direction {
+        CallDirection::Incoming => manager
+            .incoming_calls(item)
+            .await?
+            .into_iter()
+            .map(|call| {
+                // The call sites are inside the caller
+                let path = uri_to_relative_path_string(&call.from.uri);
+                let call_sites = to_file_ranges(&path, call.from_ranges);
+                (call.from, call_sites)
+            })
+            .collect(),
+        CallDirection::Outgoing => {
+            // The call sites are inside the item being expanded
+            let path = uri_to_relative_path_string(&item.uri);
+            manager
+                .outgoing_calls(item)
+                .await?
+                .into_iter()
+                .map(|call| (call.to, to_file_ranges(&path, call.from_ranges)))
+                .collect()
+        }
+    };
+    calls.sort_by_key(|(item, _)| item_key(item));
+
+    let mut nodes = Vec::new();
+    for (call_item, call_sites) in calls {
+        let in_workspace = manager
+            .is_workspace_file(&uri_to_relative_path_string(&call_item.uri))
+            .await;
+        let calls = if depth > 1 && in_workspace && visited.insert(item_key(&call_item)) {
+            Box::pin(expand_calls(
+                manager,
+                &call_item,
+                direction,
+                depth - 1,
+                visited,
+            ))
+            .await?
+        } else {
+            Vec::new()
+        };
+        nodes.push(CallHierarchyNode {
+            symbol: Symbol::from(call_item),
+            call_sites,
+            calls,
+        });
+    }
+    Ok(nodes)
+}
+
+fn item_key(item: &CallHierarchyItem) -> (String, u32, u32) {
+    (
+        uri_to_relative_path_string(&item.uri),
+        item.selection_range.start.line,
+        item.selection_range.start.character,
+    )
+}
+
+fn to_file_ranges(path: &str, ranges: Vec<lsp_types::Range>) -> Vec<FileRange> {
+    ranges
+        .into_iter()
+        .map(|range| FileRange {
+            path: path.to_string(),
+            range: Range {
+                start: Position::from(range.start),
+                end: Position::from(range.end),
+            },
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use actix_web::http::StatusCode;
+
+    use crate::api_types::FilePosition;
+    use crate::initialize_app_state;
+    use crate::test_utils::{rust_sample_path, TestContext};
+
+    async fn get_call_hierarchy(
+        state: Data<AppState>,
+        line: u32,
+        character: u32,
+        depth: u32,
+        direction: CallDirection,
+    ) -> Result<CallHierarchyResponse, Box<dyn std::error::Error>> {
+        let mock_request = Json(GetCallHierarchyRequest {
+            identifier_position: FilePosition {
+                path: String::from("src/astar.rs"),
+                position: Position { line, character },
+            },
+            depth,
+            overlays: Vec::new(),
+        });
+
+        let response = match direction {
+            CallDirection::Incoming => incoming_calls(state, mock_request).await,
+            CallDirection::Outgoing => outgoing_calls(state, mock_request).await,
+        };
+
+        assert_eq!(response.status(), StatusCode::OK, "{:?}", response.body());
+
+        let bytes = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        Ok(serde_json::from_slice(&bytes)?)
+    }
+
+    #[tokio::test]
+    async fn test_rust_incoming_calls() -> Result<(), Box<dyn std::error::Error>> {
+        let _context = TestContext::setup(&rust_sample_path(), false).await?;
+        let state = initialize_app_state().await?;
+
+        // `calc_dist` in `impl AStar`
+        let response = get_call_hierarchy(state, 31, 7, 1, CallDirection::Incoming).await?;
+
+        assert_eq!(response.selected_identifier.name, "calc_dist");
+        assert_eq!(response.roots.len(), 1);
+        let root = &response.roots[0];
+        assert_eq!(root.symbol.name, "calc_dist");
+        assert!(root.call_sites.is_empty());
+
+        let callers: Vec<(&str, u32)> = root
+            .calls
+            .iter()
+            .map(|call| {
+                (
+                    call.symbol.name.as_str(),
+                    call.call_sites[0].range.start.line,
+                )
+            })
+            .collect();
+        assert_eq!(callers, vec![("fill_open", 73), ("search", 97)]);
+        assert!(root.calls.iter().all(|call| call.calls.is_empty()));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rust_incoming_calls_with_depth() -> Result<(), Box<dyn std::error::Error>> {
+        let _context = TestContext::setup(&rust_sample_path(), false).await?;
+        let state = initialize_app_state().await?;
+
+        let response = get_call_hierarchy(state, 31, 7, 2, CallDirection::Incoming).await?;
+
+        let root = &response.roots[0];
+        let fill_open = &root.calls[0];
+        assert_eq!(fill_open.symbol.name, "fill_open");
+        let fill_open_callers: Vec<&str> = fill_open
+            .calls
+            .iter()
+            .map(|call| call.symbol.name.as_str())
+            .collect();
+        assert_eq!(fill_open_callers, vec!["search"]);
+
+        let search = &root.calls[1];
+        assert_eq!(search.symbol.name, "search");
+        let search_callers: Vec<&str> = search
+            .calls
+            .iter()
+            .map(|call| call.symbol.name.as_str())
+            .collect();
+        assert_eq!(search_callers, vec!["main"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rust_outgoing_calls() -> Result<(), Box<dyn std::error::Error>> {
+        let _context = TestContext::setup(&rust_sample_path(), false).await?;
+        let state = initialize_app_state().await?;
+
+        // `fill_open` in `impl AStar`
+        let response = get_call_hierarchy(state, 59, 7, 1, CallDirection::Outgoing).await?;
+
+        let root = &response.roots[0];
+        assert_eq!(root.symbol.name, "fill_open");
+        let callees: Vec<&str> = root
+            .calls
+            .iter()
+            .map(|call| call.symbol.name.as_str())
+            .collect();
+        for expected in ["is_valid", "calc_dist", "exist_point"] {
+            assert!(
+                callees.contains(&expected),
+                "Expected {} in {:?}",
+                expected,
+                callees
+            );
+        }
+        let calc_dist = root
+            .calls
+            .iter()
+            .find(|call| call.symbol.name == "calc_dist")
+            .unwrap();
+        assert_eq!(calc_dist.call_sites[0].path, "src/astar.rs");
+        assert_eq!(calc_dist.call_sites[0].range.start.line, 73);
+        Ok(())
+    }
+}
@@ -0,0 +1,234 @@
+use actix_web::web::{Data, Json};
+use actix_web::HttpResponse;
+use log::{error, info};
+
+use crate::api_types::{
+    CodeAction, CodeActionsRequest, CodeActionsResponse, ErrorResponse, ExecuteCodeActionRequest,
+    ExecuteCodeActionResponse, FileTextEdits,
+};
+use crate::handlers::error::IntoHttpResponse;
+use crate::AppState;
+
+/// List the code actions available for a range
+///
+/// Returns the quick fixes, refactorings and source actions the language server offers for the
+/// range, such as organize imports, add missing import or extract function.
+///
+/// Quick fixes are offered for the diagnostics in the range, so they require diagnostics to be
+/// enabled for the language in `DIAGNOSTICS_LANGUAGES`.
+///
+/// Use `kinds` to only list some kinds of actions, and `/refactor/code-actions/execute` with an
+/// action's title to execute it.
+#[utoipa::path(
+    post,
+    path = "/refactor/code-actions",
+    tag = "refactor",
+    request_body = CodeActionsRequest,
+    responses(
+        (status = 200, description = "Code actions retrieved successfully", body = CodeActionsResponse),
+        (status = 400, description = "Bad request"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn code_actions(data: Data<AppState>, info: Json<CodeActionsRequest>) -> HttpResponse {
+    info!(
+        "Received code actions request for file: {}, from line {}, character {} to line {}, character {}",
+        info.range.path,
+        info.range.range.start.line,
+        info.range.range.start.character,
+        info.range.range.end.line,
+        info.range.range.end.character
+    );
+
+    match data
+        .manager
+        .code_actions(&info.range.path, info.range.clone().into(), &info.kinds)
+        .await
+    {
+        Ok(actions) => HttpResponse::Ok().json(CodeActionsResponse {
+            actions: actions.into_iter().map(CodeAction::from).collect(),
+        }),
+        Err(e) => e.into_http_response(),
+    }
+}
+
+/// Execute a code action
+///
+/// Executes the code action with the given title among those available for the range, and
+/// returns the resulting edits.
+///
+/// By default the edits are only previewed: the response contains the text edits to each file
+/// and a unified diff, and the workspace is left untouched. Set `apply` to write the edits to
+/// the workspace files.
+#[utoipa::path(
+    post,
+    path = "/refactor/code-actions/execute",
+    tag = "refactor",
+    request_body = ExecuteCodeActionRequest,
+    responses(
+        (status = 200, description = "Code action executed successfully", body = ExecuteCodeActionResponse),
+        (status = 400, description = "Bad request"),
+        (status = 409, description = "A file changed since its edits were computed"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn execute_code_action(
+    data: Data<AppState>,
+    info: Json<ExecuteCodeActionRequest>,
+) -> HttpResponse {
+    info!(
+        "Received execute code action request for file: {}, title: {}, apply: {}",
+        info.range.path, info.title, info.apply
+    );
+
+    let actions = match data
+        .manager
+        .code_actions(&info.range.path, info.range.clone().into(), &[])
+        .await
+    {
+        Ok(actions) => actions,
+        Err(e) => return e.into_http_response(),
+    };
+
+    let Some(action) = actions
+        .into_iter()
+        .find(|action| CodeAction::from(action.clone()).title == info.title)
+    else {
+        error!("No code action titled {} in range", info.title);
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            error: format!("No code action titled `{}` in range", info.title),
+        });
+    };
+
+    let summary = CodeAction::from(action.clone());
+    if let Some(reason) = &summary.disabled_reason {
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            error: format!("`{}` is disabled: {}", info.title, reason),
+        });
+    }
+
+    let workspace_edits = match data
+        .manager
+        .code_action_edits(&info.range.path, action)
+        .await
+    {
+        Ok(workspace_edits) => workspace_edits,
+        Err(e) => return e.into_http_response(),
+    };
+
+    let file_edits = match data.manager.resolve_workspace_edits(workspace_edits).await {
+        Ok(file_edits) => file_edits,
+        Err(e) => return e.into_http_response(),
+    };
+
+    if info.apply {
+        if let Err(e) = data.manager.apply_file_edits(&file_edits).await {
+            return e.into_http_response();
+        }
+    }
+
+    HttpResponse::Ok().json(ExecuteCodeActionResponse {
+        diff: file_edits
+            .iter()
+            .map(|file_edit| file_edit.diff())
+            .collect(),
+        edits: file_edits.into_iter().map(FileTextEdits::from).collect(),
+        applied: info.apply,
+        action: summary,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use actix_web::http::StatusCode;
+
+    use crate::api_types::{FileRange, Position, Range};
+    use crate::initialize_app_state;
+    use crate::test_utils::{typescript_sample_path, TestContext};
+
+    fn main_ts_start() -> FileRange {
+        FileRange {
+            path: String::from("src/main.ts"),
+            range: Range {
+                start: Position {
+                    line: 0,
+                    character: 0,
+                },
+                end: Position {
+                    line: 0,
+                    character: 0,
+                },
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_typescript_organize_imports() -> Result<(), Box<dyn std::error::Error>> {
+        let _context = TestContext::setup(&typescript_sample_path(), false).await?;
+        let state = initialize_app_state().await?;
+
+        let mock_request = Json(CodeActionsRequest {
+            range: main_ts_start(),
+            kinds: vec![String::from("source.organizeImports")],
+        });
+
+        let response = code_actions(state.clone(), mock_request).await;
+
+        assert_eq!(response.status(), StatusCode::OK, "{:?}", response.body());
+
+        let bytes = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let actions_response: CodeActionsResponse = serde_json::from_slice(&bytes)?;
+        let organize_imports = actions_response
+            .actions
+            .into_iter()
+            .find(|action| action.kind.as_deref() == Some("source.organizeImports"))
+            .expect("Organize imports should be available");
+
+        let mock_request = Json(ExecuteCodeActionRequest {
+            range: main_ts_start(),
+            title: organize_imports.title.clone(),
+            apply: false,
+        });
+
+        let response = execute_code_action(state, mock_request).await;
+
+        assert_eq!(response.status(), StatusCode::OK, "{:?}", response.body());
+
+        let bytes = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let execute_response: ExecuteCodeActionResponse = serde_json::from_slice(&bytes)?;
+        assert_eq!(execute_response.action, organize_imports);
+        assert!(!execute_response.applied);
+        for file_edits in execute_response.edits {
+            assert_eq!(file_edits.path, "src/main.ts");
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_unknown_code_action() -> Result<(), Box<dyn std::error::Error>> {
+        let _context = TestContext::setup(&typescript_sample_path(), false).await?;
+        let state = initialize_app_state().await?;
+
+        let mock_request = Json(ExecuteCodeActionRequest {
+            range: main_ts_start(),
+            title: String::from("Not a code action"),
+            apply: false,
+        });
+
+        let response = execute_code_action(state, mock_request).await;
+
+        assert_eq!(
+            response.status(),
+            StatusCode::BAD_REQUEST,
+            "{:?}",
+            response.body()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,168 @@
+use actix_web::web::{Data, Json};
+use actix_web::HttpResponse;
+use log::info;
+use lsp_types::Position as LspPosition;
+
+use crate::api_types::{CompletionItem, CompletionsResponse, FileOverlay, GetCompletionsRequest};
+use crate::handlers::error::IntoHttpResponse;
+use crate::handlers::utils;
+use crate::AppState;
+
+/// Get the completions at a position in a file
+///
+/// Returns what the language server suggests typing at the position, e.g. the methods and
+/// fields of an object after `user.`, with their kind, the text to insert and their type or
+/// signature.
+///
+/// Completions are matched against the part of the word before the position, best matches
+/// first, and capped at `limit`.
+///
+/// Pass `text` to complete against unsaved content instead of the file in the workspace, e.g.
+/// after appending `user.` to it. The file itself is left untouched.
+///
+/// e.g. for the position after `user.` on line 1 of `src/main.py` with the code:
+/// ```
+/// 0: user = User("John", 30)
+/// 1: user.
+/// input___^
+/// ```
+/// The completions will include the `name` and `age` fields of `User`.
+#[utoipa::path(
+    post,
+    path = "/symbol/completions",
+    tag = "symbol",
+    request_body = GetCompletionsRequest,
+    responses(
+        (status = 200, description = "Completions retrieved successfully", body = CompletionsResponse),
+        (status = 400, description = "Bad request"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn completions(data: Data<AppState>, info: Json<GetCompletionsRequest>) -> HttpResponse {
+    info!(
+        "Received completions request for file: {}, line: {}, character: {}, unsaved text: {}",
+        info.position.path,
+        info.position.position.line,
+        info.position.position.character,
+        info.text.is_some()
+    );
+
+    let overlays: Vec<FileOverlay> = info
+        .text
+        .iter()
+        .map(|text| FileOverlay {
+            path: info.position.path.clone(),
+            text: text.clone(),
+        })
+        .collect();
+    utils::with_inline_overlays(&data.manager, &overlays, respond(&data, &info)).await
+}
+
+async fn respond(data: &Data<AppState>, info: &GetCompletionsRequest) -> HttpResponse {
+    match data
+        .manager
+        .completions(
+            &info.position.path,
+            LspPosition {
+                line: info.position.position.line,
+                character: info.position.position.character,
+            },
+            info.limit,
+        )
+        .await
+    {
+        Ok((items, is_incomplete)) => HttpResponse::Ok().json(CompletionsResponse {
+            items: items.into_iter().map(CompletionItem::from).collect(),
+            is_incomplete,
+        }),
+        Err(e) => e.into_http_response(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use actix_web::http::StatusCode;
+
+    use crate::api_types::{FilePosition, Position};
+    use crate::initialize_app_state;
+    use crate::test_utils::{python_sample_path, TestContext};
+
+    #[tokio::test]
+    async fn test_python_member_completions() -> Result<(), Box<dyn std::error::Error>> {
+        let _context = TestContext::setup(&python_sample_path(), false).await?;
+        let state = initialize_app_state().await?;
+
+        // Right after the `self.` in `self._barrier_cost(a, b)`
+        let mock_request = Json(GetCompletionsRequest {
+            position: FilePosition {
+                path: String::from("graph.py"),
+                position: Position {
+                    line: 39,
+                    character: 28,
+                },
+            },
+            text: None,
+            limit: 100,
+        });
+
+        let response = completions(state, mock_request).await;
+
+        assert_eq!(response.status(), StatusCode::OK, "{:?}", response.body());
+
+        let bytes = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let completions_response: CompletionsResponse = serde_json::from_slice(&bytes)?;
+        let labels: Vec<&str> = completions_response
+            .items
+            .iter()
+            .map(|item| item.label.as_str())
+            .collect();
+        assert!(labels.contains(&"_barrier_cost"), "{:?}", labels);
+        assert!(labels.contains(&"heuristic"), "{:?}", labels);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_python_unsaved_text_completions() -> Result<(), Box<dyn std::error::Error>> {
+        let _context = TestContext::setup(&python_sample_path(), false).await?;
+        let state = initialize_app_state().await?;
+
+        let original = std::fs::read_to_string(format!("{}/graph.py", python_sample_path()))?;
+        let mut lines: Vec<&str> = original.lines().collect();
+        lines[39] = "        barrier_cost = self.heur";
+        let text = lines.join("\n");
+
+        let mock_request = Json(GetCompletionsRequest {
+            position: FilePosition {
+                path: String::from("graph.py"),
+                position: Position {
+                    line: 39,
+                    character: 32,
+                },
+            },
+            text: Some(text),
+            limit: 5,
+        });
+
+        let response = completions(state, mock_request).await;
+
+        assert_eq!(response.status(), StatusCode::OK, "{:?}", response.body());
+
+        let bytes = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let completions_response: CompletionsResponse = serde_json::from_slice(&bytes)?;
+        assert!(completions_response.items.len() <= 5);
+        let first = &completions_response.items[0];
+        assert_eq!(first.label, "heuristic");
+        assert_eq!(first.kind.as_deref(), Some("method"));
+
+        // The unsaved text is never written to the workspace
+        let graph = std::fs::read_to_string(format!("{}/graph.py", python_sample_path()))?;
+        assert_eq!(graph, original);
+        Ok(())
+    }
+}
@@ -0,0 +1,205 @@
+use actix_web::web::{Data, Query};
+use actix_web::HttpResponse;
+use log::info;
+
+use crate::api_types::{
+    Diagnostic, DiagnosticSeverity, DiagnosticsResponse, FileDiagnosticsRequest,
+    WorkspaceDiagnosticsRequest,
+};
+use crate::handlers::error::IntoHttpResponse;
+use crate::utils::file_utils::uri_to_relative_path_string;
+use crate::AppState;
+
+/// Get the diagnostics of a file
+///
+/// Returns the errors, warnings and hints the language server reports for the file, such as
+/// type errors or unused variables.
+///
+/// Diagnostics are opt-in per language since computing them slows down the language servers:
+/// list the languages in the `DIAGNOSTICS_LANGUAGES` environment variable, e.g.
+/// `DIAGNOSTICS_LANGUAGES=python,rust`. Other languages respond with 501.
+///
+/// Use `min_severity` to only get diagnostics at least that severe, e.g. `warning` returns
+/// errors and warnings.
+#[utoipa::path(
+    get,
+    path = "/file/diagnostics",
+    tag = "file",
+    params(FileDiagnosticsRequest),
+    responses(
+        (status = 200, description = "Diagnostics retrieved successfully", body = DiagnosticsResponse),
+        (status = 400, description = "Bad request"),
+        (status = 500, description = "Internal server error"),
+        (status = 501, description = "Diagnostics are not enabled for the language")
+    )
+)]
+pub async fn file_diagnostics(
+    data: Data<AppState>,
+    info: Query<FileDiagnosticsRequest>,
+) -> HttpResponse {
+    info!("Received diagnostics request for file: {}", info.path);
+
+    match data.manager.file_diagnostics(&info.path).await {
+        Ok(diagnostics) => {
+            let mut diagnostics: Vec<Diagnostic> = diagnostics
+                .into_iter()
+                .map(|diagnostic| Diagnostic::from_lsp(&info.path, diagnostic))
+                .collect();
+            retain_severity(&mut diagnostics, info.min_severity);
+            sort_diagnostics(&mut diagnostics);
+            HttpResponse::Ok().json(DiagnosticsResponse { diagnostics })
+        }
+        Err(e) => e.into_http_response(),
+    }
+}
+
+/// Get the diagnostics of the whole workspace
+///
+/// Returns the errors, warnings and hints reported for every file in the workspace, from each
+/// language with diagnostics enabled in `DIAGNOSTICS_LANGUAGES`.
+///
+/// Language servers that support `workspace/diagnostic` are asked for a full report. Others only
+/// report on the files they have analyzed so far, which for most servers are the open files.
+///
+/// Use `min_severity` to only get diagnostics at least that severe.
+#[utoipa::path(
+    get,
+    path = "/workspace/diagnostics",
+    tag = "workspace",
+    params(WorkspaceDiagnosticsRequest),
+    responses(
+        (status = 200, description = "Diagnostics retrieved successfully", body = DiagnosticsResponse),
+        (status = 400, description = "Bad request"),
+        (status = 500, description = "Internal server error"),
+        (status = 501, description = "Diagnostics are not enabled for any language")
+    )
+)]
+pub async fn workspace_diagnostics(
+    data: Data<AppState>,
+    info: Query<WorkspaceDiagnosticsRequest>,
+) -> HttpResponse {
+    info!("Received workspace diagnostics request");
+
+    match data.manager.workspace_diagnostics().await {
+        Ok(files) => {
+            let mut diagnostics: Vec<Diagnostic> = files
+                .into_iter()
+                .flat_map(|(uri, diagnostics)| {
+                    let path = uri_to_relative_path_string(&uri);
+                    diagnostics
+                        .into_iter()
+                        .map(move |diagnostic| Diagnostic::from_lsp(&path, diagnostic))
+                })
+                .collect();
+            retain_severity(&mut diagnostics, info.min_severity);
+            sort_diagnostics(&mut diagnostics);
+            HttpResponse::Ok().json(DiagnosticsResponse { diagnostics })
+        }
+        Err(e) => e.into_http_response(),
+    }
+}
+
+fn retain_severity(diagnostics: &mut Vec<Diagnostic>, min_severity: Option<DiagnosticSeverity>) {
+    if let Some(min_severity) = min_severity {
+        diagnostics.retain(|diagnostic| diagnostic.severity <= min_severity);
+    }
+}
+
+fn sort_diagnostics(diagnostics: &mut [Diagnostic]) {
+    diagnostics.sort_by(|a, b| {
+        a.range
+            .path
+            .cmp(&b.range.path)
+            .then(a.range.range.start.line.cmp(&b.range.range.start.line))
+            .then(
+                a.range
+                    .range
+                    .start
+                    .character
+                    .cmp(&b.range.range.start.character),
+            )
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use actix_web::http::StatusCode;
+    use serial_test::serial;
+
+    use crate::initialize_app_state;
+    use crate::test_utils::{python_sample_path, typescript_sample_path, EnvVarGuard, TestContext};
+
+    #[tokio::test]
+    #[serial]
+    async fn test_python_file_diagnostics() -> Result<(), Box<dyn std::error::Error>> {
+        let _diagnostics_languages = EnvVarGuard::set("DIAGNOSTICS_LANGUAGES", "python");
+        let _context = TestContext::setup(&python_sample_path(), false).await?;
+        let state = initialize_app_state().await?;
+
+        // Break the signature of `main` with a syntax error
+        let original = std::fs::read_to_string(format!("{}/main.py", python_sample_path()))?;
+        state
+            .manager
+            .open_overlays(
+                "test",
+                vec![(
+                    String::from("main.py"),
+                    original.replacen("def main():", "def main(:", 1),
+                )],
+            )
+            .await?;
+
+        let mock_request = Query(FileDiagnosticsRequest {
+            path: String::from("main.py"),
+            min_severity: Some(DiagnosticSeverity::Warning),
+        });
+
+        let response = file_diagnostics(state.clone(), mock_request).await;
+        state.manager.close_overlays("test", None).await?;
+
+        assert_eq!(response.status(), StatusCode::OK, "{:?}", response.body());
+
+        let bytes = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let diagnostics_response: DiagnosticsResponse = serde_json::from_slice(&bytes)?;
+        for diagnostic in &diagnostics_response.diagnostics {
+            assert_eq!(diagnostic.range.path, "main.py");
+            assert!(diagnostic.severity <= DiagnosticSeverity::Warning);
+        }
+        assert!(
+            diagnostics_response.diagnostics.iter().any(|diagnostic| {
+                diagnostic.severity == DiagnosticSeverity::Error
+                    && diagnostic.range.range.start.line == 14
+            }),
+            "{:?}",
+            diagnostics_response.diagnostics
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_diagnostics_not_enabled() -> Result<(), Box<dyn std::error::Error>> {
+        let _diagnostics_languages = EnvVarGuard::set("DIAGNOSTICS_LANGUAGES", "python");
+        let _context = TestContext::setup(&typescript_sample_path(), false).await?;
+        let state = initialize_app_state().await?;
+
+        let mock_request = Query(FileDiagnosticsRequest {
+            path: String::from("src/main.ts"),
+            min_severity: None,
+        });
+
+        let response = file_diagnostics(state, mock_request).await;
+
+        assert_eq!(
+            response.status(),
+            StatusCode::NOT_IMPLEMENTED,
+            "{:?}",
+            response.body()
+        );
+        Ok(())
+    }
+}
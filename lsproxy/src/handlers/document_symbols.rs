@@ -0,0 +1,103 @@
+use actix_web::web::{Data, Query};
+use actix_web::HttpResponse;
+use log::info;
+
+use crate::api_types::{DocumentSymbol, FileSymbolsRequest};
+use crate::handlers::error::IntoHttpResponse;
+use crate::AppState;
+
+/// Get the symbol tree of a file (uses the language server)
+///
+/// Returns the symbols defined in the file as reported by the language server's
+/// `textDocument/documentSymbol`, each with the symbols nested in it, e.g. the fields and
+/// methods of a class.
+///
+/// Unlike `/symbol/definitions-in-file`, which is limited to the kinds covered by its ast-grep
+/// rules, this includes every kind the language server knows of, such as fields, enums, records
+/// and constants.
+///
+/// The returned positions point to the start of the symbol's identifier.
+///
+/// e.g. for `User` on line 0 of `src/main.py`:
+/// ```
+/// 0: class User:
+/// _________^
+/// 1:     def __init__(self, name, age):
+/// 2:         self.name = name
+/// 3:         self.age = age
+/// ```
+/// `User` will have `__init__` as a child, with `User` as its container name.
+#[utoipa::path(
+    get,
+    path = "/symbol/document-symbols",
+    tag = "symbol",
+    params(FileSymbolsRequest),
+    responses(
+        (status = 200, description = "Symbols retrieved successfully", body = Vec<DocumentSymbol>),
+        (status = 400, description = "Bad request"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn document_symbols(
+    data: Data<AppState>,
+    info: Query<FileSymbolsRequest>,
+) -> HttpResponse {
+    info!(
+        "Received document symbols request for file: {}",
+        info.file_path
+    );
+
+    match data.manager.document_symbols(&info.file_path).await {
+        Ok(symbols) => HttpResponse::Ok().json(symbols),
+        Err(e) => e.into_http_response(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use actix_web::http::StatusCode;
+
+    use crate::initialize_app_state;
+    use crate::test_utils::{python_sample_path, TestContext};
+
+    #[tokio::test]
+    async fn test_python_document_symbols() -> Result<(), Box<dyn std::error::Error>> {
+        let _context = TestContext::setup(&python_sample_path(), false).await?;
+        let state = initialize_app_state().await?;
+
+        let mock_request = Query(FileSymbolsRequest {
+            file_path: String::from("graph.py"),
+        });
+
+        let response = document_symbols(state, mock_request).await;
+
+        assert_eq!(response.status(), StatusCode::OK, "{:?}", response.body());
+
+        let bytes = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let symbols: Vec<DocumentSymbol> = serde_json::from_slice(&bytes)?;
+
+        let graph = symbols
+            .iter()
+            .find(|symbol| symbol.symbol.name == "AStarGraph")
+            .expect("AStarGraph should be a top level symbol");
+        assert_eq!(graph.symbol.kind, "class");
+        assert_eq!(graph.symbol.identifier_position.position.line, 12);
+        assert_eq!(graph.symbol.identifier_position.position.character, 6);
+
+        let methods: Vec<&str> = graph
+            .children
+            .iter()
+            .map(|child| child.symbol.name.as_str())
+            .collect();
+        assert!(methods.contains(&"__init__"), "{:?}", methods);
+        assert!(methods.contains(&"heuristic"), "{:?}", methods);
+        for child in &graph.children {
+            assert_eq!(child.container_name.as_deref(), Some("AStarGraph"));
+        }
+        Ok(())
+    }
+}
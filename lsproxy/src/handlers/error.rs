@@ -18,6 +18,10 @@ impl IntoHttpResponse for LspManagerError {
                     error: format!("LSP client not found for {:?}", lang),
                 })
             }
+            Self::LanguageServerUnavailable(lang, status) => HttpResponse::ServiceUnavailable()
+                .json(ErrorResponse {
+                    error: format!("{:?} language server is {}", lang, status),
+                }),
             Self::InternalError(msg) => HttpResponse::InternalServerError().json(ErrorResponse {
                 error: format!("Internal error: {}", msg),
             }),
@@ -27,6 +31,12 @@ impl IntoHttpResponse for LspManagerError {
             Self::NotImplemented(msg) => HttpResponse::NotImplemented().json(ErrorResponse {
                 error: format!("Not implemented: {}", msg),
             }),
+            Self::Conflict(msg) => HttpResponse::Conflict().json(ErrorResponse {
+                error: format!("Conflict: {}", msg),
+            }),
+            Self::Timeout(msg) => HttpResponse::GatewayTimeout().json(ErrorResponse {
+                error: format!("Timeout: {}", msg),
+            }),
         }
     }
 }
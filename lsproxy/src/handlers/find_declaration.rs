@@ -0,0 +1,96 @@
+use crate::api_types::{DefinitionResponse, GetDefinitionRequest};
+use crate::handlers::goto::goto;
+use crate::lsp::manager::GotoTarget;
+use crate::AppState;
+use actix_web::web::{Data, Json};
+use actix_web::HttpResponse;
+
+/// Get the declaration of a symbol
+///
+/// Returns the location where the symbol at the given position is declared, which can differ
+/// from its definition, e.g. a function prototype in a C header.
+///
+/// The input position should point inside the symbol's identifier.
+///
+/// The returned position points to the identifier of the declaration, and the file_path from
+/// workspace root. The response uses the same shape as `find-definition`.
+///
+/// e.g. for the declaration of `print_map` on line 3 of `src/main.c` with the code:
+/// ```
+/// 0: #include "map.h"
+/// 1:
+/// 2: int main() {
+/// 3:     print_map();
+/// input______^^^^^^^^^
+/// ```
+/// The declaration will be `void print_map();` in `src/map.h`.
+#[utoipa::path(
+    post,
+    path = "/symbol/find-declaration",
+    tag = "symbol",
+    request_body = GetDefinitionRequest,
+    responses(
+        (status = 200, description = "Declaration retrieved successfully", body = DefinitionResponse),
+        (status = 400, description = "Bad request"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn find_declaration(
+    data: Data<AppState>,
+    info: Json<GetDefinitionRequest>,
+) -> HttpResponse {
+    goto(data, info.into_inner(), GotoTarget::Declaration).await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use actix_web::http::StatusCode;
+
+    use crate::api_types::{FilePosition, Position};
+    use crate::initialize_app_state;
+    use crate::test_utils::{c_sample_path, TestContext};
+
+    #[tokio::test]
+    async fn test_c_declaration_in_header() -> Result<(), Box<dyn std::error::Error>> {
+        let _context = TestContext::setup(&c_sample_path(), false).await?;
+        let state = initialize_app_state().await?;
+
+        // `print_map(path, p_len, stops);` in `main`
+        let mock_request = Json(GetDefinitionRequest {
+            position: FilePosition {
+                path: String::from("main.c"),
+                position: Position {
+                    line: 15,
+                    character: 8,
+                },
+            },
+            include_source_code: false,
+            include_raw_response: false,
+            overlays: Vec::new(),
+        });
+
+        let response = find_declaration(state, mock_request).await;
+
+        assert_eq!(response.status(), StatusCode::OK, "{:?}", response.body());
+
+        let bytes = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let declaration_response: DefinitionResponse = serde_json::from_slice(&bytes).unwrap();
+
+        assert_eq!(declaration_response.selected_identifier.name, "print_map");
+        assert_eq!(
+            declaration_response.definitions,
+            vec![FilePosition {
+                path: String::from("map.h"),
+                position: Position {
+                    line: 11,
+                    character: 5,
+                },
+            }]
+        );
+        Ok(())
+    }
+}
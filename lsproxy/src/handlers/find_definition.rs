@@ -1,15 +1,10 @@
-use crate::api_types::{CodeContext, ErrorResponse, FileRange, Position, Range};
-use crate::handlers::error::IntoHttpResponse;
-use crate::handlers::utils;
-use crate::lsp::manager::{LspManagerError, Manager};
-use crate::utils::file_utils::uri_to_relative_path_string;
+use crate::api_types::{DefinitionResponse, GetDefinitionRequest};
+use crate::handlers::goto::goto;
+use crate::lsp::manager::GotoTarget;
+use crate::AppState;
 use actix_web::web::{Data, Json};
 use actix_web::HttpResponse;
-use log::{error, info, warn};
 
-use crate::api_types::{DefinitionResponse, GetDefinitionRequest};
-use crate::AppState;
-use lsp_types::{GotoDefinitionResponse, Location, Position as LspPosition, Range as LspRange};
 /// Get the definition of a symbol at a specific position in a file
 ///
 /// Returns the location of the definition for the symbol at the given position.
@@ -44,155 +39,7 @@ pub async fn find_definition(
     data: Data<AppState>,
     info: Json<GetDefinitionRequest>,
 ) -> HttpResponse {
-    info!(
-        "Received definition request for file: {}, line: {}, character: {}",
-        info.position.path, info.position.position.line, info.position.position.character
-    );
-
-    let file_identifiers = match data.manager.get_file_identifiers(&info.position.path).await {
-        Ok(identifiers) => identifiers,
-        Err(e) => {
-            error!("Failed to get file identifiers: {:?}", e);
-            return HttpResponse::InternalServerError().json(ErrorResponse {
-                error: format!("Failed to get file identifiers: {}", e),
-            });
-        }
-    };
-    let identifier =
-        match utils::find_identifier_at_position(file_identifiers, &info.position).await {
-            Ok(identifier) => identifier,
-            Err(e) => {
-                error!("Failed to find definition from position: {:?}", e);
-                return HttpResponse::BadRequest().json(ErrorResponse {
-                    error: format!("Failed to find definition from position: {}", e),
-                });
-            }
-        };
-
-    let definitions = match data
-        .manager
-        .find_definition(
-            &info.position.path,
-            LspPosition {
-                line: info.position.position.line,
-                character: info.position.position.character,
-            },
-        )
-        .await
-    {
-        Ok(definitions) => definitions,
-        Err(e) => {
-            return e.into_http_response();
-        }
-    };
-
-    let source_code_context = if info.include_source_code {
-        match fetch_definition_source_code(&data.manager, &definitions).await {
-            Ok(context) => Some(context),
-            Err(e) => {
-                error!("Failed to fetch definition source code: {:?}", e);
-                None
-            }
-        }
-    } else {
-        None
-    };
-
-    HttpResponse::Ok().json(DefinitionResponse {
-        raw_response: if info.include_raw_response {
-            Some(serde_json::to_value(&definitions).unwrap())
-        } else {
-            None
-        },
-        definitions: match &definitions {
-            GotoDefinitionResponse::Scalar(location) => vec![location.clone().into()],
-            GotoDefinitionResponse::Array(locations) => {
-                locations.iter().map(|l| l.clone().into()).collect()
-            }
-            GotoDefinitionResponse::Link(links) => links.iter().map(|l| l.clone().into()).collect(),
-        },
-        source_code_context,
-        selected_identifier: identifier,
-    })
-}
-
-async fn fetch_definition_source_code(
-    manager: &Manager,
-    definitions_response: &GotoDefinitionResponse,
-) -> Result<Vec<CodeContext>, LspManagerError> {
-    let mut code_contexts = Vec::new();
-    let definitions: &Vec<Location> = match definitions_response {
-        GotoDefinitionResponse::Scalar(definition) => &vec![definition.clone()],
-        GotoDefinitionResponse::Array(definitions) => definitions,
-        GotoDefinitionResponse::Link(links) => &links
-            .iter()
-            .map(|link| Location::new(link.target_uri.clone(), link.target_range))
-            .collect::<Vec<Location>>(),
-    };
-
-    for definition in definitions {
-        let relative_path = uri_to_relative_path_string(&definition.uri);
-        let file_symbols = manager.definitions_in_file_ast_grep(&relative_path).await?;
-        let symbol = file_symbols.iter().find(|s| {
-            s.get_identifier_range().start.line == definition.range.start.line
-                && s.get_identifier_range().start.column == definition.range.start.character
-        });
-
-        let source_code_context = match symbol {
-            Some(ast_grep_match) => CodeContext {
-                range: FileRange {
-                    path: relative_path,
-                    range: Range {
-                        start: Position {
-                            line: ast_grep_match.get_context_range().start.line,
-                            character: ast_grep_match.get_context_range().start.column,
-                        },
-                        end: Position {
-                            line: ast_grep_match.get_context_range().end.line,
-                            character: ast_grep_match.get_context_range().end.column,
-                        },
-                    },
-                },
-                source_code: ast_grep_match.get_source_code(),
-            },
-            None => {
-                warn!("Symbol not found for definition: {:?}", definition);
-                warn!("No exact match in file symbols (likely filtered out). Returning an approximate range instead.");
-                let range = LspRange {
-                    start: LspPosition {
-                        line: definition.range.start.line.saturating_sub(3),
-                        character: 0,
-                    },
-                    end: LspPosition {
-                        line: definition.range.end.line + 3,
-                        character: 0,
-                    },
-                };
-                let source_code = manager
-                    .read_source_code(&relative_path, Some(range))
-                    .await?;
-                CodeContext {
-                    range: FileRange {
-                        path: relative_path,
-                        range: Range {
-                            start: Position {
-                                line: definition.range.start.line.saturating_sub(3),
-                                character: 0,
-                            },
-                            end: Position {
-                                line: definition.range.end.line + 3,
-                                character: 0,
-                            },
-                        },
-                    },
-                    source_code,
-                }
-            }
-        };
-
-        code_contexts.push(source_code_context);
-    }
-    Ok(code_contexts)
+    goto(data, info.into_inner(), GotoTarget::Definition).await
 }
 
 #[cfg(test)]
@@ -201,7 +48,10 @@ mod test {
 
     use actix_web::http::StatusCode;
 
-    use crate::api_types::{FilePosition, Identifier, Position};
+    use crate::api_types::{
+        CodeContext, ErrorResponse, FileOverlay, FilePosition, FileRange, Identifier, Position,
+        Range,
+    };
     use crate::initialize_app_state;
     use crate::test_utils::{python_sample_path, TestContext};
 
@@ -220,6 +70,7 @@ mod test {
             },
             include_source_code: true,
             include_raw_response: false,
+            overlays: Vec::new(),
         });
 
         let response = find_definition(state, mock_request).await;
@@ -287,6 +138,63 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_python_definition_with_inline_overlay() -> Result<(), Box<dyn std::error::Error>>
+    {
+        let _context = TestContext::setup(&python_sample_path(), false).await?;
+        let state = initialize_app_state().await?;
+
+        // The import of `AStarGraph` moves down a line in the unsaved `main.py`
+        let original = std::fs::read_to_string(format!("{}/main.py", python_sample_path()))?;
+        let mock_request = Json(GetDefinitionRequest {
+            position: FilePosition {
+                path: String::from("main.py"),
+                position: Position {
+                    line: 2,
+                    character: 18,
+                },
+            },
+            include_source_code: false,
+            include_raw_response: false,
+            overlays: vec![FileOverlay {
+                path: String::from("main.py"),
+                text: format!("\n{}", original),
+            }],
+        });
+
+        let response = find_definition(state, mock_request).await;
+
+        assert_eq!(response.status(), StatusCode::OK, "{:?}", response.body());
+
+        let bytes = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let definition_response: DefinitionResponse = serde_json::from_slice(&bytes)?;
+        assert_eq!(definition_response.selected_identifier.name, "AStarGraph");
+        assert_eq!(
+            definition_response
+                .selected_identifier
+                .file_range
+                .range
+                .start,
+            Position {
+                line: 2,
+                character: 18,
+            }
+        );
+        assert_eq!(
+            definition_response.definitions,
+            vec![FilePosition {
+                path: String::from("graph.py"),
+                position: Position {
+                    line: 12,
+                    character: 6,
+                },
+            }]
+        );
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_invalid_position() -> Result<(), Box<dyn std::error::Error>> {
         let _context = TestContext::setup(&python_sample_path(), false).await?;
@@ -302,6 +210,7 @@ mod test {
             },
             include_source_code: false,
             include_raw_response: false,
+            overlays: Vec::new(),
         });
 
         let response = find_definition(state, mock_request).await;
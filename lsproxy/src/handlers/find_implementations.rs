@@ -0,0 +1,99 @@
+use crate::api_types::{DefinitionResponse, GetDefinitionRequest};
+use crate::handlers::goto::goto;
+use crate::lsp::manager::GotoTarget;
+use crate::AppState;
+use actix_web::web::{Data, Json};
+use actix_web::HttpResponse;
+
+/// Find the implementations of an interface, abstract class or method
+///
+/// Returns the locations of the concrete types or methods implementing the symbol at the given
+/// position, where `find-definition` would return the abstract declaration itself.
+///
+/// The input position should point inside the symbol's identifier.
+///
+/// The returned positions point to the identifier of each implementation, and the file_path from
+/// workspace root. The response uses the same shape as `find-definition`.
+///
+/// e.g. for the implementations of `Shape` on line 0 of `src/shapes.ts` with the code:
+/// ```
+/// 0: interface Shape {
+/// input________^^^^^
+/// 1:     area(): number;
+/// 2: }
+/// 3:
+/// 4: class Circle implements Shape {
+/// output___^
+/// ```
+#[utoipa::path(
+    post,
+    path = "/symbol/find-implementations",
+    tag = "symbol",
+    request_body = GetDefinitionRequest,
+    responses(
+        (status = 200, description = "Implementations retrieved successfully", body = DefinitionResponse),
+        (status = 400, description = "Bad request"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn find_implementations(
+    data: Data<AppState>,
+    info: Json<GetDefinitionRequest>,
+) -> HttpResponse {
+    goto(data, info.into_inner(), GotoTarget::Implementation).await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use actix_web::http::StatusCode;
+
+    use crate::api_types::{FilePosition, Position};
+    use crate::initialize_app_state;
+    use crate::test_utils::{typescript_sample_path, TestContext};
+
+    #[tokio::test]
+    async fn test_typescript_interface_implementations() -> Result<(), Box<dyn std::error::Error>> {
+        let _context = TestContext::setup(&typescript_sample_path(), false).await?;
+        let state = initialize_app_state().await?;
+
+        let mock_request = Json(GetDefinitionRequest {
+            position: FilePosition {
+                path: String::from("src/astar.ts"),
+                position: Position {
+                    line: 4,
+                    character: 10,
+                },
+            },
+            include_source_code: false,
+            include_raw_response: false,
+            overlays: Vec::new(),
+        });
+
+        let response = find_implementations(state, mock_request).await;
+
+        assert_eq!(response.status(), StatusCode::OK, "{:?}", response.body());
+
+        let bytes = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let implementations_response: DefinitionResponse = serde_json::from_slice(&bytes).unwrap();
+
+        assert_eq!(
+            implementations_response.selected_identifier.name,
+            "IAStarPathfinder"
+        );
+        assert_eq!(
+            implementations_response.definitions,
+            vec![FilePosition {
+                path: String::from("src/astar.ts"),
+                position: Position {
+                    line: 8,
+                    character: 13,
+                },
+            }]
+        );
+        Ok(())
+    }
+}
@@ -2,6 +2,7 @@ use crate::api_types::{
     ErrorResponse, FilePosition, GetReferencedSymbolsRequest, Identifier, Position,
     ReferenceWithSymbolDefinitions, ReferencedSymbolsResponse,
 };
+use crate::handlers::utils;
 use crate::utils::file_utils::uri_to_relative_path_string;
 use crate::AppState;
 use actix_web::web::{Data, Json};
@@ -52,6 +53,10 @@ pub async fn find_referenced_symbols(
         info.identifier_position.position.character
     );
 
+    utils::with_inline_overlays(&data.manager, &info.overlays, respond(&data, &info)).await
+}
+
+async fn respond(data: &Data<AppState>, info: &GetReferencedSymbolsRequest) -> HttpResponse {
     let referenecd_ast_symbols = match data
         .manager
         .find_referenced_symbols(
@@ -110,18 +115,7 @@ pub async fn find_referenced_symbols(
             })
             .collect();
 
-    // First get the workspace files
-    let files = match data.manager.list_files().await {
-        Ok(files) => files,
-        Err(e) => {
-            error!("Failed to list workspace files: {:?}", e);
-            return HttpResponse::InternalServerError().json(ErrorResponse {
-                error: format!("Failed to list workspace files: {}", e),
-            });
-        }
-    };
-
-    // Then categorize the definitions
+    // Categorize the definitions by whether they are in the workspace
     let mut workspace_symbols = Vec::new();
     let mut external_symbols = Vec::new();
     let mut not_found = Vec::new();
@@ -131,10 +125,15 @@ pub async fn find_referenced_symbols(
             not_found.push(identifier);
         } else {
             // Check if any definition is in workspace files
-            let has_internal_definition = definitions.iter().any(|def| files.contains(&def.path));
-            if has_internal_definition {
+            let mut internal_definitions = Vec::new();
+            for def in &definitions {
+                if data.manager.is_workspace_file(&def.path).await {
+                    internal_definitions.push(def);
+                }
+            }
+            if !internal_definitions.is_empty() {
                 let mut symbols_with_definitions = Vec::new();
-                for def in definitions.iter().filter(|def| files.contains(&def.path)) {
+                for def in internal_definitions {
                     if let Ok(symbol) = data
                         .manager
                         .get_symbol_from_position(
@@ -225,11 +224,16 @@ mod test {
     use super::*;
 
     use actix_web::http::StatusCode;
-    use tokio::time::{sleep, Duration};
+    use std::time::Duration;
 
     use crate::api_types::{FilePosition, FileRange, Position, Range, Symbol};
     use crate::initialize_app_state;
-    use crate::test_utils::{csharp_sample_path, python_sample_path, TestContext};
+    use crate::lsp::manager::INDEXING_WAIT;
+    use crate::test_utils::{
+        c_sample_path, cpp_sample_path, csharp_sample_path, go_sample_path, java_sample_path,
+        js_sample_path, php_sample_path, python_sample_path, ruby_sample_path, rust_sample_path,
+        TestContext,
+    };
 
     #[tokio::test]
     async fn test_csharp_referenced_symbols() -> Result<(), Box<dyn std::error::Error>> {
@@ -245,11 +249,10 @@ mod test {
                 },
             },
             full_scan: false,
+            overlays: Vec::new(),
         });
 
-        sleep(Duration::from_secs(5)).await;
-
-        let response = find_referenced_symbols(state, mock_request).await;
+        let response = find_referenced_symbols_when_ready(state, mock_request).await;
         assert_eq!(
             response.status(),
             StatusCode::OK,
@@ -780,11 +783,10 @@ mod test {
                 },
             },
             full_scan: false,
+            overlays: Vec::new(),
         });
 
-        sleep(Duration::from_secs(5)).await;
-
-        let response = find_referenced_symbols(state, mock_request).await;
+        let response = find_referenced_symbols_when_ready(state, mock_request).await;
         assert_eq!(
             response.status(),
             StatusCode::OK,
@@ -1272,11 +1274,10 @@ mod test {
                 },
             },
             full_scan: false,
+            overlays: Vec::new(),
         });
 
-        sleep(Duration::from_secs(5)).await;
-
-        let response = find_referenced_symbols(state, mock_request).await;
+        let response = find_referenced_symbols_when_ready(state, mock_request).await;
         assert_eq!(
             response.status(),
             StatusCode::OK,
@@ -1799,4 +1800,274 @@ mod test {
         assert_eq!(sorted_response, sorted_expected);
         Ok(())
     }
+
+    /// Finds the referenced symbols once the language server finished indexing, like a request
+    /// with the `X-Wait-For-Indexing` header.
+    async fn find_referenced_symbols_when_ready(
+        state: Data<AppState>,
+        request: Json<GetReferencedSymbolsRequest>,
+    ) -> HttpResponse {
+        INDEXING_WAIT
+            .scope(
+                Duration::from_secs(60),
+                find_referenced_symbols(state, request),
+            )
+            .await
+    }
+
+    /// The name and kind of each reference, in order.
+    fn names_and_kinds(identifiers: &[&Identifier]) -> Vec<(String, String)> {
+        identifiers
+            .iter()
+            .map(|identifier| {
+                (
+                    identifier.name.clone(),
+                    identifier.kind.clone().unwrap_or_default(),
+                )
+            })
+            .collect()
+    }
+
+    /// The name and kind of every symbol referenced from the symbol at a position, in the order
+    /// they appear, whether or not their definition was found.
+    async fn referenced_names_and_kinds(
+        sample_path: &str,
+        path: &str,
+        line: u32,
+        character: u32,
+    ) -> Result<Vec<(String, String)>, Box<dyn std::error::Error>> {
+        let _context = TestContext::setup(sample_path, false).await?;
+        let state = initialize_app_state().await?;
+
+        let mock_request = Json(GetReferencedSymbolsRequest {
+            identifier_position: FilePosition {
+                path: String::from(path),
+                position: Position { line, character },
+            },
+            full_scan: false,
+            overlays: Vec::new(),
+        });
+
+        let response = find_referenced_symbols_when_ready(state, mock_request).await;
+        assert_eq!(
+            response.status(),
+            StatusCode::OK,
+            "Response: {:?}",
+            response
+        );
+        let bytes = actix_web::body::to_bytes(response.into_body()).await?;
+        let referenced_symbols_response: ReferencedSymbolsResponse =
+            serde_json::from_slice(&bytes)?;
+
+        let mut references: Vec<&Identifier> = referenced_symbols_response
+            .workspace_symbols
+            .iter()
+            .map(|symbol| &symbol.reference)
+            .chain(&referenced_symbols_response.external_symbols)
+            .chain(&referenced_symbols_response.not_found)
+            .collect();
+        references.sort_by_key(|identifier| {
+            (
+                identifier.file_range.range.start.line,
+                identifier.file_range.range.start.character,
+            )
+        });
+        Ok(names_and_kinds(&references))
+    }
+
+    #[tokio::test]
+    async fn test_go_referenced_symbols() -> Result<(), Box<dyn std::error::Error>> {
+        let references = referenced_names_and_kinds(&go_sample_path(), "main.go", 7, 5).await?;
+        assert_eq!(
+            references,
+            vec![
+                (String::from("NewGrid"), String::from("function-call")),
+                (String::from("Node"), String::from("class-instantiation")),
+                (String::from("Node"), String::from("class-instantiation")),
+                (String::from("Printf"), String::from("function-call")),
+                (String::from("FindPath"), String::from("function-call")),
+                (String::from("Println"), String::from("function-call")),
+                (String::from("Printf"), String::from("function-call")),
+                (String::from("Printf"), String::from("function-call")),
+                (String::from("Println"), String::from("function-call")),
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_java_referenced_symbols() -> Result<(), Box<dyn std::error::Error>> {
+        let references =
+            referenced_names_and_kinds(&java_sample_path(), "AStar.java", 39, 22).await?;
+        assert_eq!(
+            references,
+            vec![
+                (String::from("add"), String::from("method-call")),
+                (
+                    String::from("addNeigborsToOpenList"),
+                    String::from("function-call")
+                ),
+                (String::from("isEmpty"), String::from("method-call")),
+                (String::from("get"), String::from("method-call")),
+                (String::from("remove"), String::from("method-call")),
+                (String::from("add"), String::from("method-call")),
+                (
+                    String::from("addNeigborsToOpenList"),
+                    String::from("function-call")
+                ),
+                (String::from("add"), String::from("method-call")),
+                (String::from("add"), String::from("method-call")),
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_php_referenced_symbols() -> Result<(), Box<dyn std::error::Error>> {
+        let references =
+            referenced_names_and_kinds(&php_sample_path(), "AStar.php", 50, 21).await?;
+        assert_eq!(
+            references,
+            vec![
+                (String::from("Node"), String::from("class-instantiation")),
+                (String::from("distance"), String::from("method-call")),
+                (String::from("count"), String::from("function-call")),
+                (String::from("count"), String::from("function-call")),
+                (
+                    String::from("findNeighborInList"),
+                    String::from("method-call")
+                ),
+                (
+                    String::from("findNeighborInList"),
+                    String::from("method-call")
+                ),
+                (String::from("usort"), String::from("function-call")),
+                (String::from("compareTo"), String::from("method-call")),
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rust_referenced_symbols() -> Result<(), Box<dyn std::error::Error>> {
+        let references =
+            referenced_names_and_kinds(&rust_sample_path(), "src/astar.rs", 88, 11).await?;
+        assert_eq!(
+            references,
+            vec![
+                (String::from("push"), String::from("method-call")),
+                (String::from("Node"), String::from("class-instantiation")),
+                (String::from("new"), String::from("function-call")),
+                (String::from("calc_dist"), String::from("method-call")),
+                (String::from("is_empty"), String::from("method-call")),
+                (String::from("sort_by"), String::from("method-call")),
+                (String::from("cmp"), String::from("method-call")),
+                (String::from("remove"), String::from("method-call")),
+                (String::from("push"), String::from("method-call")),
+                (String::from("clone"), String::from("method-call")),
+                (String::from("fill_open"), String::from("method-call")),
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cpp_referenced_symbols() -> Result<(), Box<dyn std::error::Error>> {
+        let references =
+            referenced_names_and_kinds(&cpp_sample_path(), "astar_search.cpp", 2, 4).await?;
+        assert_eq!(
+            references,
+            vec![
+                (String::from("search"), String::from("method-call")),
+                (String::from("path"), String::from("method-call")),
+                (String::from("m"), String::from("function-call")),
+                (String::from("find"), String::from("function-call")),
+                (String::from("begin"), String::from("method-call")),
+                (String::from("end"), String::from("method-call")),
+                (String::from("point"), String::from("function-call")),
+                (String::from("end"), String::from("method-call")),
+                (String::from("begin"), String::from("method-call")),
+                (String::from("end"), String::from("method-call")),
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_c_referenced_symbols() -> Result<(), Box<dyn std::error::Error>> {
+        let references = referenced_names_and_kinds(&c_sample_path(), "main.c", 5, 4).await?;
+        assert_eq!(
+            references,
+            vec![
+                (String::from("init_stops"), String::from("function-call")),
+                (String::from("init_routes"), String::from("function-call")),
+                (String::from("find_path"), String::from("function-call")),
+                (String::from("print_map"), String::from("function-call")),
+                (String::from("printf"), String::from("function-call")),
+                (String::from("printf"), String::from("function-call")),
+                (String::from("puts"), String::from("function-call")),
+                (String::from("free"), String::from("function-call")),
+                (String::from("free"), String::from("function-call")),
+                (String::from("free"), String::from("function-call")),
+                (String::from("free"), String::from("function-call")),
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_ruby_referenced_symbols() -> Result<(), Box<dyn std::error::Error>> {
+        let references = referenced_names_and_kinds(&ruby_sample_path(), "main.rb", 35, 4).await?;
+        assert_eq!(
+            references,
+            vec![
+                (
+                    String::from("AStarGraph"),
+                    String::from("class-instantiation")
+                ),
+                (
+                    String::from("AStarSearch"),
+                    String::from("class-instantiation")
+                ),
+                (String::from("search"), String::from("method-call")),
+                (String::from("puts"), String::from("function-call")),
+                (String::from("puts"), String::from("function-call")),
+                (
+                    String::from("PathPlotter"),
+                    String::from("class-instantiation")
+                ),
+                (String::from("plot_path"), String::from("method-call")),
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_js_referenced_symbols() -> Result<(), Box<dyn std::error::Error>> {
+        let references =
+            referenced_names_and_kinds(&js_sample_path(), "astar_search.js", 4, 9).await?;
+        assert_eq!(
+            references,
+            vec![
+                (String::from("Array"), String::from("function-call")),
+                (String::from("fill"), String::from("method-call")),
+                (String::from("Array"), String::from("function-call")),
+                (String::from("fill"), String::from("method-call")),
+                (String::from("manhattan"), String::from("function-call")),
+                (String::from("filter"), String::from("method-call")),
+                (String::from("find"), String::from("method-call")),
+                (String::from("lambda"), String::from("function-call")),
+                (String::from("concat"), String::from("method-call")),
+                (String::from("slice"), String::from("method-call")),
+                (String::from("filter"), String::from("method-call")),
+                (String::from("forEach"), String::from("method-call")),
+                (String::from("manhattan"), String::from("function-call")),
+                (String::from("slice"), String::from("method-call")),
+                (String::from("filter"), String::from("method-call")),
+                (String::from("sort"), String::from("method-call")),
+                (String::from("aStar"), String::from("function-call")),
+            ]
+        );
+        Ok(())
+    }
 }
@@ -54,6 +54,10 @@ pub async fn find_references(
         info.identifier_position.position.character
     );
 
+    utils::with_inline_overlays(&data.manager, &info.overlays, respond(&data, &info)).await
+}
+
+async fn respond(data: &Data<AppState>, info: &GetReferencesRequest) -> HttpResponse {
     let file_identifiers = match data
         .manager
         .get_file_identifiers(&info.identifier_position.path)
@@ -144,14 +148,13 @@ async fn find_and_filter_references(
         )
         .await?;
 
-    let files = manager.list_files().await?;
-    let mut filtered_refs: Vec<_> = references
-        .into_iter()
-        .filter(|reference| {
-            let path = uri_to_relative_path_string(&reference.uri);
-            files.contains(&path)
-        })
-        .collect();
+    let mut filtered_refs = Vec::new();
+    for reference in references {
+        let path = uri_to_relative_path_string(&reference.uri);
+        if manager.is_workspace_file(&path).await {
+            filtered_refs.push(reference);
+        }
+    }
 
     filtered_refs.sort_by(|a, b| {
         let uri_cmp = a.uri.to_string().cmp(&b.uri.to_string());
@@ -253,6 +256,7 @@ mod test {
             },
             include_code_context_lines: None,
             include_raw_response: false,
+            overlays: Vec::new(),
         });
 
         let response = find_references(state, mock_request).await;
@@ -362,6 +366,7 @@ mod test {
             },
             include_code_context_lines: None,
             include_raw_response: false,
+            overlays: Vec::new(),
         });
 
         sleep(Duration::from_secs(5)).await;
@@ -471,6 +476,7 @@ mod test {
             },
             include_code_context_lines: None,
             include_raw_response: false,
+            overlays: Vec::new(),
         });
 
         let response = find_references(state, mock_request).await;
@@ -572,6 +578,7 @@ mod test {
             },
             include_code_context_lines: None,
             include_raw_response: false,
+            overlays: Vec::new(),
         });
 
         let response = find_references(state, mock_request).await;
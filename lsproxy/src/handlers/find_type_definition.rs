@@ -0,0 +1,98 @@
+use crate::api_types::{DefinitionResponse, GetDefinitionRequest};
+use crate::handlers::goto::goto;
+use crate::lsp::manager::GotoTarget;
+use crate::AppState;
+use actix_web::web::{Data, Json};
+use actix_web::HttpResponse;
+
+/// Get the definition of the type of a symbol
+///
+/// Returns the location of the type of the symbol at the given position, e.g. the class of a
+/// variable rather than the variable itself.
+///
+/// The input position should point inside the symbol's identifier.
+///
+/// The returned position points to the identifier of the type, and the file_path from workspace
+/// root. The response uses the same shape as `find-definition`.
+///
+/// e.g. for the type definition of `user` on line 5 of `src/main.ts` with the code:
+/// ```
+/// 0: class User {
+/// output___^
+/// 1:     constructor(public name: string) {}
+/// 2: }
+/// 3:
+/// 4: const user = new User("John");
+/// 5: console.log(user.name);
+/// input_______^^^^
+/// ```
+#[utoipa::path(
+    post,
+    path = "/symbol/find-type-definition",
+    tag = "symbol",
+    request_body = GetDefinitionRequest,
+    responses(
+        (status = 200, description = "Type definition retrieved successfully", body = DefinitionResponse),
+        (status = 400, description = "Bad request"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn find_type_definition(
+    data: Data<AppState>,
+    info: Json<GetDefinitionRequest>,
+) -> HttpResponse {
+    goto(data, info.into_inner(), GotoTarget::TypeDefinition).await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use actix_web::http::StatusCode;
+
+    use crate::api_types::{FilePosition, Position};
+    use crate::initialize_app_state;
+    use crate::test_utils::{typescript_sample_path, TestContext};
+
+    #[tokio::test]
+    async fn test_typescript_type_definition() -> Result<(), Box<dyn std::error::Error>> {
+        let _context = TestContext::setup(&typescript_sample_path(), false).await?;
+        let state = initialize_app_state().await?;
+
+        // `private maze: Grid,` in the `AStar` constructor
+        let mock_request = Json(GetDefinitionRequest {
+            position: FilePosition {
+                path: String::from("src/astar.ts"),
+                position: Position {
+                    line: 15,
+                    character: 16,
+                },
+            },
+            include_source_code: false,
+            include_raw_response: false,
+            overlays: Vec::new(),
+        });
+
+        let response = find_type_definition(state, mock_request).await;
+
+        assert_eq!(response.status(), StatusCode::OK, "{:?}", response.body());
+
+        let bytes = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let type_definition_response: DefinitionResponse = serde_json::from_slice(&bytes).unwrap();
+
+        assert_eq!(type_definition_response.selected_identifier.name, "maze");
+        assert_eq!(
+            type_definition_response.definitions,
+            vec![FilePosition {
+                path: String::from("src/types.ts"),
+                position: Position {
+                    line: 5,
+                    character: 12,
+                },
+            }]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,87 @@
+use crate::api_types::{ErrorResponse, GetDefinitionRequest};
+use crate::handlers::error::IntoHttpResponse;
+use crate::handlers::utils;
+use crate::lsp::manager::GotoTarget;
+use crate::AppState;
+use actix_web::web::Data;
+use actix_web::HttpResponse;
+use log::{error, info};
+use lsp_types::Position as LspPosition;
+
+/// Answers the goto endpoints (definition, implementations, type definition and declaration),
+/// which only differ in the request they send to the language server.
+pub(crate) async fn goto(
+    data: Data<AppState>,
+    info: GetDefinitionRequest,
+    target: GotoTarget,
+) -> HttpResponse {
+    let target_name = target.to_string().to_lowercase();
+    info!(
+        "Received {} request for file: {}, line: {}, character: {}",
+        target_name,
+        info.position.path,
+        info.position.position.line,
+        info.position.position.character
+    );
+
+    utils::with_inline_overlays(
+        &data.manager,
+        &info.overlays,
+        respond(&data, &info, target, &target_name),
+    )
+    .await
+}
+
+async fn respond(
+    data: &Data<AppState>,
+    info: &GetDefinitionRequest,
+    target: GotoTarget,
+    target_name: &str,
+) -> HttpResponse {
+    let file_identifiers = match data.manager.get_file_identifiers(&info.position.path).await {
+        Ok(identifiers) => identifiers,
+        Err(e) => {
+            error!("Failed to get file identifiers: {:?}", e);
+            return e.into_http_response();
+        }
+    };
+    let identifier =
+        match utils::find_identifier_at_position(file_identifiers, &info.position).await {
+            Ok(identifier) => identifier,
+            Err(e) => {
+                error!("Failed to find {} from position: {:?}", target_name, e);
+                return HttpResponse::BadRequest().json(ErrorResponse {
+                    error: format!("Failed to find {} from position: {}", target_name, e),
+                });
+            }
+        };
+
+    let definitions = match data
+        .manager
+        .goto(
+            target,
+            &info.position.path,
+            LspPosition {
+                line: info.position.position.line,
+                character: info.position.position.character,
+            },
+        )
+        .await
+    {
+        Ok(definitions) => definitions,
+        Err(e) => {
+            return e.into_http_response();
+        }
+    };
+
+    HttpResponse::Ok().json(
+        utils::definition_response(
+            &data.manager,
+            definitions,
+            identifier,
+            info.include_source_code,
+            info.include_raw_response,
+        )
+        .await,
+    )
+}
@@ -8,7 +8,16 @@ const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 /// Get health status of the LSP proxy service
 ///
-/// Returns the service status, version and language server availability
+/// Returns the service status, version, language server availability and document cache
+/// statistics.
+///
+/// Language servers still indexing the workspace right after they started are reported as
+/// `indexing` in `language_servers`, as their results may be partial until then. Set the
+/// `X-Wait-For-Indexing` header on a request to wait up to that many seconds, at most 600, for
+/// its language servers to finish indexing.
+///
+/// Language servers that stop are restarted automatically: `language_servers` reports those that
+/// are `restarting`, and those that stopped too many times in a row as `crashed`.
 #[utoipa::path(
     get,
     path = "/system/health",
@@ -39,5 +48,7 @@ pub async fn health_check(data: Data<AppState>) -> HttpResponse {
         status: "ok".to_string(),
         version: VERSION.to_string(),
         languages,
+        language_servers: data.manager.language_server_statuses(),
+        document_cache: data.manager.document_cache_stats().await,
     })
 }
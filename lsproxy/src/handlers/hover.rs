@@ -0,0 +1,166 @@
+use actix_web::web::{Data, Json};
+use actix_web::HttpResponse;
+use log::{error, info};
+use lsp_types::Position as LspPosition;
+
+use crate::api_types::{ErrorResponse, GetHoverRequest, HoverContent, HoverResponse};
+use crate::handlers::error::IntoHttpResponse;
+use crate::handlers::utils;
+use crate::AppState;
+
+/// Get hover information for a symbol at a specific position in a file
+///
+/// Returns the type signature and documentation the language server shows when hovering over
+/// the symbol, e.g. the inferred type of a variable or the docstring of a function.
+///
+/// The input position should point inside the symbol's identifier.
+///
+/// The contents are normalized into a single markdown or plaintext document.
+///
+/// e.g. for `User` on line 5 of `src/main.py` with the code:
+/// ```
+/// 0: class User:
+/// 1:     """A registered user of the service."""
+/// 2:     def __init__(self, name, age):
+/// 3:         self.name = name
+/// 4:
+/// 5: user = User("John", 30)
+/// input_____^^^^
+/// ```
+/// The contents will contain the signature of `User` and its docstring.
+#[utoipa::path(
+    post,
+    path = "/symbol/hover",
+    tag = "symbol",
+    request_body = GetHoverRequest,
+    responses(
+        (status = 200, description = "Hover information retrieved successfully", body = HoverResponse),
+        (status = 400, description = "Bad request"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn hover(data: Data<AppState>, info: Json<GetHoverRequest>) -> HttpResponse {
+    info!(
+        "Received hover request for file: {}, line: {}, character: {}",
+        info.position.path, info.position.position.line, info.position.position.character
+    );
+
+    utils::with_inline_overlays(&data.manager, &info.overlays, respond(&data, &info)).await
+}
+
+async fn respond(data: &Data<AppState>, info: &GetHoverRequest) -> HttpResponse {
+    let file_identifiers = match data.manager.get_file_identifiers(&info.position.path).await {
+        Ok(identifiers) => identifiers,
+        Err(e) => {
+            error!("Failed to get file identifiers: {:?}", e);
+            return e.into_http_response();
+        }
+    };
+    let identifier =
+        match utils::find_identifier_at_position(file_identifiers, &info.position).await {
+            Ok(identifier) => identifier,
+            Err(e) => {
+                error!("Failed to find hover from position: {:?}", e);
+                return HttpResponse::BadRequest().json(ErrorResponse {
+                    error: format!("Failed to find hover from position: {}", e),
+                });
+            }
+        };
+
+    let hover = match data
+        .manager
+        .hover(
+            &info.position.path,
+            LspPosition {
+                line: info.position.position.line,
+                character: info.position.position.character,
+            },
+        )
+        .await
+    {
+        Ok(hover) => hover,
+        Err(e) => {
+            return e.into_http_response();
+        }
+    };
+
+    HttpResponse::Ok().json(HoverResponse {
+        raw_response: if info.include_raw_response {
+            Some(serde_json::to_value(&hover).unwrap())
+        } else {
+            None
+        },
+        contents: hover.map(|hover| HoverContent::from(hover.contents)),
+        selected_identifier: identifier,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use actix_web::http::StatusCode;
+
+    use crate::api_types::{FilePosition, HoverContentKind, Position};
+    use crate::initialize_app_state;
+    use crate::test_utils::{python_sample_path, TestContext};
+
+    #[tokio::test]
+    async fn test_python_hover() -> Result<(), Box<dyn std::error::Error>> {
+        let _context = TestContext::setup(&python_sample_path(), false).await?;
+        let state = initialize_app_state().await?;
+
+        let mock_request = Json(GetHoverRequest {
+            position: FilePosition {
+                path: String::from("main.py"),
+                position: Position {
+                    line: 1,
+                    character: 18,
+                },
+            },
+            include_raw_response: false,
+            overlays: Vec::new(),
+        });
+
+        let response = hover(state, mock_request).await;
+
+        assert_eq!(response.status(), StatusCode::OK, "{:?}", response.body());
+
+        let body = response.into_body();
+        let bytes = actix_web::body::to_bytes(body).await.unwrap();
+        let hover_response: HoverResponse = serde_json::from_slice(&bytes).unwrap();
+
+        assert_eq!(hover_response.selected_identifier.name, "AStarGraph");
+        let contents = hover_response.contents.ok_or("Expected hover contents")?;
+        assert_eq!(contents.kind, HoverContentKind::Markdown);
+        assert!(
+            contents.value.contains("AStarGraph"),
+            "Unexpected hover contents: {}",
+            contents.value
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_invalid_position() -> Result<(), Box<dyn std::error::Error>> {
+        let _context = TestContext::setup(&python_sample_path(), false).await?;
+        let state = initialize_app_state().await?;
+
+        let mock_request = Json(GetHoverRequest {
+            position: FilePosition {
+                path: String::from("main.py"),
+                position: Position {
+                    line: 0,
+                    character: 999,
+                },
+            },
+            include_raw_response: false,
+            overlays: Vec::new(),
+        });
+
+        let response = hover(state, mock_request).await;
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        Ok(())
+    }
+}
@@ -1,15 +1,36 @@
+mod call_hierarchy;
+mod code_actions;
+mod completions;
 mod definitions_in_file;
+mod diagnostics;
+mod document_symbols;
 mod error;
+mod find_declaration;
 mod find_definition;
 mod find_identifier;
+mod find_implementations;
 mod find_referenced_symbols;
 mod find_references;
+mod find_type_definition;
 mod health;
+mod hover;
 mod list_files;
+mod overlay;
 mod read_source_code;
+mod rename;
+mod signature_help;
+mod structural_rewrite;
+mod structural_search;
+mod type_hierarchy;
+mod workspace_symbols;
 
+mod goto;
 mod utils;
 pub use self::{
-    definitions_in_file::*, find_definition::*, find_identifier::*, find_referenced_symbols::*,
-    find_references::*, health::*, list_files::*, read_source_code::*,
+    call_hierarchy::*, code_actions::*, completions::*, definitions_in_file::*, diagnostics::*,
+    document_symbols::*, find_declaration::*, find_definition::*, find_identifier::*,
+    find_implementations::*, find_referenced_symbols::*, find_references::*,
+    find_type_definition::*, health::*, hover::*, list_files::*, overlay::*, read_source_code::*,
+    rename::*, signature_help::*, structural_rewrite::*, structural_search::*, type_hierarchy::*,
+    workspace_symbols::*,
 };
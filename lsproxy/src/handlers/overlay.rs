@@ -0,0 +1,241 @@
+use actix_web::web::{Data, Json};
+use actix_web::HttpResponse;
+use log::info;
+
+use crate::api_types::{CloseOverlayRequest, OverlayRequest, OverlayResponse};
+use crate::handlers::error::IntoHttpResponse;
+use crate::AppState;
+
+/// Open overlays of workspace files
+///
+/// Overlays workspace files with unsaved contents for a session, without writing them to disk.
+/// Until the overlays are closed, every request sees the overlaid contents instead of the files
+/// on disk, e.g. `find-definition` returns the definitions as they would be after the edits.
+///
+/// The session is any id chosen by the client. A file can only be overlaid by one session at a
+/// time: overlaying a file another session overlays responds with 409. Opening a file the
+/// session already overlays replaces its content.
+///
+/// To use unsaved contents for a single request, send them in the `overlays` of the request
+/// instead.
+#[utoipa::path(
+    post,
+    path = "/workspace/overlay/open",
+    tag = "workspace",
+    request_body = OverlayRequest,
+    responses(
+        (status = 200, description = "Overlays opened successfully", body = OverlayResponse),
+        (status = 400, description = "Bad request"),
+        (status = 409, description = "A file is overlaid by another session"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn open_overlay(data: Data<AppState>, info: Json<OverlayRequest>) -> HttpResponse {
+    info!(
+        "Received open overlay request for session: {}, files: {}",
+        info.session,
+        info.files.len()
+    );
+
+    let files = overlay_files(&info);
+    match data.manager.open_overlays(&info.session, files).await {
+        Ok(()) => HttpResponse::Ok().json(OverlayResponse {
+            session: info.session.clone(),
+            paths: data.manager.overlaid_files(&info.session).await,
+        }),
+        Err(e) => e.into_http_response(),
+    }
+}
+
+/// Update overlays of workspace files
+///
+/// Replaces the contents of files the session overlays. Each update is sent to the language
+/// servers as a new version of the documents.
+///
+/// Updating a file the session doesn't overlay responds with 409.
+#[utoipa::path(
+    post,
+    path = "/workspace/overlay/update",
+    tag = "workspace",
+    request_body = OverlayRequest,
+    responses(
+        (status = 200, description = "Overlays updated successfully", body = OverlayResponse),
+        (status = 400, description = "Bad request"),
+        (status = 409, description = "A file isn't overlaid by the session"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn update_overlay(data: Data<AppState>, info: Json<OverlayRequest>) -> HttpResponse {
+    info!(
+        "Received update overlay request for session: {}, files: {}",
+        info.session,
+        info.files.len()
+    );
+
+    let files = overlay_files(&info);
+    match data.manager.update_overlays(&info.session, files).await {
+        Ok(()) => HttpResponse::Ok().json(OverlayResponse {
+            session: info.session.clone(),
+            paths: data.manager.overlaid_files(&info.session).await,
+        }),
+        Err(e) => e.into_http_response(),
+    }
+}
+
+/// Close overlays of workspace files
+///
+/// Closes the session's overlays of the given files, or all of them if no paths are given, so
+/// requests see the files on disk again. Returns the files that were closed.
+#[utoipa::path(
+    post,
+    path = "/workspace/overlay/close",
+    tag = "workspace",
+    request_body = CloseOverlayRequest,
+    responses(
+        (status = 200, description = "Overlays closed successfully", body = OverlayResponse),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn close_overlay(data: Data<AppState>, info: Json<CloseOverlayRequest>) -> HttpResponse {
+    info!(
+        "Received close overlay request for session: {}",
+        info.session
+    );
+
+    match data
+        .manager
+        .close_overlays(&info.session, info.paths.clone())
+        .await
+    {
+        Ok(paths) => HttpResponse::Ok().json(OverlayResponse {
+            session: info.session.clone(),
+            paths,
+        }),
+        Err(e) => e.into_http_response(),
+    }
+}
+
+fn overlay_files(info: &OverlayRequest) -> Vec<(String, String)> {
+    info.files
+        .iter()
+        .map(|file| (file.path.clone(), file.text.clone()))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use actix_web::http::StatusCode;
+
+    use crate::api_types::{
+        DefinitionResponse, FileOverlay, FilePosition, GetDefinitionRequest, Position,
+    };
+    use crate::handlers::find_definition;
+    use crate::initialize_app_state;
+    use crate::test_utils::{python_sample_path, TestContext};
+
+    #[tokio::test]
+    async fn test_python_overlay_definition() -> Result<(), Box<dyn std::error::Error>> {
+        let _context = TestContext::setup(&python_sample_path(), false).await?;
+        let state = initialize_app_state().await?;
+
+        // Move the definition of `AStarGraph` in `graph.py` down by two lines
+        let original = std::fs::read_to_string(format!("{}/graph.py", python_sample_path()))?;
+        let overlay = FileOverlay {
+            path: String::from("graph.py"),
+            text: original.replacen("class AStarGraph", "\n\nclass AStarGraph", 1),
+        };
+
+        let response = open_overlay(
+            state.clone(),
+            Json(OverlayRequest {
+                session: String::from("agent"),
+                files: vec![overlay.clone()],
+            }),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK, "{:?}", response.body());
+
+        // A file is only overlaid by one session at a time
+        let response = open_overlay(
+            state.clone(),
+            Json(OverlayRequest {
+                session: String::from("other"),
+                files: vec![overlay],
+            }),
+        )
+        .await;
+        assert_eq!(
+            response.status(),
+            StatusCode::CONFLICT,
+            "{:?}",
+            response.body()
+        );
+
+        let definition_request = || {
+            Json(GetDefinitionRequest {
+                position: FilePosition {
+                    path: String::from("main.py"),
+                    position: Position {
+                        line: 1,
+                        character: 18,
+                    },
+                },
+                include_source_code: false,
+                include_raw_response: false,
+                overlays: Vec::new(),
+            })
+        };
+
+        let response = find_definition(state.clone(), definition_request()).await;
+        assert_eq!(response.status(), StatusCode::OK, "{:?}", response.body());
+        let bytes = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let definition_response: DefinitionResponse = serde_json::from_slice(&bytes)?;
+        assert_eq!(
+            definition_response.definitions,
+            vec![FilePosition {
+                path: String::from("graph.py"),
+                position: Position {
+                    line: 14,
+                    character: 6,
+                },
+            }]
+        );
+
+        let response = close_overlay(
+            state.clone(),
+            Json(CloseOverlayRequest {
+                session: String::from("agent"),
+                paths: None,
+            }),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK, "{:?}", response.body());
+        let bytes = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let close_response: OverlayResponse = serde_json::from_slice(&bytes)?;
+        assert_eq!(close_response.paths, vec![String::from("graph.py")]);
+
+        let response = find_definition(state, definition_request()).await;
+        let bytes = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let definition_response: DefinitionResponse = serde_json::from_slice(&bytes)?;
+        assert_eq!(
+            definition_response.definitions[0].position,
+            Position {
+                line: 12,
+                character: 6,
+            }
+        );
+
+        // The overlay was never written to the workspace
+        let graph = std::fs::read_to_string(format!("{}/graph.py", python_sample_path()))?;
+        assert_eq!(graph, original);
+        Ok(())
+    }
+}
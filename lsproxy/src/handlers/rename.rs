@@ -0,0 +1,189 @@
+use actix_web::web::{Data, Json};
+use actix_web::HttpResponse;
+use log::{error, info};
+use lsp_types::Position as LspPosition;
+
+use crate::api_types::{ErrorResponse, FileTextEdits, RenameRequest, RenameResponse};
+use crate::handlers::error::IntoHttpResponse;
+use crate::handlers::utils;
+use crate::AppState;
+
+/// Rename a symbol across the workspace
+///
+/// Renames the symbol at the position, along with all of its references, using the language
+/// server's rename refactoring.
+///
+/// The input position should point inside the symbol's identifier.
+///
+/// By default the edits are only previewed: the response contains the text edits to each file
+/// and a unified diff, and the workspace is left untouched. Set `apply` to write the edits to
+/// the workspace files.
+///
+/// e.g. renaming `User` to `Member` on line 0 of `src/main.py` with the code:
+/// ```
+/// 0: class User:
+/// input____^^^^
+/// ...
+/// 5: user = User("John", 30)
+/// ```
+/// The edits will rename both the class on line 0 and its use on line 5.
+#[utoipa::path(
+    post,
+    path = "/refactor/rename",
+    tag = "refactor",
+    request_body = RenameRequest,
+    responses(
+        (status = 200, description = "Rename computed successfully", body = RenameResponse),
+        (status = 400, description = "Bad request"),
+        (status = 409, description = "A file changed since its edits were computed"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn rename(data: Data<AppState>, info: Json<RenameRequest>) -> HttpResponse {
+    info!(
+        "Received rename request for file: {}, line: {}, character: {}, new name: {}, apply: {}",
+        info.identifier_position.path,
+        info.identifier_position.position.line,
+        info.identifier_position.position.character,
+        info.new_name,
+        info.apply
+    );
+
+    if info.new_name.trim().is_empty() {
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            error: "The new name can't be empty".to_string(),
+        });
+    }
+
+    let file_identifiers = match data
+        .manager
+        .get_file_identifiers(&info.identifier_position.path)
+        .await
+    {
+        Ok(identifiers) => identifiers,
+        Err(e) => {
+            error!("Failed to get file identifiers: {:?}", e);
+            return e.into_http_response();
+        }
+    };
+
+    let selected_identifier =
+        match utils::find_identifier_at_position(file_identifiers, &info.identifier_position).await
+        {
+            Ok(identifier) => identifier,
+            Err(e) => {
+                error!("Failed to find symbol to rename from position: {:?}", e);
+                return HttpResponse::BadRequest().json(ErrorResponse {
+                    error: format!("Failed to find symbol to rename from position: {}", e),
+                });
+            }
+        };
+
+    let workspace_edit = match data
+        .manager
+        .rename(
+            &info.identifier_position.path,
+            LspPosition {
+                line: info.identifier_position.position.line,
+                character: info.identifier_position.position.character,
+            },
+            &info.new_name,
+        )
+        .await
+    {
+        Ok(Some(workspace_edit)) => workspace_edit,
+        Ok(None) => {
+            return HttpResponse::BadRequest().json(ErrorResponse {
+                error: format!("`{}` can't be renamed", selected_identifier.name),
+            });
+        }
+        Err(e) => return e.into_http_response(),
+    };
+
+    let file_edits = match data
+        .manager
+        .resolve_workspace_edits(vec![workspace_edit])
+        .await
+    {
+        Ok(file_edits) => file_edits,
+        Err(e) => return e.into_http_response(),
+    };
+
+    if info.apply {
+        if let Err(e) = data.manager.apply_file_edits(&file_edits).await {
+            return e.into_http_response();
+        }
+    }
+
+    HttpResponse::Ok().json(RenameResponse {
+        diff: file_edits
+            .iter()
+            .map(|file_edit| file_edit.diff())
+            .collect(),
+        edits: file_edits.into_iter().map(FileTextEdits::from).collect(),
+        applied: info.apply,
+        selected_identifier,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use actix_web::http::StatusCode;
+
+    use crate::api_types::{FilePosition, Position};
+    use crate::initialize_app_state;
+    use crate::test_utils::{python_sample_path, TestContext};
+
+    #[tokio::test]
+    async fn test_python_rename_preview() -> Result<(), Box<dyn std::error::Error>> {
+        let _context = TestContext::setup(&python_sample_path(), false).await?;
+        let state = initialize_app_state().await?;
+
+        let mock_request = Json(RenameRequest {
+            identifier_position: FilePosition {
+                path: String::from("graph.py"),
+                position: Position {
+                    line: 12,
+                    character: 6,
+                },
+            },
+            new_name: String::from("PathGraph"),
+            apply: false,
+        });
+
+        let response = rename(state, mock_request).await;
+
+        assert_eq!(response.status(), StatusCode::OK, "{:?}", response.body());
+
+        let bytes = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let rename_response: RenameResponse = serde_json::from_slice(&bytes)?;
+
+        assert_eq!(rename_response.selected_identifier.name, "AStarGraph");
+        assert!(!rename_response.applied);
+        let paths: Vec<&str> = rename_response
+            .edits
+            .iter()
+            .map(|file_edits| file_edits.path.as_str())
+            .collect();
+        assert_eq!(paths, vec!["graph.py", "main.py", "search.py"]);
+        assert!(
+            rename_response
+                .diff
+                .contains("-class AStarGraph(GraphBase):\n+class PathGraph(GraphBase):\n"),
+            "Unexpected diff: {}",
+            rename_response.diff
+        );
+        assert!(rename_response
+            .diff
+            .contains("+from graph import PathGraph\n"));
+
+        // Previews leave the workspace untouched
+        let graph = std::fs::read_to_string(format!("{}/graph.py", python_sample_path()))?;
+        assert!(graph.contains("class AStarGraph(GraphBase):"));
+        Ok(())
+    }
+}
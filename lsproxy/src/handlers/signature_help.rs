@@ -0,0 +1,168 @@
+use actix_web::web::{Data, Json};
+use actix_web::HttpResponse;
+use log::{error, info};
+use lsp_types::Position as LspPosition;
+
+use crate::api_types::{GetSignatureHelpRequest, SignatureHelpResponse, SignatureInformation};
+use crate::handlers::error::IntoHttpResponse;
+use crate::handlers::utils;
+use crate::AppState;
+
+/// Get the signatures of the function or method called at a position
+///
+/// Returns the signatures the language server shows while typing a call, along with the
+/// parameter the position is at and the documentation of each parameter.
+///
+/// The input position can be anywhere inside the call's argument list. When it's on an
+/// identifier, e.g. one of the arguments, that identifier is returned as well.
+///
+/// e.g. for the call on line 5 of `src/main.py` with the code:
+/// ```
+/// 0: class User:
+/// 1:     def __init__(self, name: str, age: int):
+/// ...
+/// 5: user = User("John", 30)
+/// input______________^
+/// ```
+/// The signature will be `User(name: str, age: int)` with `age` as the active parameter.
+#[utoipa::path(
+    post,
+    path = "/symbol/signature-help",
+    tag = "symbol",
+    request_body = GetSignatureHelpRequest,
+    responses(
+        (status = 200, description = "Signature help retrieved successfully", body = SignatureHelpResponse),
+        (status = 400, description = "Bad request"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn signature_help(
+    data: Data<AppState>,
+    info: Json<GetSignatureHelpRequest>,
+) -> HttpResponse {
+    info!(
+        "Received signature help request for file: {}, line: {}, character: {}",
+        info.position.path, info.position.position.line, info.position.position.character
+    );
+
+    utils::with_inline_overlays(&data.manager, &info.overlays, respond(&data, &info)).await
+}
+
+async fn respond(data: &Data<AppState>, info: &GetSignatureHelpRequest) -> HttpResponse {
+    let file_identifiers = match data.manager.get_file_identifiers(&info.position.path).await {
+        Ok(identifiers) => identifiers,
+        Err(e) => {
+            error!("Failed to get file identifiers: {:?}", e);
+            return e.into_http_response();
+        }
+    };
+    // Positions between arguments aren't on any identifier, which is fine for signature help
+    let identifier = utils::find_identifier_at_position(file_identifiers, &info.position)
+        .await
+        .ok();
+
+    let signature_help = match data
+        .manager
+        .signature_help(
+            &info.position.path,
+            LspPosition {
+                line: info.position.position.line,
+                character: info.position.position.character,
+            },
+        )
+        .await
+    {
+        Ok(signature_help) => signature_help,
+        Err(e) => {
+            return e.into_http_response();
+        }
+    };
+
+    let raw_response = if info.include_raw_response {
+        Some(serde_json::to_value(&signature_help).unwrap())
+    } else {
+        None
+    };
+    let Some(signature_help) = signature_help else {
+        return HttpResponse::Ok().json(SignatureHelpResponse {
+            raw_response,
+            signatures: Vec::new(),
+            active_signature: None,
+            active_parameter: None,
+            selected_identifier: identifier,
+        });
+    };
+
+    // A signature's own active parameter takes precedence over the one for all signatures
+    let active_signature = (!signature_help.signatures.is_empty()).then(|| {
+        signature_help
+            .active_signature
+            .unwrap_or(0)
+            .min(signature_help.signatures.len() as u32 - 1)
+    });
+    let active_parameter = active_signature
+        .and_then(|index| signature_help.signatures[index as usize].active_parameter)
+        .or(signature_help.active_parameter);
+
+    HttpResponse::Ok().json(SignatureHelpResponse {
+        raw_response,
+        signatures: signature_help
+            .signatures
+            .into_iter()
+            .map(SignatureInformation::from)
+            .collect(),
+        active_signature,
+        active_parameter,
+        selected_identifier: identifier,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use actix_web::http::StatusCode;
+
+    use crate::api_types::{FilePosition, Position};
+    use crate::initialize_app_state;
+    use crate::test_utils::{python_sample_path, TestContext};
+
+    #[tokio::test]
+    async fn test_python_signature_help() -> Result<(), Box<dyn std::error::Error>> {
+        let _context = TestContext::setup(&python_sample_path(), false).await?;
+        let state = initialize_app_state().await?;
+
+        // The `b` in `self._barrier_cost(a, b)`
+        let mock_request = Json(GetSignatureHelpRequest {
+            position: FilePosition {
+                path: String::from("graph.py"),
+                position: Position {
+                    line: 39,
+                    character: 45,
+                },
+            },
+            include_raw_response: false,
+            overlays: Vec::new(),
+        });
+
+        let response = signature_help(state, mock_request).await;
+
+        assert_eq!(response.status(), StatusCode::OK, "{:?}", response.body());
+
+        let bytes = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let signature_response: SignatureHelpResponse = serde_json::from_slice(&bytes)?;
+
+        let signature = &signature_response.signatures[0];
+        assert!(
+            signature.label.contains("_barrier_cost"),
+            "Unexpected signature: {}",
+            signature.label
+        );
+        assert_eq!(signature_response.active_signature, Some(0));
+        assert_eq!(signature_response.active_parameter, Some(1));
+        assert_eq!(signature_response.selected_identifier.unwrap().name, "b");
+        Ok(())
+    }
+}
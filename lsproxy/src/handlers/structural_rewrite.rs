@@ -0,0 +1,180 @@
+use actix_web::web::{Data, Json};
+use actix_web::HttpResponse;
+use log::{error, info};
+
+use crate::api_types::{
+    ErrorResponse, FileTextEdits, StructuralRewriteRequest, StructuralRewriteResponse,
+};
+use crate::ast_grep::fix::Fix;
+use crate::handlers::error::IntoHttpResponse;
+use crate::handlers::utils;
+use crate::AppState;
+
+/// Rewrite code by its structure
+///
+/// Replaces the code in the workspace matching an [ast-grep](https://ast-grep.github.io)
+/// pattern or rule with a fix, where the metavariables of the pattern or rule are replaced with
+/// the code they captured, e.g. `$OBJ.execute($$$ARGS)` with the fix `$OBJ.run($$$ARGS)` renames
+/// every call to a method named `execute`, keeping the object and arguments.
+///
+/// Returns the edits to each file and their diff. The edits are only written to the workspace
+/// when `apply` is true, so a request without it previews the rewrite.
+///
+/// Only the files of the requested language are rewritten, optionally narrowed down with
+/// `paths`, and at most `max_files` of them. Files with an overlay are rewritten from the
+/// overlay's text, and applying the edits saves the overlay.
+#[utoipa::path(
+    post,
+    path = "/refactor/structural-rewrite",
+    tag = "refactor",
+    request_body = StructuralRewriteRequest,
+    responses(
+        (status = 200, description = "Rewrite computed successfully", body = StructuralRewriteResponse),
+        (status = 400, description = "Bad request"),
+        (status = 409, description = "A file changed since its edits were computed"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn structural_rewrite(
+    data: Data<AppState>,
+    info: Json<StructuralRewriteRequest>,
+) -> HttpResponse {
+    info!(
+        "Received structural rewrite request for language: {}, pattern: {:?}, fix: {:?}, paths: {:?}, apply: {}",
+        info.language, info.pattern, info.fix, info.paths, info.apply
+    );
+
+    let rule = match utils::structural_rule(
+        &info.language,
+        info.pattern.as_deref(),
+        info.rule.as_deref(),
+    ) {
+        Ok(rule) => rule,
+        Err(e) => {
+            error!("Invalid structural rewrite: {}", e);
+            return HttpResponse::BadRequest().json(ErrorResponse {
+                error: format!("Invalid rewrite: {}", e),
+            });
+        }
+    };
+
+    let paths = match utils::path_globs(info.paths.as_deref()) {
+        Ok(paths) => paths,
+        Err(e) => {
+            return HttpResponse::BadRequest().json(ErrorResponse {
+                error: format!("Invalid path glob {}", e),
+            })
+        }
+    };
+
+    let mut file_edits = match data
+        .manager
+        .structural_rewrite(rule, Fix::new(&info.fix), &paths)
+        .await
+    {
+        Ok(file_edits) => file_edits,
+        Err(e) => return e.into_http_response(),
+    };
+    let total_files = file_edits.len();
+    file_edits.truncate(info.max_files);
+
+    if info.apply {
+        if let Err(e) = data.manager.apply_file_edits(&file_edits).await {
+            return e.into_http_response();
+        }
+    }
+
+    HttpResponse::Ok().json(StructuralRewriteResponse {
+        diff: file_edits
+            .iter()
+            .map(|file_edit| file_edit.diff())
+            .collect(),
+        edits: file_edits.into_iter().map(FileTextEdits::from).collect(),
+        applied: info.apply,
+        total_files,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use actix_web::http::StatusCode;
+
+    use crate::initialize_app_state;
+    use crate::test_utils::{python_sample_path, TestContext};
+
+    #[tokio::test]
+    async fn test_python_structural_rewrite() -> Result<(), Box<dyn std::error::Error>> {
+        let _context = TestContext::setup(&python_sample_path(), false).await?;
+        let state = initialize_app_state().await?;
+
+        let mock_request = Json(StructuralRewriteRequest {
+            pattern: Some(String::from("print($$$ARGS)")),
+            rule: None,
+            fix: String::from("logging.info($$$ARGS)"),
+            language: String::from("python"),
+            paths: None,
+            max_files: 1,
+            apply: false,
+        });
+        let response = structural_rewrite(state.clone(), mock_request).await;
+        assert_eq!(response.status(), StatusCode::OK, "{:?}", response.body());
+        let bytes = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let rewrite: StructuralRewriteResponse = serde_json::from_slice(&bytes)?;
+
+        assert!(!rewrite.applied);
+        assert_eq!(rewrite.total_files, 2);
+        assert_eq!(rewrite.edits.len(), 1);
+        assert_eq!(rewrite.edits[0].path, "decorators.py");
+        assert!(rewrite
+            .diff
+            .starts_with("--- a/decorators.py\n+++ b/decorators.py\n"));
+        assert!(rewrite.diff.contains(
+            "+        logging.info(f\"{func.__name__} took {end_time - start_time:.2f} seconds to execute\")\n"
+        ));
+
+        let mock_request = Json(StructuralRewriteRequest {
+            pattern: Some(String::from("print($$$ARGS)")),
+            rule: None,
+            fix: String::from("logging.info($$$ARGS)"),
+            language: String::from("python"),
+            paths: Some(vec![String::from("main.py")]),
+            max_files: 100,
+            apply: false,
+        });
+        let response = structural_rewrite(state.clone(), mock_request).await;
+        assert_eq!(response.status(), StatusCode::OK, "{:?}", response.body());
+        let bytes = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let rewrite: StructuralRewriteResponse = serde_json::from_slice(&bytes)?;
+        let new_texts: Vec<&str> = rewrite.edits[0]
+            .edits
+            .iter()
+            .map(|edit| edit.new_text.as_str())
+            .collect();
+        assert_eq!(
+            new_texts,
+            vec![
+                "logging.info(\"route\", result)",
+                "logging.info(\"cost\", cost)"
+            ]
+        );
+
+        let mock_request = Json(StructuralRewriteRequest {
+            pattern: Some(String::from("print($$$ARGS)")),
+            rule: None,
+            fix: String::from("logging.info($$$ARGS)"),
+            language: String::from("cobol"),
+            paths: None,
+            max_files: 100,
+            apply: false,
+        });
+        let response = structural_rewrite(state, mock_request).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        Ok(())
+    }
+}
@@ -0,0 +1,151 @@
+use actix_web::web::{Data, Json};
+use actix_web::HttpResponse;
+use log::{error, info};
+
+use crate::api_types::{
+    ErrorResponse, StructuralMatch, StructuralSearchRequest, StructuralSearchResponse,
+};
+use crate::handlers::error::IntoHttpResponse;
+use crate::handlers::utils;
+use crate::AppState;
+
+/// Search code by its structure
+///
+/// Returns the code in the workspace matching an [ast-grep](https://ast-grep.github.io) pattern
+/// or rule, with the code captured by its metavariables.
+///
+/// Unlike a text search, a pattern matches code by its syntax tree, ignoring formatting and
+/// comments, e.g. `$OBJ.execute($$$ARGS)` matches every call to a method named `execute`,
+/// whatever the object and arguments. Use a rule for what a pattern can't express, like calls
+/// inside a given function or a kind of node with a given child.
+///
+/// Only the files of the requested language are searched, optionally narrowed down with
+/// `paths`. Files with an overlay are searched with the overlay's text.
+#[utoipa::path(
+    post,
+    path = "/search/structural",
+    tag = "search",
+    request_body = StructuralSearchRequest,
+    responses(
+        (status = 200, description = "Search completed successfully", body = StructuralSearchResponse),
+        (status = 400, description = "Bad request"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn structural_search(
+    data: Data<AppState>,
+    info: Json<StructuralSearchRequest>,
+) -> HttpResponse {
+    info!(
+        "Received structural search request for language: {}, pattern: {:?}, paths: {:?}",
+        info.language, info.pattern, info.paths
+    );
+
+    let rule = match utils::structural_rule(
+        &info.language,
+        info.pattern.as_deref(),
+        info.rule.as_deref(),
+    ) {
+        Ok(rule) => rule,
+        Err(e) => {
+            error!("Invalid structural search: {}", e);
+            return HttpResponse::BadRequest().json(ErrorResponse {
+                error: format!("Invalid search: {}", e),
+            });
+        }
+    };
+
+    let paths = match utils::path_globs(info.paths.as_deref()) {
+        Ok(paths) => paths,
+        Err(e) => {
+            return HttpResponse::BadRequest().json(ErrorResponse {
+                error: format!("Invalid path glob {}", e),
+            })
+        }
+    };
+
+    match data.manager.structural_search(rule, &paths).await {
+        Ok(matches) => HttpResponse::Ok().json(StructuralSearchResponse {
+            matches: matches.into_iter().map(StructuralMatch::from).collect(),
+        }),
+        Err(e) => e.into_http_response(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use actix_web::http::StatusCode;
+
+    use crate::initialize_app_state;
+    use crate::test_utils::{python_sample_path, TestContext};
+
+    #[tokio::test]
+    async fn test_python_structural_search() -> Result<(), Box<dyn std::error::Error>> {
+        let _context = TestContext::setup(&python_sample_path(), false).await?;
+        let state = initialize_app_state().await?;
+
+        let mock_request = Json(StructuralSearchRequest {
+            pattern: Some(String::from("plt.plot($$$ARGS)")),
+            rule: None,
+            language: String::from("python"),
+            paths: Some(vec![String::from("main.py")]),
+        });
+        let response = structural_search(state.clone(), mock_request).await;
+        assert_eq!(response.status(), StatusCode::OK, "{:?}", response.body());
+        let bytes = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let search: StructuralSearchResponse = serde_json::from_slice(&bytes)?;
+
+        let lines: Vec<u32> = search
+            .matches
+            .iter()
+            .map(|m| m.file_range.range.start.line)
+            .collect();
+        assert_eq!(lines, vec![7, 9]);
+        let first = &search.matches[0];
+        assert_eq!(first.file_range.path, "main.py");
+        assert_eq!(
+            first.text,
+            "plt.plot([v[0] for v in path], [v[1] for v in path])"
+        );
+        let args: Vec<&str> = first.multi["ARGS"]
+            .iter()
+            .map(|arg| arg.text.as_str())
+            .collect();
+        assert_eq!(args, vec!["[v[0] for v in path]", "[v[1] for v in path]"]);
+
+        let mock_request = Json(StructuralSearchRequest {
+            pattern: None,
+            rule: Some(String::from(
+                "rule:\n  pattern: $FUNC($$$)\n  inside:\n    kind: function_definition\n    stopBy: end\n    has: { field: name, regex: ^main$ }\nconstraints:\n  FUNC: { regex: ^print$ }\n",
+            )),
+            language: String::from("python"),
+            paths: None,
+        });
+        let response = structural_search(state.clone(), mock_request).await;
+        assert_eq!(response.status(), StatusCode::OK, "{:?}", response.body());
+        let bytes = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let search: StructuralSearchResponse = serde_json::from_slice(&bytes)?;
+        let texts: Vec<&str> = search.matches.iter().map(|m| m.text.as_str()).collect();
+        assert_eq!(
+            texts,
+            vec!["print(\"route\", result)", "print(\"cost\", cost)"]
+        );
+        assert_eq!(search.matches[0].single["FUNC"].text, "print");
+
+        let mock_request = Json(StructuralSearchRequest {
+            pattern: Some(String::from("def (")),
+            rule: None,
+            language: String::from("python"),
+            paths: None,
+        });
+        let response = structural_search(state, mock_request).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        Ok(())
+    }
+}
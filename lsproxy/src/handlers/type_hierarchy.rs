@@ -0,0 +1,571 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use actix_web::web::{Data, Json};
+use actix_web::HttpResponse;
+use log::{error, info, warn};
+use lsp_types::{
+    GotoDefinitionResponse, Location, Position as LspPosition, SymbolKind, TypeHierarchyItem, Url,
+};
+
+use crate::api_types::{
+    ErrorResponse, GetTypeHierarchyRequest, Identifier, Symbol, TypeHierarchyNode,
+    TypeHierarchyResponse, TypeHierarchySource,
+};
+use crate::ast_grep::types::AstGrepMatch;
+use crate::handlers::error::IntoHttpResponse;
+use crate::handlers::utils;
+use crate::lsp::manager::{LspManagerError, Manager};
+use crate::utils::file_utils::{
+    absolute_path_to_relative_path_string, uri_to_relative_path_string,
+};
+use crate::AppState;
+
+const MAX_TYPE_HIERARCHY_DEPTH: u32 = 5;
+
+#[derive(Debug, Clone, Copy)]
+enum TypeDirection {
+    Supertypes,
+    Subtypes,
+}
+
+/// Looks up the supertypes and subtypes of a type, either through the langserver or through the
+/// ast-grep type relations of the whole workspace.
+enum TypeResolver {
+    Lsp,
+    AstGrep { relations: Vec<AstGrepMatch> },
+}
+
+/// Get the type hierarchy of a class, interface or struct
+///
+/// The input position should point to the identifier of the type, either at its declaration or
+/// at one of its usages.
+///
+/// Returns the type(s) at the position as roots, each with the types it extends or implements
+/// (supertypes) and the types that extend or implement it (subtypes).
+/// Use `depth` to also expand the supertypes of the supertypes and the subtypes of the subtypes.
+///
+/// When the langserver doesn't support type hierarchies, the relations are found with ast-grep
+/// from `class X(Base)`, `extends` and `implements` clauses instead.
+///
+/// e.g. for `Animal` on line 0 of `src/zoo.py` with the code:
+/// ```
+/// 0: class Animal(Base):
+/// input____^^^^^^
+/// ...
+/// 9: class Dog(Animal):
+/// ```
+/// The root will be `Animal`, with `Base` as its supertype and `Dog` as its subtype.
+#[utoipa::path(
+    post,
+    path = "/symbol/type-hierarchy",
+    tag = "symbol",
+    request_body = GetTypeHierarchyRequest,
+    responses(
+        (status = 200, description = "Type hierarchy retrieved successfully", body = TypeHierarchyResponse),
+        (status = 400, description = "Bad request"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn type_hierarchy(
+    data: Data<AppState>,
+    info: Json<GetTypeHierarchyRequest>,
+) -> HttpResponse {
+    info!(
+        "Received type hierarchy request for file: {}, line: {}, character: {}, depth: {}",
+        info.identifier_position.path,
+        info.identifier_position.position.line,
+        info.identifier_position.position.character,
+        info.depth
+    );
+
+    utils::with_inline_overlays(&data.manager, &info.overlays, respond(&data, &info)).await
+}
+
+async fn respond(data: &Data<AppState>, info: &GetTypeHierarchyRequest) -> HttpResponse {
+    let file_identifiers = match data
+        .manager
+        .get_file_identifiers(&info.identifier_position.path)
+        .await
+    {
+        Ok(identifiers) => identifiers,
+        Err(e) => {
+            error!("Failed to get file identifiers: {:?}", e);
+            return e.into_http_response();
+        }
+    };
+
+    let selected_identifier =
+        match utils::find_identifier_at_position(file_identifiers, &info.identifier_position).await
+        {
+            Ok(identifier) => identifier,
+            Err(e) => {
+                error!("Failed to find type hierarchy from position: {:?}", e);
+                return HttpResponse::BadRequest().json(ErrorResponse {
+                    error: format!("Failed to find type hierarchy from position: {}", e),
+                });
+            }
+        };
+
+    let depth = info.depth.clamp(1, MAX_TYPE_HIERARCHY_DEPTH);
+    let position = LspPosition {
+        line: info.identifier_position.position.line,
+        character: info.identifier_position.position.character,
+    };
+    match build_type_hierarchy(
+        &data.manager,
+        &info.identifier_position.path,
+        position,
+        &selected_identifier,
+        depth,
+    )
+    .await
+    {
+        Ok((source, roots)) => HttpResponse::Ok().json(TypeHierarchyResponse {
+            roots,
+            source,
+            selected_identifier,
+        }),
+        Err(e) => e.into_http_response(),
+    }
+}
+
+async fn build_type_hierarchy(
+    manager: &Manager,
+    file_path: &str,
+    position: LspPosition,
+    selected_identifier: &Identifier,
+    depth: u32,
+) -> Result<(TypeHierarchySource, Vec<TypeHierarchyNode>), LspManagerError> {
+    let (resolver, items) = match manager.prepare_type_hierarchy(file_path, position).await {
+        Ok(items) if !items.is_empty() => (TypeResolver::Lsp, items),
+        Err(e @ LspManagerError::FileNotFound(_)) => return Err(e),
+        result => {
+            if let Err(e) = result {
+                info!("Falling back to ast-grep for type hierarchy: {}", e);
+            }
+            let relations = manager.type_relations_ast_grep().await?;
+            let items = find_type_declarations(manager, file_path, selected_identifier).await?;
+            (TypeResolver::AstGrep { relations }, items)
+        }
+    };
+
+    let mut roots = Vec::new();
+    for item in items {
+        let mut supertypes_visited = HashSet::from([item_key(&item)]);
+        let mut subtypes_visited = HashSet::from([item_key(&item)]);
+        let supertypes = expand_types(
+            manager,
+            &resolver,
+            &item,
+            TypeDirection::Supertypes,
+            depth,
+            &mut supertypes_visited,
+        )
+        .await?;
+        let subtypes = expand_types(
+            manager,
+            &resolver,
+            &item,
+            TypeDirection::Subtypes,
+            depth,
+            &mut subtypes_visited,
+        )
+        .await?;
+        roots.push(TypeHierarchyNode {
+            symbol: Symbol::from(item),
+            supertypes,
+            subtypes,
+        });
+    }
+    let source = match resolver {
+        TypeResolver::Lsp => TypeHierarchySource::Lsp,
+        TypeResolver::AstGrep { .. } => TypeHierarchySource::AstGrep,
+    };
+    Ok((source, roots))
+}
+
+/// Fetches the supertypes or subtypes of `item`, recursing until `depth` levels are expanded.
+/// Each type is expanded at most once so cyclic or diamond hierarchies terminate.
+async fn expand_types(
+    manager: &Manager,
+    resolver: &TypeResolver,
+    item: &TypeHierarchyItem,
+    direction: TypeDirection,
+    depth: u32,
+    visited: &mut HashSet<(String, u32, u32)>,
+) -> Result<Vec<TypeHierarchyNode>, LspManagerError> {
+    let mut related = match resolver {
+        TypeResolver::Lsp => match direction {
+            TypeDirection::Supertypes => manager.supertypes(item).await?,
+            TypeDirection::Subtypes => manager.subtypes(item).await?,
+        },
+        TypeResolver::AstGrep { relations } => match direction {
+            TypeDirection::Supertypes => ast_grep_supertypes(manager, relations, item).await?,
+            TypeDirection::Subtypes => ast_grep_subtypes(manager, relations, item).await,
+        },
+    };
+    related.sort_by_key(item_key);
+    related.dedup_by_key(|item| item_key(item));
+
+    let mut nodes = Vec::new();
+    for related_item in related {
+        let in_workspace = manager
+            .is_workspace_file(&uri_to_relative_path_string(&related_item.uri))
+            .await;
+        let children = if depth > 1 && in_workspace && visited.insert(item_key(&related_item)) {
+            Box::pin(expand_types(
+                manager,
+                resolver,
+                &related_item,
+                direction,
+                depth - 1,
+                visited,
+            ))
+            .await?
+        } else {
+            Vec::new()
+        };
+        let symbol = Symbol::from(related_item);
+        nodes.push(match direction {
+            TypeDirection::Supertypes => TypeHierarchyNode {
+                symbol,
+                supertypes: children,
+                subtypes: Vec::new(),
+            },
+            TypeDirection::Subtypes => TypeHierarchyNode {
+                symbol,
+                supertypes: Vec::new(),
+                subtypes: children,
+            },
+        });
+    }
+    Ok(nodes)
+}
+
+/// Finds the declaration of the selected type with ast-grep, following the definition of the
+/// identifier when it isn't the declaration itself.
+async fn find_type_declarations(
+    manager: &Manager,
+    file_path: &str,
+    selected_identifier: &Identifier,
+) -> Result<Vec<TypeHierarchyItem>, LspManagerError> {
+    let start = lsp_types::Position::from(selected_identifier.file_range.range.start.clone());
+    if let Some(item) = declaration_at(manager, file_path, start).await? {
+        return Ok(vec![item]);
+    }
+
+    let mut items = Vec::new();
+    for location in find_definition_locations(manager, file_path, start).await {
+        let path = uri_to_relative_path_string(&location.uri);
+        if !manager.is_workspace_file(&path).await {
+            continue;
+        }
+        if let Some(item) = declaration_at(manager, &path, location.range.start).await? {
+            items.push(item);
+        }
+    }
+    Ok(items)
+}
+
+async fn ast_grep_supertypes(
+    manager: &Manager,
+    relations: &[AstGrepMatch],
+    item: &TypeHierarchyItem,
+) -> Result<Vec<TypeHierarchyItem>, LspManagerError> {
+    let item_path = uri_to_relative_path_string(&item.uri);
+    let mut supertypes = Vec::new();
+    for relation in relations {
+        let relation_path = absolute_path_to_relative_path_string(&PathBuf::from(&relation.file));
+        let Some(supertype) = &relation.meta_variables.single.supertype else {
+            continue;
+        };
+        if relation_path != item_path
+            || lsp_types::Range::from(relation.get_identifier_range()).start
+                != item.selection_range.start
+        {
+            continue;
+        }
+
+        let supertype_range = lsp_types::Range::from(supertype.range.clone());
+        for location in
+            find_definition_locations(manager, &relation_path, supertype_range.start).await
+        {
+            let path = uri_to_relative_path_string(&location.uri);
+            let declaration = if manager.is_workspace_file(&path).await {
+                declaration_at(manager, &path, location.range.start).await?
+            } else {
+                None
+            };
+            // Types from outside the workspace (e.g. the standard library) are returned as-is
+            supertypes.push(declaration.unwrap_or_else(|| TypeHierarchyItem {
+                name: type_name(&supertype.text).to_string(),
+                kind: SymbolKind::CLASS,
+                tags: None,
+                detail: None,
+                uri: location.uri.clone(),
+                range: location.range,
+                selection_range: location.range,
+                data: None,
+            }));
+        }
+    }
+    Ok(supertypes)
+}
+
+async fn ast_grep_subtypes(
+    manager: &Manager,
+    relations: &[AstGrepMatch],
+    item: &TypeHierarchyItem,
+) -> Vec<TypeHierarchyItem> {
+    let item_path = uri_to_relative_path_string(&item.uri);
+    let mut subtypes = Vec::new();
+    for relation in relations {
+        let Some(supertype) = &relation.meta_variables.single.supertype else {
+            continue;
+        };
+        if type_name(&supertype.text) != item.name {
+            continue;
+        }
+
+        // Skip same-named types that resolve to a different declaration
+        let relation_path = absolute_path_to_relative_path_string(&PathBuf::from(&relation.file));
+        let supertype_range = lsp_types::Range::from(supertype.range.clone());
+        let definitions =
+            find_definition_locations(manager, &relation_path, supertype_range.start).await;
+        let resolves_to_item = definitions.is_empty()
+            || definitions.iter().any(|location| {
+                uri_to_relative_path_string(&location.uri) == item_path
+                    && location.range.start.line == item.selection_range.start.line
+            });
+        if !resolves_to_item {
+            continue;
+        }
+
+        if let Some(subtype) = relation_to_item(relation) {
+            subtypes.push(subtype);
+        }
+    }
+    subtypes
+}
+
+async fn declaration_at(
+    manager: &Manager,
+    file_path: &str,
+    position: LspPosition,
+) -> Result<Option<TypeHierarchyItem>, LspManagerError> {
+    let symbols = manager.definitions_in_file_ast_grep(file_path).await?;
+    Ok(symbols
+        .iter()
+        .find(|symbol| lsp_types::Range::from(symbol.get_identifier_range()).start == position)
+        .and_then(|symbol| {
+            let kind = match symbol.rule_id.as_str() {
+                "interface" => SymbolKind::INTERFACE,
+                "struct" => SymbolKind::STRUCT,
+                "enum" => SymbolKind::ENUM,
+                "trait" => SymbolKind::INTERFACE,
+                _ => SymbolKind::CLASS,
+            };
+            ast_grep_match_to_item(symbol, kind)
+        }))
+}
+
+/// Converts a type relation match into an item for the type declaring the relation, whose kind
+/// is the one of the rule that matched, e.g. `interface-supertype`.
+fn relation_to_item(relation: &AstGrepMatch) -> Option<TypeHierarchyItem> {
+    let kind = match relation.rule_id.as_str() {
+        "interface-supertype" => SymbolKind::INTERFACE,
+        "struct-supertype" => SymbolKind::STRUCT,
+        "enum-supertype" => SymbolKind::ENUM,
+        _ => SymbolKind::CLASS,
+    };
+    ast_grep_match_to_item(relation, kind)
+}
+
+fn ast_grep_match_to_item(ast_match: &AstGrepMatch, kind: SymbolKind) -> Option<TypeHierarchyItem> {
+    Some(TypeHierarchyItem {
+        name: ast_match.meta_variables.single.name.text.clone(),
+        kind,
+        tags: None,
+        detail: None,
+        uri: Url::from_file_path(&ast_match.file).ok()?,
+        range: ast_match.get_context_range().into(),
+        selection_range: ast_match.get_identifier_range().into(),
+        data: None,
+    })
+}
+
+async fn find_definition_locations(
+    manager: &Manager,
+    file_path: &str,
+    position: LspPosition,
+) -> Vec<Location> {
+    match manager.find_definition(file_path, position).await {
+        Ok(GotoDefinitionResponse::Scalar(location)) => vec![location],
+        Ok(GotoDefinitionResponse::Array(locations)) => locations,
+        Ok(GotoDefinitionResponse::Link(links)) => links
+            .into_iter()
+            .map(|link| Location {
+                uri: link.target_uri,
+                range: link.target_selection_range,
+            })
+            .collect(),
+        Err(e) => {
+            warn!("Failed to resolve supertype definition: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// Strips qualifiers from a supertype, e.g. `models.Base` -> `Base`.
+fn type_name(supertype: &str) -> &str {
+    supertype.rsplit('.').next().unwrap_or(supertype)
+}
+
+fn item_key(item: &TypeHierarchyItem) -> (String, u32, u32) {
+    (
+        uri_to_relative_path_string(&item.uri),
+        item.selection_range.start.line,
+        item.selection_range.start.character,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use actix_web::http::StatusCode;
+
+    use crate::api_types::{FilePosition, Position};
+    use crate::ast_grep::client::AstGrepClient;
+    use crate::initialize_app_state;
+    use crate::test_utils::{python_sample_path, typescript_sample_path, TestContext};
+
+    async fn get_type_hierarchy(
+        state: Data<AppState>,
+        path: &str,
+        line: u32,
+        character: u32,
+    ) -> Result<TypeHierarchyResponse, Box<dyn std::error::Error>> {
+        let mock_request = Json(GetTypeHierarchyRequest {
+            identifier_position: FilePosition {
+                path: String::from(path),
+                position: Position { line, character },
+            },
+            depth: 1,
+            overlays: Vec::new(),
+        });
+
+        let response = type_hierarchy(state, mock_request).await;
+
+        assert_eq!(response.status(), StatusCode::OK, "{:?}", response.body());
+
+        let bytes = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        Ok(serde_json::from_slice(&bytes)?)
+    }
+
+    #[tokio::test]
+    async fn test_python_supertypes() -> Result<(), Box<dyn std::error::Error>> {
+        let _context = TestContext::setup(&python_sample_path(), false).await?;
+        let state = initialize_app_state().await?;
+
+        // `class AStarGraph(GraphBase):`
+        let response = get_type_hierarchy(state, "graph.py", 12, 6).await?;
+
+        assert_eq!(response.source, TypeHierarchySource::AstGrep);
+        assert_eq!(response.roots.len(), 1);
+        let root = &response.roots[0];
+        assert_eq!(root.symbol.name, "AStarGraph");
+        assert_eq!(root.supertypes.len(), 1);
+        assert_eq!(root.supertypes[0].symbol.name, "GraphBase");
+        assert_eq!(
+            root.supertypes[0].symbol.identifier_position,
+            FilePosition {
+                path: String::from("graph.py"),
+                position: Position {
+                    line: 4,
+                    character: 6,
+                },
+            }
+        );
+        assert!(root.subtypes.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_python_subtypes() -> Result<(), Box<dyn std::error::Error>> {
+        let _context = TestContext::setup(&python_sample_path(), false).await?;
+        let state = initialize_app_state().await?;
+
+        // `class GraphBase:`
+        let response = get_type_hierarchy(state, "graph.py", 4, 6).await?;
+
+        let root = &response.roots[0];
+        assert_eq!(root.symbol.name, "GraphBase");
+        assert!(root.supertypes.is_empty());
+        let subtypes: Vec<&str> = root
+            .subtypes
+            .iter()
+            .map(|node| node.symbol.name.as_str())
+            .collect();
+        assert_eq!(subtypes, vec!["AStarGraph"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_typescript_interface_subtypes() -> Result<(), Box<dyn std::error::Error>> {
+        let _context = TestContext::setup(&typescript_sample_path(), false).await?;
+        let state = initialize_app_state().await?;
+
+        // `interface IAStarPathfinder {`
+        let response = get_type_hierarchy(state, "src/astar.ts", 4, 10).await?;
+
+        let root = &response.roots[0];
+        assert_eq!(root.symbol.name, "IAStarPathfinder");
+        let subtypes: Vec<(&str, u32)> = root
+            .subtypes
+            .iter()
+            .map(|node| {
+                (
+                    node.symbol.name.as_str(),
+                    node.symbol.identifier_position.position.line,
+                )
+            })
+            .collect();
+        assert_eq!(subtypes, vec![("AStar", 8)]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_relation_kinds() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("shapes.ts");
+        std::fs::write(
+            &path,
+            "interface FooBar {}\n\
+             interface Foo extends FooBar {}\n\
+             class Bar implements Foo {\n\
+             \x20   // Implements interface Bar, er, Foo\n\
+             }\n",
+        )?;
+
+        let relations = AstGrepClient::default()
+            .get_type_relations(&[path.to_string_lossy().into_owned()])
+            .await?;
+        let kinds: Vec<(String, SymbolKind)> = relations
+            .iter()
+            .filter_map(relation_to_item)
+            .map(|item| (item.name, item.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (String::from("Foo"), SymbolKind::INTERFACE),
+                (String::from("Bar"), SymbolKind::CLASS),
+            ]
+        );
+        Ok(())
+    }
+}
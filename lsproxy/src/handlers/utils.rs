@@ -1,4 +1,17 @@
-use crate::api_types::{FilePosition, Identifier};
+use crate::api_types::{
+    CodeContext, DefinitionResponse, FileOverlay, FilePosition, FileRange, Identifier, Position,
+    Range,
+};
+use crate::ast_grep::language::AstGrepLanguage;
+use crate::ast_grep::rule::{RuleConfig, SerializableRuleConfig};
+use crate::handlers::error::IntoHttpResponse;
+use crate::lsp::manager::{LspManagerError, Manager};
+use crate::utils::file_utils::uri_to_relative_path_string;
+use actix_web::HttpResponse;
+use log::{error, warn};
+use lsp_types::{GotoDefinitionResponse, Location, Position as LspPosition, Range as LspRange};
+use std::future::Future;
+use std::sync::Arc;
 
 #[derive(Debug)]
 pub enum PositionError {
@@ -64,3 +77,241 @@ pub(crate) async fn find_identifier_at_position<'a>(
 
     Err(PositionError::IdentifierNotFound { closest })
 }
+
+/// Runs a handler with the overlays sent inline with its request open, closing them once the
+/// handler has responded, or once the request is dropped before then, e.g. when the client
+/// disconnects.
+pub(crate) async fn with_inline_overlays(
+    manager: &Arc<Manager>,
+    overlays: &[FileOverlay],
+    handler: impl Future<Output = HttpResponse>,
+) -> HttpResponse {
+    let files = overlays
+        .iter()
+        .map(|overlay| (overlay.path.clone(), overlay.text.clone()))
+        .collect();
+    let session = match manager.open_inline_overlays(files).await {
+        Ok(session) => session.map(|session| InlineOverlays {
+            manager: Arc::clone(manager),
+            session: Some(session),
+        }),
+        Err(e) => return e.into_http_response(),
+    };
+
+    let response = handler.await;
+
+    if let Some(session) = session {
+        session.close().await;
+    }
+    response
+}
+
+/// The session of a request's inline overlays, closed in the background if it is dropped
+/// before being closed.
+struct InlineOverlays {
+    manager: Arc<Manager>,
+    session: Option<String>,
+}
+
+impl InlineOverlays {
+    async fn close(mut self) {
+        if let Some(session) = self.session.take() {
+            close_inline_overlays(&self.manager, &session).await;
+        }
+    }
+}
+
+impl Drop for InlineOverlays {
+    fn drop(&mut self) {
+        let Some(session) = self.session.take() else {
+            return;
+        };
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            error!("Failed to close inline overlays of {}: no runtime", session);
+            return;
+        };
+        let manager = Arc::clone(&self.manager);
+        runtime.spawn(async move { close_inline_overlays(&manager, &session).await });
+    }
+}
+
+async fn close_inline_overlays(manager: &Manager, session: &str) {
+    if let Err(e) = manager.close_overlays(session, None).await {
+        error!("Failed to close inline overlays: {}", e);
+    }
+}
+
+/// Builds the response shared by the goto endpoints (definition, implementations, type
+/// definition and declaration).
+pub(crate) async fn definition_response(
+    manager: &Manager,
+    definitions: GotoDefinitionResponse,
+    selected_identifier: Identifier,
+    include_source_code: bool,
+    include_raw_response: bool,
+) -> DefinitionResponse {
+    let source_code_context = if include_source_code {
+        match fetch_definition_source_code(manager, &definitions).await {
+            Ok(context) => Some(context),
+            Err(e) => {
+                error!("Failed to fetch definition source code: {:?}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    DefinitionResponse {
+        raw_response: if include_raw_response {
+            Some(serde_json::to_value(&definitions).unwrap())
+        } else {
+            None
+        },
+        definitions: match &definitions {
+            GotoDefinitionResponse::Scalar(location) => vec![location.clone().into()],
+            GotoDefinitionResponse::Array(locations) => {
+                locations.iter().map(|l| l.clone().into()).collect()
+            }
+            GotoDefinitionResponse::Link(links) => links.iter().map(|l| l.clone().into()).collect(),
+        },
+        source_code_context,
+        selected_identifier,
+    }
+}
+
+async fn fetch_definition_source_code(
+    manager: &Manager,
+    definitions_response: &GotoDefinitionResponse,
+) -> Result<Vec<CodeContext>, LspManagerError> {
+    let mut code_contexts = Vec::new();
+    let definitions: &Vec<Location> = match definitions_response {
+        GotoDefinitionResponse::Scalar(definition) => &vec![definition.clone()],
+        GotoDefinitionResponse::Array(definitions) => definitions,
+        GotoDefinitionResponse::Link(links) => &links
+            .iter()
+            .map(|link| Location::new(link.target_uri.clone(), link.target_range))
+            .collect::<Vec<Location>>(),
+    };
+
+    for definition in definitions {
+        let relative_path = uri_to_relative_path_string(&definition.uri);
+        let file_symbols = manager.definitions_in_file_ast_grep(&relative_path).await?;
+        let symbol = file_symbols.iter().find(|s| {
+            s.get_identifier_range().start.line == definition.range.start.line
+                && s.get_identifier_range().start.column == definition.range.start.character
+        });
+
+        let source_code_context = match symbol {
+            Some(ast_grep_match) => CodeContext {
+                range: FileRange {
+                    path: relative_path,
+                    range: Range {
+                        start: Position {
+                            line: ast_grep_match.get_context_range().start.line,
+                            character: ast_grep_match.get_context_range().start.column,
+                        },
+                        end: Position {
+                            line: ast_grep_match.get_context_range().end.line,
+                            character: ast_grep_match.get_context_range().end.column,
+                        },
+                    },
+                },
+                source_code: ast_grep_match.get_source_code(),
+            },
+            None => {
+                warn!("Symbol not found for definition: {:?}", definition);
+                warn!("No exact match in file symbols (likely filtered out). Returning an approximate range instead.");
+                let range = LspRange {
+                    start: LspPosition {
+                        line: definition.range.start.line.saturating_sub(3),
+                        character: 0,
+                    },
+                    end: LspPosition {
+                        line: definition.range.end.line + 3,
+                        character: 0,
+                    },
+                };
+                let source_code = manager
+                    .read_source_code(&relative_path, Some(range))
+                    .await?;
+                CodeContext {
+                    range: FileRange {
+                        path: relative_path,
+                        range: Range {
+                            start: Position {
+                                line: definition.range.start.line.saturating_sub(3),
+                                character: 0,
+                            },
+                            end: Position {
+                                line: definition.range.end.line + 3,
+                                character: 0,
+                            },
+                        },
+                    },
+                    source_code,
+                }
+            }
+        };
+
+        code_contexts.push(source_code_context);
+    }
+    Ok(code_contexts)
+}
+
+/// Compiles the rule of a structural search or rewrite, from either a pattern or a YAML rule.
+pub(crate) fn structural_rule(
+    language: &str,
+    pattern: Option<&str>,
+    rule: Option<&str>,
+) -> Result<RuleConfig, String> {
+    language
+        .parse::<AstGrepLanguage>()
+        .map_err(|_| format!("Unsupported language `{}`", language))
+        .and_then(|language| SerializableRuleConfig::for_search(pattern, rule, language))
+        .and_then(SerializableRuleConfig::compile)
+}
+
+/// Parses the path globs of a request, which default to none.
+pub(crate) fn path_globs(paths: Option<&[String]>) -> Result<Vec<glob::Pattern>, String> {
+    paths
+        .into_iter()
+        .flatten()
+        .map(|path| glob::Pattern::new(path).map_err(|e| format!("`{}`: {}", path, e)))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use tokio::time::{sleep, timeout, Duration};
+
+    use crate::initialize_app_state;
+    use crate::test_utils::{python_sample_path, TestContext};
+
+    #[tokio::test]
+    async fn test_inline_overlays_closed_when_dropped() -> Result<(), Box<dyn std::error::Error>> {
+        let _context = TestContext::setup(&python_sample_path(), false).await?;
+        let state = initialize_app_state().await?;
+
+        let overlays = vec![FileOverlay {
+            path: String::from("main.py"),
+            text: String::from("print('unsaved')\n"),
+        }];
+        // The handler never responds, like one whose client disconnected
+        let handler = async {
+            assert_eq!(
+                state.manager.overlaid_files("inline-0").await,
+                vec![String::from("main.py")]
+            );
+            std::future::pending::<HttpResponse>().await
+        };
+        let request = with_inline_overlays(&state.manager, &overlays, handler);
+        assert!(timeout(Duration::from_millis(500), request).await.is_err());
+
+        sleep(Duration::from_millis(100)).await;
+        assert!(state.manager.overlaid_files("inline-0").await.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,104 @@
+use actix_web::web::{Data, Query};
+use actix_web::HttpResponse;
+use log::info;
+
+use crate::api_types::{Symbol, WorkspaceSymbolsRequest};
+use crate::handlers::error::IntoHttpResponse;
+use crate::utils::symbol_utils::fuzzy_match_score;
+use crate::AppState;
+
+/// Search symbols across the workspace
+///
+/// Returns the symbols (classes, functions, methods, etc.) defined anywhere in the workspace
+/// whose name matches the query, best matches first.
+///
+/// Every language server is asked with `workspace/symbol`. Languages whose server doesn't
+/// return anything fall back to the symbols found by ast-grep.
+///
+/// Matching is case-insensitive and fuzzy: exact matches rank first, then names starting with
+/// the query, then names containing it, then names containing its characters in order, e.g.
+/// `astgr` matches `AStarGraph`. Use `kind` to only get symbols of one kind.
+///
+/// The returned positions point to the start of the symbol's identifier.
+#[utoipa::path(
+    get,
+    path = "/symbol/workspace-symbols",
+    tag = "symbol",
+    params(WorkspaceSymbolsRequest),
+    responses(
+        (status = 200, description = "Symbols retrieved successfully", body = Vec<Symbol>),
+        (status = 400, description = "Bad request"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+pub async fn workspace_symbols(
+    data: Data<AppState>,
+    info: Query<WorkspaceSymbolsRequest>,
+) -> HttpResponse {
+    info!(
+        "Received workspace symbols request for query: {}, kind: {:?}",
+        info.query, info.kind
+    );
+
+    match data.manager.workspace_symbols(&info.query).await {
+        Ok(symbols) => {
+            let mut scored: Vec<(u32, Symbol)> = symbols
+                .into_iter()
+                .filter(|symbol| {
+                    info.kind
+                        .as_ref()
+                        .is_none_or(|kind| symbol.kind.eq_ignore_ascii_case(kind))
+                })
+                .filter_map(|symbol| {
+                    fuzzy_match_score(&info.query, &symbol.name).map(|score| (score, symbol))
+                })
+                .collect();
+            // Symbols come sorted by location, which the stable sort keeps within a score
+            scored.sort_by(|(a, _), (b, _)| b.cmp(a));
+            let symbols: Vec<Symbol> = scored.into_iter().map(|(_, symbol)| symbol).collect();
+            HttpResponse::Ok().json(symbols)
+        }
+        Err(e) => e.into_http_response(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use actix_web::http::StatusCode;
+
+    use crate::initialize_app_state;
+    use crate::test_utils::{python_sample_path, TestContext};
+
+    #[tokio::test]
+    async fn test_python_workspace_symbols() -> Result<(), Box<dyn std::error::Error>> {
+        let _context = TestContext::setup(&python_sample_path(), false).await?;
+        let state = initialize_app_state().await?;
+
+        let mock_request = Query(WorkspaceSymbolsRequest {
+            query: String::from("astargr"),
+            kind: Some(String::from("class")),
+        });
+
+        let response = workspace_symbols(state, mock_request).await;
+
+        assert_eq!(response.status(), StatusCode::OK, "{:?}", response.body());
+
+        let bytes = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let symbols: Vec<Symbol> = serde_json::from_slice(&bytes)?;
+
+        let first = symbols.first().expect("AStarGraph should be found");
+        assert_eq!(first.name, "AStarGraph");
+        assert_eq!(first.kind, "class");
+        assert_eq!(first.identifier_position.path, "graph.py");
+        assert_eq!(first.identifier_position.position.line, 12);
+        assert_eq!(first.identifier_position.position.character, 6);
+        for symbol in &symbols {
+            assert_eq!(symbol.kind, "class");
+        }
+        Ok(())
+    }
+}
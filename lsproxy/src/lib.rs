@@ -7,7 +7,7 @@ use actix_web::{
 use api_types::{FindIdentifierRequest, IdentifierResponse};
 use handlers::{find_identifier, read_source_code};
 use log::{error, info, warn};
-use middleware::{validate_jwt_config, JwtMiddleware};
+use middleware::{validate_jwt_config, IndexingMiddleware, JwtMiddleware};
 use std::fs;
 use std::fs::File;
 use std::io::Write;
@@ -22,15 +22,31 @@ mod handlers;
 mod lsp;
 pub mod utils;
 
+pub use ast_grep::scan::set_global_rules_dir;
+
 use crate::api_types::{
-    get_mount_dir, set_global_mount_dir, CodeContext, DefinitionResponse, ErrorResponse,
-    FilePosition, FileRange, FileSymbolsRequest, GetDefinitionRequest, GetReferencedSymbolsRequest,
-    GetReferencesRequest, HealthResponse, Position, ReferenceWithSymbolDefinitions,
-    ReferencedSymbolsResponse, ReferencesResponse, SupportedLanguages, Symbol, SymbolResponse,
+    get_mount_dir, set_global_mount_dir, CallHierarchyNode, CallHierarchyResponse,
+    CloseOverlayRequest, CodeAction, CodeActionsRequest, CodeActionsResponse, CodeContext,
+    CompletionItem, CompletionsResponse, DefinitionResponse, Diagnostic, DiagnosticSeverity,
+    DiagnosticsResponse, DocumentCacheStats, DocumentSymbol, ErrorResponse,
+    ExecuteCodeActionRequest, ExecuteCodeActionResponse, FileDiagnosticsRequest, FileOverlay,
+    FilePosition, FileRange, FileSymbolsRequest, FileTextEdits, GetCallHierarchyRequest,
+    GetCompletionsRequest, GetDefinitionRequest, GetHoverRequest, GetReferencedSymbolsRequest,
+    GetReferencesRequest, GetSignatureHelpRequest, GetTypeHierarchyRequest, HealthResponse,
+    HoverContent, HoverContentKind, HoverResponse, LanguageServerStatus, OverlayRequest,
+    OverlayResponse, ParameterInformation, Position, ReferenceWithSymbolDefinitions,
+    ReferencedSymbolsResponse, ReferencesResponse, RenameRequest, RenameResponse,
+    SignatureHelpResponse, SignatureInformation, SupportedLanguages, Symbol, SymbolResponse,
+    TextEdit, TypeHierarchyNode, TypeHierarchyResponse, TypeHierarchySource,
+    WorkspaceDiagnosticsRequest, WorkspaceSymbolsRequest,
 };
 use crate::handlers::{
-    definitions_in_file, find_definition, find_referenced_symbols, find_references, health_check,
-    list_files,
+    close_overlay, code_actions, completions, definitions_in_file, document_symbols,
+    execute_code_action, file_diagnostics, find_declaration, find_definition, find_implementations,
+    find_referenced_symbols, find_references, find_type_definition, health_check, hover,
+    incoming_calls, list_files, open_overlay, outgoing_calls, rename, signature_help,
+    structural_rewrite, structural_search, type_hierarchy, update_overlay, workspace_diagnostics,
+    workspace_symbols,
 };
 use crate::lsp::manager::Manager;
 // use crate::utils::doc_utils::make_code_sample;
@@ -72,8 +88,48 @@ pub fn check_mount_dir() -> std::io::Result<()> {
             CodeContext,
             FileRange,
             HealthResponse,
+            DocumentCacheStats,
+            LanguageServerStatus,
             FindIdentifierRequest,
             IdentifierResponse,
+            GetHoverRequest,
+            HoverResponse,
+            HoverContent,
+            HoverContentKind,
+            GetCallHierarchyRequest,
+            CallHierarchyResponse,
+            CallHierarchyNode,
+            GetTypeHierarchyRequest,
+            TypeHierarchyResponse,
+            TypeHierarchyNode,
+            TypeHierarchySource,
+            FileDiagnosticsRequest,
+            WorkspaceDiagnosticsRequest,
+            DiagnosticsResponse,
+            Diagnostic,
+            DiagnosticSeverity,
+            RenameRequest,
+            RenameResponse,
+            FileTextEdits,
+            TextEdit,
+            CodeActionsRequest,
+            CodeActionsResponse,
+            CodeAction,
+            ExecuteCodeActionRequest,
+            ExecuteCodeActionResponse,
+            WorkspaceSymbolsRequest,
+            DocumentSymbol,
+            GetSignatureHelpRequest,
+            SignatureHelpResponse,
+            SignatureInformation,
+            ParameterInformation,
+            GetCompletionsRequest,
+            CompletionsResponse,
+            CompletionItem,
+            FileOverlay,
+            OverlayRequest,
+            CloseOverlayRequest,
+            OverlayResponse,
         )
     ),
     paths(
@@ -85,6 +141,27 @@ pub fn check_mount_dir() -> std::io::Result<()> {
         crate::handlers::read_source_code,
         crate::handlers::find_referenced_symbols,
         crate::handlers::find_identifier,
+        crate::handlers::hover,
+        crate::handlers::incoming_calls,
+        crate::handlers::outgoing_calls,
+        crate::handlers::type_hierarchy,
+        crate::handlers::find_implementations,
+        crate::handlers::find_type_definition,
+        crate::handlers::find_declaration,
+        crate::handlers::file_diagnostics,
+        crate::handlers::workspace_diagnostics,
+        crate::handlers::rename,
+        crate::handlers::code_actions,
+        crate::handlers::execute_code_action,
+        crate::handlers::structural_rewrite,
+        crate::handlers::workspace_symbols,
+        crate::handlers::document_symbols,
+        crate::handlers::signature_help,
+        crate::handlers::completions,
+        crate::handlers::open_overlay,
+        crate::handlers::update_overlay,
+        crate::handlers::close_overlay,
+        crate::handlers::structural_search,
     ),
     tags(
         (name = "lsproxy-api", description = "LSP Proxy API")
@@ -208,14 +285,56 @@ pub async fn run_server_with_port_and_host(
             api_scope = match (path.as_str(), method) {
                 ("/symbol/find-definition", Some(Method::Post)) =>
                     api_scope.service(resource(path).route(post().to(find_definition))),
+                ("/symbol/find-implementations", Some(Method::Post)) =>
+                    api_scope.service(resource(path).route(post().to(find_implementations))),
+                ("/symbol/find-type-definition", Some(Method::Post)) =>
+                    api_scope.service(resource(path).route(post().to(find_type_definition))),
+                ("/symbol/find-declaration", Some(Method::Post)) =>
+                    api_scope.service(resource(path).route(post().to(find_declaration))),
                 ("/symbol/find-references", Some(Method::Post)) =>
                     api_scope.service(resource(path).route(post().to(find_references))),
                 ("/symbol/find-referenced-symbols", Some(Method::Post)) =>
                     api_scope.service(resource(path).route(post().to(find_referenced_symbols))),
                 ("/symbol/find-identifier", Some(Method::Post)) =>
                     api_scope.service(resource(path).route(post().to(find_identifier))),
+                ("/symbol/hover", Some(Method::Post)) =>
+                    api_scope.service(resource(path).route(post().to(hover))),
+                ("/symbol/call-hierarchy/incoming", Some(Method::Post)) =>
+                    api_scope.service(resource(path).route(post().to(incoming_calls))),
+                ("/symbol/call-hierarchy/outgoing", Some(Method::Post)) =>
+                    api_scope.service(resource(path).route(post().to(outgoing_calls))),
+                ("/symbol/type-hierarchy", Some(Method::Post)) =>
+                    api_scope.service(resource(path).route(post().to(type_hierarchy))),
                 ("/symbol/definitions-in-file", Some(Method::Get)) =>
                     api_scope.service(resource(path).route(get().to(definitions_in_file))),
+                ("/symbol/workspace-symbols", Some(Method::Get)) =>
+                    api_scope.service(resource(path).route(get().to(workspace_symbols))),
+                ("/symbol/document-symbols", Some(Method::Get)) =>
+                    api_scope.service(resource(path).route(get().to(document_symbols))),
+                ("/symbol/signature-help", Some(Method::Post)) =>
+                    api_scope.service(resource(path).route(post().to(signature_help))),
+                ("/symbol/completions", Some(Method::Post)) =>
+                    api_scope.service(resource(path).route(post().to(completions))),
+                ("/workspace/overlay/open", Some(Method::Post)) =>
+                    api_scope.service(resource(path).route(post().to(open_overlay))),
+                ("/workspace/overlay/update", Some(Method::Post)) =>
+                    api_scope.service(resource(path).route(post().to(update_overlay))),
+                ("/workspace/overlay/close", Some(Method::Post)) =>
+                    api_scope.service(resource(path).route(post().to(close_overlay))),
+                ("/refactor/rename", Some(Method::Post)) =>
+                    api_scope.service(resource(path).route(post().to(rename))),
+                ("/refactor/code-actions", Some(Method::Post)) =>
+                    api_scope.service(resource(path).route(post().to(code_actions))),
+                ("/refactor/code-actions/execute", Some(Method::Post)) =>
+                    api_scope.service(resource(path).route(post().to(execute_code_action))),
+                ("/refactor/structural-rewrite", Some(Method::Post)) =>
+                    api_scope.service(resource(path).route(post().to(structural_rewrite))),
+                ("/file/diagnostics", Some(Method::Get)) =>
+                    api_scope.service(resource(path).route(get().to(file_diagnostics))),
+                ("/workspace/diagnostics", Some(Method::Get)) =>
+                    api_scope.service(resource(path).route(get().to(workspace_diagnostics))),
+                ("/search/structural", Some(Method::Post)) =>
+                    api_scope.service(resource(path).route(post().to(structural_search))),
                 ("/workspace/list-files", Some(Method::Get)) =>
                     api_scope.service(resource(path).route(get().to(list_files))),
                 ("/workspace/read-source-code", Some(Method::Post)) =>
@@ -234,6 +353,7 @@ pub async fn run_server_with_port_and_host(
             .wrap(Cors::permissive())
             .app_data(app_state.clone())
             .configure(|cfg| {
+                let api_scope = api_scope.wrap(IndexingMiddleware);
                 if middleware::is_auth_enabled() {
                     cfg.service(api_scope.wrap(JwtMiddleware));
                 } else {
@@ -5,25 +5,148 @@ use crate::utils::file_utils::{detect_language_string, search_paths, FileType};
 use async_trait::async_trait;
 use log::{debug, error, info, warn};
 use lsp_types::{
-    ClientCapabilities, DidOpenTextDocumentParams, DocumentSymbolClientCapabilities,
-    GotoDefinitionParams, GotoDefinitionResponse, InitializeParams, InitializeResult, Location,
-    PartialResultParams, Position, PublishDiagnosticsClientCapabilities, ReferenceContext,
-    ReferenceParams, TagSupport, TextDocumentClientCapabilities, TextDocumentIdentifier,
-    TextDocumentItem, TextDocumentPositionParams, Url, WorkDoneProgressParams, WorkspaceFolder,
+    ApplyWorkspaceEditParams, CallHierarchyIncomingCall, CallHierarchyIncomingCallsParams,
+    CallHierarchyItem, CallHierarchyOutgoingCall, CallHierarchyOutgoingCallsParams,
+    CallHierarchyPrepareParams, ClientCapabilities, CodeAction, CodeActionCapabilityResolveSupport,
+    CodeActionClientCapabilities, CodeActionContext, CodeActionKind, CodeActionKindLiteralSupport,
+    CodeActionLiteralSupport, CodeActionOrCommand, CodeActionParams, CodeActionTriggerKind,
+    Command, CompletionClientCapabilities, CompletionContext, CompletionItem,
+    CompletionItemCapability, CompletionItemCapabilityResolveSupport, CompletionParams,
+    CompletionResponse, CompletionTriggerKind, Diagnostic, DiagnosticClientCapabilities,
+    DidChangeTextDocumentParams, DidChangeWatchedFilesClientCapabilities,
+    DidChangeWatchedFilesParams, DidCloseTextDocumentParams, DidOpenTextDocumentParams,
+    DocumentDiagnosticParams, DocumentDiagnosticReport, DocumentDiagnosticReportResult,
+    DocumentSymbolClientCapabilities, DocumentSymbolParams, DocumentSymbolResponse,
+    ExecuteCommandParams, FileEvent, GotoDefinitionParams, GotoDefinitionResponse, Hover,
+    HoverParams, InitializeParams, InitializeResult, Location, MarkupKind, PartialResultParams,
+    Position, PrepareRenameResponse, PublishDiagnosticsClientCapabilities, Range, ReferenceContext,
+    ReferenceParams, RenameClientCapabilities, RenameParams, SignatureHelp, SignatureHelpContext,
+    SignatureHelpParams, SignatureHelpTriggerKind, TagSupport, TextDocumentClientCapabilities,
+    TextDocumentContentChangeEvent, TextDocumentIdentifier, TextDocumentItem,
+    TextDocumentPositionParams, TypeHierarchyItem, TypeHierarchyPrepareParams,
+    TypeHierarchySubtypesParams, TypeHierarchySupertypesParams, Url,
+    VersionedTextDocumentIdentifier, WindowClientCapabilities, WorkDoneProgressParams,
+    WorkspaceClientCapabilities, WorkspaceDiagnosticParams, WorkspaceDiagnosticReportResult,
+    WorkspaceDocumentDiagnosticReport, WorkspaceEdit, WorkspaceEditClientCapabilities,
+    WorkspaceFolder, WorkspaceSymbolParams, WorkspaceSymbolResponse,
 };
+use std::collections::HashMap;
 use std::error::Error;
+use std::fmt;
 use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
 
 use crate::utils::workspace_documents::{
     DidOpenConfiguration, WorkspaceDocuments, WorkspaceDocumentsHandler, DEFAULT_EXCLUDE_PATTERNS,
 };
 
-use super::PendingRequests;
+use super::{DiagnosticsCache, PendingRequests, ProgressTracker, ServerRequestHandlers};
+
+/// How long to wait for a server to publish the diagnostics of a newly opened document.
+const PUBLISH_DIAGNOSTICS_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long to wait for the response to a request, unless the method has its own timeout.
+const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Timeouts of the methods that take longer than most, like those that scan the workspace or
+/// initialization, during which some servers index the whole workspace.
+const METHOD_REQUEST_TIMEOUTS: &[(&str, Duration)] = &[
+    ("initialize", Duration::from_secs(600)),
+    ("textDocument/references", Duration::from_secs(120)),
+    ("workspace/symbol", Duration::from_secs(120)),
+    ("workspace/diagnostic", Duration::from_secs(120)),
+    ("workspace/executeCommand", Duration::from_secs(120)),
+];
+
+/// Timeouts overriding the defaults, read from `REQUEST_TIMEOUTS`.
+static REQUEST_TIMEOUT_OVERRIDES: LazyLock<HashMap<String, Duration>> = LazyLock::new(|| {
+    std::env::var("REQUEST_TIMEOUTS")
+        .map(|timeouts| parse_request_timeouts(&timeouts))
+        .unwrap_or_default()
+});
+
+/// Parses comma separated `method=seconds` timeouts, where the `default` method sets the timeout
+/// of every method without its own, e.g. `default=30,textDocument/references=300`.
+pub fn parse_request_timeouts(timeouts: &str) -> HashMap<String, Duration> {
+    timeouts
+        .split(',')
+        .filter_map(|timeout| {
+            let (method, seconds) = timeout.split_once('=')?;
+            match seconds.trim().parse() {
+                Ok(seconds) => Some((method.trim().to_string(), Duration::from_secs(seconds))),
+                Err(_) => {
+                    warn!("Ignoring invalid request timeout: {}", timeout);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// The error of a request the server didn't answer in time, after which it was cancelled.
+#[derive(Debug)]
+pub struct RequestTimeout {
+    pub method: String,
+    pub timeout: Duration,
+}
+
+impl fmt::Display for RequestTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} request timed out after {}s",
+            self.method,
+            self.timeout.as_secs()
+        )
+    }
+}
+
+impl std::error::Error for RequestTimeout {}
+
+/// Cancels a request when dropped before its response arrived, e.g. when it timed out or the
+/// HTTP client disconnected and the handler waiting for it was dropped: the request stops
+/// being pending and the server is sent `$/cancelRequest`.
+struct RequestCancellation {
+    id: u64,
+    process: ProcessHandler,
+    json_rpc: JsonRpcHandler,
+    pending_requests: PendingRequests,
+    responded: bool,
+}
+
+impl Drop for RequestCancellation {
+    fn drop(&mut self) {
+        if self.responded {
+            return;
+        }
+        let id = self.id;
+        let process = self.process.clone();
+        let json_rpc = self.json_rpc.clone();
+        let pending_requests = self.pending_requests.clone();
+        tokio::spawn(async move {
+            if let Ok(None) = pending_requests.remove_request(id).await {
+                // The response arrived in the meantime
+                return;
+            }
+            debug!("Cancelling request {}", id);
+            let notification =
+                json_rpc.create_notification("$/cancelRequest", serde_json::json!({ "id": id }));
+            let message = format!(
+                "Content-Length: {}\r\n\r\n{}",
+                notification.len(),
+                notification
+            );
+            let _ = process.send(&message).await;
+        });
+    }
+}
 
 #[async_trait]
-pub trait LspClient: Send {
+pub trait LspClient: Send + Sync {
     async fn initialize(
-        &mut self,
+        &self,
         root_path: String,
     ) -> Result<InitializeResult, Box<dyn Error + Send + Sync>> {
         info!("Initializing LSP client with root path: {:?}", root_path);
@@ -40,7 +163,7 @@ pub trait LspClient: Send {
         Ok(init_result)
     }
 
-    fn get_capabilities(&mut self) -> ClientCapabilities {
+    fn get_capabilities(&self) -> ClientCapabilities {
         let mut capabilities = ClientCapabilities::default();
         capabilities.text_document = Some(TextDocumentClientCapabilities {
             document_symbol: Some(DocumentSymbolClientCapabilities {
@@ -48,7 +171,11 @@ pub trait LspClient: Send {
                 hierarchical_document_symbol_support: Some(true),
                 ..Default::default()
             }),
-            // Turn off diagnostics for performance, we don't use them at the moment
+            diagnostic: self.get_diagnostic_capabilities(),
+            code_action: self.get_code_action_capabilities(),
+            completion: self.get_completion_capabilities(),
+            rename: self.get_rename_capabilities(),
+            // Keep diagnostics minimal for performance, they're only collected when enabled
             publish_diagnostics: Some(PublishDiagnosticsClientCapabilities {
                 related_information: Some(false),
                 tag_support: Some(TagSupport { value_set: vec![] }),
@@ -58,6 +185,8 @@ pub trait LspClient: Send {
             }),
             ..Default::default()
         });
+        capabilities.workspace = self.get_workspace_capabilities();
+        capabilities.window = self.get_window_capabilities();
 
         capabilities.experimental = Some(serde_json::json!({
             "serverStatusNotification": true
@@ -65,8 +194,114 @@ pub trait LspClient: Send {
         capabilities
     }
 
+    /// Lets renames check the position with `textDocument/prepareRename` first, see
+    /// `Manager::rename`.
+    fn get_rename_capabilities(&self) -> Option<RenameClientCapabilities> {
+        Some(RenameClientCapabilities {
+            dynamic_registration: Some(false),
+            prepare_support: Some(true),
+            ..Default::default()
+        })
+    }
+
+    /// Asks for code action literals, so actions come with their title and kind, and lets servers
+    /// defer computing an action's edit until it's resolved.
+    fn get_code_action_capabilities(&self) -> Option<CodeActionClientCapabilities> {
+        Some(CodeActionClientCapabilities {
+            dynamic_registration: Some(false),
+            code_action_literal_support: Some(CodeActionLiteralSupport {
+                code_action_kind: CodeActionKindLiteralSupport {
+                    value_set: [
+                        CodeActionKind::EMPTY,
+                        CodeActionKind::QUICKFIX,
+                        CodeActionKind::REFACTOR,
+                        CodeActionKind::REFACTOR_EXTRACT,
+                        CodeActionKind::REFACTOR_INLINE,
+                        CodeActionKind::REFACTOR_REWRITE,
+                        CodeActionKind::SOURCE,
+                        CodeActionKind::SOURCE_ORGANIZE_IMPORTS,
+                        CodeActionKind::SOURCE_FIX_ALL,
+                    ]
+                    .iter()
+                    .map(|kind| kind.as_str().to_string())
+                    .collect(),
+                },
+            }),
+            is_preferred_support: Some(true),
+            disabled_support: Some(true),
+            data_support: Some(true),
+            resolve_support: Some(CodeActionCapabilityResolveSupport {
+                properties: vec!["edit".to_string()],
+            }),
+            honors_change_annotations: Some(false),
+        })
+    }
+
+    /// Asks for plain text completions, since snippets can't be inserted as is, and lets servers
+    /// defer computing the details of each item until it's resolved.
+    fn get_completion_capabilities(&self) -> Option<CompletionClientCapabilities> {
+        Some(CompletionClientCapabilities {
+            dynamic_registration: Some(false),
+            completion_item: Some(CompletionItemCapability {
+                snippet_support: Some(false),
+                documentation_format: Some(vec![MarkupKind::Markdown, MarkupKind::PlainText]),
+                preselect_support: Some(true),
+                resolve_support: Some(CompletionItemCapabilityResolveSupport {
+                    properties: vec!["detail".to_string(), "documentation".to_string()],
+                }),
+                label_details_support: Some(true),
+                ..Default::default()
+            }),
+            context_support: Some(true),
+            ..Default::default()
+        })
+    }
+
+    /// Lets servers send `workspace/applyEdit` requests, e.g. when executing a code action's
+    /// command. The edits are captured rather than applied, see `workspace_execute_command`.
+    /// Servers can ask for their settings with `workspace/configuration`, see
+    /// `get_workspace_settings`.
+    ///
+    /// Files changed on disk are sent with `workspace/didChangeWatchedFiles` whether or not
+    /// servers register for them.
+    fn get_workspace_capabilities(&self) -> Option<WorkspaceClientCapabilities> {
+        Some(WorkspaceClientCapabilities {
+            apply_edit: Some(true),
+            configuration: Some(true),
+            workspace_edit: Some(WorkspaceEditClientCapabilities {
+                document_changes: Some(true),
+                ..Default::default()
+            }),
+            did_change_watched_files: Some(DidChangeWatchedFilesClientCapabilities {
+                dynamic_registration: Some(false),
+                relative_pattern_support: Some(false),
+            }),
+            ..Default::default()
+        })
+    }
+
+    /// Lets servers report the work they do, such as indexing, so their readiness can be
+    /// tracked, see `ProgressTracker`.
+    fn get_window_capabilities(&self) -> Option<WindowClientCapabilities> {
+        Some(WindowClientCapabilities {
+            work_done_progress: Some(true),
+            ..Default::default()
+        })
+    }
+
+    /// Advertises pull diagnostics only when diagnostics are enabled for the language, so servers
+    /// don't compute them otherwise.
+    fn get_diagnostic_capabilities(&self) -> Option<DiagnosticClientCapabilities> {
+        self.get_diagnostics()
+            .is_enabled()
+            .then_some(DiagnosticClientCapabilities {
+                dynamic_registration: Some(false),
+                related_document_support: Some(false),
+            })
+    }
+
     async fn get_initialize_params(
-        &mut self,
+        &self,
         root_path: String,
     ) -> Result<InitializeParams, Box<dyn Error + Send + Sync>> {
         let workspace_folders = self.find_workspace_folders(root_path.clone()).await?;
@@ -78,23 +313,53 @@ pub trait LspClient: Send {
         })
     }
 
+    /// How long to wait for the response to a request before cancelling it.
+    fn get_request_timeout(&self, method: &str) -> Duration {
+        let overrides = &*REQUEST_TIMEOUT_OVERRIDES;
+        overrides
+            .get(method)
+            .or_else(|| {
+                METHOD_REQUEST_TIMEOUTS
+                    .iter()
+                    .find(|(timeout_method, _)| *timeout_method == method)
+                    .map(|(_, timeout)| timeout)
+            })
+            .or_else(|| overrides.get("default"))
+            .copied()
+            .unwrap_or(DEFAULT_REQUEST_TIMEOUT)
+    }
+
+    /// Sends a request and waits for its response, cancelling it if it takes longer than
+    /// `get_request_timeout` or the caller stops waiting.
     async fn send_request(
-        &mut self,
+        &self,
         method: &str,
         params: Option<serde_json::Value>,
     ) -> Result<serde_json::Value, Box<dyn Error + Send + Sync>> {
         let (id, request) = self.get_json_rpc().create_request(method, params);
 
         let mut response_receiver = self.get_pending_requests().add_request(id).await?;
+        let mut cancellation = RequestCancellation {
+            id,
+            process: self.get_process().clone(),
+            json_rpc: self.get_json_rpc().clone(),
+            pending_requests: self.get_pending_requests().clone(),
+            responded: false,
+        };
 
         let message = format!("Content-Length: {}\r\n\r\n{}", request.len(), request);
         debug!("Message: {:?}", message);
         self.get_process().send(&message).await?;
 
-        let response = response_receiver
-            .recv()
+        let timeout = self.get_request_timeout(method);
+        let response = tokio::time::timeout(timeout, response_receiver.recv())
             .await
+            .map_err(|_| RequestTimeout {
+                method: method.to_string(),
+                timeout,
+            })?
             .map_err(|e| format!("Failed to receive response: {}", e))?;
+        cancellation.responded = true;
 
         if let Some(result) = response.result {
             Ok(result)
@@ -109,34 +374,69 @@ pub trait LspClient: Send {
         }
     }
 
-    async fn start_response_listener(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
-        let mut process = self.get_process().clone();
+    async fn start_response_listener(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let process = self.get_process().clone();
         let pending_requests = self.get_pending_requests().clone();
         let json_rpc = self.get_json_rpc().clone();
+        let diagnostics = self.get_diagnostics().clone();
+        let progress = self.get_progress().clone();
+        let handlers = self.get_server_request_handlers();
 
         tokio::spawn(async move {
-            loop {
-                if let Ok(raw_response) = process.receive().await {
+            let reason = loop {
+                let received = tokio::select! {
+                    received = process.receive() => received,
+                    exit = process.wait_for_exit() => {
+                        break format!("Language server exited: {}", exit)
+                    }
+                };
+                match &received {
+                    Err(e) if e.is::<std::io::Error>() => {
+                        break format!("Language server stopped: {}", e)
+                    }
+                    _ => {}
+                }
+                if let Ok(raw_response) = received {
                     if let Ok(message) = json_rpc.parse_message(&raw_response) {
-                        if let Some(id) = message.id {
+                        if let (Some(id), Some(method)) = (message.id, message.method.as_deref()) {
+                            debug!("Received server request {} - Method: {}", id, method);
+                            let response = match handlers.handle(method, &message).await {
+                                Ok(result) => json_rpc.create_response(id, result),
+                                Err(error) => json_rpc.create_error_response(id, error),
+                            };
+                            let message =
+                                format!("Content-Length: {}\r\n\r\n{}", response.len(), response);
+                            let _ = process.send(&message).await;
+                        } else if let Some(id) = message.id {
                             debug!("Received response for request {}", id);
                             if let Ok(Some(sender)) = pending_requests.remove_request(id).await {
                                 if sender.send(message.clone()).is_err() {
                                     error!("Failed to send response for request {}", id);
                                 }
                             } else {
-                                debug!(
-                                    "Responding to server message {} - Message: {:?}",
-                                    id, message
-                                );
-                                let response = json_rpc.create_success_response(id);
-
-                                let message = format!(
-                                    "Content-Length: {}\r\n\r\n{}",
-                                    response.len(),
-                                    response
-                                );
-                                let _ = process.send(&message).await;
+                                // A late response to a request that timed out or was
+                                // cancelled, which nothing waits for anymore.
+                                debug!("Dropping response for request {} no longer pending", id);
+                            }
+                        } else if message.method.as_deref()
+                            == Some("textDocument/publishDiagnostics")
+                        {
+                            if let Some(params) = message
+                                .params
+                                .and_then(|params| serde_json::from_value(params).ok())
+                            {
+                                diagnostics.publish(params).await;
+                            }
+                        } else if message.method.as_deref() == Some("$/progress") {
+                            if let Some(params) = message
+                                .params
+                                .and_then(|params| serde_json::from_value(params).ok())
+                            {
+                                progress.update(params);
+                            }
+                        } else if message.method.as_deref() == Some("experimental/serverStatus") {
+                            if let Some(params) = message.params {
+                                progress.set_server_status(params);
                             }
                         } else if let Some(params) = message.params.clone() {
                             let message_key = ExpectedMessageKey {
@@ -151,13 +451,15 @@ pub trait LspClient: Send {
                         }
                     }
                 }
-            }
+            };
+            error!("{}", reason);
+            pending_requests.close(&reason).await;
         });
 
         Ok(())
     }
 
-    async fn send_initialized(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
+    async fn send_initialized(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
         debug!("Sending 'initialized' notification");
         let notification = self
             .get_json_rpc()
@@ -171,7 +473,7 @@ pub trait LspClient: Send {
     }
 
     async fn text_document_did_open(
-        &mut self,
+        &self,
         item: lsp_types::TextDocumentItem,
     ) -> Result<(), Box<dyn Error + Send + Sync>> {
         let params = DidOpenTextDocumentParams {
@@ -188,16 +490,14 @@ pub trait LspClient: Send {
         self.get_process().send(&message).await
     }
 
-    async fn text_document_definition(
-        &mut self,
+    /// Sends `textDocument/didOpen` for the file if the client opens documents lazily and the
+    /// file hasn't been opened yet.
+    async fn open_document_if_needed(
+        &self,
         file_path: &str,
-        position: Position,
-    ) -> Result<GotoDefinitionResponse, Box<dyn Error + Send + Sync>> {
-        debug!(
-            "Requesting goto definition for {}, line {}, character {}",
-            file_path, position.line, position.character
-        );
-
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let _document_sync = self.get_workspace_documents().lock_document_sync().await;
+        // Get the configuration and check if document is opened first
         let needs_open = {
             let workspace_documents = self.get_workspace_documents();
             workspace_documents.get_did_open_configuration() == DidOpenConfiguration::Lazy
@@ -222,6 +522,189 @@ pub trait LspClient: Send {
             self.get_workspace_documents()
                 .add_did_open_document(file_path);
         }
+        Ok(())
+    }
+
+    /// Updates the content of a file that was edited on disk, sending the full new text with
+    /// `textDocument/didChange` if the document is open in the server.
+    async fn text_document_did_change(
+        &self,
+        file_path: &str,
+        text: String,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let _document_sync = self.get_workspace_documents().lock_document_sync().await;
+        self.get_workspace_documents()
+            .update_text_document(Path::new(file_path), text.clone())
+            .await;
+        self.send_did_change(file_path, text).await?;
+        Ok(())
+    }
+
+    /// Sends the full text of an open document with `textDocument/didChange`, leaving the cached
+    /// file content as is. Returns false without sending anything if the document isn't open.
+    ///
+    /// Callers should hold `lock_document_sync` so versions are sent in order.
+    async fn send_did_change(
+        &self,
+        file_path: &str,
+        text: String,
+    ) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        let version = match self
+            .get_workspace_documents()
+            .next_document_version(file_path)
+        {
+            Some(version) => version,
+            None => return Ok(false),
+        };
+
+        let params = DidChangeTextDocumentParams {
+            text_document: VersionedTextDocumentIdentifier {
+                uri: Url::from_file_path(file_path).map_err(|_| "Invalid file path")?,
+                version,
+            },
+            content_changes: vec![TextDocumentContentChangeEvent {
+                range: None,
+                range_length: None,
+                text,
+            }],
+        };
+        let notification = self
+            .get_json_rpc()
+            .create_notification("textDocument/didChange", serde_json::to_value(params)?);
+        let message = format!(
+            "Content-Length: {}\r\n\r\n{}",
+            notification.len(),
+            notification
+        );
+        self.get_process().send(&message).await?;
+        Ok(true)
+    }
+
+    async fn text_document_did_close(
+        &self,
+        file_path: &str,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let _document_sync = self.get_workspace_documents().lock_document_sync().await;
+        let params = DidCloseTextDocumentParams {
+            text_document: TextDocumentIdentifier {
+                uri: Url::from_file_path(file_path).map_err(|_| "Invalid file path")?,
+            },
+        };
+        let notification = self
+            .get_json_rpc()
+            .create_notification("textDocument/didClose", serde_json::to_value(params)?);
+        let message = format!(
+            "Content-Length: {}\r\n\r\n{}",
+            notification.len(),
+            notification
+        );
+        self.get_process().send(&message).await?;
+        self.get_workspace_documents()
+            .remove_did_open_document(file_path);
+        Ok(())
+    }
+
+    async fn workspace_did_change_watched_files(
+        &self,
+        changes: Vec<FileEvent>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let params = DidChangeWatchedFilesParams { changes };
+        let notification = self.get_json_rpc().create_notification(
+            "workspace/didChangeWatchedFiles",
+            serde_json::to_value(params)?,
+        );
+        let message = format!(
+            "Content-Length: {}\r\n\r\n{}",
+            notification.len(),
+            notification
+        );
+        self.get_process().send(&message).await
+    }
+
+    /// Makes the server see `text` as the content of the file, without writing it to disk,
+    /// opening the document if needed. Each call sends a new version of the document.
+    async fn show_overlay(
+        &self,
+        file_path: &str,
+        text: String,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let _document_sync = self.get_workspace_documents().lock_document_sync().await;
+        if self.send_did_change(file_path, text.clone()).await? {
+            return Ok(());
+        }
+        self.text_document_did_open(TextDocumentItem {
+            uri: Url::from_file_path(file_path).map_err(|_| "Invalid file path")?,
+            language_id: detect_language_string(file_path)?,
+            version: 1,
+            text,
+        })
+        .await?;
+        self.get_workspace_documents()
+            .add_did_open_document(file_path);
+        Ok(())
+    }
+
+    /// Reverts the server to the content of the file on disk after `show_overlay`.
+    async fn hide_overlay(&self, file_path: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let _document_sync = self.get_workspace_documents().lock_document_sync().await;
+        let text = self
+            .get_workspace_documents()
+            .read_text_document(&PathBuf::from(file_path), None)
+            .await?;
+        self.send_did_change(file_path, text).await?;
+        Ok(())
+    }
+
+    async fn text_document_definition(
+        &self,
+        file_path: &str,
+        position: Position,
+    ) -> Result<GotoDefinitionResponse, Box<dyn Error + Send + Sync>> {
+        self.text_document_goto("textDocument/definition", file_path, position)
+            .await
+    }
+
+    async fn text_document_implementation(
+        &self,
+        file_path: &str,
+        position: Position,
+    ) -> Result<GotoDefinitionResponse, Box<dyn Error + Send + Sync>> {
+        self.text_document_goto("textDocument/implementation", file_path, position)
+            .await
+    }
+
+    async fn text_document_type_definition(
+        &self,
+        file_path: &str,
+        position: Position,
+    ) -> Result<GotoDefinitionResponse, Box<dyn Error + Send + Sync>> {
+        self.text_document_goto("textDocument/typeDefinition", file_path, position)
+            .await
+    }
+
+    async fn text_document_declaration(
+        &self,
+        file_path: &str,
+        position: Position,
+    ) -> Result<GotoDefinitionResponse, Box<dyn Error + Send + Sync>> {
+        self.text_document_goto("textDocument/declaration", file_path, position)
+            .await
+    }
+
+    /// Sends one of the goto requests (definition, implementation, typeDefinition, declaration),
+    /// which all share the same params and response shapes.
+    async fn text_document_goto(
+        &self,
+        method: &str,
+        file_path: &str,
+        position: Position,
+    ) -> Result<GotoDefinitionResponse, Box<dyn Error + Send + Sync>> {
+        debug!(
+            "Requesting {} for {}, line {}, character {}",
+            method, file_path, position.line, position.character
+        );
+
+        self.open_document_if_needed(file_path).await?;
 
         let params = GotoDefinitionParams {
             text_document_position_params: TextDocumentPositionParams {
@@ -235,10 +718,7 @@ pub trait LspClient: Send {
         };
 
         let result = self
-            .send_request(
-                "textDocument/definition",
-                Some(serde_json::to_value(params)?),
-            )
+            .send_request(method, Some(serde_json::to_value(params)?))
             .await?;
 
         // If result is null, default to an empty array response instead of failing deserialization
@@ -248,40 +728,16 @@ pub trait LspClient: Send {
             serde_json::from_value(result)?
         };
 
-        debug!("Received goto definition response");
+        debug!("Received {} response", method);
         Ok(goto_resp)
     }
 
     async fn text_document_reference(
-        &mut self,
+        &self,
         file_path: &str,
         position: Position,
     ) -> Result<Vec<Location>, Box<dyn Error + Send + Sync>> {
-        // Get the configuration and check if document is opened first
-        let needs_open = {
-            let workspace_documents = self.get_workspace_documents();
-            workspace_documents.get_did_open_configuration() == DidOpenConfiguration::Lazy
-                && !workspace_documents.is_did_open_document(file_path)
-        };
-
-        // If needed, read the document text and send didOpen
-        if needs_open {
-            let document_text = self
-                .get_workspace_documents()
-                .read_text_document(&PathBuf::from(file_path), None)
-                .await?;
-
-            self.text_document_did_open(TextDocumentItem {
-                uri: Url::from_file_path(file_path).unwrap(),
-                language_id: detect_language_string(file_path)?,
-                version: 1,
-                text: document_text,
-            })
-            .await?;
-
-            self.get_workspace_documents()
-                .add_did_open_document(file_path);
-        }
+        self.open_document_if_needed(file_path).await?;
 
         let params = ReferenceParams {
             text_document_position: TextDocumentPositionParams {
@@ -313,55 +769,741 @@ pub trait LspClient: Send {
         Ok(ref_resp)
     }
 
-    fn get_process(&mut self) -> &mut ProcessHandler;
+    async fn text_document_hover(
+        &self,
+        file_path: &str,
+        position: Position,
+    ) -> Result<Option<Hover>, Box<dyn Error + Send + Sync>> {
+        debug!(
+            "Requesting hover for {}, line {}, character {}",
+            file_path, position.line, position.character
+        );
 
-    fn get_json_rpc(&mut self) -> &mut JsonRpcHandler;
+        self.open_document_if_needed(file_path).await?;
 
-    fn get_root_files(&mut self) -> Vec<String> {
-        vec![".git".to_string()]
-    }
+        let params = HoverParams {
+            text_document_position_params: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier {
+                    uri: Url::from_file_path(file_path).map_err(|_| "Invalid file path")?,
+                },
+                position,
+            },
+            work_done_progress_params: WorkDoneProgressParams::default(),
+        };
 
-    fn get_pending_requests(&mut self) -> &mut PendingRequests;
+        let result = self
+            .send_request("textDocument/hover", Some(serde_json::to_value(params)?))
+            .await?;
 
-    fn get_workspace_documents(&mut self) -> &mut WorkspaceDocumentsHandler;
-    /// Sets up the workspace for the language server.
-    ///
-    /// Some language servers require specific commands to be run before
-    /// workspace-wide features are available. For example:
-    /// - TypeScript Language Server needs an explicit didOpen notification for each file
-    /// - Rust Analyzer needs a reloadWorkspace command
-    ///
-    /// # Arguments
-    ///
-    /// * `root_path` - The root path of the workspace
-    ///
-    /// # Returns
-    ///
-    /// A Result containing () if successful, or a boxed Error if an error occurred
-    #[allow(unused)]
-    async fn setup_workspace(
-        &mut self,
-        root_path: &str,
-    ) -> Result<(), Box<dyn Error + Send + Sync>> {
-        Ok(())
+        // Servers return null when there is nothing to show at the position
+        let hover_resp: Option<Hover> = if result.is_null() {
+            None
+        } else {
+            Some(serde_json::from_value(result)?)
+        };
+        debug!("Received hover response");
+        Ok(hover_resp)
     }
 
-    async fn find_workspace_folders(
-        &mut self,
-        root_path: String,
-    ) -> Result<Vec<WorkspaceFolder>, Box<dyn Error + Send + Sync>> {
-        let mut workspace_folders: Vec<WorkspaceFolder> = Vec::new();
-        let include_patterns = self
-            .get_root_files()
-            .into_iter()
-            .map(|f| format!("**/{f}"))
-            .collect();
-        let exclude_patterns = DEFAULT_EXCLUDE_PATTERNS
-            .iter()
-            .map(|&s| s.to_string())
-            .collect();
+    async fn text_document_completion(
+        &self,
+        file_path: &str,
+        position: Position,
+    ) -> Result<Option<CompletionResponse>, Box<dyn Error + Send + Sync>> {
+        debug!(
+            "Requesting completions for {}, line {}, character {}",
+            file_path, position.line, position.character
+        );
 
-        match search_paths(Path::new(&root_path), include_patterns, exclude_patterns, true, FileType::Dir) {
+        self.open_document_if_needed(file_path).await?;
+
+        let params = CompletionParams {
+            text_document_position: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier {
+                    uri: Url::from_file_path(file_path).map_err(|_| "Invalid file path")?,
+                },
+                position,
+            },
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: PartialResultParams::default(),
+            context: Some(CompletionContext {
+                trigger_kind: CompletionTriggerKind::INVOKED,
+                trigger_character: None,
+            }),
+        };
+
+        let result = self
+            .send_request(
+                "textDocument/completion",
+                Some(serde_json::to_value(params)?),
+            )
+            .await?;
+
+        let completions: Option<CompletionResponse> = serde_json::from_value(result)?;
+        debug!("Received completion response");
+        Ok(completions)
+    }
+
+    async fn completion_item_resolve(
+        &self,
+        item: CompletionItem,
+    ) -> Result<CompletionItem, Box<dyn Error + Send + Sync>> {
+        debug!("Resolving completion item: {}", item.label);
+
+        let result = self
+            .send_request("completionItem/resolve", Some(serde_json::to_value(item)?))
+            .await?;
+
+        Ok(serde_json::from_value(result)?)
+    }
+
+    async fn text_document_signature_help(
+        &self,
+        file_path: &str,
+        position: Position,
+    ) -> Result<Option<SignatureHelp>, Box<dyn Error + Send + Sync>> {
+        debug!(
+            "Requesting signature help for {}, line {}, character {}",
+            file_path, position.line, position.character
+        );
+
+        self.open_document_if_needed(file_path).await?;
+
+        let params = SignatureHelpParams {
+            context: Some(SignatureHelpContext {
+                trigger_kind: SignatureHelpTriggerKind::INVOKED,
+                trigger_character: None,
+                is_retrigger: false,
+                active_signature_help: None,
+            }),
+            text_document_position_params: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier {
+                    uri: Url::from_file_path(file_path).map_err(|_| "Invalid file path")?,
+                },
+                position,
+            },
+            work_done_progress_params: WorkDoneProgressParams::default(),
+        };
+
+        let result = self
+            .send_request(
+                "textDocument/signatureHelp",
+                Some(serde_json::to_value(params)?),
+            )
+            .await?;
+
+        // Servers return null when the position isn't inside a call
+        let signature_help: Option<SignatureHelp> = if result.is_null() {
+            None
+        } else {
+            Some(serde_json::from_value(result)?)
+        };
+        debug!("Received signature help response");
+        Ok(signature_help)
+    }
+
+    async fn text_document_prepare_rename(
+        &self,
+        file_path: &str,
+        position: Position,
+    ) -> Result<Option<PrepareRenameResponse>, Box<dyn Error + Send + Sync>> {
+        debug!(
+            "Preparing rename for {}, line {}, character {}",
+            file_path, position.line, position.character
+        );
+
+        self.open_document_if_needed(file_path).await?;
+
+        let params = TextDocumentPositionParams {
+            text_document: TextDocumentIdentifier {
+                uri: Url::from_file_path(file_path).map_err(|_| "Invalid file path")?,
+            },
+            position,
+        };
+
+        let result = self
+            .send_request(
+                "textDocument/prepareRename",
+                Some(serde_json::to_value(params)?),
+            )
+            .await?;
+
+        // Servers return null when the symbol at the position can't be renamed
+        let prepare_resp: Option<PrepareRenameResponse> = if result.is_null() {
+            None
+        } else {
+            Some(serde_json::from_value(result)?)
+        };
+        debug!("Received prepare rename response");
+        Ok(prepare_resp)
+    }
+
+    async fn text_document_rename(
+        &self,
+        file_path: &str,
+        position: Position,
+        new_name: &str,
+    ) -> Result<Option<WorkspaceEdit>, Box<dyn Error + Send + Sync>> {
+        debug!(
+            "Requesting rename for {}, line {}, character {} to {}",
+            file_path, position.line, position.character, new_name
+        );
+
+        self.open_document_if_needed(file_path).await?;
+
+        let params = RenameParams {
+            text_document_position: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier {
+                    uri: Url::from_file_path(file_path).map_err(|_| "Invalid file path")?,
+                },
+                position,
+            },
+            new_name: new_name.to_string(),
+            work_done_progress_params: WorkDoneProgressParams::default(),
+        };
+
+        let result = self
+            .send_request("textDocument/rename", Some(serde_json::to_value(params)?))
+            .await?;
+
+        let rename_resp: Option<WorkspaceEdit> = if result.is_null() {
+            None
+        } else {
+            Some(serde_json::from_value(result)?)
+        };
+        debug!("Received rename response");
+        Ok(rename_resp)
+    }
+
+    async fn text_document_symbols(
+        &self,
+        file_path: &str,
+    ) -> Result<Option<DocumentSymbolResponse>, Box<dyn Error + Send + Sync>> {
+        debug!("Requesting document symbols for {}", file_path);
+
+        self.open_document_if_needed(file_path).await?;
+
+        let params = DocumentSymbolParams {
+            text_document: TextDocumentIdentifier {
+                uri: Url::from_file_path(file_path).map_err(|_| "Invalid file path")?,
+            },
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: PartialResultParams::default(),
+        };
+
+        let result = self
+            .send_request(
+                "textDocument/documentSymbol",
+                Some(serde_json::to_value(params)?),
+            )
+            .await?;
+
+        let symbols: Option<DocumentSymbolResponse> = serde_json::from_value(result)?;
+        debug!("Received document symbols response");
+        Ok(symbols)
+    }
+
+    async fn workspace_symbols(
+        &self,
+        query: &str,
+    ) -> Result<Option<WorkspaceSymbolResponse>, Box<dyn Error + Send + Sync>> {
+        debug!("Requesting workspace symbols for query: {}", query);
+
+        let params = WorkspaceSymbolParams {
+            query: query.to_string(),
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: PartialResultParams::default(),
+        };
+
+        let result = self
+            .send_request("workspace/symbol", Some(serde_json::to_value(params)?))
+            .await?;
+
+        let symbols: Option<WorkspaceSymbolResponse> = serde_json::from_value(result)?;
+        debug!("Received workspace symbols response");
+        Ok(symbols)
+    }
+
+    async fn text_document_code_action(
+        &self,
+        file_path: &str,
+        range: Range,
+        diagnostics: Vec<Diagnostic>,
+        only: Option<Vec<CodeActionKind>>,
+    ) -> Result<Vec<CodeActionOrCommand>, Box<dyn Error + Send + Sync>> {
+        debug!(
+            "Requesting code actions for {}, from line {}, character {} to line {}, character {}",
+            file_path, range.start.line, range.start.character, range.end.line, range.end.character
+        );
+
+        self.open_document_if_needed(file_path).await?;
+
+        let params = CodeActionParams {
+            text_document: TextDocumentIdentifier {
+                uri: Url::from_file_path(file_path).map_err(|_| "Invalid file path")?,
+            },
+            range,
+            context: CodeActionContext {
+                diagnostics,
+                only,
+                trigger_kind: Some(CodeActionTriggerKind::INVOKED),
+            },
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: PartialResultParams::default(),
+        };
+
+        let result = self
+            .send_request(
+                "textDocument/codeAction",
+                Some(serde_json::to_value(params)?),
+            )
+            .await?;
+
+        let actions: Vec<CodeActionOrCommand> = if result.is_null() {
+            Vec::new()
+        } else {
+            serde_json::from_value(result)?
+        };
+        debug!("Received {} code actions", actions.len());
+        Ok(actions)
+    }
+
+    async fn code_action_resolve(
+        &self,
+        action: CodeAction,
+    ) -> Result<CodeAction, Box<dyn Error + Send + Sync>> {
+        debug!("Resolving code action: {}", action.title);
+
+        let result = self
+            .send_request("codeAction/resolve", Some(serde_json::to_value(action)?))
+            .await?;
+
+        Ok(serde_json::from_value(result)?)
+    }
+
+    /// Executes a command on the server and returns the workspace edits the server asked to
+    /// apply while executing it.
+    async fn workspace_execute_command(
+        &self,
+        command: Command,
+    ) -> Result<Vec<WorkspaceEdit>, Box<dyn Error + Send + Sync>> {
+        debug!("Executing command: {}", command.command);
+
+        let params = ExecuteCommandParams {
+            command: command.command,
+            arguments: command.arguments.unwrap_or_default(),
+            work_done_progress_params: WorkDoneProgressParams::default(),
+        };
+
+        let _command = self.get_pending_requests().lock_commands().await;
+        let mut edit_receiver = self
+            .get_pending_requests()
+            .add_server_request("workspace/applyEdit")
+            .await?;
+        let result = self
+            .send_request(
+                "workspace/executeCommand",
+                Some(serde_json::to_value(params)?),
+            )
+            .await;
+        self.get_pending_requests()
+            .remove_server_request("workspace/applyEdit")
+            .await;
+        result?;
+
+        // Servers wait for the answer to their edits before completing the command
+        let mut edits = Vec::new();
+        while let Ok(message) = edit_receiver.try_recv() {
+            if let Some(params) = message.params {
+                let params: ApplyWorkspaceEditParams = serde_json::from_value(params)?;
+                edits.push(params.edit);
+            }
+        }
+        debug!("Command requested {} workspace edits", edits.len());
+        Ok(edits)
+    }
+
+    async fn text_document_prepare_call_hierarchy(
+        &self,
+        file_path: &str,
+        position: Position,
+    ) -> Result<Vec<CallHierarchyItem>, Box<dyn Error + Send + Sync>> {
+        debug!(
+            "Preparing call hierarchy for {}, line {}, character {}",
+            file_path, position.line, position.character
+        );
+
+        self.open_document_if_needed(file_path).await?;
+
+        let params = CallHierarchyPrepareParams {
+            text_document_position_params: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier {
+                    uri: Url::from_file_path(file_path).map_err(|_| "Invalid file path")?,
+                },
+                position,
+            },
+            work_done_progress_params: WorkDoneProgressParams::default(),
+        };
+
+        let result = self
+            .send_request(
+                "textDocument/prepareCallHierarchy",
+                Some(serde_json::to_value(params)?),
+            )
+            .await?;
+
+        let items: Vec<CallHierarchyItem> = if result.is_null() {
+            Vec::new()
+        } else {
+            serde_json::from_value(result)?
+        };
+        debug!("Received prepare call hierarchy response");
+        Ok(items)
+    }
+
+    async fn call_hierarchy_incoming_calls(
+        &self,
+        item: CallHierarchyItem,
+    ) -> Result<Vec<CallHierarchyIncomingCall>, Box<dyn Error + Send + Sync>> {
+        if let Ok(path) = item.uri.to_file_path() {
+            self.open_document_if_needed(&path.to_string_lossy())
+                .await?;
+        }
+
+        let params = CallHierarchyIncomingCallsParams {
+            item,
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: PartialResultParams::default(),
+        };
+
+        let result = self
+            .send_request(
+                "callHierarchy/incomingCalls",
+                Some(serde_json::to_value(params)?),
+            )
+            .await?;
+
+        let calls: Vec<CallHierarchyIncomingCall> = if result.is_null() {
+            Vec::new()
+        } else {
+            serde_json::from_value(result)?
+        };
+        debug!("Received incoming calls response");
+        Ok(calls)
+    }
+
+    async fn call_hierarchy_outgoing_calls(
+        &self,
+        item: CallHierarchyItem,
+    ) -> Result<Vec<CallHierarchyOutgoingCall>, Box<dyn Error + Send + Sync>> {
+        if let Ok(path) = item.uri.to_file_path() {
+            self.open_document_if_needed(&path.to_string_lossy())
+                .await?;
+        }
+
+        let params = CallHierarchyOutgoingCallsParams {
+            item,
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: PartialResultParams::default(),
+        };
+
+        let result = self
+            .send_request(
+                "callHierarchy/outgoingCalls",
+                Some(serde_json::to_value(params)?),
+            )
+            .await?;
+
+        let calls: Vec<CallHierarchyOutgoingCall> = if result.is_null() {
+            Vec::new()
+        } else {
+            serde_json::from_value(result)?
+        };
+        debug!("Received outgoing calls response");
+        Ok(calls)
+    }
+
+    async fn text_document_prepare_type_hierarchy(
+        &self,
+        file_path: &str,
+        position: Position,
+    ) -> Result<Vec<TypeHierarchyItem>, Box<dyn Error + Send + Sync>> {
+        debug!(
+            "Preparing type hierarchy for {}, line {}, character {}",
+            file_path, position.line, position.character
+        );
+
+        self.open_document_if_needed(file_path).await?;
+
+        let params = TypeHierarchyPrepareParams {
+            text_document_position_params: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier {
+                    uri: Url::from_file_path(file_path).map_err(|_| "Invalid file path")?,
+                },
+                position,
+            },
+            work_done_progress_params: WorkDoneProgressParams::default(),
+        };
+
+        let result = self
+            .send_request(
+                "textDocument/prepareTypeHierarchy",
+                Some(serde_json::to_value(params)?),
+            )
+            .await?;
+
+        let items: Vec<TypeHierarchyItem> = if result.is_null() {
+            Vec::new()
+        } else {
+            serde_json::from_value(result)?
+        };
+        debug!("Received prepare type hierarchy response");
+        Ok(items)
+    }
+
+    async fn type_hierarchy_supertypes(
+        &self,
+        item: TypeHierarchyItem,
+    ) -> Result<Vec<TypeHierarchyItem>, Box<dyn Error + Send + Sync>> {
+        if let Ok(path) = item.uri.to_file_path() {
+            self.open_document_if_needed(&path.to_string_lossy())
+                .await?;
+        }
+
+        let params = TypeHierarchySupertypesParams {
+            item,
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: PartialResultParams::default(),
+        };
+
+        let result = self
+            .send_request(
+                "typeHierarchy/supertypes",
+                Some(serde_json::to_value(params)?),
+            )
+            .await?;
+
+        let items: Vec<TypeHierarchyItem> = if result.is_null() {
+            Vec::new()
+        } else {
+            serde_json::from_value(result)?
+        };
+        debug!("Received supertypes response");
+        Ok(items)
+    }
+
+    async fn type_hierarchy_subtypes(
+        &self,
+        item: TypeHierarchyItem,
+    ) -> Result<Vec<TypeHierarchyItem>, Box<dyn Error + Send + Sync>> {
+        if let Ok(path) = item.uri.to_file_path() {
+            self.open_document_if_needed(&path.to_string_lossy())
+                .await?;
+        }
+
+        let params = TypeHierarchySubtypesParams {
+            item,
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: PartialResultParams::default(),
+        };
+
+        let result = self
+            .send_request(
+                "typeHierarchy/subtypes",
+                Some(serde_json::to_value(params)?),
+            )
+            .await?;
+
+        let items: Vec<TypeHierarchyItem> = if result.is_null() {
+            Vec::new()
+        } else {
+            serde_json::from_value(result)?
+        };
+        debug!("Received subtypes response");
+        Ok(items)
+    }
+
+    /// Gets the diagnostics of a document, pulling them from the server if it supports
+    /// `textDocument/diagnostic` and otherwise waiting for them to be published.
+    async fn text_document_diagnostic(
+        &self,
+        file_path: &str,
+    ) -> Result<Vec<Diagnostic>, Box<dyn Error + Send + Sync>> {
+        let uri = Url::from_file_path(file_path).map_err(|_| "Invalid file path")?;
+
+        if self.get_diagnostics().supports_document_pull() {
+            self.open_document_if_needed(file_path).await?;
+
+            let params = DocumentDiagnosticParams {
+                text_document: TextDocumentIdentifier { uri: uri.clone() },
+                identifier: None,
+                previous_result_id: None,
+                work_done_progress_params: WorkDoneProgressParams::default(),
+                partial_result_params: PartialResultParams::default(),
+            };
+
+            let result = self
+                .send_request(
+                    "textDocument/diagnostic",
+                    Some(serde_json::to_value(params)?),
+                )
+                .await?;
+
+            // Without a previous result id the server should always send a full report
+            let report: DocumentDiagnosticReportResult = serde_json::from_value(result)?;
+            if let DocumentDiagnosticReportResult::Report(DocumentDiagnosticReport::Full(report)) =
+                report
+            {
+                self.get_diagnostics()
+                    .set(uri.clone(), report.full_document_diagnostic_report.items)
+                    .await;
+            }
+            debug!("Received document diagnostic response");
+            return Ok(self.get_diagnostics().get(&uri).await.unwrap_or_default());
+        }
+
+        if let Some(diagnostics) = self.get_diagnostics().get(&uri).await {
+            return Ok(diagnostics);
+        }
+
+        // Servers only publish diagnostics for open documents, so open it and wait for them
+        let mut updates = self.get_diagnostics().subscribe();
+        let document_sync = self.get_workspace_documents().lock_document_sync().await;
+        if !self
+            .get_workspace_documents()
+            .is_did_open_document(file_path)
+        {
+            let document_text = self
+                .get_workspace_documents()
+                .read_text_document(&PathBuf::from(file_path), None)
+                .await?;
+            self.text_document_did_open(TextDocumentItem {
+                uri: uri.clone(),
+                language_id: detect_language_string(file_path)?,
+                version: 1,
+                text: document_text,
+            })
+            .await?;
+            self.get_workspace_documents()
+                .add_did_open_document(file_path);
+        }
+        drop(document_sync);
+
+        let published = async {
+            loop {
+                match updates.recv().await {
+                    Ok(updated) if updated == uri => break,
+                    Err(RecvError::Closed) => break,
+                    _ => {}
+                }
+            }
+        };
+        if tokio::time::timeout(PUBLISH_DIAGNOSTICS_TIMEOUT, published)
+            .await
+            .is_err()
+        {
+            debug!("No diagnostics published for {}", file_path);
+        }
+        Ok(self.get_diagnostics().get(&uri).await.unwrap_or_default())
+    }
+
+    /// Gets the diagnostics of every document the server has reported on, pulling them first if
+    /// the server supports `workspace/diagnostic`.
+    async fn workspace_diagnostic(
+        &self,
+    ) -> Result<Vec<(Url, Vec<Diagnostic>)>, Box<dyn Error + Send + Sync>> {
+        if self.get_diagnostics().supports_workspace_pull() {
+            let params = WorkspaceDiagnosticParams {
+                identifier: None,
+                previous_result_ids: Vec::new(),
+                work_done_progress_params: WorkDoneProgressParams::default(),
+                partial_result_params: PartialResultParams::default(),
+            };
+
+            let result = self
+                .send_request("workspace/diagnostic", Some(serde_json::to_value(params)?))
+                .await?;
+
+            let items = match serde_json::from_value(result)? {
+                WorkspaceDiagnosticReportResult::Report(report) => report.items,
+                WorkspaceDiagnosticReportResult::Partial(report) => report.items,
+            };
+            for item in items {
+                if let WorkspaceDocumentDiagnosticReport::Full(report) = item {
+                    self.get_diagnostics()
+                        .set(report.uri, report.full_document_diagnostic_report.items)
+                        .await;
+                }
+            }
+            debug!("Received workspace diagnostic response");
+        }
+        Ok(self.get_diagnostics().all().await)
+    }
+
+    fn get_process(&self) -> &ProcessHandler;
+
+    fn get_json_rpc(&self) -> &JsonRpcHandler;
+
+    fn get_root_files(&self) -> Vec<String> {
+        vec![".git".to_string()]
+    }
+
+    fn get_pending_requests(&self) -> &PendingRequests;
+
+    fn get_diagnostics(&self) -> &DiagnosticsCache;
+
+    fn get_progress(&self) -> &ProgressTracker;
+
+    /// The settings answering the server's `workspace/configuration` requests, by section.
+    fn get_workspace_settings(&self) -> serde_json::Value {
+        serde_json::json!({})
+    }
+
+    /// The handlers answering the requests the server sends, see `ServerRequestHandlers`.
+    /// Clients register their own to answer more requests or answer them differently.
+    fn get_server_request_handlers(&self) -> ServerRequestHandlers {
+        ServerRequestHandlers::new(
+            self.get_workspace_settings(),
+            self.get_pending_requests().clone(),
+        )
+    }
+
+    fn get_workspace_documents(&self) -> &WorkspaceDocumentsHandler;
+    /// Sets up the workspace for the language server.
+    ///
+    /// Some language servers require specific commands to be run before
+    /// workspace-wide features are available. For example:
+    /// - TypeScript Language Server needs an explicit didOpen notification for each file
+    /// - Rust Analyzer needs a reloadWorkspace command
+    ///
+    /// # Arguments
+    ///
+    /// * `root_path` - The root path of the workspace
+    ///
+    /// # Returns
+    ///
+    /// A Result containing () if successful, or a boxed Error if an error occurred
+    #[allow(unused)]
+    async fn setup_workspace(&self, root_path: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+        Ok(())
+    }
+
+    async fn find_workspace_folders(
+        &self,
+        root_path: String,
+    ) -> Result<Vec<WorkspaceFolder>, Box<dyn Error + Send + Sync>> {
+        let mut workspace_folders: Vec<WorkspaceFolder> = Vec::new();
+        let include_patterns = self
+            .get_root_files()
+            .into_iter()
+            .map(|f| format!("**/{f}"))
+            .collect();
+        let exclude_patterns = DEFAULT_EXCLUDE_PATTERNS
+            .iter()
+            .map(|&s| s.to_string())
+            .collect();
+
+        match search_paths(Path::new(&root_path), include_patterns, exclude_patterns, true, FileType::Dir) {
             Ok(dirs) => {
                 for dir in dirs {
                     let folder_path = Path::new(&root_path).join(&dir);
@@ -394,3 +1536,109 @@ pub trait LspClient: Send {
         Ok(workspace_folders.into_iter().collect())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Stdio;
+    use tokio::process::Command;
+
+    struct TestClient {
+        process: ProcessHandler,
+        json_rpc: JsonRpcHandler,
+        workspace_documents: WorkspaceDocumentsHandler,
+        pending_requests: PendingRequests,
+        diagnostics: DiagnosticsCache,
+        progress: ProgressTracker,
+    }
+
+    #[async_trait]
+    impl LspClient for TestClient {
+        fn get_process(&self) -> &ProcessHandler {
+            &self.process
+        }
+        fn get_json_rpc(&self) -> &JsonRpcHandler {
+            &self.json_rpc
+        }
+        fn get_workspace_documents(&self) -> &WorkspaceDocumentsHandler {
+            &self.workspace_documents
+        }
+        fn get_pending_requests(&self) -> &PendingRequests {
+            &self.pending_requests
+        }
+        fn get_diagnostics(&self) -> &DiagnosticsCache {
+            &self.diagnostics
+        }
+        fn get_progress(&self) -> &ProgressTracker {
+            &self.progress
+        }
+        fn get_request_timeout(&self, _method: &str) -> Duration {
+            Duration::from_millis(100)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_late_response_after_timeout() -> Result<(), Box<dyn Error + Send + Sync>> {
+        let dir = tempfile::tempdir()?;
+        let received = dir.path().join("received");
+        // Answers the first request after it timed out, then records what it receives.
+        let response = r#"{"jsonrpc":"2.0","id":0,"result":null}"#;
+        let script = format!(
+            "sleep 0.3; printf 'Content-Length: {}\\r\\n\\r\\n{}'; sleep 0.3; cat > \"$0\"",
+            response.len(),
+            response
+        );
+        let child = Command::new("sh")
+            .arg("-c")
+            .arg(script)
+            .arg(&received)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let (_watch_events_tx, watch_events_rx) = tokio::sync::broadcast::channel(1);
+        let client = TestClient {
+            process: ProcessHandler::new(child).await?,
+            json_rpc: JsonRpcHandler::new(),
+            workspace_documents: WorkspaceDocumentsHandler::new(
+                dir.path(),
+                vec![],
+                vec![],
+                watch_events_rx,
+                DidOpenConfiguration::Lazy,
+            ),
+            pending_requests: PendingRequests::new(),
+            diagnostics: DiagnosticsCache::new(),
+            progress: ProgressTracker::new(),
+        };
+        client.start_response_listener().await?;
+
+        let error = client
+            .send_request("textDocument/hover", None)
+            .await
+            .unwrap_err();
+        assert!(error.is::<RequestTimeout>());
+
+        tokio::time::sleep(Duration::from_millis(1000)).await;
+        let received = std::fs::read_to_string(&received)?;
+        assert!(received.contains("\"method\":\"textDocument/hover\""));
+        assert!(received.contains("\"method\":\"$/cancelRequest\""));
+        assert!(!received.contains("\"result\""), "{}", received);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_request_timeouts() {
+        let timeouts =
+            parse_request_timeouts("default=30, textDocument/references = 300,hover=soon");
+        assert_eq!(
+            timeouts,
+            HashMap::from([
+                ("default".to_string(), Duration::from_secs(30)),
+                (
+                    "textDocument/references".to_string(),
+                    Duration::from_secs(300)
+                ),
+            ])
+        );
+    }
+}
@@ -0,0 +1,160 @@
+use lsp_types::{Diagnostic, DiagnosticServerCapabilities, PublishDiagnosticsParams, Url};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::broadcast::{channel, Receiver, Sender};
+use tokio::sync::RwLock;
+
+/// The diagnostics reported by a language server, keyed by document.
+///
+/// Collecting diagnostics is opt-in: until `enable` is called, published diagnostics are dropped
+/// and the client doesn't advertise pull diagnostics to the server.
+#[derive(Clone)]
+pub struct DiagnosticsCache {
+    enabled: Arc<AtomicBool>,
+    document_pull: Arc<AtomicBool>,
+    workspace_pull: Arc<AtomicBool>,
+    documents: Arc<RwLock<HashMap<Url, Vec<Diagnostic>>>>,
+    updates: Sender<Url>,
+}
+
+impl DiagnosticsCache {
+    pub fn new() -> Self {
+        let (updates, _) = channel(100);
+        Self {
+            enabled: Arc::new(AtomicBool::new(false)),
+            document_pull: Arc::new(AtomicBool::new(false)),
+            workspace_pull: Arc::new(AtomicBool::new(false)),
+            documents: Arc::new(RwLock::new(HashMap::new())),
+            updates,
+        }
+    }
+
+    pub fn enable(&self) {
+        self.enabled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Records which pull requests the server supports, from its `diagnosticProvider` capability.
+    pub fn set_pull_support(&self, capabilities: &Option<DiagnosticServerCapabilities>) {
+        let workspace_diagnostics = match capabilities {
+            Some(DiagnosticServerCapabilities::Options(options)) => options.workspace_diagnostics,
+            Some(DiagnosticServerCapabilities::RegistrationOptions(options)) => {
+                options.diagnostic_options.workspace_diagnostics
+            }
+            None => false,
+        };
+        self.document_pull
+            .store(capabilities.is_some(), Ordering::Relaxed);
+        self.workspace_pull
+            .store(workspace_diagnostics, Ordering::Relaxed);
+    }
+
+    pub fn supports_document_pull(&self) -> bool {
+        self.is_enabled() && self.document_pull.load(Ordering::Relaxed)
+    }
+
+    pub fn supports_workspace_pull(&self) -> bool {
+        self.is_enabled() && self.workspace_pull.load(Ordering::Relaxed)
+    }
+
+    /// Stores the diagnostics from a `textDocument/publishDiagnostics` notification, replacing
+    /// the previous ones for the document.
+    pub async fn publish(&self, params: PublishDiagnosticsParams) {
+        if self.is_enabled() {
+            self.set(params.uri, params.diagnostics).await;
+        }
+    }
+
+    pub async fn set(&self, uri: Url, diagnostics: Vec<Diagnostic>) {
+        self.documents
+            .write()
+            .await
+            .insert(uri.clone(), diagnostics);
+        // No one may be waiting for the update
+        let _ = self.updates.send(uri);
+    }
+
+    pub async fn get(&self, uri: &Url) -> Option<Vec<Diagnostic>> {
+        self.documents.read().await.get(uri).cloned()
+    }
+
+    pub async fn all(&self) -> Vec<(Url, Vec<Diagnostic>)> {
+        self.documents
+            .read()
+            .await
+            .iter()
+            .map(|(uri, diagnostics)| (uri.clone(), diagnostics.clone()))
+            .collect()
+    }
+
+    /// Subscribes to the uris of documents whose diagnostics are updated.
+    pub fn subscribe(&self) -> Receiver<Url> {
+        self.updates.subscribe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lsp_types::{DiagnosticOptions, Position, Range};
+
+    fn publish_params(uri: &Url, message: &str) -> PublishDiagnosticsParams {
+        PublishDiagnosticsParams {
+            uri: uri.clone(),
+            diagnostics: vec![Diagnostic {
+                range: Range::new(Position::new(0, 0), Position::new(0, 1)),
+                message: message.to_string(),
+                ..Default::default()
+            }],
+            version: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_publish_requires_enable() {
+        let cache = DiagnosticsCache::new();
+        let uri = Url::parse("file:///mnt/workspace/main.py").unwrap();
+
+        cache.publish(publish_params(&uri, "ignored")).await;
+        assert_eq!(cache.get(&uri).await, None);
+
+        cache.enable();
+        let mut updates = cache.subscribe();
+        cache.publish(publish_params(&uri, "first")).await;
+        cache.publish(publish_params(&uri, "second")).await;
+
+        let diagnostics = cache.get(&uri).await.unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message, "second");
+        assert_eq!(updates.recv().await.unwrap(), uri);
+        assert_eq!(cache.all().await.len(), 1);
+    }
+
+    #[test]
+    fn test_set_pull_support() {
+        let cache = DiagnosticsCache::new();
+        cache.enable();
+        assert!(!cache.supports_document_pull());
+
+        cache.set_pull_support(&Some(DiagnosticServerCapabilities::Options(
+            DiagnosticOptions {
+                workspace_diagnostics: false,
+                ..Default::default()
+            },
+        )));
+        assert!(cache.supports_document_pull());
+        assert!(!cache.supports_workspace_pull());
+
+        cache.set_pull_support(&Some(DiagnosticServerCapabilities::Options(
+            DiagnosticOptions {
+                workspace_diagnostics: true,
+                ..Default::default()
+            },
+        )));
+        assert!(cache.supports_workspace_pull());
+    }
+}
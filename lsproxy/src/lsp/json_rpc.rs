@@ -6,10 +6,11 @@ use std::fmt;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use tokio::sync::broadcast::{channel, Receiver, Sender};
-use tokio::sync::Mutex;
+use tokio::sync::{watch, Mutex, MutexGuard};
 
 pub trait JsonRpc: Send + Sync {
-    fn create_success_response(&self, id: u64) -> String;
+    fn create_response(&self, id: u64, result: Value) -> String;
+    fn create_error_response(&self, id: u64, error: JsonRpcError) -> String;
     fn create_request(&self, method: &str, params: Option<Value>) -> (u64, String);
     fn create_notification(&self, method: &str, params: Value) -> String;
     fn parse_message(&self, data: &str) -> Result<JsonRpcMessage, JsonRpcError>;
@@ -30,6 +31,10 @@ pub struct InnerMessage {
     pub message: String,
     pub r#type: String,
 }
+/// The error code of the requests failed because the server stopped, in the range reserved for
+/// implementation-defined server errors.
+pub const SERVER_STOPPED: i32 = -32099;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct JsonRpcError {
     pub code: i32,
@@ -59,11 +64,20 @@ impl JsonRpcHandler {
 }
 
 impl JsonRpc for JsonRpcHandler {
-    fn create_success_response(&self, id: u64) -> String {
+    fn create_response(&self, id: u64, result: Value) -> String {
         serde_json::json!({
             "jsonrpc": "2.0",
             "id": id,
-            "result": null
+            "result": result
+        })
+        .to_string()
+    }
+
+    fn create_error_response(&self, id: u64, error: JsonRpcError) -> String {
+        serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": error
         })
         .to_string()
     }
@@ -108,6 +122,10 @@ pub struct ExpectedMessageKey {
 pub struct PendingRequests {
     request_channels: Arc<Mutex<HashMap<u64, Sender<JsonRpcMessage>>>>,
     notification_channels: Arc<Mutex<HashMap<ExpectedMessageKey, Sender<JsonRpcMessage>>>>,
+    server_request_channels: Arc<Mutex<HashMap<String, Sender<JsonRpcMessage>>>>,
+    commands: Arc<Mutex<()>>,
+    /// Why the server stopped responding, once it has.
+    closed: Arc<watch::Sender<Option<String>>>,
 }
 
 impl PendingRequests {
@@ -115,15 +133,23 @@ impl PendingRequests {
         Self {
             request_channels: Arc::new(Mutex::new(HashMap::new())),
             notification_channels: Arc::new(Mutex::new(HashMap::new())),
+            server_request_channels: Arc::new(Mutex::new(HashMap::new())),
+            commands: Arc::new(Mutex::new(())),
+            closed: Arc::new(watch::Sender::new(None)),
         }
     }
 
+    /// Subscribes to the response to a request, failing if the server has stopped.
     pub async fn add_request(
         &self,
         id: u64,
     ) -> Result<Receiver<JsonRpcMessage>, Box<dyn Error + Send + Sync>> {
+        let mut request_channels = self.request_channels.lock().await;
+        if let Some(reason) = self.closed.borrow().as_ref() {
+            return Err(reason.clone().into());
+        }
         let (tx, rx) = channel::<JsonRpcMessage>(16);
-        self.request_channels.lock().await.insert(id, tx);
+        request_channels.insert(id, tx);
         Ok(rx)
     }
 
@@ -152,4 +178,93 @@ impl PendingRequests {
     ) -> Option<Sender<JsonRpcMessage>> {
         self.notification_channels.lock().await.remove(&pattern)
     }
+
+    /// Subscribes to the requests the server sends with the given method, until
+    /// `remove_server_request` is called.
+    pub async fn add_server_request(
+        &self,
+        method: &str,
+    ) -> Result<Receiver<JsonRpcMessage>, Box<dyn Error + Send + Sync>> {
+        let (tx, rx) = channel::<JsonRpcMessage>(16);
+        self.server_request_channels
+            .lock()
+            .await
+            .insert(method.to_string(), tx);
+        Ok(rx)
+    }
+
+    pub async fn get_server_request(&self, method: &str) -> Option<Sender<JsonRpcMessage>> {
+        self.server_request_channels
+            .lock()
+            .await
+            .get(method)
+            .cloned()
+    }
+
+    pub async fn remove_server_request(&self, method: &str) {
+        self.server_request_channels.lock().await.remove(method);
+    }
+
+    /// Fails every pending request with `reason` when the server stops, as well as those made
+    /// afterwards. Waiters for notifications and server requests see their channel closed.
+    pub async fn close(&self, reason: &str) {
+        let mut request_channels = self.request_channels.lock().await;
+        self.closed.send_replace(Some(reason.to_string()));
+        for (id, sender) in request_channels.drain() {
+            let _ = sender.send(JsonRpcMessage {
+                jsonrpc: "2.0".to_string(),
+                id: Some(id),
+                method: None,
+                params: None,
+                result: None,
+                error: Some(JsonRpcError {
+                    code: SERVER_STOPPED,
+                    message: reason.to_string(),
+                    data: None,
+                }),
+            });
+        }
+        drop(request_channels);
+        self.notification_channels.lock().await.clear();
+        self.server_request_channels.lock().await.clear();
+    }
+
+    /// Waits until the server stops, returning why.
+    pub async fn wait_closed(&self) -> String {
+        let mut closed = self.closed.subscribe();
+        let reason = match closed.wait_for(Option::is_some).await {
+            Ok(reason) => reason.clone().unwrap_or_default(),
+            Err(_) => "server stopped".to_string(),
+        };
+        reason
+    }
+
+    /// Waits until no other command is executing. The requests a server sends while executing a
+    /// command can't be told apart from those of another command, so commands run one at a time.
+    pub async fn lock_commands(&self) -> MutexGuard<'_, ()> {
+        self.commands.lock().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_close_fails_pending_requests() -> Result<(), Box<dyn Error + Send + Sync>> {
+        let pending_requests = PendingRequests::new();
+        let mut response = pending_requests.add_request(1).await?;
+
+        pending_requests.close("Language server exited").await;
+
+        let error = response.recv().await?.error.unwrap();
+        assert_eq!(error.code, SERVER_STOPPED);
+        assert_eq!(error.message, "Language server exited");
+        assert!(pending_requests.add_request(2).await.is_err());
+        assert_eq!(
+            pending_requests.wait_closed().await,
+            "Language server exited"
+        );
+        Ok(())
+    }
 }
@@ -10,7 +10,10 @@ use crate::lsp::{ExpectedMessageKey, JsonRpc, Process};
 use crate::utils::file_utils::{search_paths, FileType};
 use crate::utils::workspace_documents::DidOpenConfiguration;
 use crate::{
-    lsp::{JsonRpcHandler, LspClient, PendingRequests, ProcessHandler},
+    lsp::{
+        DiagnosticsCache, JsonRpcHandler, LspClient, PendingRequests, ProcessHandler,
+        ProgressTracker,
+    },
     utils::workspace_documents::{
         WorkspaceDocumentsHandler, CPP_ROOT_FILES, C_AND_CPP_FILE_PATTERNS,
         DEFAULT_EXCLUDE_PATTERNS,
@@ -29,34 +32,41 @@ pub struct ClangdClient {
     json_rpc: JsonRpcHandler,
     workspace_documents: WorkspaceDocumentsHandler,
     pending_requests: PendingRequests,
+    diagnostics: DiagnosticsCache,
+    progress: ProgressTracker,
 }
 
 #[async_trait]
 impl LspClient for ClangdClient {
-    fn get_process(&mut self) -> &mut ProcessHandler {
-        &mut self.process
+    fn get_process(&self) -> &ProcessHandler {
+        &self.process
     }
 
-    fn get_json_rpc(&mut self) -> &mut JsonRpcHandler {
-        &mut self.json_rpc
+    fn get_json_rpc(&self) -> &JsonRpcHandler {
+        &self.json_rpc
     }
 
-    fn get_root_files(&mut self) -> Vec<String> {
+    fn get_root_files(&self) -> Vec<String> {
         CPP_ROOT_FILES.iter().map(|s| s.to_string()).collect()
     }
 
-    fn get_workspace_documents(&mut self) -> &mut WorkspaceDocumentsHandler {
-        &mut self.workspace_documents
+    fn get_workspace_documents(&self) -> &WorkspaceDocumentsHandler {
+        &self.workspace_documents
     }
 
-    fn get_pending_requests(&mut self) -> &mut PendingRequests {
-        &mut self.pending_requests
+    fn get_pending_requests(&self) -> &PendingRequests {
+        &self.pending_requests
     }
 
-    async fn setup_workspace(
-        &mut self,
-        root_path: &str,
-    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    fn get_diagnostics(&self) -> &DiagnosticsCache {
+        &self.diagnostics
+    }
+
+    fn get_progress(&self) -> &ProgressTracker {
+        &self.progress
+    }
+
+    async fn setup_workspace(&self, root_path: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
         let compile_db_files = search_paths(
             Path::new(root_path),
             vec![String::from("**/compile_commands.json")],
@@ -83,7 +93,7 @@ impl LspClient for ClangdClient {
     }
 
     async fn get_initialize_params(
-        &mut self,
+        &self,
         root_path: String,
     ) -> Result<InitializeParams, Box<dyn Error + Send + Sync>> {
         let capabilities = self.get_capabilities();
@@ -98,7 +108,7 @@ impl LspClient for ClangdClient {
     }
 
     async fn text_document_did_open(
-        &mut self,
+        &self,
         item: lsp_types::TextDocumentItem,
     ) -> Result<(), Box<dyn Error + Send + Sync>> {
         let params = DidOpenTextDocumentParams {
@@ -171,6 +181,8 @@ impl ClangdClient {
             json_rpc: json_rpc_handler,
             workspace_documents,
             pending_requests,
+            diagnostics: DiagnosticsCache::new(),
+            progress: ProgressTracker::new(),
         })
     }
 }
@@ -1,5 +1,8 @@
 use crate::{
-    lsp::{JsonRpcHandler, LspClient, PendingRequests, ProcessHandler},
+    lsp::{
+        DiagnosticsCache, JsonRpcHandler, LspClient, PendingRequests, ProcessHandler,
+        ProgressTracker,
+    },
     utils::workspace_documents::{
         DidOpenConfiguration, WorkspaceDocumentsHandler, CSHARP_FILE_PATTERNS, CSHARP_ROOT_FILES,
         DEFAULT_EXCLUDE_PATTERNS,
@@ -16,27 +19,37 @@ pub struct CSharpClient {
     json_rpc: JsonRpcHandler,
     workspace_documents: WorkspaceDocumentsHandler,
     pending_requests: PendingRequests,
+    diagnostics: DiagnosticsCache,
+    progress: ProgressTracker,
 }
 #[async_trait]
 impl LspClient for CSharpClient {
-    fn get_process(&mut self) -> &mut ProcessHandler {
-        &mut self.process
+    fn get_process(&self) -> &ProcessHandler {
+        &self.process
     }
-    fn get_json_rpc(&mut self) -> &mut JsonRpcHandler {
-        &mut self.json_rpc
+    fn get_json_rpc(&self) -> &JsonRpcHandler {
+        &self.json_rpc
     }
-    fn get_root_files(&mut self) -> Vec<String> {
+    fn get_root_files(&self) -> Vec<String> {
         CSHARP_ROOT_FILES.iter().map(|&s| s.to_owned()).collect()
     }
-    fn get_workspace_documents(&mut self) -> &mut WorkspaceDocumentsHandler {
-        &mut self.workspace_documents
+    fn get_workspace_documents(&self) -> &WorkspaceDocumentsHandler {
+        &self.workspace_documents
+    }
+    fn get_pending_requests(&self) -> &PendingRequests {
+        &self.pending_requests
     }
-    fn get_pending_requests(&mut self) -> &mut PendingRequests {
-        &mut self.pending_requests
+
+    fn get_diagnostics(&self) -> &DiagnosticsCache {
+        &self.diagnostics
+    }
+
+    fn get_progress(&self) -> &ProgressTracker {
+        &self.progress
     }
 
     async fn get_initialize_params(
-        &mut self,
+        &self,
         root_path: String,
     ) -> Result<InitializeParams, Box<dyn Error + Send + Sync>> {
         let workspace_folders = self.find_workspace_folders(root_path.clone()).await?;
@@ -87,6 +100,8 @@ impl CSharpClient {
             json_rpc: json_rpc_handler,
             workspace_documents,
             pending_requests,
+            diagnostics: DiagnosticsCache::new(),
+            progress: ProgressTracker::new(),
         })
     }
 }
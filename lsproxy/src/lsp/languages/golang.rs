@@ -1,5 +1,8 @@
 use crate::{
-    lsp::{JsonRpcHandler, LspClient, PendingRequests, ProcessHandler},
+    lsp::{
+        DiagnosticsCache, JsonRpcHandler, LspClient, PendingRequests, ProcessHandler,
+        ProgressTracker,
+    },
     utils::workspace_documents::{
         DidOpenConfiguration, WorkspaceDocumentsHandler, DEFAULT_EXCLUDE_PATTERNS,
         GOLANG_FILE_PATTERNS, GOLANG_ROOT_FILES,
@@ -21,28 +24,48 @@ pub struct GoplsClient {
     json_rpc: JsonRpcHandler,
     workspace_documents: WorkspaceDocumentsHandler,
     pending_requests: PendingRequests,
+    diagnostics: DiagnosticsCache,
+    progress: ProgressTracker,
 }
 
 #[async_trait]
 impl LspClient for GoplsClient {
-    fn get_process(&mut self) -> &mut ProcessHandler {
-        &mut self.process
+    fn get_process(&self) -> &ProcessHandler {
+        &self.process
     }
-    fn get_json_rpc(&mut self) -> &mut JsonRpcHandler {
-        &mut self.json_rpc
+    fn get_json_rpc(&self) -> &JsonRpcHandler {
+        &self.json_rpc
     }
-    fn get_root_files(&mut self) -> Vec<String> {
+    fn get_root_files(&self) -> Vec<String> {
         GOLANG_ROOT_FILES.iter().map(|&s| s.to_owned()).collect()
     }
-    fn get_workspace_documents(&mut self) -> &mut WorkspaceDocumentsHandler {
-        &mut self.workspace_documents
+    fn get_workspace_documents(&self) -> &WorkspaceDocumentsHandler {
+        &self.workspace_documents
+    }
+    fn get_pending_requests(&self) -> &PendingRequests {
+        &self.pending_requests
+    }
+
+    fn get_diagnostics(&self) -> &DiagnosticsCache {
+        &self.diagnostics
+    }
+
+    fn get_progress(&self) -> &ProgressTracker {
+        &self.progress
     }
-    fn get_pending_requests(&mut self) -> &mut PendingRequests {
-        &mut self.pending_requests
+
+    /// gopls pulls its settings rather than reading initialization options. Workspace symbols
+    /// are searched in the workspace packages only, not in their dependencies.
+    fn get_workspace_settings(&self) -> serde_json::Value {
+        serde_json::json!({
+            "gopls": {
+                "symbolScope": "workspace"
+            }
+        })
     }
 
     async fn get_initialize_params(
-        &mut self,
+        &self,
         root_path: String,
     ) -> Result<InitializeParams, Box<dyn Error + Send + Sync>> {
         let workspace_folders = self.find_workspace_folders(root_path.clone()).await?;
@@ -57,7 +80,7 @@ impl LspClient for GoplsClient {
     }
 
     async fn find_workspace_folders(
-        &mut self,
+        &self,
         root_path: String,
     ) -> Result<Vec<WorkspaceFolder>, Box<dyn Error + Send + Sync>> {
         let root = PathBuf::from(&root_path);
@@ -163,6 +186,8 @@ impl GoplsClient {
             json_rpc: json_rpc_handler,
             workspace_documents,
             pending_requests,
+            diagnostics: DiagnosticsCache::new(),
+            progress: ProgressTracker::new(),
         })
     }
 }
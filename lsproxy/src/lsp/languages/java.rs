@@ -8,7 +8,10 @@ use notify_debouncer_mini::DebouncedEvent;
 use tokio::{process::Command, sync::broadcast::Receiver};
 
 use crate::{
-    lsp::{ExpectedMessageKey, JsonRpcHandler, LspClient, PendingRequests, ProcessHandler},
+    lsp::{
+        DiagnosticsCache, ExpectedMessageKey, JsonRpcHandler, LspClient, PendingRequests,
+        ProcessHandler, ProgressTracker,
+    },
     utils::workspace_documents::{
         DidOpenConfiguration, WorkspaceDocumentsHandler, DEFAULT_EXCLUDE_PATTERNS,
         JAVA_FILE_PATTERNS, JAVA_ROOT_FILES,
@@ -20,32 +23,42 @@ pub struct JdtlsClient {
     json_rpc: JsonRpcHandler,
     workspace_documents: WorkspaceDocumentsHandler,
     pending_requests: PendingRequests,
+    diagnostics: DiagnosticsCache,
+    progress: ProgressTracker,
 }
 
 #[async_trait]
 impl LspClient for JdtlsClient {
-    fn get_process(&mut self) -> &mut ProcessHandler {
-        &mut self.process
+    fn get_process(&self) -> &ProcessHandler {
+        &self.process
     }
 
-    fn get_json_rpc(&mut self) -> &mut JsonRpcHandler {
-        &mut self.json_rpc
+    fn get_json_rpc(&self) -> &JsonRpcHandler {
+        &self.json_rpc
     }
 
-    fn get_root_files(&mut self) -> Vec<String> {
+    fn get_root_files(&self) -> Vec<String> {
         JAVA_ROOT_FILES.iter().map(|&s| s.to_string()).collect()
     }
 
-    fn get_workspace_documents(&mut self) -> &mut WorkspaceDocumentsHandler {
-        &mut self.workspace_documents
+    fn get_workspace_documents(&self) -> &WorkspaceDocumentsHandler {
+        &self.workspace_documents
+    }
+
+    fn get_pending_requests(&self) -> &PendingRequests {
+        &self.pending_requests
+    }
+
+    fn get_diagnostics(&self) -> &DiagnosticsCache {
+        &self.diagnostics
     }
 
-    fn get_pending_requests(&mut self) -> &mut PendingRequests {
-        &mut self.pending_requests
+    fn get_progress(&self) -> &ProgressTracker {
+        &self.progress
     }
 
     async fn initialize(
-        &mut self,
+        &self,
         root_path: String,
     ) -> Result<InitializeResult, Box<dyn Error + Send + Sync>> {
         debug!("Initializing LSP client with root path: {:?}", root_path);
@@ -157,6 +170,8 @@ impl JdtlsClient {
             json_rpc: json_rpc_handler,
             workspace_documents,
             pending_requests: PendingRequests::new(),
+            diagnostics: DiagnosticsCache::new(),
+            progress: ProgressTracker::new(),
         })
     }
 }
@@ -1,5 +1,8 @@
 use crate::{
-    lsp::{JsonRpcHandler, LspClient, PendingRequests, ProcessHandler},
+    lsp::{
+        DiagnosticsCache, JsonRpcHandler, LspClient, PendingRequests, ProcessHandler,
+        ProgressTracker,
+    },
     utils::workspace_documents::{
         DidOpenConfiguration, WorkspaceDocumentsHandler, DEFAULT_EXCLUDE_PATTERNS,
         PHP_FILE_PATTERNS, PHP_ROOT_FILES,
@@ -18,28 +21,38 @@ pub struct PhpactorClient {
     json_rpc: JsonRpcHandler,
     workspace_documents: WorkspaceDocumentsHandler,
     pending_requests: PendingRequests,
+    diagnostics: DiagnosticsCache,
+    progress: ProgressTracker,
 }
 
 #[async_trait]
 impl LspClient for PhpactorClient {
-    fn get_process(&mut self) -> &mut ProcessHandler {
-        &mut self.process
+    fn get_process(&self) -> &ProcessHandler {
+        &self.process
     }
-    fn get_json_rpc(&mut self) -> &mut JsonRpcHandler {
-        &mut self.json_rpc
+    fn get_json_rpc(&self) -> &JsonRpcHandler {
+        &self.json_rpc
     }
-    fn get_root_files(&mut self) -> Vec<String> {
+    fn get_root_files(&self) -> Vec<String> {
         PHP_ROOT_FILES.iter().map(|&s| s.to_owned()).collect()
     }
-    fn get_workspace_documents(&mut self) -> &mut WorkspaceDocumentsHandler {
-        &mut self.workspace_documents
+    fn get_workspace_documents(&self) -> &WorkspaceDocumentsHandler {
+        &self.workspace_documents
+    }
+    fn get_pending_requests(&self) -> &PendingRequests {
+        &self.pending_requests
     }
-    fn get_pending_requests(&mut self) -> &mut PendingRequests {
-        &mut self.pending_requests
+
+    fn get_diagnostics(&self) -> &DiagnosticsCache {
+        &self.diagnostics
+    }
+
+    fn get_progress(&self) -> &ProgressTracker {
+        &self.progress
     }
 
     async fn get_initialize_params(
-        &mut self,
+        &self,
         root_path: String,
     ) -> Result<InitializeParams, Box<dyn Error + Send + Sync>> {
         let workspace_folders = self
@@ -128,6 +141,8 @@ impl PhpactorClient {
             json_rpc: json_rpc_handler,
             workspace_documents,
             pending_requests: PendingRequests::new(),
+            diagnostics: DiagnosticsCache::new(),
+            progress: ProgressTracker::new(),
         })
     }
 }
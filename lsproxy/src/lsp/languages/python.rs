@@ -5,7 +5,9 @@ use notify_debouncer_mini::DebouncedEvent;
 use tokio::process::Command;
 use tokio::sync::broadcast::Receiver;
 
-use crate::lsp::{JsonRpcHandler, LspClient, PendingRequests, ProcessHandler};
+use crate::lsp::{
+    DiagnosticsCache, JsonRpcHandler, LspClient, PendingRequests, ProcessHandler, ProgressTracker,
+};
 
 use crate::utils::workspace_documents::{
     DidOpenConfiguration, WorkspaceDocumentsHandler, DEFAULT_EXCLUDE_PATTERNS,
@@ -17,28 +19,38 @@ pub struct JediClient {
     json_rpc: JsonRpcHandler,
     workspace_documents: WorkspaceDocumentsHandler,
     pending_requests: PendingRequests,
+    diagnostics: DiagnosticsCache,
+    progress: ProgressTracker,
 }
 
 #[async_trait]
 impl LspClient for JediClient {
-    fn get_process(&mut self) -> &mut ProcessHandler {
-        &mut self.process
+    fn get_process(&self) -> &ProcessHandler {
+        &self.process
     }
 
-    fn get_json_rpc(&mut self) -> &mut JsonRpcHandler {
-        &mut self.json_rpc
+    fn get_json_rpc(&self) -> &JsonRpcHandler {
+        &self.json_rpc
     }
 
-    fn get_root_files(&mut self) -> Vec<String> {
+    fn get_root_files(&self) -> Vec<String> {
         PYTHON_ROOT_FILES.iter().map(|&s| s.to_string()).collect()
     }
 
-    fn get_workspace_documents(&mut self) -> &mut WorkspaceDocumentsHandler {
-        &mut self.workspace_documents
+    fn get_workspace_documents(&self) -> &WorkspaceDocumentsHandler {
+        &self.workspace_documents
+    }
+
+    fn get_pending_requests(&self) -> &PendingRequests {
+        &self.pending_requests
+    }
+
+    fn get_diagnostics(&self) -> &DiagnosticsCache {
+        &self.diagnostics
     }
 
-    fn get_pending_requests(&mut self) -> &mut PendingRequests {
-        &mut self.pending_requests
+    fn get_progress(&self) -> &ProgressTracker {
+        &self.progress
     }
 }
 
@@ -80,6 +92,8 @@ impl JediClient {
             json_rpc: json_rpc_handler,
             workspace_documents,
             pending_requests: PendingRequests::new(),
+            diagnostics: DiagnosticsCache::new(),
+            progress: ProgressTracker::new(),
         })
     }
 }
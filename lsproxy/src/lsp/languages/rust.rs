@@ -10,7 +10,9 @@ use tokio::process::Command;
 use tokio::sync::broadcast::Receiver;
 use url::Url;
 
-use crate::lsp::{JsonRpcHandler, LspClient, PendingRequests, ProcessHandler};
+use crate::lsp::{
+    DiagnosticsCache, JsonRpcHandler, LspClient, PendingRequests, ProcessHandler, ProgressTracker,
+};
 
 use crate::utils::workspace_documents::{
     DidOpenConfiguration, WorkspaceDocumentsHandler, DEFAULT_EXCLUDE_PATTERNS, RUST_FILE_PATTERNS,
@@ -22,19 +24,27 @@ pub struct RustAnalyzerClient {
     json_rpc: JsonRpcHandler,
     workspace_documents: WorkspaceDocumentsHandler,
     pending_requests: PendingRequests,
+    diagnostics: DiagnosticsCache,
+    progress: ProgressTracker,
 }
 
 #[async_trait]
 impl LspClient for RustAnalyzerClient {
-    fn get_capabilities(&mut self) -> ClientCapabilities {
+    fn get_capabilities(&self) -> ClientCapabilities {
         let mut capabilities = ClientCapabilities::default();
         capabilities.text_document = Some(TextDocumentClientCapabilities {
             document_symbol: Some(DocumentSymbolClientCapabilities {
                 hierarchical_document_symbol_support: Some(true),
                 ..Default::default()
             }),
+            diagnostic: self.get_diagnostic_capabilities(),
+            code_action: self.get_code_action_capabilities(),
+            completion: self.get_completion_capabilities(),
+            rename: self.get_rename_capabilities(),
             ..Default::default()
         });
+        capabilities.workspace = self.get_workspace_capabilities();
+        capabilities.window = self.get_window_capabilities();
 
         capabilities.experimental = Some(serde_json::json!({
             "serverStatusNotification": true
@@ -43,7 +53,7 @@ impl LspClient for RustAnalyzerClient {
     }
 
     async fn get_initialize_params(
-        &mut self,
+        &self,
         root_path: String,
     ) -> Result<InitializeParams, Box<dyn Error + Send + Sync>> {
         Ok(InitializeParams {
@@ -54,39 +64,52 @@ impl LspClient for RustAnalyzerClient {
                     .unwrap(),
             ),
             root_uri: Some(Url::from_file_path(&root_path).map_err(|_| "Invalid root path")?),
-            initialization_options: Some(serde_json::json!({
+            initialization_options: Some(self.get_workspace_settings()["rust-analyzer"].clone()),
+            ..Default::default()
+        })
+    }
+
+    /// Also passed as initialization options, so the settings rust-analyzer pulls when its
+    /// configuration changes are the ones it started with.
+    fn get_workspace_settings(&self) -> serde_json::Value {
+        serde_json::json!({
+            "rust-analyzer": {
                 "cargo": {
                     "sysroot": serde_json::Value::Null
                 }
-            })),
-            ..Default::default()
+            }
         })
     }
 
-    fn get_process(&mut self) -> &mut ProcessHandler {
-        &mut self.process
+    fn get_process(&self) -> &ProcessHandler {
+        &self.process
     }
 
-    fn get_json_rpc(&mut self) -> &mut JsonRpcHandler {
-        &mut self.json_rpc
+    fn get_json_rpc(&self) -> &JsonRpcHandler {
+        &self.json_rpc
     }
 
-    fn get_root_files(&mut self) -> Vec<String> {
+    fn get_root_files(&self) -> Vec<String> {
         RUST_ROOT_FILES.iter().map(|&s| s.to_owned()).collect()
     }
 
-    fn get_workspace_documents(&mut self) -> &mut WorkspaceDocumentsHandler {
-        &mut self.workspace_documents
+    fn get_workspace_documents(&self) -> &WorkspaceDocumentsHandler {
+        &self.workspace_documents
+    }
+
+    fn get_pending_requests(&self) -> &PendingRequests {
+        &self.pending_requests
+    }
+
+    fn get_diagnostics(&self) -> &DiagnosticsCache {
+        &self.diagnostics
     }
 
-    fn get_pending_requests(&mut self) -> &mut PendingRequests {
-        &mut self.pending_requests
+    fn get_progress(&self) -> &ProgressTracker {
+        &self.progress
     }
 
-    async fn setup_workspace(
-        &mut self,
-        _root_path: &str,
-    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    async fn setup_workspace(&self, _root_path: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
         // This is required for workspace features like go to definition to work
         self.send_request("rust-analyzer/reloadWorkspace", None)
             .await?;
@@ -128,6 +151,8 @@ impl RustAnalyzerClient {
             json_rpc: json_rpc_handler,
             workspace_documents,
             pending_requests: PendingRequests::new(),
+            diagnostics: DiagnosticsCache::new(),
+            progress: ProgressTracker::new(),
         })
     }
 }
@@ -923,3 +923,45 @@ async fn test_definition() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_concurrent_requests() -> Result<(), Box<dyn std::error::Error>> {
+    let context = TestContext::setup(&python_sample_path(), true).await?;
+    let manager = context
+        .manager
+        .as_ref()
+        .ok_or("Manager is not initialized")?;
+    let position = lsp_types::Position {
+        line: 12,
+        character: 6,
+    };
+
+    // Requests to the same language server are in flight at the same time
+    let (references, definition, hover) = tokio::join!(
+        manager.find_references("graph.py", position),
+        manager.find_definition(
+            "main.py",
+            lsp_types::Position {
+                line: 1,
+                character: 18,
+            }
+        ),
+        manager.hover("graph.py", position),
+    );
+
+    assert!(!references?.is_empty());
+    let definitions = match definition? {
+        GotoDefinitionResponse::Scalar(location) => vec![location],
+        GotoDefinitionResponse::Array(locations) => locations,
+        GotoDefinitionResponse::Link(_links) => Vec::new(),
+    };
+    assert_eq!(
+        definitions
+            .iter()
+            .map(|location| location.range.start)
+            .collect::<Vec<_>>(),
+        vec![position]
+    );
+    assert!(hover?.is_some());
+    Ok(())
+}
@@ -1,38 +1,116 @@
-use crate::api_types::{get_mount_dir, Identifier, SupportedLanguages, Symbol};
+use crate::api_types::{
+    get_mount_dir, lsp_symbol_to_symbol, DocumentCacheStats, DocumentSymbol, Identifier,
+    LanguageServerStatus, SupportedLanguages, Symbol,
+};
 use crate::ast_grep::client::AstGrepClient;
-use crate::ast_grep::types::AstGrepMatch;
-use crate::lsp::client::LspClient;
+use crate::ast_grep::fix::Fix;
+use crate::ast_grep::rule::RuleConfig;
+use crate::ast_grep::types::{AstGrepMatch, SearchMatch};
+use crate::lsp::client::{LspClient, RequestTimeout};
 use crate::lsp::languages::{
     CSharpClient, ClangdClient, GoplsClient, JdtlsClient, JediClient, PhpactorClient, RubyClient,
     RubySorbetClient, RustAnalyzerClient, TypeScriptLanguageClient,
 };
+use crate::utils::edit_utils::{offset_to_position, FileEdit};
+use crate::utils::file_index::FileIndex;
 use crate::utils::file_utils::uri_to_relative_path_string;
 use crate::utils::file_utils::{
     absolute_path_to_relative_path_string, detect_language, search_paths, FileType,
 };
+use crate::utils::overlays::Overlays;
+use crate::utils::symbol_utils::{find_identifier_in_range, fuzzy_match_score, identifier_prefix};
 use crate::utils::workspace_documents::{
-    WorkspaceDocuments, CSHARP_FILE_PATTERNS, C_AND_CPP_FILE_PATTERNS, DEFAULT_EXCLUDE_PATTERNS,
-    GOLANG_FILE_PATTERNS, JAVA_FILE_PATTERNS, PHP_FILE_PATTERNS, PYTHON_FILE_PATTERNS,
-    RUBY_FILE_PATTERNS, RUBY_SORBET_FILE_PATTERNS, RUST_FILE_PATTERNS,
+    WorkspaceDocuments, WorkspaceDocumentsHandler, CSHARP_FILE_PATTERNS, C_AND_CPP_FILE_PATTERNS,
+    DEFAULT_EXCLUDE_PATTERNS, GOLANG_FILE_PATTERNS, JAVA_FILE_PATTERNS, PHP_FILE_PATTERNS,
+    PYTHON_FILE_PATTERNS, RUBY_FILE_PATTERNS, RUBY_SORBET_FILE_PATTERNS, RUST_FILE_PATTERNS,
     TYPESCRIPT_AND_JAVASCRIPT_FILE_PATTERNS,
 };
-use log::{error, info, warn};
-use lsp_types::{GotoDefinitionResponse, Location, Position, Range};
-use notify::RecursiveMode;
-use notify_debouncer_mini::{new_debouncer, DebounceEventResult, DebouncedEvent};
-use std::collections::HashMap;
+use log::{debug, error, info, warn};
+use lsp_types::{
+    AnnotatedTextEdit, CallHierarchyIncomingCall, CallHierarchyItem, CallHierarchyOutgoingCall,
+    CodeAction, CodeActionKind, CodeActionOrCommand, CompletionItem, CompletionResponse,
+    Diagnostic, DocumentChangeOperation, DocumentChanges, DocumentSymbolResponse, FileChangeType,
+    FileEvent, GotoDefinitionResponse, Hover, Location, OneOf, Position, Range, SignatureHelp,
+    SymbolKind, TextEdit, TypeHierarchyItem, Url, WorkspaceEdit, WorkspaceSymbolResponse,
+};
+use notify::{RecommendedWatcher, RecursiveMode};
+use notify_debouncer_mini::{new_debouncer, DebounceEventResult, DebouncedEvent, Debouncer};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::error::Error;
 use std::fmt;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
-use tokio::sync::broadcast::{channel, Sender};
-use tokio::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast::error::{RecvError, TryRecvError};
+use tokio::sync::broadcast::{channel, Receiver, Sender};
+use tokio::sync::RwLock;
+
+/// The goto requests that share the `textDocument/definition` params and response shapes.
+#[derive(Debug, Clone, Copy)]
+pub enum GotoTarget {
+    Definition,
+    Implementation,
+    TypeDefinition,
+    Declaration,
+}
+
+impl fmt::Display for GotoTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GotoTarget::Definition => write!(f, "Definition"),
+            GotoTarget::Implementation => write!(f, "Implementation"),
+            GotoTarget::TypeDefinition => write!(f, "Type definition"),
+            GotoTarget::Declaration => write!(f, "Declaration"),
+        }
+    }
+}
+
+/// How long to wait before restarting a language server that stopped, doubled after each restart
+/// in a row.
+const RESTART_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_RESTART_BACKOFF: Duration = Duration::from_secs(30);
+/// How many times in a row a language server is restarted before it's considered crashed.
+const MAX_RESTARTS: u32 = 5;
+/// How long a language server must run before its restarts no longer count as in a row.
+const STABLE_UPTIME: Duration = Duration::from_secs(300);
+
+tokio::task_local! {
+    /// How long requests wait for their language server to finish indexing before using it,
+    /// set for an HTTP request with the `X-Wait-For-Indexing` header. Requests don't wait
+    /// otherwise.
+    pub static INDEXING_WAIT: Duration;
+}
+
+/// A started language server, whose client is replaced whenever the server is restarted.
+enum LanguageServer {
+    Ready(Arc<dyn LspClient>),
+    Restarting,
+    Crashed,
+}
+
+impl LanguageServer {
+    fn status(&self) -> LanguageServerStatus {
+        match self {
+            LanguageServer::Ready(client) if client.get_progress().is_ready() => {
+                LanguageServerStatus::Ready
+            }
+            LanguageServer::Ready(_) => LanguageServerStatus::Indexing,
+            LanguageServer::Restarting => LanguageServerStatus::Restarting,
+            LanguageServer::Crashed => LanguageServerStatus::Crashed,
+        }
+    }
+}
 
 pub struct Manager {
-    lsp_clients: HashMap<SupportedLanguages, Arc<Mutex<Box<dyn LspClient>>>>,
+    lsp_clients: Arc<std::sync::RwLock<HashMap<SupportedLanguages, LanguageServer>>>,
     watch_events_sender: Sender<DebouncedEvent>,
+    // Watching stops when the debouncer is dropped
+    _debouncer: Debouncer<RecommendedWatcher>,
     ast_grep: AstGrepClient,
+    overlays: Arc<RwLock<Overlays>>,
+    inline_sessions: AtomicU64,
+    file_index: Arc<RwLock<FileIndex>>,
 }
 
 impl Manager {
@@ -58,11 +136,23 @@ impl Manager {
             .watch(Path::new(root_path), RecursiveMode::Recursive)
             .expect("Failed to watch path");
 
-        let ast_grep = AstGrepClient {};
+        let file_index = Arc::new(RwLock::new(FileIndex::new(Path::new(root_path))));
+        tokio::spawn(update_file_index(
+            Arc::clone(&file_index),
+            event_sender.subscribe(),
+        ));
+
+        let overlays = Arc::new(RwLock::new(Overlays::default()));
+        let ast_grep = AstGrepClient::new(Arc::clone(&overlays));
+        ast_grep.load_rule_sets()?;
         Ok(Self {
-            lsp_clients: HashMap::new(),
+            lsp_clients: Arc::new(std::sync::RwLock::new(HashMap::new())),
             watch_events_sender: event_sender,
+            _debouncer: debouncer,
             ast_grep,
+            overlays,
+            inline_sessions: AtomicU64::new(0),
+            file_index,
         })
     }
 
@@ -93,6 +183,14 @@ impl Manager {
             .map(|langs| langs.split(',').filter_map(Self::parse_language).collect())
     }
 
+    /// Reads and parses the DIAGNOSTICS_LANGUAGES environment variable.
+    /// Diagnostics are opt-in, so returns an empty set if not set.
+    fn get_diagnostics_languages() -> HashSet<SupportedLanguages> {
+        std::env::var("DIAGNOSTICS_LANGUAGES")
+            .map(|langs| langs.split(',').filter_map(Self::parse_language).collect())
+            .unwrap_or_default()
+    }
+
     /// Detects the languages in the workspace by searching for files that match the language server's file patterns, before LSPs are started.
     /// If ENABLED_LANGUAGES is set, only searches for those languages.
     fn detect_languages_in_workspace(&self, root_path: &str) -> Vec<SupportedLanguages> {
@@ -181,76 +279,36 @@ impl Manager {
         workspace_path: &str,
     ) -> Result<(), Box<dyn std::error::Error>> {
         let lsps = self.detect_languages_in_workspace(workspace_path);
+        let diagnostics_languages = Self::get_diagnostics_languages();
         for lsp in lsps {
-            if self.get_client(lsp).is_some() {
+            if self.lsp_clients.read().unwrap().contains_key(&lsp) {
                 continue;
             }
             info!("Starting {:?} LSP", lsp);
-            let mut client: Box<dyn LspClient> = match lsp {
-                SupportedLanguages::Python => Box::new(
-                    JediClient::new(workspace_path, self.watch_events_sender.subscribe())
-                        .await
-                        .map_err(|e| e.to_string())?,
-                ),
-                SupportedLanguages::TypeScriptJavaScript => Box::new(
-                    TypeScriptLanguageClient::new(
-                        workspace_path,
-                        self.watch_events_sender.subscribe(),
-                    )
-                    .await
-                    .map_err(|e| e.to_string())?,
-                ),
-                SupportedLanguages::Rust => Box::new(
-                    RustAnalyzerClient::new(workspace_path, self.watch_events_sender.subscribe())
-                        .await
-                        .map_err(|e| e.to_string())?,
-                ),
-                SupportedLanguages::CPP => Box::new(
-                    ClangdClient::new(workspace_path, self.watch_events_sender.subscribe())
-                        .await
-                        .map_err(|e| e.to_string())?,
-                ),
-                SupportedLanguages::CSharp => Box::new(
-                    CSharpClient::new(workspace_path, self.watch_events_sender.subscribe())
-                        .await
-                        .map_err(|e| e.to_string())?,
-                ),
-                SupportedLanguages::Java => Box::new(
-                    JdtlsClient::new(workspace_path, self.watch_events_sender.subscribe())
-                        .await
-                        .map_err(|e| e.to_string())?,
-                ),
-                SupportedLanguages::Golang => Box::new(
-                    GoplsClient::new(workspace_path, self.watch_events_sender.subscribe())
-                        .await
-                        .map_err(|e| e.to_string())?,
-                ),
-                SupportedLanguages::PHP => Box::new(
-                    PhpactorClient::new(workspace_path, self.watch_events_sender.subscribe())
-                        .await
-                        .map_err(|e| e.to_string())?,
-                ),
-                SupportedLanguages::Ruby => Box::new(
-                    RubyClient::new(workspace_path, self.watch_events_sender.subscribe())
-                        .await
-                        .map_err(|e| e.to_string())?,
-                ),
-                SupportedLanguages::RubySorbet => Box::new(
-                    RubySorbetClient::new(workspace_path, self.watch_events_sender.subscribe())
-                        .await
-                        .map_err(|e| e.to_string())?,
-                ),
-            };
-            client
-                .initialize(workspace_path.to_string())
-                .await
-                .map_err(|e| e.to_string())?;
-            info!("Setting up workspace");
-            client
-                .setup_workspace(workspace_path)
+            let diagnostics = diagnostics_languages.contains(&lsp);
+            let client =
+                start_langserver(lsp, workspace_path, &self.watch_events_sender, diagnostics)
+                    .await?;
+            let (include_patterns, exclude_patterns) =
+                client.get_workspace_documents().patterns().await;
+            let files = client.get_workspace_documents().list_files().await;
+            self.file_index
+                .write()
                 .await
-                .map_err(|e| e.to_string())?;
-            self.lsp_clients.insert(lsp, Arc::new(Mutex::new(client)));
+                .add_workspace(include_patterns, exclude_patterns, files);
+            self.lsp_clients
+                .write()
+                .unwrap()
+                .insert(lsp, LanguageServer::Ready(Arc::clone(&client)));
+            tokio::spawn(supervise_langserver(
+                lsp,
+                workspace_path.to_string(),
+                client,
+                diagnostics,
+                Arc::clone(&self.lsp_clients),
+                self.watch_events_sender.clone(),
+                Arc::clone(&self.overlays),
+            ));
         }
         Ok(())
     }
@@ -259,8 +317,7 @@ impl Manager {
         &self,
         file_path: &str,
     ) -> Result<Vec<AstGrepMatch>, LspManagerError> {
-        let workspace_files = self.list_files().await?;
-        if !workspace_files.contains(&file_path.to_string()) {
+        if !self.is_workspace_file(file_path).await {
             return Err(LspManagerError::FileNotFound(file_path.to_string()));
         }
         let full_path = get_mount_dir().join(file_path);
@@ -272,6 +329,139 @@ impl Manager {
             .map_err(|e| LspManagerError::InternalError(format!("Symbol retrieval failed: {}", e)))
     }
 
+    /// Searches the symbols of the whole workspace, asking every language server with
+    /// `workspace/symbol`.
+    ///
+    /// Language servers that fail or return nothing fall back to the ast-grep symbols of their
+    /// files, unfiltered, so the results should be matched against the query by the caller.
+    /// Symbols outside the workspace are left out and duplicates are merged.
+    pub async fn workspace_symbols(&self, query: &str) -> Result<Vec<Symbol>, LspManagerError> {
+        let workspace_files: HashSet<String> = self.list_files().await?.into_iter().collect();
+        let mut symbols = Vec::new();
+        for (lsp_type, client) in self.ready_clients().await {
+            let response = match client.workspace_symbols(query).await {
+                Ok(response) => response,
+                Err(e) => {
+                    warn!("Workspace symbols failed for {:?}: {}", lsp_type, e);
+                    None
+                }
+            };
+            let lsp_symbols = workspace_symbol_locations(response)
+                .into_iter()
+                .filter(|(_, _, location)| {
+                    workspace_files.contains(&uri_to_relative_path_string(&location.uri))
+                })
+                .collect::<Vec<_>>();
+            if !lsp_symbols.is_empty() {
+                let overlays = self.overlays.read().await;
+                symbols.extend(locate_symbol_identifiers(lsp_symbols, &overlays).await);
+                continue;
+            }
+
+            info!(
+                "No workspace symbols from {:?}, falling back to ast-grep",
+                lsp_type
+            );
+            let files: Vec<String> = client
+                .get_workspace_documents()
+                .list_files()
+                .await
+                .iter()
+                .map(|path| path.to_string_lossy().into_owned())
+                .collect();
+            let matches = self
+                .ast_grep
+                .get_symbols_in_files(&files)
+                .await
+                .map_err(|e| {
+                    LspManagerError::InternalError(format!("Symbol retrieval failed: {}", e))
+                })?;
+            symbols.extend(
+                matches
+                    .into_iter()
+                    .filter(|m| m.rule_id != "local-variable")
+                    .map(Symbol::from),
+            );
+        }
+
+        // Servers sharing languages, like clangd for C and C++, report the same symbols
+        symbols.sort_by(|a, b| {
+            a.identifier_position
+                .path
+                .cmp(&b.identifier_position.path)
+                .then(
+                    a.identifier_position
+                        .position
+                        .line
+                        .cmp(&b.identifier_position.position.line),
+                )
+                .then(
+                    a.identifier_position
+                        .position
+                        .character
+                        .cmp(&b.identifier_position.position.character),
+                )
+                .then(a.name.cmp(&b.name))
+        });
+        symbols.dedup_by(|a, b| a.name == b.name && a.identifier_position == b.identifier_position);
+        Ok(symbols)
+    }
+
+    /// Gets the symbols of a file from its language server, nested under the symbols containing
+    /// them.
+    pub async fn document_symbols(
+        &self,
+        file_path: &str,
+    ) -> Result<Vec<DocumentSymbol>, LspManagerError> {
+        let lsp_type = self.workspace_file_language(file_path).await?;
+        let full_path = get_mount_dir().join(file_path);
+        let full_path_str = full_path.to_str().unwrap_or_default();
+        let client = self.client_for_language(lsp_type).await?;
+
+        let response = client
+            .text_document_symbols(full_path_str)
+            .await
+            .map_err(|e| LspManagerError::request_failed("Document symbol retrieval failed", e))?;
+        let uri = Url::from_file_path(&full_path).map_err(|_| {
+            LspManagerError::InternalError(format!("Invalid file path: {}", file_path))
+        })?;
+
+        Ok(match response {
+            Some(DocumentSymbolResponse::Nested(symbols)) => symbols
+                .into_iter()
+                .map(|symbol| DocumentSymbol::from_lsp(&uri, symbol, None))
+                .collect(),
+            // Servers ignoring the hierarchical support only report the whole declaration ranges
+            Some(DocumentSymbolResponse::Flat(symbols)) => {
+                let container_names: Vec<Option<String>> = symbols
+                    .iter()
+                    .map(|symbol| symbol.container_name.clone())
+                    .collect();
+                let symbols = locate_symbol_identifiers(
+                    symbols
+                        .into_iter()
+                        .map(|symbol| (symbol.name, symbol.kind, symbol.location))
+                        .collect(),
+                    &*self.overlays.read().await,
+                )
+                .await;
+                DocumentSymbol::nest(
+                    symbols
+                        .into_iter()
+                        .zip(container_names)
+                        .map(|(symbol, container_name)| DocumentSymbol {
+                            symbol,
+                            detail: None,
+                            container_name,
+                            children: Vec::new(),
+                        })
+                        .collect(),
+                )
+            }
+            None => Vec::new(),
+        })
+    }
+
     pub async fn get_symbol_from_position(
         &self,
         file_path: &str,
@@ -294,28 +484,47 @@ impl Manager {
         file_path: &str,
         position: Position,
     ) -> Result<GotoDefinitionResponse, LspManagerError> {
-        let workspace_files = self.list_files().await.map_err(|e| {
-            LspManagerError::InternalError(format!("Workspace file retrieval failed: {}", e))
-        })?;
-        if !workspace_files.contains(&file_path.to_string()) {
-            return Err(LspManagerError::FileNotFound(file_path.to_string()));
-        }
+        self.goto(GotoTarget::Definition, file_path, position).await
+    }
+
+    /// Finds the locations the goto request of the target resolves the symbol at the position
+    /// to, sorted by path.
+    pub async fn goto(
+        &self,
+        target: GotoTarget,
+        file_path: &str,
+        position: Position,
+    ) -> Result<GotoDefinitionResponse, LspManagerError> {
+        let lsp_type = self.workspace_file_language(file_path).await?;
         let full_path = get_mount_dir().join(file_path);
         let full_path_str = full_path.to_str().unwrap_or_default();
-        let lsp_type = detect_language(full_path_str).map_err(|e| {
-            LspManagerError::InternalError(format!("Language detection failed: {}", e))
-        })?;
 
-        let client = self
-            .get_client(lsp_type)
-            .ok_or(LspManagerError::LspClientNotFound(lsp_type))?;
-        let mut locked_client = client.lock().await;
-        let mut definition = locked_client
-            .text_document_definition(full_path_str, position)
-            .await
-            .map_err(|e| {
-                LspManagerError::InternalError(format!("Definition retrieval failed: {}", e))
-            })?;
+        let client = self.client_for_language(lsp_type).await?;
+        let result = match target {
+            GotoTarget::Definition => {
+                client
+                    .text_document_definition(full_path_str, position)
+                    .await
+            }
+            GotoTarget::Implementation => {
+                client
+                    .text_document_implementation(full_path_str, position)
+                    .await
+            }
+            GotoTarget::TypeDefinition => {
+                client
+                    .text_document_type_definition(full_path_str, position)
+                    .await
+            }
+            GotoTarget::Declaration => {
+                client
+                    .text_document_declaration(full_path_str, position)
+                    .await
+            }
+        };
+        let mut definition = result.map_err(|e| {
+            LspManagerError::request_failed(&format!("{} retrieval failed", target), e)
+        })?;
 
         // Sort the locations if there are multiple
         match &mut definition {
@@ -349,11 +558,61 @@ impl Manager {
         Ok(definition)
     }
 
-    pub fn get_client(
+    pub fn get_client(&self, lsp_type: SupportedLanguages) -> Option<Arc<dyn LspClient>> {
+        match self.lsp_clients.read().unwrap().get(&lsp_type) {
+            Some(LanguageServer::Ready(client)) => Some(Arc::clone(client)),
+            _ => None,
+        }
+    }
+
+    /// Like `get_client`, but tells apart a language server that is restarting or crashed from
+    /// one that was never started, and waits for the server to finish indexing if the request
+    /// asked to with `INDEXING_WAIT`.
+    async fn client_for_language(
         &self,
         lsp_type: SupportedLanguages,
-    ) -> Option<Arc<Mutex<Box<dyn LspClient>>>> {
-        self.lsp_clients.get(&lsp_type).cloned()
+    ) -> Result<Arc<dyn LspClient>, LspManagerError> {
+        let client = match self.lsp_clients.read().unwrap().get(&lsp_type) {
+            Some(LanguageServer::Ready(client)) => Arc::clone(client),
+            Some(server) => {
+                return Err(LspManagerError::LanguageServerUnavailable(
+                    lsp_type,
+                    server.status(),
+                ))
+            }
+            None => return Err(LspManagerError::LspClientNotFound(lsp_type)),
+        };
+        wait_for_indexing(lsp_type, client.as_ref()).await;
+        Ok(client)
+    }
+
+    /// The clients of the language servers that are ready, after waiting for them to finish
+    /// indexing if the request asked to with `INDEXING_WAIT`.
+    async fn ready_clients(&self) -> Vec<(SupportedLanguages, Arc<dyn LspClient>)> {
+        let clients: Vec<(SupportedLanguages, Arc<dyn LspClient>)> = self
+            .lsp_clients
+            .read()
+            .unwrap()
+            .iter()
+            .filter_map(|(lsp_type, server)| match server {
+                LanguageServer::Ready(client) => Some((*lsp_type, Arc::clone(client))),
+                _ => None,
+            })
+            .collect();
+        for (lsp_type, client) in &clients {
+            wait_for_indexing(*lsp_type, client.as_ref()).await;
+        }
+        clients
+    }
+
+    /// The status of each started language server.
+    pub fn language_server_statuses(&self) -> HashMap<SupportedLanguages, LanguageServerStatus> {
+        self.lsp_clients
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(lsp_type, server)| (*lsp_type, server.status()))
+            .collect()
     }
 
     pub async fn find_references(
@@ -361,180 +620,1343 @@ impl Manager {
         file_path: &str,
         position: Position,
     ) -> Result<Vec<Location>, LspManagerError> {
-        let workspace_files = self.list_files().await.map_err(|e| {
-            LspManagerError::InternalError(format!("Workspace file retrieval failed: {}", e))
-        })?;
+        let lsp_type = self.workspace_file_language(file_path).await?;
+        let full_path = get_mount_dir().join(file_path);
+        let full_path_str = full_path.to_str().unwrap_or_default();
+        let client = self.client_for_language(lsp_type).await?;
 
-        if !workspace_files.contains(&file_path.to_string()) {
-            return Err(LspManagerError::FileNotFound(file_path.to_string()));
-        }
+        client
+            .text_document_reference(full_path_str, position)
+            .await
+            .map_err(|e| LspManagerError::request_failed("Reference retrieval failed", e))
+    }
 
+    pub async fn hover(
+        &self,
+        file_path: &str,
+        position: Position,
+    ) -> Result<Option<Hover>, LspManagerError> {
+        let lsp_type = self.workspace_file_language(file_path).await?;
         let full_path = get_mount_dir().join(file_path);
         let full_path_str = full_path.to_str().unwrap_or_default();
-        let lsp_type = detect_language(full_path_str).map_err(|e| {
-            LspManagerError::InternalError(format!("Language detection failed: {}", e))
-        })?;
-        let client = self
-            .get_client(lsp_type)
-            .ok_or(LspManagerError::LspClientNotFound(lsp_type))?;
-        let mut locked_client = client.lock().await;
+        let client = self.client_for_language(lsp_type).await?;
 
-        locked_client
-            .text_document_reference(full_path_str, position)
+        client
+            .text_document_hover(full_path_str, position)
             .await
-            .map_err(|e| {
-                LspManagerError::InternalError(format!("Reference retrieval failed: {}", e))
-            })
+            .map_err(|e| LspManagerError::request_failed("Hover retrieval failed", e))
     }
 
-    pub async fn find_referenced_symbols(
+    /// Gets the completions at a position, best matches for the word being completed first,
+    /// and resolves the details of the returned ones. Also returns whether the completions are
+    /// incomplete, from the server or from the limit.
+    pub async fn completions(
         &self,
         file_path: &str,
         position: Position,
-        full_scan: bool,
-    ) -> Result<Vec<(AstGrepMatch, GotoDefinitionResponse)>, LspManagerError> {
-        let workspace_files = self.list_files().await.map_err(|e| {
-            LspManagerError::InternalError(format!("Workspace file retrieval failed: {}", e))
+        limit: usize,
+    ) -> Result<(Vec<CompletionItem>, bool), LspManagerError> {
+        let lsp_type = self.workspace_file_language(file_path).await?;
+        let full_path = get_mount_dir().join(file_path);
+        let full_path_str = full_path.to_str().unwrap_or_default();
+        let client = self.client_for_language(lsp_type).await?;
+
+        let content = self.read_file(&full_path).await.map_err(|e| {
+            LspManagerError::InternalError(format!("Failed to read {}: {}", file_path, e))
         })?;
+        ranked_completions(client.as_ref(), full_path_str, position, &content, limit).await
+    }
 
-        if !workspace_files.iter().any(|f| f == file_path) {
-            return Err(LspManagerError::FileNotFound(file_path.to_string()));
+    pub async fn signature_help(
+        &self,
+        file_path: &str,
+        position: Position,
+    ) -> Result<Option<SignatureHelp>, LspManagerError> {
+        let lsp_type = self.workspace_file_language(file_path).await?;
+        let full_path = get_mount_dir().join(file_path);
+        let full_path_str = full_path.to_str().unwrap_or_default();
+        let client = self.client_for_language(lsp_type).await?;
+
+        client
+            .text_document_signature_help(full_path_str, position)
+            .await
+            .map_err(|e| LspManagerError::request_failed("Signature help retrieval failed", e))
+    }
+
+    /// Renames the symbol at the position across the workspace, returning None if the symbol
+    /// can't be renamed.
+    pub async fn rename(
+        &self,
+        file_path: &str,
+        position: Position,
+        new_name: &str,
+    ) -> Result<Option<WorkspaceEdit>, LspManagerError> {
+        let lsp_type = self.workspace_file_language(file_path).await?;
+        let full_path = get_mount_dir().join(file_path);
+        let full_path_str = full_path.to_str().unwrap_or_default();
+        let client = self.client_for_language(lsp_type).await?;
+
+        // Not every server supports prepareRename, in which case the rename itself reports
+        // whether the symbol can be renamed
+        match client
+            .text_document_prepare_rename(full_path_str, position)
+            .await
+        {
+            Ok(None) => return Ok(None),
+            Ok(Some(_)) => {}
+            Err(e) => warn!("Prepare rename failed, renaming anyway: {}", e),
         }
 
+        client
+            .text_document_rename(full_path_str, position, new_name)
+            .await
+            .map_err(|e| LspManagerError::request_failed("Rename failed", e))
+    }
+
+    /// Lists the code actions available for a range, optionally only those of the given kinds
+    /// or their sub-kinds, e.g. `refactor` includes `refactor.extract`.
+    ///
+    /// The diagnostics cached for the file are passed along, so servers can offer their quick
+    /// fixes.
+    pub async fn code_actions(
+        &self,
+        file_path: &str,
+        range: Range,
+        kinds: &[String],
+    ) -> Result<Vec<CodeActionOrCommand>, LspManagerError> {
+        let lsp_type = self.workspace_file_language(file_path).await?;
         let full_path = get_mount_dir().join(file_path);
         let full_path_str = full_path.to_str().unwrap_or_default();
+        let client = self.client_for_language(lsp_type).await?;
+
+        let uri = Url::from_file_path(&full_path).map_err(|_| {
+            LspManagerError::InternalError(format!("Invalid file path: {}", file_path))
+        })?;
+        let diagnostics = client
+            .get_diagnostics()
+            .get(&uri)
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|diagnostic| {
+                diagnostic.range.start <= range.end && range.start <= diagnostic.range.end
+            })
+            .collect();
+        let only = (!kinds.is_empty()).then(|| {
+            kinds
+                .iter()
+                .map(|kind| CodeActionKind::from(kind.clone()))
+                .collect()
+        });
+
+        let actions = client
+            .text_document_code_action(full_path_str, range, diagnostics, only)
+            .await
+            .map_err(|e| LspManagerError::request_failed("Code action retrieval failed", e))?;
 
+        // Servers are free to ignore the requested kinds
+        Ok(actions
+            .into_iter()
+            .filter(|action| kinds.is_empty() || code_action_has_kind(action, kinds))
+            .collect())
+    }
+
+    /// Computes the workspace edits of a code action, resolving it first if the server deferred
+    /// its edit, and executing its command if it has one.
+    ///
+    /// Edits the server requests while executing the command are captured, not applied.
+    pub async fn code_action_edits(
+        &self,
+        file_path: &str,
+        action: CodeActionOrCommand,
+    ) -> Result<Vec<WorkspaceEdit>, LspManagerError> {
+        let full_path = get_mount_dir().join(file_path);
+        let full_path_str = full_path.to_str().unwrap_or_default();
         let lsp_type = detect_language(full_path_str).map_err(|e| {
             LspManagerError::InternalError(format!("Language detection failed: {}", e))
         })?;
+        let client = self.client_for_language(lsp_type).await?;
 
-        // Only Python and TypeScript/JavaScript are currently supported
-        match lsp_type {
-            SupportedLanguages::Python | SupportedLanguages::TypeScriptJavaScript | SupportedLanguages::CSharp => (),
-            _ => return Err(LspManagerError::NotImplemented(
-                "Find referenced symbols is only implemented for Python, TypeScript/JavaScript, and C#"
-                    .to_string(),
-            )),
+        let (edit, command) = match action {
+            CodeActionOrCommand::Command(command) => (None, Some(command)),
+            CodeActionOrCommand::CodeAction(action) => {
+                let action = if action.edit.is_none() && action.data.is_some() {
+                    client.code_action_resolve(action).await.map_err(|e| {
+                        LspManagerError::request_failed("Code action resolution failed", e)
+                    })?
+                } else {
+                    action
+                };
+                (action.edit, action.command)
+            }
+        };
+
+        let mut edits: Vec<WorkspaceEdit> = edit.into_iter().collect();
+        if let Some(command) = command {
+            let command_edits = client
+                .workspace_execute_command(command)
+                .await
+                .map_err(|e| LspManagerError::request_failed("Command execution failed", e))?;
+            edits.extend(command_edits);
         }
+        Ok(edits)
+    }
 
-        // Get the symbol and its references
-        let (_, references_to_symbols) = match self
-            .ast_grep
-            .get_symbol_and_references(full_path_str, &position, full_scan)
-            .await
-        {
-            Ok(result) => result,
-            Err(e) => {
+    /// Flattens workspace edits into the text edits of each file, sorted by path, and computes
+    /// the edited content of the files without writing them.
+    ///
+    /// Edits to the same file from different workspace edits must not overlap.
+    pub async fn resolve_workspace_edits(
+        &self,
+        workspace_edits: Vec<WorkspaceEdit>,
+    ) -> Result<Vec<FileEdit>, LspManagerError> {
+        let mut changes: Vec<(Url, Vec<TextEdit>)> = Vec::new();
+        for edit in workspace_edits {
+            changes.extend(workspace_edit_changes(edit)?);
+        }
+
+        let mut edits_by_path: BTreeMap<String, Vec<TextEdit>> = BTreeMap::new();
+        for (uri, edits) in changes {
+            let path = uri_to_relative_path_string(&uri);
+            if !self.is_workspace_file(&path).await {
                 return Err(LspManagerError::InternalError(format!(
-                    "Failed to find referenced symbols, {}",
-                    e
+                    "Edit outside of the workspace: {}",
+                    uri
                 )));
             }
-        };
-
-        let client = self
-            .get_client(lsp_type)
-            .ok_or(LspManagerError::LspClientNotFound(lsp_type))?;
-        let mut locked_client = client.lock().await;
-        let mut definitions = Vec::new();
+            edits_by_path.entry(path).or_default().extend(edits);
+        }
 
-        // Get direct definitions for each reference
-        for ast_match in references_to_symbols.iter() {
-            match locked_client
-                .text_document_definition(full_path_str, lsp_types::Position::from(ast_match))
+        let mut file_edits = Vec::new();
+        for (path, edits) in edits_by_path {
+            let original = self
+                .read_file(&get_mount_dir().join(&path))
                 .await
-            {
-                Ok(definition) => {
-                    definitions.push((ast_match.clone(), definition));
-                }
-                Err(e) => {
-                    // Log the error but continue processing other references
-                    log::warn!(
-                        "Definition retrieval failed for reference: {}, error: {}",
-                        ast_match.meta_variables.single.name.text,
-                        e
-                    );
-                }
+                .map_err(|e| {
+                    LspManagerError::InternalError(format!("Failed to read {}: {}", path, e))
+                })?;
+            let file_edit = FileEdit::new(&path, original, edits).map_err(|e| {
+                LspManagerError::InternalError(format!("Failed to edit {}: {}", path, e))
+            })?;
+            file_edits.push(file_edit);
+        }
+        Ok(file_edits)
+    }
+
+    /// Writes edited files to the workspace and syncs them with their language servers.
+    ///
+    /// Edits to an overlaid file were computed against its overlay, so writing them saves the
+    /// overlay and closes it.
+    ///
+    /// Nothing is written if a file changed since its edits were computed, which is a conflict.
+    /// The files are written next to themselves first and only then moved into place, so a
+    /// failed write leaves every file as it was. Should moving a file fail, the error lists the
+    /// files already written.
+    pub async fn apply_file_edits(&self, file_edits: &[FileEdit]) -> Result<(), LspManagerError> {
+        let full_paths: Vec<PathBuf> = file_edits
+            .iter()
+            .map(|file_edit| get_mount_dir().join(&file_edit.path))
+            .collect();
+        for (file_edit, full_path) in file_edits.iter().zip(&full_paths) {
+            let current = self.read_file(full_path).await.map_err(|e| {
+                LspManagerError::InternalError(format!("Failed to read {}: {}", file_edit.path, e))
+            })?;
+            if current != file_edit.original {
+                return Err(LspManagerError::Conflict(format!(
+                    "{} changed since its edits were computed, no files were written",
+                    file_edit.path
+                )));
             }
         }
 
-        // Only return an error if we couldn't get any definitions at all
-        if definitions.is_empty() && !references_to_symbols.is_empty() {
-            return Err(LspManagerError::InternalError(
-                "Failed to retrieve any definitions for the referenced symbols".to_string(),
+        let mut temp_paths = Vec::new();
+        for (file_edit, full_path) in file_edits.iter().zip(&full_paths) {
+            let temp_path = full_path.with_file_name(format!(
+                ".{}.lsproxy-edit",
+                full_path.file_name().unwrap_or_default().to_string_lossy()
             ));
+            let written = async {
+                tokio::fs::write(&temp_path, &file_edit.edited).await?;
+                let permissions = tokio::fs::metadata(full_path).await?.permissions();
+                tokio::fs::set_permissions(&temp_path, permissions).await
+            }
+            .await;
+            temp_paths.push(temp_path);
+            if let Err(e) = written {
+                remove_files(&temp_paths).await;
+                return Err(LspManagerError::InternalError(format!(
+                    "Failed to write {}, no files were written: {}",
+                    file_edit.path, e
+                )));
+            }
         }
 
-        Ok(definitions)
-    }
-
-    pub async fn list_files(&self) -> Result<Vec<String>, LspManagerError> {
-        let mut files = Vec::new();
-        for client in self.lsp_clients.values() {
-            let mut locked_client = client.lock().await;
-            files.extend(
-                locked_client
-                    .get_workspace_documents()
-                    .list_files()
-                    .await
+        for (i, (temp_path, full_path)) in temp_paths.iter().zip(&full_paths).enumerate() {
+            if let Err(e) = tokio::fs::rename(temp_path, full_path).await {
+                remove_files(&temp_paths[i..]).await;
+                let written: Vec<&str> = file_edits[..i]
                     .iter()
-                    .filter_map(|f| Some(absolute_path_to_relative_path_string(f)))
-                    .collect::<Vec<String>>(),
-            );
+                    .map(|file_edit| file_edit.path.as_str())
+                    .collect();
+                return Err(LspManagerError::InternalError(format!(
+                    "Failed to write {}: {}, files written: {:?}",
+                    file_edits[i].path, e, written
+                )));
+            }
+        }
+
+        for (file_edit, full_path) in file_edits.iter().zip(&full_paths) {
+            self.overlays.write().await.remove(full_path);
+
+            let full_path_str = full_path.to_str().unwrap_or_default();
+            let client = match detect_language(full_path_str)
+                .ok()
+                .and_then(|lsp_type| self.get_client(lsp_type))
+            {
+                Some(client) => client,
+                None => continue,
+            };
+            client
+                .text_document_did_change(full_path_str, file_edit.edited.clone())
+                .await
+                .map_err(|e| {
+                    LspManagerError::InternalError(format!(
+                        "Failed to sync {}: {}",
+                        file_edit.path, e
+                    ))
+                })?;
         }
-        files.sort();
-        Ok(files)
+        Ok(())
     }
 
-    pub async fn read_source_code(
+    pub async fn file_diagnostics(
         &self,
         file_path: &str,
-        range: Option<Range>,
-    ) -> Result<String, LspManagerError> {
-        let client = self.get_client(detect_language(file_path)?).ok_or(
-            LspManagerError::LspClientNotFound(detect_language(file_path)?),
-        )?;
+    ) -> Result<Vec<Diagnostic>, LspManagerError> {
+        let lsp_type = self.workspace_file_language(file_path).await?;
         let full_path = get_mount_dir().join(file_path);
-        let mut locked_client = client.lock().await;
-        locked_client
-            .get_workspace_documents()
-            .read_text_document(&full_path, range)
+        let full_path_str = full_path.to_str().unwrap_or_default();
+        let client = self.client_for_language(lsp_type).await?;
+
+        if !client.get_diagnostics().is_enabled() {
+            return Err(LspManagerError::NotImplemented(format!(
+                "Diagnostics are not enabled for {:?}, add it to DIAGNOSTICS_LANGUAGES",
+                lsp_type
+            )));
+        }
+
+        client
+            .text_document_diagnostic(full_path_str)
             .await
-            .map_err(|e| {
-                LspManagerError::InternalError(format!("Source code retrieval failed: {}", e))
-            })
+            .map_err(|e| LspManagerError::request_failed("Diagnostics retrieval failed", e))
     }
 
-    pub async fn get_file_identifiers(
+    /// Collects the diagnostics of the workspace files from every language with diagnostics
+    /// enabled, sorted by file.
+    pub async fn workspace_diagnostics(
         &self,
-        file_path: &str,
-    ) -> Result<Vec<Identifier>, LspManagerError> {
-        let full_path = get_mount_dir().join(file_path);
-        let workspace_files = self.list_files().await.map_err(|e| {
-            LspManagerError::InternalError(format!("Workspace file retrieval failed: {}", e))
-        })?;
-        if !workspace_files.contains(&file_path.to_string()) {
-            return Err(LspManagerError::FileNotFound(file_path.to_string()));
+    ) -> Result<Vec<(Url, Vec<Diagnostic>)>, LspManagerError> {
+        let workspace_files: HashSet<String> = self.list_files().await?.into_iter().collect();
+
+        let mut enabled = false;
+        let mut diagnostics = Vec::new();
+        for (_, client) in self.ready_clients().await {
+            if !client.get_diagnostics().is_enabled() {
+                continue;
+            }
+            enabled = true;
+            diagnostics.extend(
+                client.workspace_diagnostic().await.map_err(|e| {
+                    LspManagerError::request_failed("Diagnostics retrieval failed", e)
+                })?,
+            );
         }
-        let full_path_str = full_path.to_str().unwrap_or_default();
-        let ast_grep_result = self
-            .ast_grep
-            .get_file_identifiers(full_path_str)
-            .await
+
+        if !enabled {
+            return Err(LspManagerError::NotImplemented(
+                "Diagnostics are not enabled for any language, set DIAGNOSTICS_LANGUAGES"
+                    .to_string(),
+            ));
+        }
+
+        // Servers can report on files outside the workspace, e.g. dependencies
+        diagnostics.retain(|(uri, _)| workspace_files.contains(&uri_to_relative_path_string(uri)));
+        diagnostics.sort_by_key(|(uri, _)| uri_to_relative_path_string(uri));
+        Ok(diagnostics)
+    }
+
+    pub async fn prepare_call_hierarchy(
+        &self,
+        file_path: &str,
+        position: Position,
+    ) -> Result<Vec<CallHierarchyItem>, LspManagerError> {
+        let lsp_type = self.workspace_file_language(file_path).await?;
+        let full_path = get_mount_dir().join(file_path);
+        let full_path_str = full_path.to_str().unwrap_or_default();
+        let client = self.client_for_language(lsp_type).await?;
+
+        client
+            .text_document_prepare_call_hierarchy(full_path_str, position)
+            .await
+            .map_err(|e| LspManagerError::request_failed("Call hierarchy retrieval failed", e))
+    }
+
+    /// Returns the callers of an item previously returned by `prepare_call_hierarchy`.
+    pub async fn incoming_calls(
+        &self,
+        item: &CallHierarchyItem,
+    ) -> Result<Vec<CallHierarchyIncomingCall>, LspManagerError> {
+        let client = self.get_client_for_uri(&item.uri).await?;
+
+        client
+            .call_hierarchy_incoming_calls(item.clone())
+            .await
+            .map_err(|e| LspManagerError::request_failed("Incoming calls retrieval failed", e))
+    }
+
+    /// Returns the callees of an item previously returned by `prepare_call_hierarchy`.
+    pub async fn outgoing_calls(
+        &self,
+        item: &CallHierarchyItem,
+    ) -> Result<Vec<CallHierarchyOutgoingCall>, LspManagerError> {
+        let client = self.get_client_for_uri(&item.uri).await?;
+
+        client
+            .call_hierarchy_outgoing_calls(item.clone())
+            .await
+            .map_err(|e| LspManagerError::request_failed("Outgoing calls retrieval failed", e))
+    }
+
+    pub async fn prepare_type_hierarchy(
+        &self,
+        file_path: &str,
+        position: Position,
+    ) -> Result<Vec<TypeHierarchyItem>, LspManagerError> {
+        let lsp_type = self.workspace_file_language(file_path).await?;
+        let full_path = get_mount_dir().join(file_path);
+        let full_path_str = full_path.to_str().unwrap_or_default();
+        let client = self.client_for_language(lsp_type).await?;
+
+        client
+            .text_document_prepare_type_hierarchy(full_path_str, position)
+            .await
+            .map_err(|e| LspManagerError::request_failed("Type hierarchy retrieval failed", e))
+    }
+
+    /// Returns the supertypes of an item previously returned by `prepare_type_hierarchy`.
+    pub async fn supertypes(
+        &self,
+        item: &TypeHierarchyItem,
+    ) -> Result<Vec<TypeHierarchyItem>, LspManagerError> {
+        let client = self.get_client_for_uri(&item.uri).await?;
+
+        client
+            .type_hierarchy_supertypes(item.clone())
+            .await
+            .map_err(|e| LspManagerError::request_failed("Supertypes retrieval failed", e))
+    }
+
+    /// Returns the subtypes of an item previously returned by `prepare_type_hierarchy`.
+    pub async fn subtypes(
+        &self,
+        item: &TypeHierarchyItem,
+    ) -> Result<Vec<TypeHierarchyItem>, LspManagerError> {
+        let client = self.get_client_for_uri(&item.uri).await?;
+
+        client
+            .type_hierarchy_subtypes(item.clone())
+            .await
+            .map_err(|e| LspManagerError::request_failed("Subtypes retrieval failed", e))
+    }
+
+    /// Finds every `class X(Base)` / `extends` / `implements` relation in the workspace with
+    /// ast-grep, for langservers that don't support type hierarchies.
+    pub async fn type_relations_ast_grep(&self) -> Result<Vec<AstGrepMatch>, LspManagerError> {
+        let mount_dir = get_mount_dir();
+        let files: Vec<String> = self
+            .list_files()
+            .await?
+            .iter()
+            .map(|file| mount_dir.join(file).to_string_lossy().into_owned())
+            .collect();
+        self.ast_grep.get_type_relations(&files).await.map_err(|e| {
+            LspManagerError::InternalError(format!("Type relation retrieval failed: {}", e))
+        })
+    }
+
+    async fn get_client_for_uri(&self, uri: &Url) -> Result<Arc<dyn LspClient>, LspManagerError> {
+        let full_path = uri
+            .to_file_path()
+            .map_err(|_| LspManagerError::InternalError(format!("Invalid file uri: {}", uri)))?;
+        let lsp_type = detect_language(full_path.to_str().unwrap_or_default()).map_err(|e| {
+            LspManagerError::InternalError(format!("Language detection failed: {}", e))
+        })?;
+        self.client_for_language(lsp_type).await
+    }
+
+    pub async fn find_referenced_symbols(
+        &self,
+        file_path: &str,
+        position: Position,
+        full_scan: bool,
+    ) -> Result<Vec<(AstGrepMatch, GotoDefinitionResponse)>, LspManagerError> {
+        let lsp_type = self.workspace_file_language(file_path).await?;
+        let full_path = get_mount_dir().join(file_path);
+        let full_path_str = full_path.to_str().unwrap_or_default();
+
+        // Get the symbol and its references
+        let (_, references_to_symbols) = match self
+            .ast_grep
+            .get_symbol_and_references(full_path_str, &position, full_scan)
+            .await
+        {
+            Ok(result) => result,
+            Err(e) => {
+                return Err(LspManagerError::InternalError(format!(
+                    "Failed to find referenced symbols, {}",
+                    e
+                )));
+            }
+        };
+
+        let client = self.client_for_language(lsp_type).await?;
+        let mut definitions = Vec::new();
+
+        // Get direct definitions for each reference
+        for ast_match in references_to_symbols.iter() {
+            match client
+                .text_document_definition(full_path_str, lsp_types::Position::from(ast_match))
+                .await
+            {
+                Ok(definition) => {
+                    definitions.push((ast_match.clone(), definition));
+                }
+                Err(e) => {
+                    // Log the error but continue processing other references
+                    log::warn!(
+                        "Definition retrieval failed for reference: {}, error: {}",
+                        ast_match.meta_variables.single.name.text,
+                        e
+                    );
+                }
+            }
+        }
+
+        // Only return an error if we couldn't get any definitions at all
+        if definitions.is_empty() && !references_to_symbols.is_empty() {
+            return Err(LspManagerError::InternalError(
+                "Failed to retrieve any definitions for the referenced symbols".to_string(),
+            ));
+        }
+
+        Ok(definitions)
+    }
+
+    /// Overlays workspace files with in-memory contents for a session: the language servers
+    /// and ast-grep see the contents instead of the files on disk until the overlays are
+    /// closed. Opening a file the session already overlays replaces its contents.
+    pub async fn open_overlays(
+        &self,
+        session: &str,
+        files: Vec<(String, String)>,
+    ) -> Result<(), LspManagerError> {
+        self.set_overlays(session, files, false).await
+    }
+
+    /// Replaces the contents of files the session overlays.
+    pub async fn update_overlays(
+        &self,
+        session: &str,
+        files: Vec<(String, String)>,
+    ) -> Result<(), LspManagerError> {
+        self.set_overlays(session, files, true).await
+    }
+
+    async fn set_overlays(
+        &self,
+        session: &str,
+        files: Vec<(String, String)>,
+        update: bool,
+    ) -> Result<(), LspManagerError> {
+        for (file_path, text) in files {
+            let lsp_type = self.workspace_file_language(&file_path).await?;
+            let full_path = get_mount_dir().join(&file_path);
+            let full_path_str = full_path.to_str().unwrap_or_default();
+            let client = self.client_for_language(lsp_type).await?;
+
+            {
+                let mut overlays = self.overlays.write().await;
+                let result = if update {
+                    overlays.update(session, &full_path, text.clone())
+                } else {
+                    overlays.open(session, &full_path, text.clone())
+                };
+                result
+                    .map_err(|e| LspManagerError::Conflict(format!("'{}' is {}", file_path, e)))?;
+            }
+
+            client
+                .show_overlay(full_path_str, text)
+                .await
+                .map_err(|e| {
+                    LspManagerError::InternalError(format!(
+                        "Failed to send the overlay of {}: {}",
+                        file_path, e
+                    ))
+                })?;
+        }
+        Ok(())
+    }
+
+    /// Closes the session's overlays of `file_paths`, or all of them if None, reverting the
+    /// language servers to the files on disk. Returns the files that were overlaid.
+    pub async fn close_overlays(
+        &self,
+        session: &str,
+        file_paths: Option<Vec<String>>,
+    ) -> Result<Vec<String>, LspManagerError> {
+        let full_paths: Option<Vec<PathBuf>> = file_paths.map(|file_paths| {
+            file_paths
+                .iter()
+                .map(|file_path| get_mount_dir().join(file_path))
+                .collect()
+        });
+        let closed = self
+            .overlays
+            .write()
+            .await
+            .close(session, full_paths.as_deref());
+
+        for full_path in &closed {
+            let full_path_str = full_path.to_str().unwrap_or_default();
+            let Some(client) = detect_language(full_path_str)
+                .ok()
+                .and_then(|lsp_type| self.get_client(lsp_type))
+            else {
+                continue;
+            };
+            client.hide_overlay(full_path_str).await.map_err(|e| {
+                LspManagerError::InternalError(format!(
+                    "Failed to revert {}: {}",
+                    full_path.display(),
+                    e
+                ))
+            })?;
+        }
+        Ok(closed
+            .iter()
+            .map(absolute_path_to_relative_path_string)
+            .collect())
+    }
+
+    /// The files the session overlays.
+    pub async fn overlaid_files(&self, session: &str) -> Vec<String> {
+        self.overlays
+            .read()
+            .await
+            .session_paths(session)
+            .iter()
+            .map(absolute_path_to_relative_path_string)
+            .collect()
+    }
+
+    /// Opens overlays that only last for a single request, returning the session to close them
+    /// with, or None if there are no files to overlay.
+    pub async fn open_inline_overlays(
+        &self,
+        files: Vec<(String, String)>,
+    ) -> Result<Option<String>, LspManagerError> {
+        if files.is_empty() {
+            return Ok(None);
+        }
+        let session = format!(
+            "inline-{}",
+            self.inline_sessions.fetch_add(1, Ordering::Relaxed)
+        );
+        if let Err(e) = self.open_overlays(&session, files).await {
+            if let Err(close_error) = self.close_overlays(&session, None).await {
+                error!("Failed to close inline overlays: {}", close_error);
+            }
+            return Err(e);
+        }
+        Ok(Some(session))
+    }
+
+    /// Reads a workspace file, or its overlay if it is overlaid.
+    async fn read_file(&self, full_path: &Path) -> std::io::Result<String> {
+        if let Some(text) = self.overlays.read().await.text(full_path) {
+            return Ok(text.to_string());
+        }
+        tokio::fs::read_to_string(full_path).await
+    }
+
+    pub async fn list_files(&self) -> Result<Vec<String>, LspManagerError> {
+        Ok(self.file_index.read().await.files())
+    }
+
+    /// Whether the path, relative to the workspace root, is a workspace file of one of the
+    /// language servers.
+    pub async fn is_workspace_file(&self, file_path: &str) -> bool {
+        self.file_index.read().await.contains(file_path)
+    }
+
+    /// The language of a workspace file, or FileNotFound if it isn't one.
+    async fn workspace_file_language(
+        &self,
+        file_path: &str,
+    ) -> Result<SupportedLanguages, LspManagerError> {
+        self.file_index.read().await.language(file_path)
+    }
+
+    /// The document cache statistics summed over the language servers.
+    pub async fn document_cache_stats(&self) -> DocumentCacheStats {
+        let mut total = DocumentCacheStats::default();
+        for (_, client) in self.ready_clients().await {
+            let stats = client.get_workspace_documents().cache_stats().await;
+            total.files += stats.files;
+            total.cached_documents += stats.cached_documents;
+            total.hits += stats.hits;
+            total.misses += stats.misses;
+        }
+        total
+    }
+
+    pub async fn read_source_code(
+        &self,
+        file_path: &str,
+        range: Option<Range>,
+    ) -> Result<String, LspManagerError> {
+        let client = self
+            .client_for_language(detect_language(file_path)?)
+            .await?;
+        let full_path = get_mount_dir().join(file_path);
+        if let Some(text) = self.overlays.read().await.text(&full_path) {
+            return match range {
+                Some(range) => WorkspaceDocumentsHandler::extract_range(text, range),
+                None => Ok(text.to_string()),
+            }
+            .map_err(|e| {
+                LspManagerError::InternalError(format!("Source code retrieval failed: {}", e))
+            });
+        }
+        client
+            .get_workspace_documents()
+            .read_text_document(&full_path, range)
+            .await
+            .map_err(|e| {
+                LspManagerError::InternalError(format!("Source code retrieval failed: {}", e))
+            })
+    }
+
+    pub async fn get_file_identifiers(
+        &self,
+        file_path: &str,
+    ) -> Result<Vec<Identifier>, LspManagerError> {
+        let full_path = get_mount_dir().join(file_path);
+        if !self.is_workspace_file(file_path).await {
+            return Err(LspManagerError::FileNotFound(file_path.to_string()));
+        }
+        let full_path_str = full_path.to_str().unwrap_or_default();
+        let ast_grep_result = self
+            .ast_grep
+            .get_file_identifiers(full_path_str)
+            .await
             .map_err(|e| {
                 LspManagerError::InternalError(format!("Symbol retrieval failed: {}", e))
             })?;
         Ok(ast_grep_result.into_iter().map(|s| s.into()).collect())
     }
+
+    /// Searches the workspace files matching any of the globs, or all of them without globs,
+    /// for the code the rule matches.
+    pub async fn structural_search(
+        &self,
+        rule: RuleConfig,
+        paths: &[glob::Pattern],
+    ) -> Result<Vec<SearchMatch>, LspManagerError> {
+        let files = self.files_matching_globs(paths).await?;
+        self.ast_grep
+            .search(rule, &files)
+            .await
+            .map_err(|e| LspManagerError::InternalError(format!("Structural search failed: {}", e)))
+    }
+
+    /// Computes the edits replacing the code the rule matches with the fix in the workspace
+    /// files matching any of the globs, or all of them without globs, without writing them.
+    /// Returns the files the fix changes, sorted by path.
+    pub async fn structural_rewrite(
+        &self,
+        rule: RuleConfig,
+        fix: Fix,
+        paths: &[glob::Pattern],
+    ) -> Result<Vec<FileEdit>, LspManagerError> {
+        let files = self.files_matching_globs(paths).await?;
+        let rewrites = self
+            .ast_grep
+            .rewrite(rule, fix, &files)
+            .await
+            .map_err(|e| {
+                LspManagerError::InternalError(format!("Structural rewrite failed: {}", e))
+            })?;
+
+        let mut file_edits = Vec::new();
+        for rewrite in rewrites {
+            let path = absolute_path_to_relative_path_string(&PathBuf::from(&rewrite.file));
+            let edits = rewrite
+                .edits
+                .into_iter()
+                .map(|(range, new_text)| TextEdit {
+                    range: Range {
+                        start: offset_to_position(&rewrite.source, range.start),
+                        end: offset_to_position(&rewrite.source, range.end),
+                    },
+                    new_text,
+                })
+                .collect();
+            let file_edit = FileEdit::new(&path, rewrite.source, edits).map_err(|e| {
+                LspManagerError::InternalError(format!("Failed to edit {}: {}", path, e))
+            })?;
+            file_edits.push(file_edit);
+        }
+        file_edits.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(file_edits)
+    }
+
+    /// The absolute paths of the workspace files matching any of the globs, or of all of them
+    /// without globs.
+    async fn files_matching_globs(
+        &self,
+        paths: &[glob::Pattern],
+    ) -> Result<Vec<String>, LspManagerError> {
+        let mount_dir = get_mount_dir();
+        Ok(self
+            .list_files()
+            .await?
+            .into_iter()
+            .filter(|file| paths.is_empty() || paths.iter().any(|glob| glob.matches(file)))
+            .map(|file| mount_dir.join(file).to_string_lossy().into_owned())
+            .collect())
+    }
+}
+
+/// Waits for a language server to finish indexing for as long as `INDEXING_WAIT`, if set.
+async fn wait_for_indexing(lsp_type: SupportedLanguages, client: &dyn LspClient) {
+    let Ok(wait) = INDEXING_WAIT.try_with(|wait| *wait) else {
+        return;
+    };
+    if !client.get_progress().wait_until_ready(wait).await {
+        warn!(
+            "{:?} language server still indexing after {:?}, results may be partial",
+            lsp_type, wait
+        );
+    }
+}
+
+/// Starts and initializes the language server of `lsp` for the workspace.
+async fn start_langserver(
+    lsp: SupportedLanguages,
+    workspace_path: &str,
+    watch_events_sender: &Sender<DebouncedEvent>,
+    diagnostics: bool,
+) -> Result<Arc<dyn LspClient>, String> {
+    let client: Box<dyn LspClient> = match lsp {
+        SupportedLanguages::Python => Box::new(
+            JediClient::new(workspace_path, watch_events_sender.subscribe())
+                .await
+                .map_err(|e| e.to_string())?,
+        ),
+        SupportedLanguages::TypeScriptJavaScript => Box::new(
+            TypeScriptLanguageClient::new(workspace_path, watch_events_sender.subscribe())
+                .await
+                .map_err(|e| e.to_string())?,
+        ),
+        SupportedLanguages::Rust => Box::new(
+            RustAnalyzerClient::new(workspace_path, watch_events_sender.subscribe())
+                .await
+                .map_err(|e| e.to_string())?,
+        ),
+        SupportedLanguages::CPP => Box::new(
+            ClangdClient::new(workspace_path, watch_events_sender.subscribe())
+                .await
+                .map_err(|e| e.to_string())?,
+        ),
+        SupportedLanguages::CSharp => Box::new(
+            CSharpClient::new(workspace_path, watch_events_sender.subscribe())
+                .await
+                .map_err(|e| e.to_string())?,
+        ),
+        SupportedLanguages::Java => Box::new(
+            JdtlsClient::new(workspace_path, watch_events_sender.subscribe())
+                .await
+                .map_err(|e| e.to_string())?,
+        ),
+        SupportedLanguages::Golang => Box::new(
+            GoplsClient::new(workspace_path, watch_events_sender.subscribe())
+                .await
+                .map_err(|e| e.to_string())?,
+        ),
+        SupportedLanguages::PHP => Box::new(
+            PhpactorClient::new(workspace_path, watch_events_sender.subscribe())
+                .await
+                .map_err(|e| e.to_string())?,
+        ),
+        SupportedLanguages::Ruby => Box::new(
+            RubyClient::new(workspace_path, watch_events_sender.subscribe())
+                .await
+                .map_err(|e| e.to_string())?,
+        ),
+        SupportedLanguages::RubySorbet => Box::new(
+            RubySorbetClient::new(workspace_path, watch_events_sender.subscribe())
+                .await
+                .map_err(|e| e.to_string())?,
+        ),
+    };
+    if diagnostics {
+        info!("Enabling diagnostics for {:?}", lsp);
+        client.get_diagnostics().enable();
+    }
+    let init_result = client
+        .initialize(workspace_path.to_string())
+        .await
+        .map_err(|e| e.to_string())?;
+    client
+        .get_diagnostics()
+        .set_pull_support(&init_result.capabilities.diagnostic_provider);
+    info!("Setting up workspace");
+    client
+        .setup_workspace(workspace_path)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(Arc::from(client))
+}
+
+/// Forwards file changes to a language server and restarts it whenever it stops, waiting longer
+/// after each restart. After `MAX_RESTARTS` restarts in a row that didn't last
+/// `STABLE_UPTIME`, the server is considered crashed and left stopped.
+async fn supervise_langserver(
+    lsp: SupportedLanguages,
+    workspace_path: String,
+    mut client: Arc<dyn LspClient>,
+    diagnostics: bool,
+    lsp_clients: Arc<std::sync::RwLock<HashMap<SupportedLanguages, LanguageServer>>>,
+    watch_events_sender: Sender<DebouncedEvent>,
+    overlays: Arc<RwLock<Overlays>>,
+) {
+    let mut restarts = 0;
+    loop {
+        let started = Instant::now();
+        forward_file_changes(
+            Arc::clone(&client),
+            watch_events_sender.subscribe(),
+            Arc::clone(&overlays),
+        )
+        .await;
+        let reason = client.get_pending_requests().wait_closed().await;
+        if started.elapsed() >= STABLE_UPTIME {
+            restarts = 0;
+        }
+
+        client = loop {
+            if restarts == MAX_RESTARTS {
+                error!(
+                    "{:?} language server stopped {} times in a row, giving up",
+                    lsp, MAX_RESTARTS
+                );
+                lsp_clients
+                    .write()
+                    .unwrap()
+                    .insert(lsp, LanguageServer::Crashed);
+                return;
+            }
+            lsp_clients
+                .write()
+                .unwrap()
+                .insert(lsp, LanguageServer::Restarting);
+            let backoff = (RESTART_BACKOFF * 2u32.pow(restarts)).min(MAX_RESTART_BACKOFF);
+            warn!(
+                "{:?} language server stopped ({}), restarting in {:?}",
+                lsp, reason, backoff
+            );
+            tokio::time::sleep(backoff).await;
+            restarts += 1;
+            match start_langserver(lsp, &workspace_path, &watch_events_sender, diagnostics).await {
+                Ok(client) => break client,
+                Err(e) => error!("Failed to restart {:?} language server: {}", lsp, e),
+            }
+        };
+
+        // The new server only knows the files on disk
+        let overlaid: Vec<(PathBuf, String)> = overlays
+            .read()
+            .await
+            .iter()
+            .map(|(path, overlay)| (path.clone(), overlay.text.clone()))
+            .collect();
+        for (path, text) in overlaid {
+            let path = path.to_string_lossy();
+            if detect_language(&path).ok() != Some(lsp) {
+                continue;
+            }
+            if let Err(e) = client.show_overlay(&path, text).await {
+                error!("Failed to send the overlay of {}: {}", path, e);
+            }
+        }
+        info!("Restarted {:?} language server", lsp);
+        lsp_clients
+            .write()
+            .unwrap()
+            .insert(lsp, LanguageServer::Ready(Arc::clone(&client)));
+    }
+}
+
+/// Keeps the file index up to date with the changes reported by the watcher.
+async fn update_file_index(
+    file_index: Arc<RwLock<FileIndex>>,
+    mut watch_events: Receiver<DebouncedEvent>,
+) {
+    loop {
+        match watch_events.recv().await {
+            Ok(event) => file_index.write().await.update(&event.path),
+            Err(RecvError::Lagged(skipped)) => {
+                warn!(
+                    "Missed {} file change events, rebuilding the file index",
+                    skipped
+                );
+                file_index.write().await.rebuild();
+            }
+            Err(RecvError::Closed) => break,
+        }
+    }
+}
+
+/// Sends file changes to a language server until it stops.
+async fn forward_file_changes(
+    client: Arc<dyn LspClient>,
+    mut watch_events: Receiver<DebouncedEvent>,
+    overlays: Arc<RwLock<Overlays>>,
+) {
+    let mut known_files: HashSet<PathBuf> = client
+        .get_workspace_documents()
+        .list_files()
+        .await
+        .into_iter()
+        .collect();
+
+    loop {
+        let received = tokio::select! {
+            received = watch_events.recv() => received,
+            _ = client.get_pending_requests().wait_closed() => break,
+        };
+        let mut paths = match received {
+            Ok(event) => vec![event.path],
+            Err(RecvError::Lagged(skipped)) => {
+                warn!("Missed {} file change events", skipped);
+                continue;
+            }
+            Err(RecvError::Closed) => break,
+        };
+        // The debouncer sends the events it collected at once, so sync them together
+        loop {
+            match watch_events.try_recv() {
+                Ok(event) => paths.push(event.path),
+                Err(TryRecvError::Lagged(skipped)) => {
+                    warn!("Missed {} file change events", skipped)
+                }
+                Err(_) => break,
+            }
+        }
+
+        let mut changes = Vec::new();
+        for path in paths {
+            let is_workspace_file = client
+                .get_workspace_documents()
+                .is_workspace_file(&path)
+                .await;
+            changes.extend(classify_file_change(
+                &mut known_files,
+                &path,
+                is_workspace_file,
+            ));
+        }
+        if let Err(e) = sync_file_changes(client.as_ref(), changes, &overlays).await {
+            error!("Failed to sync file changes: {}", e);
+        }
+    }
+}
+
+/// Tells whether a path the watcher reported was created, changed or deleted, given the files
+/// known to exist so far, and updates them. A deleted directory deletes the files it contained.
+fn classify_file_change(
+    known_files: &mut HashSet<PathBuf>,
+    path: &Path,
+    is_workspace_file: bool,
+) -> Vec<(PathBuf, FileChangeType)> {
+    if path.is_file() {
+        if !is_workspace_file {
+            return Vec::new();
+        }
+        let change = if known_files.insert(path.to_path_buf()) {
+            FileChangeType::CREATED
+        } else {
+            FileChangeType::CHANGED
+        };
+        return vec![(path.to_path_buf(), change)];
+    }
+    if path.exists() {
+        return Vec::new();
+    }
+
+    let mut deleted: Vec<PathBuf> = known_files
+        .iter()
+        .filter(|known_file| known_file.starts_with(path))
+        .cloned()
+        .collect();
+    deleted.sort();
+    deleted
+        .into_iter()
+        .map(|deleted_file| {
+            known_files.remove(&deleted_file);
+            (deleted_file, FileChangeType::DELETED)
+        })
+        .collect()
+}
+
+/// Notifies a language server of files changed on disk, and syncs the documents it has open:
+/// changed ones get their new content, unless overlaid, and deleted ones are closed.
+async fn sync_file_changes(
+    client: &dyn LspClient,
+    changes: Vec<(PathBuf, FileChangeType)>,
+    overlays: &RwLock<Overlays>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if changes.is_empty() {
+        return Ok(());
+    }
+    let mut events = Vec::new();
+    for (path, change) in changes {
+        let uri = Url::from_file_path(&path).map_err(|_| "Invalid file path")?;
+        events.push(FileEvent::new(uri, change));
+
+        let path_str = path.to_str().unwrap_or_default();
+        if !client
+            .get_workspace_documents()
+            .is_did_open_document(path_str)
+        {
+            continue;
+        }
+        if change == FileChangeType::DELETED {
+            overlays.write().await.remove(&path);
+            client.text_document_did_close(path_str).await?;
+        } else if change == FileChangeType::CHANGED {
+            let text = tokio::fs::read_to_string(&path).await?;
+            if overlays.read().await.get(&path).is_some() {
+                client
+                    .get_workspace_documents()
+                    .update_text_document(&path, text)
+                    .await;
+            } else {
+                client.text_document_did_change(path_str, text).await?;
+            }
+        }
+    }
+    debug!("Sending {} file changes", events.len());
+    client.workspace_did_change_watched_files(events).await
+}
+
+/// Flattens a workspace edit into the text edits of each document.
+fn workspace_edit_changes(
+    edit: WorkspaceEdit,
+) -> Result<Vec<(Url, Vec<TextEdit>)>, LspManagerError> {
+    let mut changes: Vec<(Url, Vec<TextEdit>)> = Vec::new();
+    match edit.document_changes {
+        Some(DocumentChanges::Edits(edits)) => {
+            for edit in edits {
+                changes.push((edit.text_document.uri, annotated_text_edits(edit.edits)));
+            }
+        }
+        Some(DocumentChanges::Operations(operations)) => {
+            for operation in operations {
+                match operation {
+                    DocumentChangeOperation::Edit(edit) => {
+                        changes.push((edit.text_document.uri, annotated_text_edits(edit.edits)))
+                    }
+                    DocumentChangeOperation::Op(_) => {
+                        return Err(LspManagerError::NotImplemented(
+                            "Creating, renaming or deleting files is not supported".to_string(),
+                        ))
+                    }
+                }
+            }
+        }
+        None => changes.extend(edit.changes.unwrap_or_default()),
+    }
+
+    Ok(changes)
+}
+
+/// Requests the completions at a position, keeps those matching the word being completed,
+/// ranks them and resolves the ones within the limit.
+async fn ranked_completions(
+    client: &dyn LspClient,
+    file_path: &str,
+    position: Position,
+    content: &str,
+    limit: usize,
+) -> Result<(Vec<CompletionItem>, bool), LspManagerError> {
+    let response = client
+        .text_document_completion(file_path, position)
+        .await
+        .map_err(|e| LspManagerError::request_failed("Completion retrieval failed", e))?;
+    let (items, is_incomplete) = match response {
+        Some(CompletionResponse::Array(items)) => (items, false),
+        Some(CompletionResponse::List(list)) => (list.items, list.is_incomplete),
+        None => (Vec::new(), false),
+    };
+
+    // Servers leave the filtering by what's been typed so far to the client
+    let prefix = identifier_prefix(content, position);
+    let mut scored: Vec<(u32, CompletionItem)> = items
+        .into_iter()
+        .filter_map(|item| {
+            let filter_text = item.filter_text.as_deref().unwrap_or(&item.label);
+            fuzzy_match_score(prefix, filter_text).map(|score| (score, item))
+        })
+        .collect();
+    scored.sort_by(|(a_score, a), (b_score, b)| {
+        b_score
+            .cmp(a_score)
+            .then(
+                b.preselect
+                    .unwrap_or(false)
+                    .cmp(&a.preselect.unwrap_or(false)),
+            )
+            .then(
+                a.sort_text
+                    .as_ref()
+                    .unwrap_or(&a.label)
+                    .cmp(b.sort_text.as_ref().unwrap_or(&b.label)),
+            )
+    });
+    let is_incomplete = is_incomplete || scored.len() > limit;
+    scored.truncate(limit);
+
+    let mut completions = Vec::new();
+    for (_, item) in scored {
+        if item.detail.is_some() && item.documentation.is_some() {
+            completions.push(item);
+            continue;
+        }
+        match client.completion_item_resolve(item.clone()).await {
+            Ok(resolved) => completions.push(resolved),
+            Err(e) => {
+                debug!("Failed to resolve completion {}: {}", item.label, e);
+                completions.push(item);
+            }
+        }
+    }
+    Ok((completions, is_incomplete))
+}
+
+/// Flattens a `workspace/symbol` response into the name, kind and location of each symbol.
+///
+/// Symbols with only a document and no range are left out, they need a `workspaceSymbol/resolve`
+/// which isn't advertised.
+fn workspace_symbol_locations(
+    response: Option<WorkspaceSymbolResponse>,
+) -> Vec<(String, SymbolKind, Location)> {
+    match response {
+        Some(WorkspaceSymbolResponse::Flat(symbols)) => symbols
+            .into_iter()
+            .map(|symbol| (symbol.name, symbol.kind, symbol.location))
+            .collect(),
+        Some(WorkspaceSymbolResponse::Nested(symbols)) => symbols
+            .into_iter()
+            .filter_map(|symbol| match symbol.location {
+                OneOf::Left(location) => Some((symbol.name, symbol.kind, location)),
+                OneOf::Right(_) => None,
+            })
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+/// Converts workspace symbols to `Symbol`s, finding their identifiers within the declaration
+/// ranges the servers report.
+async fn locate_symbol_identifiers(
+    symbols: Vec<(String, SymbolKind, Location)>,
+    overlays: &Overlays,
+) -> Vec<Symbol> {
+    let mut contents: HashMap<Url, Option<String>> = HashMap::new();
+    let mut located = Vec::new();
+    for (name, kind, location) in symbols {
+        if !contents.contains_key(&location.uri) {
+            let content = match location.uri.to_file_path() {
+                Ok(path) => match overlays.text(&path) {
+                    Some(text) => Some(text.to_string()),
+                    None => tokio::fs::read_to_string(path).await.ok(),
+                },
+                Err(_) => None,
+            };
+            contents.insert(location.uri.clone(), content);
+        }
+        let identifier_start = contents[&location.uri]
+            .as_deref()
+            .and_then(|content| find_identifier_in_range(content, location.range, &name))
+            .unwrap_or(location.range.start);
+        located.push(lsp_symbol_to_symbol(
+            name,
+            kind,
+            &location.uri,
+            location.range,
+            Range::new(identifier_start, identifier_start),
+        ));
+    }
+    located
+}
+
+fn code_action_has_kind(action: &CodeActionOrCommand, kinds: &[String]) -> bool {
+    let kind = match action {
+        CodeActionOrCommand::CodeAction(CodeAction {
+            kind: Some(kind), ..
+        }) => kind.as_str(),
+        _ => return false,
+    };
+    kinds.iter().any(|requested| {
+        kind == requested
+            || kind
+                .strip_prefix(requested.as_str())
+                .is_some_and(|rest| rest.starts_with('.'))
+    })
+}
+
+/// Removes files, ignoring those that can't be removed.
+async fn remove_files(paths: &[PathBuf]) {
+    for path in paths {
+        if let Err(e) = tokio::fs::remove_file(path).await {
+            warn!("Failed to remove {}: {}", path.display(), e);
+        }
+    }
+}
+
+fn annotated_text_edits(edits: Vec<OneOf<TextEdit, AnnotatedTextEdit>>) -> Vec<TextEdit> {
+    edits
+        .into_iter()
+        .map(|edit| match edit {
+            OneOf::Left(edit) => edit,
+            OneOf::Right(annotated) => annotated.text_edit,
+        })
+        .collect()
 }
 
 #[derive(Debug)]
 pub enum LspManagerError {
     FileNotFound(String),
     LspClientNotFound(SupportedLanguages),
+    LanguageServerUnavailable(SupportedLanguages, LanguageServerStatus),
     InternalError(String),
     UnsupportedFileType(String),
     NotImplemented(String),
+    Conflict(String),
+    Timeout(String),
 }
 
 impl fmt::Display for LspManagerError {
@@ -546,6 +1968,9 @@ impl fmt::Display for LspManagerError {
             LspManagerError::LspClientNotFound(lang) => {
                 write!(f, "LSP client not found for {:?}", lang)
             }
+            LspManagerError::LanguageServerUnavailable(lang, status) => {
+                write!(f, "{:?} language server is {}", lang, status)
+            }
             LspManagerError::InternalError(msg) => write!(f, "Internal error: {}", msg),
             LspManagerError::UnsupportedFileType(path) => {
                 write!(f, "Unsupported file type: {}", path)
@@ -553,15 +1978,28 @@ impl fmt::Display for LspManagerError {
             LspManagerError::NotImplemented(msg) => {
                 write!(f, "Not implemented: {}", msg)
             }
+            LspManagerError::Conflict(msg) => write!(f, "Conflict: {}", msg),
+            LspManagerError::Timeout(msg) => write!(f, "Timeout: {}", msg),
         }
     }
 }
 
 impl std::error::Error for LspManagerError {}
 
+impl LspManagerError {
+    /// Wraps the error of a language server request, keeping timeouts apart from other errors.
+    fn request_failed(context: &str, e: Box<dyn Error + Send + Sync>) -> Self {
+        match e.downcast::<RequestTimeout>() {
+            Ok(timeout) => LspManagerError::Timeout(timeout.to_string()),
+            Err(e) => LspManagerError::InternalError(format!("{}: {}", context, e)),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_utils::TestContext;
     use serial_test::serial;
 
     #[test]
@@ -698,4 +2136,119 @@ mod tests {
 
         std::env::remove_var("ENABLED_LANGUAGES");
     }
+
+    #[test]
+    #[serial]
+    fn test_get_diagnostics_languages() {
+        // Not set - diagnostics are disabled for every language
+        std::env::remove_var("DIAGNOSTICS_LANGUAGES");
+        assert!(Manager::get_diagnostics_languages().is_empty());
+
+        std::env::set_var("DIAGNOSTICS_LANGUAGES", "python, rust,invalid");
+        let result = Manager::get_diagnostics_languages();
+        assert_eq!(result.len(), 2);
+        assert!(result.contains(&SupportedLanguages::Python));
+        assert!(result.contains(&SupportedLanguages::Rust));
+
+        std::env::remove_var("DIAGNOSTICS_LANGUAGES");
+    }
+
+    #[test]
+    fn test_classify_file_change() -> Result<(), Box<dyn Error>> {
+        let dir = tempfile::tempdir()?;
+        let main = dir.path().join("main.py");
+        let package = dir.path().join("package");
+        let module = package.join("module.py");
+        std::fs::create_dir(&package)?;
+        std::fs::write(&main, "x = 1")?;
+        std::fs::write(&module, "y = 1")?;
+        let mut known_files = HashSet::from([main.clone()]);
+
+        assert_eq!(
+            classify_file_change(&mut known_files, &main, true),
+            vec![(main.clone(), FileChangeType::CHANGED)]
+        );
+        assert_eq!(
+            classify_file_change(&mut known_files, &module, true),
+            vec![(module.clone(), FileChangeType::CREATED)]
+        );
+        assert!(classify_file_change(&mut known_files, &package, false).is_empty());
+        assert!(
+            classify_file_change(&mut known_files, &dir.path().join("notes.txt"), false).is_empty()
+        );
+
+        std::fs::remove_dir_all(&package)?;
+        assert_eq!(
+            classify_file_change(&mut known_files, &package, false),
+            vec![(module.clone(), FileChangeType::DELETED)]
+        );
+        assert_eq!(known_files, HashSet::from([main]));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_apply_file_edits() -> Result<(), Box<dyn Error>> {
+        let dir = tempfile::tempdir()?;
+        let root = dir.path().to_str().unwrap_or_default();
+        let _context = TestContext::setup(root, false).await?;
+        let manager = Manager::new(root).await?;
+        std::fs::write(dir.path().join("a.py"), "a = 1\n")?;
+        std::fs::write(dir.path().join("b.py"), "b = 1\n")?;
+
+        let edit = |path: &str, original: &str| {
+            let end = Position {
+                line: 0,
+                character: 5,
+            };
+            FileEdit::new(
+                path,
+                original.to_string(),
+                vec![TextEdit {
+                    range: Range { start: end, end },
+                    new_text: String::from(" + 1"),
+                }],
+            )
+        };
+        let file_edits = vec![edit("a.py", "a = 1\n")?, edit("b.py", "b = 1\n")?];
+
+        // b.py changed after its edits were computed, so neither file is written
+        std::fs::write(dir.path().join("b.py"), "b = 2\n")?;
+        let error = manager.apply_file_edits(&file_edits).await.unwrap_err();
+        assert!(matches!(error, LspManagerError::Conflict(_)), "{}", error);
+        assert_eq!(std::fs::read_to_string(dir.path().join("a.py"))?, "a = 1\n");
+
+        std::fs::write(dir.path().join("b.py"), "b = 1\n")?;
+        manager.apply_file_edits(&file_edits).await?;
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("a.py"))?,
+            "a = 1 + 1\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("b.py"))?,
+            "b = 1 + 1\n"
+        );
+        assert_eq!(std::fs::read_dir(dir.path())?.count(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_code_action_has_kind() {
+        let action = |kind: Option<&str>| {
+            CodeActionOrCommand::CodeAction(CodeAction {
+                title: "Extract function".to_string(),
+                kind: kind.map(|kind| CodeActionKind::from(kind.to_string())),
+                ..Default::default()
+            })
+        };
+        let kinds = vec!["refactor".to_string()];
+
+        assert!(code_action_has_kind(&action(Some("refactor")), &kinds));
+        assert!(code_action_has_kind(
+            &action(Some("refactor.extract.function")),
+            &kinds
+        ));
+        assert!(!code_action_has_kind(&action(Some("refactoring")), &kinds));
+        assert!(!code_action_has_kind(&action(Some("quickfix")), &kinds));
+        assert!(!code_action_has_kind(&action(None), &kinds));
+    }
 }
@@ -1,6 +1,11 @@
 pub(crate) mod client;
+pub(crate) mod diagnostics;
 pub(crate) mod json_rpc;
 pub(crate) mod languages;
 pub(crate) mod manager;
 pub(crate) mod process;
-pub use self::{client::*, json_rpc::*, process::*};
+pub(crate) mod progress;
+pub(crate) mod server_requests;
+pub use self::{
+    client::*, diagnostics::*, json_rpc::*, process::*, progress::*, server_requests::*,
+};
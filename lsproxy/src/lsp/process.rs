@@ -1,13 +1,13 @@
-use log::debug;
+use log::{debug, error};
 use std::error::Error;
 use std::sync::Arc;
 use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
 use tokio::process::{Child, ChildStdin, ChildStdout};
-use tokio::sync::Mutex;
+use tokio::sync::{watch, Mutex};
 
 #[async_trait::async_trait]
 pub trait Process: Send + Sync {
-    async fn send(&mut self, data: &str) -> Result<(), Box<dyn Error + Send + Sync>>;
+    async fn send(&self, data: &str) -> Result<(), Box<dyn Error + Send + Sync>>;
     async fn receive(&self) -> Result<String, Box<dyn Error + Send + Sync>>;
 }
 
@@ -15,22 +15,46 @@ pub trait Process: Send + Sync {
 pub struct ProcessHandler {
     pub stdin: Arc<Mutex<ChildStdin>>,
     pub stdout: Arc<Mutex<BufReader<ChildStdout>>>,
+    /// How the process exited, once it has.
+    exit: watch::Receiver<Option<String>>,
 }
 
 impl ProcessHandler {
     pub async fn new(mut child: Child) -> Result<Self, Box<dyn Error + Send + Sync>> {
         let stdin = child.stdin.take().ok_or("Failed to open stdin")?;
         let stdout = child.stdout.take().ok_or("Failed to open stdout")?;
+        let (exit_sender, exit) = watch::channel(None);
+        tokio::spawn(async move {
+            let exit = match child.wait().await {
+                Ok(status) => status.to_string(),
+                Err(e) => {
+                    error!("Failed to wait for language server process: {}", e);
+                    e.to_string()
+                }
+            };
+            let _ = exit_sender.send(Some(exit));
+        });
         Ok(Self {
             stdin: Arc::new(Mutex::new(stdin)),
             stdout: Arc::new(Mutex::new(BufReader::new(stdout))),
+            exit,
         })
     }
+
+    /// Waits until the process exits, returning its exit status.
+    pub async fn wait_for_exit(&self) -> String {
+        let mut exit = self.exit.clone();
+        let status = match exit.wait_for(Option::is_some).await {
+            Ok(status) => status.clone().unwrap_or_default(),
+            Err(_) => "unknown exit status".to_string(),
+        };
+        status
+    }
 }
 
 #[async_trait::async_trait]
 impl Process for ProcessHandler {
-    async fn send(&mut self, data: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+    async fn send(&self, data: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
         let mut stdin = self.stdin.lock().await;
         stdin.write_all(data.as_bytes()).await?;
         stdin.flush().await?;
@@ -45,7 +69,10 @@ impl Process for ProcessHandler {
             let mut stdout = self.stdout.lock().await;
             let n = stdout.read_until(b'\n', &mut buffer).await?;
             if n == 0 {
-                continue;
+                return Err(Box::new(std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    "language server closed its output",
+                )));
             }
 
             let line = String::from_utf8_lossy(&buffer[buffer.len() - n..]);
@@ -64,3 +91,27 @@ impl Process for ProcessHandler {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Stdio;
+    use tokio::process::Command;
+
+    #[tokio::test]
+    async fn test_receive_after_exit() -> Result<(), Box<dyn Error + Send + Sync>> {
+        let child = Command::new("true")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let process = ProcessHandler::new(child).await?;
+
+        let error = process.receive().await.unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<std::io::Error>().map(|e| e.kind()),
+            Some(std::io::ErrorKind::UnexpectedEof)
+        );
+        assert_eq!(process.wait_for_exit().await, "exit status: 0");
+        Ok(())
+    }
+}
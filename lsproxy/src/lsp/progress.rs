@@ -0,0 +1,164 @@
+use lsp_types::{NumberOrString, ProgressParams, ProgressParamsValue, WorkDoneProgress};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+
+/// The work a language server reports doing, such as indexing the workspace after it started,
+/// during which its results may be partial.
+///
+/// Servers report work with `$/progress` notifications, from the `Begin` to the `End` of each
+/// token. Tokens created with `window/workDoneProgress/create` but never begun don't count as
+/// work, since the server may never use them. rust-analyzer also reports whether it has work
+/// left with `experimental/serverStatus`.
+///
+/// A server may take a moment after `initialize` to begin its first work, so a fresh server
+/// isn't ready until it reports progress or status, or `STARTUP_GRACE` passes without either.
+#[derive(Clone)]
+pub struct ProgressTracker {
+    state: Arc<watch::Sender<ProgressState>>,
+    started: Instant,
+    startup_grace: Duration,
+}
+
+/// How long a server reporting neither progress nor status is given to start reporting.
+const STARTUP_GRACE: Duration = Duration::from_secs(3);
+
+#[derive(Default)]
+struct ProgressState {
+    /// The tokens of the work in progress.
+    tasks: HashSet<String>,
+    /// Whether the last `experimental/serverStatus` reported work left.
+    busy: bool,
+    /// Whether the server reported any progress or status yet.
+    reported: bool,
+}
+
+impl ProgressState {
+    fn is_ready(&self) -> bool {
+        self.tasks.is_empty() && !self.busy
+    }
+}
+
+/// The part of rust-analyzer's `experimental/serverStatus` params telling whether it is done.
+#[derive(Deserialize)]
+struct ServerStatusParams {
+    quiescent: bool,
+}
+
+impl ProgressTracker {
+    pub fn new() -> Self {
+        Self::with_startup_grace(STARTUP_GRACE)
+    }
+
+    fn with_startup_grace(startup_grace: Duration) -> Self {
+        Self {
+            state: Arc::new(watch::Sender::new(ProgressState::default())),
+            started: Instant::now(),
+            startup_grace,
+        }
+    }
+
+    /// Updates the work in progress from a `$/progress` notification.
+    pub fn update(&self, params: ProgressParams) {
+        let token = token_key(&params.token);
+        let ProgressParamsValue::WorkDone(progress) = params.value;
+        self.state.send_modify(|state| {
+            state.reported = true;
+            match progress {
+                WorkDoneProgress::Begin(_) => {
+                    state.tasks.insert(token);
+                }
+                WorkDoneProgress::Report(_) => {}
+                WorkDoneProgress::End(_) => {
+                    state.tasks.remove(&token);
+                }
+            }
+        });
+    }
+
+    /// Records whether the server has work left from an `experimental/serverStatus`
+    /// notification.
+    pub fn set_server_status(&self, params: serde_json::Value) {
+        if let Ok(status) = serde_json::from_value::<ServerStatusParams>(params) {
+            self.state.send_modify(|state| {
+                state.reported = true;
+                state.busy = !status.quiescent;
+            });
+        }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        let state = self.state.borrow();
+        state.is_ready() && (state.reported || self.started.elapsed() >= self.startup_grace)
+    }
+
+    /// Waits up to `timeout` for the server to be ready, returning whether it is.
+    pub async fn wait_until_ready(&self, timeout: Duration) -> bool {
+        let deadline = tokio::time::Instant::now() + timeout;
+        let grace_end = tokio::time::Instant::from_std(self.started + self.startup_grace);
+        let mut state = self.state.subscribe();
+        // Give a fresh server until the end of the grace period to report its first work.
+        let _ =
+            tokio::time::timeout_at(grace_end.min(deadline), state.wait_for(|s| s.reported)).await;
+        let ready =
+            tokio::time::timeout_at(deadline, state.wait_for(ProgressState::is_ready)).await;
+        matches!(ready, Ok(Ok(_))) && self.is_ready()
+    }
+}
+
+fn token_key(token: &NumberOrString) -> String {
+    match token {
+        NumberOrString::Number(number) => number.to_string(),
+        NumberOrString::String(string) => string.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lsp_types::{WorkDoneProgressBegin, WorkDoneProgressEnd};
+
+    #[tokio::test]
+    async fn test_progress_readiness() {
+        let progress = ProgressTracker::new();
+        assert!(!progress.is_ready());
+
+        let token = NumberOrString::String("indexing".to_string());
+        progress.update(ProgressParams {
+            token: token.clone(),
+            value: ProgressParamsValue::WorkDone(WorkDoneProgress::Begin(WorkDoneProgressBegin {
+                title: "Indexing".to_string(),
+                ..Default::default()
+            })),
+        });
+        assert!(!progress.wait_until_ready(Duration::from_millis(10)).await);
+
+        progress.update(ProgressParams {
+            token,
+            value: ProgressParamsValue::WorkDone(WorkDoneProgress::End(WorkDoneProgressEnd {
+                message: None,
+            })),
+        });
+        assert!(progress.wait_until_ready(Duration::from_millis(10)).await);
+
+        progress.set_server_status(serde_json::json!({ "health": "ok", "quiescent": false }));
+        assert!(!progress.is_ready());
+        progress.set_server_status(serde_json::json!({ "health": "ok", "quiescent": true }));
+        assert!(progress.is_ready());
+    }
+
+    #[tokio::test]
+    async fn test_fresh_server_readiness() {
+        let progress = ProgressTracker::with_startup_grace(Duration::from_millis(100));
+        assert!(!progress.is_ready());
+        assert!(!progress.wait_until_ready(Duration::from_millis(10)).await);
+        assert!(progress.wait_until_ready(Duration::from_secs(1)).await);
+
+        let progress = ProgressTracker::with_startup_grace(Duration::from_secs(3600));
+        assert!(!progress.wait_until_ready(Duration::from_millis(10)).await);
+        progress.set_server_status(serde_json::json!({ "health": "ok", "quiescent": true }));
+        assert!(progress.wait_until_ready(Duration::from_millis(10)).await);
+    }
+}
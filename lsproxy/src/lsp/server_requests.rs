@@ -0,0 +1,262 @@
+use async_trait::async_trait;
+use log::debug;
+use lsp_types::{
+    ApplyWorkspaceEditParams, ConfigurationParams, RegistrationParams, UnregistrationParams,
+    WorkDoneProgressCreateParams,
+};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::{JsonRpcError, JsonRpcMessage, PendingRequests};
+
+/// The error code of requests with invalid params, from the JSON-RPC spec.
+const INVALID_PARAMS: i32 = -32602;
+
+/// Answers a request the server sends to the client.
+#[async_trait]
+pub trait ServerRequestHandler: Send + Sync {
+    async fn handle(&self, message: &JsonRpcMessage) -> Result<Value, JsonRpcError>;
+}
+
+/// The handlers of the requests a server sends to its client, by method.
+///
+/// Requests without a handler are answered with a null result, which is how most requests
+/// decline, e.g. `window/showMessageRequest` without picking an action.
+#[derive(Clone, Default)]
+pub struct ServerRequestHandlers {
+    handlers: HashMap<String, Arc<dyn ServerRequestHandler>>,
+}
+
+impl ServerRequestHandlers {
+    /// Handles the requests every client answers the same way: `workspace/configuration` with
+    /// `settings`, capability registrations, progress tokens and workspace edits.
+    pub fn new(settings: Value, pending_requests: PendingRequests) -> Self {
+        let mut handlers = Self::default();
+        handlers.register("workspace/configuration", ConfigurationHandler { settings });
+        handlers.register("client/registerCapability", RegisterCapabilityHandler);
+        handlers.register("client/unregisterCapability", UnregisterCapabilityHandler);
+        handlers.register(
+            "window/workDoneProgress/create",
+            WorkDoneProgressCreateHandler,
+        );
+        handlers.register("workspace/applyEdit", ApplyEditHandler { pending_requests });
+        handlers
+    }
+
+    /// Registers the handler of a method, replacing its previous handler.
+    pub fn register(&mut self, method: &str, handler: impl ServerRequestHandler + 'static) {
+        self.handlers.insert(method.to_string(), Arc::new(handler));
+    }
+
+    pub async fn handle(
+        &self,
+        method: &str,
+        message: &JsonRpcMessage,
+    ) -> Result<Value, JsonRpcError> {
+        match self.handlers.get(method) {
+            Some(handler) => handler.handle(message).await,
+            None => {
+                debug!("No handler for server request {}, answering null", method);
+                Ok(Value::Null)
+            }
+        }
+    }
+}
+
+fn parse_params<T: DeserializeOwned>(message: &JsonRpcMessage) -> Result<T, JsonRpcError> {
+    serde_json::from_value(message.params.clone().unwrap_or(Value::Null)).map_err(|e| {
+        JsonRpcError {
+            code: INVALID_PARAMS,
+            message: e.to_string(),
+            data: None,
+        }
+    })
+}
+
+/// Answers each item of `workspace/configuration` with the settings of its section, where
+/// sections are dotted paths into the settings like `python.analysis`, or null if there are
+/// none.
+struct ConfigurationHandler {
+    settings: Value,
+}
+
+#[async_trait]
+impl ServerRequestHandler for ConfigurationHandler {
+    async fn handle(&self, message: &JsonRpcMessage) -> Result<Value, JsonRpcError> {
+        let params: ConfigurationParams = parse_params(message)?;
+        Ok(Value::Array(
+            params
+                .items
+                .iter()
+                .map(|item| match &item.section {
+                    Some(section) => section
+                        .split('.')
+                        .try_fold(&self.settings, |settings, key| settings.get(key))
+                        .cloned()
+                        .unwrap_or(Value::Null),
+                    None => self.settings.clone(),
+                })
+                .collect(),
+        ))
+    }
+}
+
+/// Accepts dynamic capability registrations. File changes are sent to every server whether
+/// they registered for them or not, so registrations don't change anything.
+struct RegisterCapabilityHandler;
+
+#[async_trait]
+impl ServerRequestHandler for RegisterCapabilityHandler {
+    async fn handle(&self, message: &JsonRpcMessage) -> Result<Value, JsonRpcError> {
+        let params: RegistrationParams = parse_params(message)?;
+        for registration in params.registrations {
+            debug!("Server registered {}", registration.method);
+        }
+        Ok(Value::Null)
+    }
+}
+
+struct UnregisterCapabilityHandler;
+
+#[async_trait]
+impl ServerRequestHandler for UnregisterCapabilityHandler {
+    async fn handle(&self, message: &JsonRpcMessage) -> Result<Value, JsonRpcError> {
+        let params: UnregistrationParams = parse_params(message)?;
+        for unregistration in params.unregisterations {
+            debug!("Server unregistered {}", unregistration.method);
+        }
+        Ok(Value::Null)
+    }
+}
+
+/// Accepts the progress tokens servers create, whose work is only tracked once it begins.
+struct WorkDoneProgressCreateHandler;
+
+#[async_trait]
+impl ServerRequestHandler for WorkDoneProgressCreateHandler {
+    async fn handle(&self, message: &JsonRpcMessage) -> Result<Value, JsonRpcError> {
+        let _: WorkDoneProgressCreateParams = parse_params(message)?;
+        Ok(Value::Null)
+    }
+}
+
+/// Hands workspace edits to whoever is executing the command that triggered them, see
+/// `workspace_execute_command`, which returns them to be previewed or applied like the edits of
+/// any refactoring. Edits no command is waiting for are rejected.
+///
+/// Either way nothing is written while the server waits for the answer, so it's told the edit
+/// wasn't applied. Edits applied once the command completes are synced to the server like any
+/// other change to the files.
+struct ApplyEditHandler {
+    pending_requests: PendingRequests,
+}
+
+#[async_trait]
+impl ServerRequestHandler for ApplyEditHandler {
+    async fn handle(&self, message: &JsonRpcMessage) -> Result<Value, JsonRpcError> {
+        let _: ApplyWorkspaceEditParams = parse_params(message)?;
+        match self
+            .pending_requests
+            .get_server_request("workspace/applyEdit")
+            .await
+        {
+            Some(sender) if sender.send(message.clone()).is_ok() => Ok(serde_json::json!({
+                "applied": false,
+                "failureReason": "The edit is previewed, and only written once the command completes"
+            })),
+            _ => Ok(serde_json::json!({
+                "applied": false,
+                "failureReason": "No workspace edit was expected"
+            })),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(method: &str, params: Value) -> JsonRpcMessage {
+        JsonRpcMessage {
+            jsonrpc: "2.0".to_string(),
+            id: Some(1),
+            method: Some(method.to_string()),
+            params: Some(params),
+            result: None,
+            error: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_server_request_handlers() -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+    {
+        let pending_requests = PendingRequests::new();
+        let handlers = ServerRequestHandlers::new(
+            serde_json::json!({ "gopls": { "symbolScope": "workspace" } }),
+            pending_requests.clone(),
+        );
+
+        let configuration = request(
+            "workspace/configuration",
+            serde_json::json!({ "items": [
+                { "section": "gopls" },
+                { "section": "gopls.symbolScope" },
+                { "section": "python" },
+            ]}),
+        );
+        assert_eq!(
+            handlers
+                .handle("workspace/configuration", &configuration)
+                .await?,
+            serde_json::json!([{ "symbolScope": "workspace" }, "workspace", null])
+        );
+
+        let create = request(
+            "window/workDoneProgress/create",
+            serde_json::json!({ "token": 1 }),
+        );
+        assert_eq!(
+            handlers
+                .handle("window/workDoneProgress/create", &create)
+                .await?,
+            Value::Null
+        );
+
+        let apply_edit = request(
+            "workspace/applyEdit",
+            serde_json::json!({ "edit": { "changes": {} } }),
+        );
+        assert_eq!(
+            handlers.handle("workspace/applyEdit", &apply_edit).await?["applied"],
+            false
+        );
+        let mut edits = pending_requests
+            .add_server_request("workspace/applyEdit")
+            .await?;
+        let response = handlers.handle("workspace/applyEdit", &apply_edit).await?;
+        assert_eq!(response["applied"], false);
+        assert!(response["failureReason"]
+            .as_str()
+            .is_some_and(|reason| reason.contains("previewed")));
+        assert!(edits.try_recv().is_ok());
+
+        let invalid = request("workspace/applyEdit", serde_json::json!({}));
+        assert_eq!(
+            handlers
+                .handle("workspace/applyEdit", &invalid)
+                .await
+                .unwrap_err()
+                .code,
+            INVALID_PARAMS
+        );
+        assert_eq!(
+            handlers
+                .handle("window/showMessageRequest", &invalid)
+                .await?,
+            Value::Null
+        );
+        Ok(())
+    }
+}
@@ -2,9 +2,10 @@ use clap::Parser;
 
 use log::{error, info};
 use lsproxy::{
-    initialize_app_state_with_mount_dir, run_server_with_port_and_host, write_openapi_to_file,
+    initialize_app_state_with_mount_dir, run_server_with_port_and_host, set_global_rules_dir,
+    write_openapi_to_file,
 };
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 /// Command line interface for LSProxy server
 #[derive(Parser, Debug)]
@@ -22,6 +23,11 @@ struct Cli {
     #[arg(long)]
     mount_dir: Option<String>,
 
+    /// Load ast-grep rule sets from this directory instead of the built-in ones, each in a
+    /// subdirectory with a config.yml like `symbol/config.yml`
+    #[arg(long)]
+    rules_dir: Option<String>,
+
     /// Port number to bind the server to
     #[arg(long, default_value_t = 4444)]
     port: u16,
@@ -54,6 +60,18 @@ async fn main() -> std::io::Result<()> {
         return Ok(());
     }
 
+    if let Some(rules_dir) = &cli.rules_dir {
+        if !Path::new(rules_dir).is_dir() {
+            error!("Rules directory '{}' doesn't exist", rules_dir);
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("{} is not a directory", rules_dir),
+            ));
+        }
+        info!("Loading ast-grep rules from {}", rules_dir);
+        set_global_rules_dir(rules_dir);
+    }
+
     // Initialize application state with optional mount directory override
     let app_state = initialize_app_state_with_mount_dir(cli.mount_dir.as_deref())
         .await
@@ -0,0 +1,70 @@
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::Error;
+use futures_util::future::LocalBoxFuture;
+use futures_util::future::{ready, Ready};
+use std::time::Duration;
+
+use crate::lsp::manager::INDEXING_WAIT;
+
+/// The header asking a request to wait up to the given number of seconds for its language
+/// servers to finish indexing, rather than get partial results.
+pub const WAIT_FOR_INDEXING_HEADER: &str = "X-Wait-For-Indexing";
+
+/// The longest a request waits for indexing, whatever its header asks for.
+pub const MAX_INDEXING_WAIT: Duration = Duration::from_secs(600);
+
+pub struct IndexingMiddleware;
+
+impl<S, B> Transform<S, ServiceRequest> for IndexingMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = IndexingMiddlewareService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(IndexingMiddlewareService { service }))
+    }
+}
+
+pub struct IndexingMiddlewareService<S> {
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for IndexingMiddlewareService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let Some(header) = req.headers().get(WAIT_FOR_INDEXING_HEADER) else {
+            return Box::pin(self.service.call(req));
+        };
+        let Some(seconds) = header
+            .to_str()
+            .ok()
+            .and_then(|seconds| seconds.trim().parse::<u64>().ok())
+        else {
+            return Box::pin(async move {
+                Err(actix_web::error::ErrorBadRequest(format!(
+                    "Invalid {} header, expected a number of seconds",
+                    WAIT_FOR_INDEXING_HEADER
+                )))
+            });
+        };
+        let wait = Duration::from_secs(seconds).min(MAX_INDEXING_WAIT);
+        Box::pin(INDEXING_WAIT.scope(wait, self.service.call(req)))
+    }
+}
@@ -1,5 +1,7 @@
+pub mod indexing;
 pub mod jwt;
 #[cfg(test)]
 mod tests;
 
+pub use indexing::IndexingMiddleware;
 pub use jwt::{is_auth_enabled, validate_jwt_config, JwtMiddleware};
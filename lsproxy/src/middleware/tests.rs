@@ -1,4 +1,6 @@
+use super::indexing::{IndexingMiddleware, MAX_INDEXING_WAIT, WAIT_FOR_INDEXING_HEADER};
 use super::jwt::{Claims, JwtMiddleware};
+use crate::lsp::manager::INDEXING_WAIT;
 use actix_web::test::{self, TestRequest};
 use actix_web::{web, App, HttpResponse};
 use jsonwebtoken::{encode, EncodingKey, Header};
@@ -99,3 +101,45 @@ async fn test_missing_jwt_secret() {
     let resp = err.error_response();
     assert_eq!(resp.status().as_u16(), 500);
 }
+
+async fn indexing_wait_handler() -> HttpResponse {
+    match INDEXING_WAIT.try_with(|wait| *wait) {
+        Ok(wait) => HttpResponse::Ok().body(wait.as_secs().to_string()),
+        Err(_) => HttpResponse::Ok().body("none"),
+    }
+}
+
+#[actix_web::test]
+async fn test_wait_for_indexing_header() {
+    let app = test::init_service(
+        App::new()
+            .wrap(IndexingMiddleware)
+            .route("/", web::get().to(indexing_wait_handler)),
+    )
+    .await;
+
+    let req = TestRequest::get()
+        .uri("/")
+        .insert_header((WAIT_FOR_INDEXING_HEADER, "30"))
+        .to_request();
+    let body = test::call_and_read_body(&app, req).await;
+    assert_eq!(body, "30");
+
+    let req = TestRequest::get()
+        .uri("/")
+        .insert_header((WAIT_FOR_INDEXING_HEADER, "86400"))
+        .to_request();
+    let body = test::call_and_read_body(&app, req).await;
+    assert_eq!(body, MAX_INDEXING_WAIT.as_secs().to_string());
+
+    let req = TestRequest::get().uri("/").to_request();
+    let body = test::call_and_read_body(&app, req).await;
+    assert_eq!(body, "none");
+
+    let req = TestRequest::get()
+        .uri("/")
+        .insert_header((WAIT_FOR_INDEXING_HEADER, "soon"))
+        .to_request();
+    let err = test::try_call_service(&app, req).await.unwrap_err();
+    assert_eq!(err.error_response().status().as_u16(), 400);
+}
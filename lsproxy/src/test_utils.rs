@@ -71,3 +71,27 @@ impl Drop for TestContext {
         unset_thread_local_mount_dir();
     }
 }
+
+/// Sets an environment variable until dropped, then restores its previous value. Tests using it
+/// must be `#[serial]` since the environment is shared by the tests running in parallel.
+pub struct EnvVarGuard {
+    name: &'static str,
+    previous: Option<String>,
+}
+
+impl EnvVarGuard {
+    pub fn set(name: &'static str, value: &str) -> Self {
+        let previous = std::env::var(name).ok();
+        std::env::set_var(name, value);
+        Self { name, previous }
+    }
+}
+
+impl Drop for EnvVarGuard {
+    fn drop(&mut self) {
+        match &self.previous {
+            Some(value) => std::env::set_var(self.name, value),
+            None => std::env::remove_var(self.name),
+        }
+    }
+}
@@ -0,0 +1,387 @@
+use lsp_types::{Position, TextEdit};
+
+/// Number of unchanged lines shown around each change in a unified diff.
+const DIFF_CONTEXT_LINES: usize = 3;
+
+/// Edit distance above which the diff gives up on finding the minimal set of changed lines and
+/// replaces the whole changed region instead.
+const MAX_DIFF_EDIT_DISTANCE: usize = 2000;
+
+/// The edits to a single workspace file, along with its content before and after the edits.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileEdit {
+    /// Path to the file, relative to the workspace root
+    pub path: String,
+    pub edits: Vec<TextEdit>,
+    pub original: String,
+    pub edited: String,
+}
+
+impl FileEdit {
+    pub fn new(path: &str, original: String, edits: Vec<TextEdit>) -> Result<Self, String> {
+        let edited = apply_text_edits(&original, &edits)?;
+        Ok(Self {
+            path: path.to_string(),
+            edits,
+            original,
+            edited,
+        })
+    }
+
+    pub fn diff(&self) -> String {
+        unified_diff(&self.path, &self.original, &self.edited)
+    }
+}
+
+/// Converts an LSP position into a byte offset into `content`.
+///
+/// Characters are counted in UTF-16 code units, the LSP default. Positions past the end of a
+/// line or of the content are clamped, as the LSP specification requires.
+pub fn position_to_offset(content: &str, position: Position) -> usize {
+    let mut line_start = 0;
+    for _ in 0..position.line {
+        match content[line_start..].find('\n') {
+            Some(index) => line_start += index + 1,
+            None => return content.len(),
+        }
+    }
+
+    let line = content[line_start..].split('\n').next().unwrap_or_default();
+    let line = line.strip_suffix('\r').unwrap_or(line);
+    let mut units = 0;
+    for (index, c) in line.char_indices() {
+        if units >= position.character as usize {
+            return line_start + index;
+        }
+        units += c.len_utf16();
+    }
+    line_start + line.len()
+}
+
+/// Converts a byte offset into `content` into an LSP position, the inverse of
+/// `position_to_offset`.
+pub fn offset_to_position(content: &str, offset: usize) -> Position {
+    let offset = offset.min(content.len());
+    let line_start = content[..offset].rfind('\n').map_or(0, |index| index + 1);
+    Position {
+        line: content[..line_start].matches('\n').count() as u32,
+        character: content[line_start..offset]
+            .chars()
+            .map(char::len_utf16)
+            .sum::<usize>() as u32,
+    }
+}
+
+/// Applies text edits to `content`.
+///
+/// All ranges refer to the original content, as in an LSP `WorkspaceEdit`. Edits inserting at
+/// the same position are applied in the order given. Overlapping edits are rejected.
+pub fn apply_text_edits(content: &str, edits: &[TextEdit]) -> Result<String, String> {
+    let mut offsets: Vec<(usize, usize, &str)> = edits
+        .iter()
+        .map(|edit| {
+            (
+                position_to_offset(content, edit.range.start),
+                position_to_offset(content, edit.range.end),
+                edit.new_text.as_str(),
+            )
+        })
+        .collect();
+    // Stable, so inserts at the same position keep their order
+    offsets.sort_by_key(|(start, end, _)| (*start, *end));
+
+    let mut result = String::with_capacity(content.len());
+    let mut cursor = 0;
+    for (start, end, new_text) in offsets {
+        if start < cursor || end < start {
+            return Err(format!(
+                "Overlapping or invalid edit at byte offset {}",
+                start
+            ));
+        }
+        result.push_str(&content[cursor..start]);
+        result.push_str(new_text);
+        cursor = end;
+    }
+    result.push_str(&content[cursor..]);
+    Ok(result)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DiffOp {
+    Equal(usize, usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+/// Creates a unified diff between two versions of a file, in the format of `diff -u` with `a/`
+/// and `b/` path prefixes. Returns an empty string if the contents are equal.
+pub fn unified_diff(path: &str, original: &str, edited: &str) -> String {
+    let old_lines: Vec<&str> = original.split_inclusive('\n').collect();
+    let new_lines: Vec<&str> = edited.split_inclusive('\n').collect();
+    let ops = diff_lines(&old_lines, &new_lines);
+    if ops.iter().all(|op| matches!(op, DiffOp::Equal(..))) {
+        return String::new();
+    }
+
+    let mut diff = format!("--- a/{}\n+++ b/{}\n", path, path);
+    let mut index = 0;
+    while let Some(first_change) = ops[index..]
+        .iter()
+        .position(|op| !matches!(op, DiffOp::Equal(..)))
+        .map(|position| index + position)
+    {
+        // Extend the hunk until the unchanged lines between two changes are too many to share
+        // context
+        let mut last_change = first_change;
+        let mut cursor = first_change + 1;
+        while cursor < ops.len() {
+            if !matches!(ops[cursor], DiffOp::Equal(..)) {
+                last_change = cursor;
+            } else if cursor - last_change > 2 * DIFF_CONTEXT_LINES {
+                break;
+            }
+            cursor += 1;
+        }
+        let start = first_change.saturating_sub(DIFF_CONTEXT_LINES).max(index);
+        let end = (last_change + 1 + DIFF_CONTEXT_LINES).min(ops.len());
+        diff.push_str(&format_hunk(&ops[start..end], &old_lines, &new_lines));
+        index = end;
+    }
+    diff
+}
+
+fn format_hunk(ops: &[DiffOp], old_lines: &[&str], new_lines: &[&str]) -> String {
+    // The first old and new line of the hunk. A hunk without any old (or new) lines only happens
+    // when that side of the diff is empty.
+    let old_start = ops
+        .iter()
+        .find_map(|op| match op {
+            DiffOp::Equal(old, _) | DiffOp::Delete(old) => Some(*old),
+            DiffOp::Insert(_) => None,
+        })
+        .unwrap_or(0);
+    let new_start = ops
+        .iter()
+        .find_map(|op| match op {
+            DiffOp::Equal(_, new) | DiffOp::Insert(new) => Some(*new),
+            DiffOp::Delete(_) => None,
+        })
+        .unwrap_or(0);
+    let old_count = ops
+        .iter()
+        .filter(|op| !matches!(op, DiffOp::Insert(_)))
+        .count();
+    let new_count = ops
+        .iter()
+        .filter(|op| !matches!(op, DiffOp::Delete(_)))
+        .count();
+
+    // Empty ranges point at the line before them, so they don't get the 1-based adjustment
+    let old_start = if old_count == 0 {
+        old_start
+    } else {
+        old_start + 1
+    };
+    let new_start = if new_count == 0 {
+        new_start
+    } else {
+        new_start + 1
+    };
+    let mut hunk = format!(
+        "@@ -{},{} +{},{} @@\n",
+        old_start, old_count, new_start, new_count
+    );
+    for op in ops {
+        let (prefix, line) = match op {
+            DiffOp::Equal(old, _) => (' ', old_lines[*old]),
+            DiffOp::Delete(old) => ('-', old_lines[*old]),
+            DiffOp::Insert(new) => ('+', new_lines[*new]),
+        };
+        hunk.push(prefix);
+        hunk.push_str(line);
+        if !line.ends_with('\n') {
+            hunk.push_str("\n\\ No newline at end of file\n");
+        }
+    }
+    hunk
+}
+
+/// Computes the line operations turning `old` into `new`, using Myers' algorithm on the lines
+/// between the common prefix and suffix.
+fn diff_lines(old: &[&str], new: &[&str]) -> Vec<DiffOp> {
+    let prefix = old
+        .iter()
+        .zip(new.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut ops: Vec<DiffOp> = (0..prefix).map(|i| DiffOp::Equal(i, i)).collect();
+    let old_middle = &old[prefix..old.len() - suffix];
+    let new_middle = &new[prefix..new.len() - suffix];
+    match myers(old_middle, new_middle) {
+        Some(middle) => ops.extend(middle.into_iter().map(|op| match op {
+            DiffOp::Equal(a, b) => DiffOp::Equal(a + prefix, b + prefix),
+            DiffOp::Delete(a) => DiffOp::Delete(a + prefix),
+            DiffOp::Insert(b) => DiffOp::Insert(b + prefix),
+        })),
+        None => {
+            ops.extend((prefix..old.len() - suffix).map(DiffOp::Delete));
+            ops.extend((prefix..new.len() - suffix).map(DiffOp::Insert));
+        }
+    }
+    ops.extend((0..suffix).map(|i| DiffOp::Equal(old.len() - suffix + i, new.len() - suffix + i)));
+    ops
+}
+
+/// Myers' shortest edit script, or None if the edit distance exceeds `MAX_DIFF_EDIT_DISTANCE`.
+fn myers(old: &[&str], new: &[&str]) -> Option<Vec<DiffOp>> {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let max = (old.len() + new.len()).min(MAX_DIFF_EDIT_DISTANCE) as isize;
+    let offset = max + 1;
+    let mut v = vec![0isize; 2 * offset as usize + 1];
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+
+    for d in 0..=max {
+        trace.push(v.clone());
+        for k in (-d..=d).step_by(2) {
+            let index = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && v[index - 1] < v[index + 1]) {
+                v[index + 1]
+            } else {
+                v[index - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[index] = x;
+            if x >= n && y >= m {
+                return Some(backtrack(&trace, d, offset, n, m));
+            }
+        }
+    }
+    None
+}
+
+fn backtrack(trace: &[Vec<isize>], d: isize, offset: isize, n: isize, m: isize) -> Vec<DiffOp> {
+    let mut ops = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for d in (0..=d).rev() {
+        let v = &trace[d as usize];
+        let k = x - y;
+        let prev_k =
+            if k == -d || (k != d && v[(k - 1 + offset) as usize] < v[(k + 1 + offset) as usize]) {
+                k + 1
+            } else {
+                k - 1
+            };
+        let prev_x = v[(prev_k + offset) as usize];
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            x -= 1;
+            y -= 1;
+            ops.push(DiffOp::Equal(x as usize, y as usize));
+        }
+        if d > 0 {
+            if x == prev_x {
+                ops.push(DiffOp::Insert(prev_y as usize));
+            } else {
+                ops.push(DiffOp::Delete(prev_x as usize));
+            }
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+    ops.reverse();
+    ops
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lsp_types::Range;
+
+    fn edit(start: (u32, u32), end: (u32, u32), new_text: &str) -> TextEdit {
+        TextEdit {
+            range: Range::new(Position::new(start.0, start.1), Position::new(end.0, end.1)),
+            new_text: new_text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_position_to_offset() {
+        let content = "ab\r\nc😀d\n";
+        assert_eq!(position_to_offset(content, Position::new(0, 1)), 1);
+        // Past the end of the line, before the line terminator
+        assert_eq!(position_to_offset(content, Position::new(0, 10)), 2);
+        // The emoji is two UTF-16 code units and four bytes
+        assert_eq!(position_to_offset(content, Position::new(1, 3)), 9);
+        assert_eq!(
+            position_to_offset(content, Position::new(5, 0)),
+            content.len()
+        );
+    }
+
+    #[test]
+    fn test_apply_text_edits() {
+        let content = "class Graph:\n    pass\n\ngraph = Graph()\n";
+        let edited = apply_text_edits(
+            content,
+            &[
+                edit((3, 8), (3, 13), "Network"),
+                edit((0, 6), (0, 11), "Network"),
+                edit((3, 0), (3, 0), "# new\n"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            edited,
+            "class Network:\n    pass\n\n# new\ngraph = Network()\n"
+        );
+
+        let overlapping = apply_text_edits(
+            content,
+            &[edit((0, 0), (0, 8), "x"), edit((0, 6), (0, 11), "y")],
+        );
+        assert!(overlapping.is_err());
+    }
+
+    #[test]
+    fn test_unified_diff() {
+        let original = (1..=12)
+            .map(|i| format!("line {}\n", i))
+            .collect::<String>();
+        let edited = original
+            .replace("line 2\n", "line two\n")
+            .replace("line 11\n", "");
+
+        assert_eq!(
+            unified_diff("src/lines.txt", &original, &edited),
+            "--- a/src/lines.txt\n+++ b/src/lines.txt\n\
+             @@ -1,5 +1,5 @@\n line 1\n-line 2\n+line two\n line 3\n line 4\n line 5\n\
+             @@ -8,5 +8,4 @@\n line 8\n line 9\n line 10\n-line 11\n line 12\n"
+        );
+        assert_eq!(unified_diff("src/lines.txt", &original, &original), "");
+    }
+
+    #[test]
+    fn test_unified_diff_insert_at_end_without_newline() {
+        assert_eq!(
+            unified_diff("main.py", "a\nb", "a\nb\nc"),
+            "--- a/main.py\n+++ b/main.py\n\
+             @@ -1,2 +1,3 @@\n a\n-b\n\\ No newline at end of file\n+b\n+c\n\\ No newline at end of file\n"
+        );
+        assert_eq!(
+            unified_diff("main.py", "", "a\n"),
+            "--- a/main.py\n+++ b/main.py\n@@ -0,0 +1,1 @@\n+a\n"
+        );
+    }
+}
@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use log::error;
+
+use crate::api_types::SupportedLanguages;
+use crate::lsp::manager::LspManagerError;
+use crate::utils::file_utils::{
+    detect_language, is_excluded_path, matches_workspace_patterns, search_paths, FileType,
+};
+
+/// The workspace files of every language server, with their language.
+///
+/// The workspace is only walked when a language server is added, after which watch events keep
+/// the index up to date, so checking a path doesn't need to lock the clients or walk the
+/// workspace.
+pub struct FileIndex {
+    root_path: PathBuf,
+    /// The include and exclude patterns of each language server's workspace.
+    patterns: Vec<(Vec<String>, Vec<String>)>,
+    /// The language of each file by path relative to the root, None if it isn't supported.
+    files: HashMap<String, Option<SupportedLanguages>>,
+}
+
+impl FileIndex {
+    pub fn new(root_path: &Path) -> Self {
+        Self {
+            root_path: root_path.to_path_buf(),
+            patterns: Vec::new(),
+            files: HashMap::new(),
+        }
+    }
+
+    /// Adds a language server's workspace, given its patterns and the files they match.
+    pub fn add_workspace(
+        &mut self,
+        include_patterns: Vec<String>,
+        exclude_patterns: Vec<String>,
+        files: Vec<PathBuf>,
+    ) {
+        for file in files {
+            self.insert(&file);
+        }
+        self.patterns.push((include_patterns, exclude_patterns));
+    }
+
+    pub fn contains(&self, path: &str) -> bool {
+        self.files.contains_key(path)
+    }
+
+    /// The language of a workspace file, given its path relative to the root.
+    pub fn language(&self, path: &str) -> Result<SupportedLanguages, LspManagerError> {
+        match self.files.get(path) {
+            Some(Some(language)) => Ok(*language),
+            Some(None) => Err(LspManagerError::UnsupportedFileType(path.to_string())),
+            None => Err(LspManagerError::FileNotFound(path.to_string())),
+        }
+    }
+
+    /// The workspace files relative to the root, sorted.
+    pub fn files(&self) -> Vec<String> {
+        let mut files: Vec<String> = self.files.keys().cloned().collect();
+        files.sort();
+        files
+    }
+
+    /// Updates the index for a path reported by the watcher: a new or changed file is added
+    /// with its current language, a new directory is walked and a deleted file or directory is
+    /// removed. A rename is reported as the deletion of the old path and the creation of the new
+    /// one.
+    pub fn update(&mut self, path: &Path) {
+        if path.is_file() {
+            if self.patterns.iter().any(|(include, exclude)| {
+                matches_workspace_patterns(path, include, exclude, &self.root_path)
+            }) {
+                self.insert(path);
+            }
+        } else if path.is_dir() {
+            for file in self.walk(path) {
+                self.insert(&file);
+            }
+        } else if let Some(relative_path) = self.relative_path(path) {
+            self.files
+                .retain(|file, _| !Path::new(file).starts_with(&relative_path));
+        }
+    }
+
+    /// Walks the whole workspace again, when watch events were missed.
+    pub fn rebuild(&mut self) {
+        let files = self.walk(&self.root_path.clone());
+        self.files.clear();
+        for file in files {
+            self.insert(&file);
+        }
+    }
+
+    /// The files below `path` matching the patterns of any language server.
+    fn walk(&self, path: &Path) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        for (include, exclude) in &self.patterns {
+            if is_excluded_path(path, exclude, &self.root_path) {
+                continue;
+            }
+            files.extend(
+                search_paths(path, include.clone(), exclude.clone(), true, FileType::File)
+                    .unwrap_or_else(|err| {
+                        error!("Error searching files: {}", err);
+                        Vec::new()
+                    }),
+            );
+        }
+        files
+    }
+
+    fn insert(&mut self, path: &Path) {
+        if let Some(relative_path) = self.relative_path(path) {
+            let language = detect_language(&path.to_string_lossy()).ok();
+            self.files.insert(relative_path, language);
+        }
+    }
+
+    fn relative_path(&self, path: &Path) -> Option<String> {
+        path.strip_prefix(&self.root_path)
+            .ok()
+            .map(|path| path.to_string_lossy().into_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_file_index_updates() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempdir()?;
+        fs::create_dir(dir.path().join("src"))?;
+        fs::write(dir.path().join("src/main.py"), "import lib")?;
+        fs::write(dir.path().join("src/lib.py"), "x = 1")?;
+        fs::write(dir.path().join("README.md"), "")?;
+        let include = vec!["**/*.py".to_string()];
+        let exclude = vec!["**/.venv".to_string()];
+        let mut index = FileIndex::new(dir.path());
+        let files = search_paths(
+            dir.path(),
+            include.clone(),
+            exclude.clone(),
+            true,
+            FileType::File,
+        )?;
+        index.add_workspace(include, exclude, files);
+
+        assert_eq!(index.files(), vec!["src/lib.py", "src/main.py"]);
+        assert!(matches!(
+            index.language("src/main.py"),
+            Ok(SupportedLanguages::Python)
+        ));
+        assert!(matches!(
+            index.language("README.md"),
+            Err(LspManagerError::FileNotFound(_))
+        ));
+
+        fs::rename(
+            dir.path().join("src/lib.py"),
+            dir.path().join("src/util.py"),
+        )?;
+        index.update(&dir.path().join("src/lib.py"));
+        index.update(&dir.path().join("src/util.py"));
+        fs::create_dir_all(dir.path().join("tests"))?;
+        fs::write(dir.path().join("tests/test_main.py"), "")?;
+        index.update(&dir.path().join("tests"));
+        fs::create_dir_all(dir.path().join(".venv/lib"))?;
+        fs::write(dir.path().join(".venv/lib/site.py"), "")?;
+        index.update(&dir.path().join(".venv"));
+        index.update(&dir.path().join(".venv/lib/site.py"));
+        index.update(&dir.path().join("README.md"));
+
+        assert_eq!(
+            index.files(),
+            vec!["src/main.py", "src/util.py", "tests/test_main.py"]
+        );
+
+        fs::remove_dir_all(dir.path().join("src"))?;
+        index.update(&dir.path().join("src"));
+        assert_eq!(index.files(), vec!["tests/test_main.py"]);
+
+        fs::write(dir.path().join("setup.py"), "")?;
+        index.rebuild();
+        assert_eq!(index.files(), vec!["setup.py", "tests/test_main.py"]);
+        Ok(())
+    }
+}
@@ -122,6 +122,34 @@ pub fn search_paths(
         .collect())
 }
 
+/// Whether the path matches an include pattern, and neither it nor one of its parent directories
+/// below `root_path` matches an exclude pattern, like the files found by `search_paths`.
+pub fn matches_workspace_patterns(
+    path: &Path,
+    include_patterns: &[String],
+    exclude_patterns: &[String],
+    root_path: &Path,
+) -> bool {
+    let path_str = path.to_string_lossy();
+    include_patterns
+        .iter()
+        .any(|pat| glob::Pattern::new(pat).unwrap().matches(&path_str))
+        && !is_excluded_path(path, exclude_patterns, root_path)
+}
+
+/// Whether the path or one of its parent directories below `root_path` matches an exclude
+/// pattern.
+pub fn is_excluded_path(path: &Path, exclude_patterns: &[String], root_path: &Path) -> bool {
+    path.ancestors()
+        .take_while(|ancestor| *ancestor != root_path)
+        .any(|ancestor| {
+            let ancestor_str = ancestor.to_string_lossy();
+            exclude_patterns
+                .iter()
+                .any(|pat| glob::Pattern::new(pat).unwrap().matches(&ancestor_str))
+        })
+}
+
 pub fn uri_to_relative_path_string(uri: &Url) -> String {
     let path = uri.to_file_path().unwrap_or_else(|e| {
         warn!("Failed to convert URI to file path: {:?}", e);
@@ -1,2 +1,6 @@
+pub(crate) mod edit_utils;
+pub(crate) mod file_index;
 pub mod file_utils;
+pub(crate) mod overlays;
+pub(crate) mod symbol_utils;
 pub(crate) mod workspace_documents;
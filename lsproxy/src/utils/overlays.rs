@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// In-memory contents that stand in for workspace files, each owned by the session that
+/// opened it.
+#[derive(Default)]
+pub struct Overlays {
+    documents: HashMap<PathBuf, Overlay>,
+}
+
+pub struct Overlay {
+    pub session: String,
+    pub text: String,
+}
+
+#[derive(Debug)]
+pub enum OverlayError {
+    /// The file is already overlaid by another session.
+    OwnedBy(String),
+    /// The file isn't overlaid by the session.
+    NotOpen,
+}
+
+impl fmt::Display for OverlayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OverlayError::OwnedBy(session) => write!(f, "overlaid by session '{}'", session),
+            OverlayError::NotOpen => write!(f, "not overlaid by the session"),
+        }
+    }
+}
+
+impl std::error::Error for OverlayError {}
+
+impl Overlays {
+    /// Overlays the file with `text` for the session. Opening a file the session already
+    /// overlays replaces its text.
+    pub fn open(&mut self, session: &str, path: &Path, text: String) -> Result<(), OverlayError> {
+        if let Some(overlay) = self.documents.get(path) {
+            if overlay.session != session {
+                return Err(OverlayError::OwnedBy(overlay.session.clone()));
+            }
+        }
+        self.documents.insert(
+            path.to_path_buf(),
+            Overlay {
+                session: session.to_string(),
+                text,
+            },
+        );
+        Ok(())
+    }
+
+    /// Replaces the text of a file the session overlays.
+    pub fn update(&mut self, session: &str, path: &Path, text: String) -> Result<(), OverlayError> {
+        match self.documents.get(path) {
+            Some(overlay) if overlay.session == session => self.open(session, path, text),
+            Some(overlay) => Err(OverlayError::OwnedBy(overlay.session.clone())),
+            None => Err(OverlayError::NotOpen),
+        }
+    }
+
+    /// Removes the session's overlays of `paths`, or all of them if `paths` is None, returning
+    /// the paths that were overlaid.
+    pub fn close(&mut self, session: &str, paths: Option<&[PathBuf]>) -> Vec<PathBuf> {
+        let mut closed = self.session_paths(session);
+        closed.retain(|path| paths.is_none_or(|paths| paths.contains(path)));
+        for path in &closed {
+            self.remove(path);
+        }
+        closed
+    }
+
+    /// Removes the overlay of a file whatever its session, returning whether there was one.
+    pub fn remove(&mut self, path: &Path) -> bool {
+        self.documents.remove(path).is_some()
+    }
+
+    /// The files the session overlays, sorted.
+    pub fn session_paths(&self, session: &str) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = self
+            .documents
+            .iter()
+            .filter(|(_, overlay)| overlay.session == session)
+            .map(|(path, _)| path.clone())
+            .collect();
+        paths.sort();
+        paths
+    }
+
+    /// Every overlaid file with its overlay, whatever its session.
+    pub fn iter(&self) -> impl Iterator<Item = (&PathBuf, &Overlay)> {
+        self.documents.iter()
+    }
+
+    pub fn get(&self, path: &Path) -> Option<&Overlay> {
+        self.documents.get(path)
+    }
+
+    pub fn text(&self, path: &Path) -> Option<&str> {
+        self.get(path).map(|overlay| overlay.text.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overlay_sessions() -> Result<(), Box<dyn std::error::Error>> {
+        let mut overlays = Overlays::default();
+        let path = Path::new("/mnt/workspace/src/main.py");
+
+        overlays.open("agent", path, "x = 1".to_string())?;
+        assert_eq!(overlays.text(path), Some("x = 1"));
+
+        overlays.update("agent", path, "x = 2".to_string())?;
+        assert_eq!(overlays.text(path), Some("x = 2"));
+        assert_eq!(overlays.session_paths("agent"), vec![path.to_path_buf()]);
+
+        assert!(matches!(
+            overlays.open("other", path, "y = 1".to_string()),
+            Err(OverlayError::OwnedBy(session)) if session == "agent"
+        ));
+        assert!(matches!(
+            overlays.update("agent", Path::new("/mnt/workspace/other.py"), String::new()),
+            Err(OverlayError::NotOpen)
+        ));
+
+        assert!(overlays.close("other", None).is_empty());
+        assert_eq!(overlays.close("agent", None), vec![path.to_path_buf()]);
+        assert_eq!(overlays.text(path), None);
+        Ok(())
+    }
+}
@@ -0,0 +1,139 @@
+use lsp_types::{Position, Range};
+
+use super::edit_utils::{offset_to_position, position_to_offset};
+
+/// Scores how well a symbol name matches a search query, higher is better, or returns `None` if
+/// it doesn't match at all.
+///
+/// Matching is case-insensitive and fuzzy: the characters of the query have to appear in the
+/// name in order, e.g. `astgr` matches `AStarGraph`. Exact matches rank first, then prefixes,
+/// then substrings, then the other fuzzy matches. Within each tier, names with fewer extra
+/// characters rank higher. An empty query matches everything.
+pub fn fuzzy_match_score(query: &str, name: &str) -> Option<u32> {
+    let query: Vec<char> = query.to_lowercase().chars().collect();
+    let name: Vec<char> = name.to_lowercase().chars().collect();
+    if query.is_empty() {
+        return Some(0);
+    }
+
+    let extra = |count: usize| 999 - count.min(999) as u32;
+    let unmatched = name.len().saturating_sub(query.len());
+    if name == query {
+        return Some(4000);
+    }
+    if name.starts_with(&query) {
+        return Some(3000 + extra(unmatched));
+    }
+    if name.windows(query.len()).any(|window| window == query) {
+        return Some(2000 + extra(unmatched));
+    }
+
+    // Match each query character to its earliest occurrence, and measure the matched span
+    let mut query_chars = query.iter().peekable();
+    let mut span = (None, 0);
+    for (index, c) in name.iter().enumerate() {
+        if query_chars.peek() == Some(&c) {
+            query_chars.next();
+            span = (span.0.or(Some(index)), index);
+        }
+    }
+    match (query_chars.peek(), span) {
+        (None, (Some(first), last)) => Some(1000 + extra(last - first + 1 - query.len())),
+        _ => None,
+    }
+}
+
+/// Returns the part of the identifier before the position, e.g. `na` for `user.na|`, which is
+/// what completions are matched against.
+pub fn identifier_prefix(content: &str, position: Position) -> &str {
+    let end = position_to_offset(content, position);
+    let start = content[..end]
+        .char_indices()
+        .rev()
+        .take_while(|&(_, c)| is_identifier_char(c))
+        .last()
+        .map_or(end, |(index, _)| index);
+    &content[start..end]
+}
+
+/// Finds the position of a symbol's identifier within the range of its declaration, for
+/// language servers that only report the whole range.
+///
+/// Only whole-word occurrences of the name count, e.g. `User` isn't found in `UserId`.
+pub fn find_identifier_in_range(content: &str, range: Range, name: &str) -> Option<Position> {
+    if name.is_empty() {
+        return None;
+    }
+    let start = position_to_offset(content, range.start);
+    let end = position_to_offset(content, range.end).max(start);
+
+    content[start..end]
+        .match_indices(name)
+        .map(|(index, _)| start + index)
+        .find(|&offset| {
+            let before = content[..offset].chars().next_back();
+            let after = content[offset + name.len()..].chars().next();
+            !before.is_some_and(is_identifier_char) && !after.is_some_and(is_identifier_char)
+        })
+        .map(|offset| offset_to_position(content, offset))
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_match_score() {
+        assert_eq!(fuzzy_match_score("", "User"), Some(0));
+        assert_eq!(fuzzy_match_score("xyz", "User"), None);
+        assert_eq!(fuzzy_match_score("graphs", "Graph"), None);
+
+        let exact = fuzzy_match_score("graph", "Graph").unwrap();
+        let prefix = fuzzy_match_score("graph", "GraphBase").unwrap();
+        let longer_prefix = fuzzy_match_score("graph", "GraphBaseNode").unwrap();
+        let substring = fuzzy_match_score("graph", "AStarGraph").unwrap();
+        let fuzzy = fuzzy_match_score("astgr", "AStarGraph").unwrap();
+        let looser_fuzzy = fuzzy_match_score("agh", "AStarGraph").unwrap();
+        assert!(exact > prefix);
+        assert!(prefix > longer_prefix);
+        assert!(longer_prefix > substring);
+        assert!(substring > fuzzy);
+        assert!(fuzzy > looser_fuzzy);
+    }
+
+    #[test]
+    fn test_identifier_prefix() {
+        let content = "user = User()\nuser.na\n";
+        assert_eq!(identifier_prefix(content, Position::new(1, 7)), "na");
+        assert_eq!(identifier_prefix(content, Position::new(1, 5)), "");
+        assert_eq!(identifier_prefix(content, Position::new(0, 4)), "user");
+        assert_eq!(identifier_prefix(content, Position::new(0, 0)), "");
+    }
+
+    #[test]
+    fn test_find_identifier_in_range() {
+        let content = "# UserId\nclass UserId:\n    pass\nclass User(UserId):\n    pass\n";
+        let range = |start: (u32, u32), end: (u32, u32)| Range {
+            start: Position::new(start.0, start.1),
+            end: Position::new(end.0, end.1),
+        };
+
+        assert_eq!(
+            find_identifier_in_range(content, range((3, 0), (4, 8)), "User"),
+            Some(Position::new(3, 6))
+        );
+        assert_eq!(
+            find_identifier_in_range(content, range((1, 0), (2, 8)), "UserId"),
+            Some(Position::new(1, 6))
+        );
+        // The identifier has to be within the range
+        assert_eq!(
+            find_identifier_in_range(content, range((1, 0), (2, 8)), "User"),
+            None
+        );
+    }
+}
@@ -1,16 +1,25 @@
-use crate::utils::file_utils::{search_paths, FileType};
+use crate::api_types::DocumentCacheStats;
+use crate::utils::file_utils::{
+    is_excluded_path, matches_workspace_patterns, search_paths, FileType,
+};
 use log::{debug, error, warn};
 use lsp_types::Range;
 use notify_debouncer_mini::DebouncedEvent;
 use std::{
-    collections::{HashMap, HashSet},
+    collections::HashMap,
     error::Error,
     path::{Path, PathBuf},
-    sync::Arc,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
 };
 use tokio::{
     fs::read,
-    sync::{broadcast::Receiver, RwLock},
+    sync::{
+        broadcast::{error::RecvError, Receiver},
+        Mutex, MutexGuard, RwLock,
+    },
 };
 use url::Url;
 
@@ -124,17 +133,41 @@ pub trait WorkspaceDocuments: Send + Sync {
         range: Option<Range>,
     ) -> Result<String, Box<dyn Error + Send + Sync>>;
     async fn list_files(&self) -> Vec<PathBuf>;
+    async fn cache_stats(&self) -> DocumentCacheStats;
+    async fn patterns(&self) -> (Vec<String>, Vec<String>);
+    /// Whether the path matches the include patterns and none of the exclude patterns.
+    async fn is_workspace_file(&self, path: &Path) -> bool;
+    /// Replaces the cached content of a file after it was edited.
+    async fn update_text_document(&self, full_file_path: &Path, content: String);
     fn get_did_open_configuration(&self) -> DidOpenConfiguration;
     fn is_did_open_document(&self, file_path: &str) -> bool;
-    fn add_did_open_document(&mut self, file_path: &str);
+    fn add_did_open_document(&self, file_path: &str);
+    fn remove_did_open_document(&self, file_path: &str);
+    /// Increments the version of an opened document, returning None if it isn't open.
+    fn next_document_version(&self, file_path: &str) -> Option<i32>;
+    /// Serializes the notifications that open, change or close documents between concurrent
+    /// requests, so a document is opened once and its versions are sent in order.
+    async fn lock_document_sync(&self) -> MutexGuard<'_, ()>;
+}
+
+/// The workspace files, with the content of those read so far.
+#[derive(Default)]
+struct DocumentCache {
+    /// Whether `files` lists every workspace file. The workspace is only walked for the first
+    /// listing, after which watch events keep the list up to date.
+    listed: bool,
+    files: HashMap<PathBuf, Option<String>>,
 }
 
 pub struct WorkspaceDocumentsHandler {
-    cache: Arc<RwLock<HashMap<PathBuf, Option<String>>>>,
+    cache: Arc<RwLock<DocumentCache>>,
     patterns: Arc<RwLock<(Vec<String>, Vec<String>)>>,
     root_path: PathBuf,
-    did_open_text_documents: HashSet<Url>,
+    did_open_text_documents: std::sync::Mutex<HashMap<Url, i32>>,
     did_open_configuration: DidOpenConfiguration,
+    document_sync: Mutex<()>,
+    hits: AtomicU64,
+    misses: AtomicU64,
 }
 
 impl WorkspaceDocumentsHandler {
@@ -145,20 +178,34 @@ impl WorkspaceDocumentsHandler {
         watch_events_rx: Receiver<DebouncedEvent>,
         did_open_configuration: DidOpenConfiguration,
     ) -> Self {
-        let cache = Arc::new(RwLock::new(HashMap::new()));
+        let cache = Arc::new(RwLock::new(DocumentCache::default()));
         let patterns = Arc::new(RwLock::new((include_patterns, exclude_patterns)));
         let root_path = root_path.to_path_buf();
 
         let cache_clone = Arc::clone(&cache);
         let patterns_clone = Arc::clone(&patterns);
+        let root_path_clone = root_path.clone();
 
         tokio::spawn(async move {
             let mut watch_events_rx = watch_events_rx; // Make it mutable
-            while let Ok(event) = watch_events_rx.recv().await {
-                debug!("Received event: {:?}", event);
-                if WorkspaceDocumentsHandler::matches_patterns(&event.path, &patterns_clone).await {
-                    cache_clone.write().await.clear();
-                    debug!("Cache cleared for {:?}", event.path);
+            loop {
+                match watch_events_rx.recv().await {
+                    Ok(event) => {
+                        debug!("Received event: {:?}", event);
+                        Self::invalidate(
+                            &cache_clone,
+                            &patterns_clone,
+                            &root_path_clone,
+                            &event.path,
+                        )
+                        .await;
+                    }
+                    // Changes were missed, so nothing cached can be trusted anymore
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!("Missed {} events, clearing the cache", skipped);
+                        *cache_clone.write().await = DocumentCache::default();
+                    }
+                    Err(RecvError::Closed) => break,
                 }
             }
         });
@@ -167,25 +214,74 @@ impl WorkspaceDocumentsHandler {
             cache,
             patterns,
             root_path,
-            did_open_text_documents: HashSet::new(),
+            did_open_text_documents: std::sync::Mutex::new(HashMap::new()),
             did_open_configuration,
+            document_sync: Mutex::new(()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
         }
     }
 
     async fn matches_patterns(
         path: &Path,
         patterns: &Arc<RwLock<(Vec<String>, Vec<String>)>>,
+        root_path: &Path,
     ) -> bool {
         let patterns_guard = patterns.read().await;
         let (include, exclude) = &*patterns_guard;
-        let path_str = path.to_string_lossy();
+        matches_workspace_patterns(path, include, exclude, root_path)
+    }
 
-        include
-            .iter()
-            .any(|pat| glob::Pattern::new(pat).unwrap().matches(&path_str))
-            && !exclude
-                .iter()
-                .any(|pat| glob::Pattern::new(pat).unwrap().matches(&path_str))
+    /// Updates the cache for a path reported by the watcher: a changed file is read again on
+    /// next access, a new file or directory is added to the file list and a deleted one is
+    /// removed from it. A rename is reported as the deletion of the old path and the creation
+    /// of the new one.
+    async fn invalidate(
+        cache: &RwLock<DocumentCache>,
+        patterns: &Arc<RwLock<(Vec<String>, Vec<String>)>>,
+        root_path: &Path,
+        path: &Path,
+    ) {
+        if path.is_file() {
+            if !Self::matches_patterns(path, patterns, root_path).await {
+                return;
+            }
+            let mut cache = cache.write().await;
+            if cache.listed || cache.files.contains_key(path) {
+                cache.files.insert(path.to_path_buf(), None);
+                debug!("Cache invalidated for {:?}", path);
+            }
+        } else if path.is_dir() {
+            if !cache.read().await.listed {
+                return;
+            }
+            let (include_patterns, exclude_patterns) = patterns.read().await.clone();
+            if is_excluded_path(path, &exclude_patterns, root_path) {
+                return;
+            }
+            let file_paths = search_paths(
+                path,
+                include_patterns,
+                exclude_patterns,
+                true,
+                FileType::File,
+            )
+            .unwrap_or_else(|err| {
+                error!("Error searching files: {}", err);
+                Vec::new()
+            });
+            let mut cache = cache.write().await;
+            for file_path in file_paths {
+                cache.files.entry(file_path).or_insert(None);
+            }
+        } else {
+            cache
+                .write()
+                .await
+                .files
+                .retain(|file_path, _| !file_path.starts_with(path));
+            debug!("Cache removed for {:?}", path);
+        }
     }
 
     async fn get_content(
@@ -193,9 +289,13 @@ impl WorkspaceDocumentsHandler {
         full_file_path: &PathBuf,
     ) -> Result<String, Box<dyn Error + Send + Sync>> {
         let mut cache = self.cache.write().await;
-        match cache.get(full_file_path) {
-            Some(Some(content)) => Ok(content.clone()),
+        match cache.files.get(full_file_path) {
+            Some(Some(content)) => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                Ok(content.clone())
+            }
             _ => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
                 let bytes = read(full_file_path).await?;
 
                 if String::from_utf8(bytes.clone()).is_err() {
@@ -203,13 +303,21 @@ impl WorkspaceDocumentsHandler {
                 }
 
                 let content = String::from_utf8_lossy(&bytes).into_owned();
-                cache.insert(full_file_path.clone(), Some(content.clone()));
+                // Once listed, only cache workspace files so the list stays accurate
+                if !cache.listed || cache.files.contains_key(full_file_path) {
+                    cache
+                        .files
+                        .insert(full_file_path.clone(), Some(content.clone()));
+                }
                 Ok(content)
             }
         }
     }
 
-    fn extract_range(content: &str, range: Range) -> Result<String, Box<dyn Error + Send + Sync>> {
+    pub(crate) fn extract_range(
+        content: &str,
+        range: Range,
+    ) -> Result<String, Box<dyn Error + Send + Sync>> {
         let lines: Vec<&str> = content.lines().collect();
         let total_lines = lines.len();
 
@@ -280,22 +388,56 @@ impl WorkspaceDocuments for WorkspaceDocumentsHandler {
 
     async fn list_files(&self) -> Vec<PathBuf> {
         let cache_read = self.cache.read().await;
-        if cache_read.is_empty() {
-            drop(cache_read);
-            let (include_patterns, exclude_patterns) = self.patterns.read().await.clone();
-            let file_paths =
-                search_paths(&self.root_path, include_patterns, exclude_patterns, true, FileType::File)
-                    .unwrap_or_else(|err| {
-                        error!("Error searching files: {}", err);
-                        Vec::new()
-                    });
-            let mut cache_write = self.cache.write().await;
-            for file_path in file_paths {
-                cache_write.insert(file_path, None);
-            }
-            cache_write.keys().cloned().collect()
-        } else {
-            cache_read.keys().cloned().collect()
+        if cache_read.listed {
+            return cache_read.files.keys().cloned().collect();
+        }
+        drop(cache_read);
+        let (include_patterns, exclude_patterns) = self.patterns.read().await.clone();
+        let file_paths = search_paths(
+            &self.root_path,
+            include_patterns,
+            exclude_patterns,
+            true,
+            FileType::File,
+        )
+        .unwrap_or_else(|err| {
+            error!("Error searching files: {}", err);
+            Vec::new()
+        });
+        let mut cache_write = self.cache.write().await;
+        for file_path in file_paths {
+            cache_write.files.entry(file_path).or_insert(None);
+        }
+        cache_write.listed = true;
+        cache_write.files.keys().cloned().collect()
+    }
+
+    async fn patterns(&self) -> (Vec<String>, Vec<String>) {
+        self.patterns.read().await.clone()
+    }
+
+    async fn cache_stats(&self) -> DocumentCacheStats {
+        let cache = self.cache.read().await;
+        DocumentCacheStats {
+            files: cache.files.len(),
+            cached_documents: cache
+                .files
+                .values()
+                .filter(|content| content.is_some())
+                .count(),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+
+    async fn is_workspace_file(&self, path: &Path) -> bool {
+        Self::matches_patterns(path, &self.patterns, &self.root_path).await
+    }
+
+    async fn update_text_document(&self, full_file_path: &Path, content: String) {
+        // Only files that are already known, so the file list stays accurate
+        if let Some(entry) = self.cache.write().await.files.get_mut(full_file_path) {
+            *entry = Some(content);
         }
     }
 
@@ -305,12 +447,33 @@ impl WorkspaceDocuments for WorkspaceDocumentsHandler {
 
     fn is_did_open_document(&self, file_path: &str) -> bool {
         self.did_open_text_documents
-            .contains(&Url::from_file_path(file_path).unwrap())
+            .lock()
+            .unwrap()
+            .contains_key(&Url::from_file_path(file_path).unwrap())
     }
 
-    fn add_did_open_document(&mut self, file_path: &str) {
+    fn add_did_open_document(&self, file_path: &str) {
         self.did_open_text_documents
-            .insert(Url::from_file_path(file_path).unwrap());
+            .lock()
+            .unwrap()
+            .insert(Url::from_file_path(file_path).unwrap(), 1);
+    }
+
+    fn remove_did_open_document(&self, file_path: &str) {
+        if let Ok(uri) = Url::from_file_path(file_path) {
+            self.did_open_text_documents.lock().unwrap().remove(&uri);
+        }
+    }
+
+    fn next_document_version(&self, file_path: &str) -> Option<i32> {
+        let mut did_open_text_documents = self.did_open_text_documents.lock().unwrap();
+        let version = did_open_text_documents.get_mut(&Url::from_file_path(file_path).ok()?)?;
+        *version += 1;
+        Some(*version)
+    }
+
+    async fn lock_document_sync(&self) -> MutexGuard<'_, ()> {
+        self.document_sync.lock().await
     }
 }
 
@@ -364,6 +527,36 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_update_text_document() -> Result<(), Box<dyn Error + Send + Sync>> {
+        let dir = tempdir()?;
+        let file_path = dir.path().join("test.txt");
+        fs::write(&file_path, "Hello, world!")?;
+        let (_, rx) = create_test_watcher_channels();
+        let handler = WorkspaceDocumentsHandler::new(
+            dir.path(),
+            vec!["*.txt".to_string()],
+            vec![],
+            rx,
+            DidOpenConfiguration::Lazy,
+        );
+
+        handler.list_files().await;
+        handler
+            .update_text_document(&file_path, "Hello, edit!".to_string())
+            .await;
+        let content = handler.read_text_document(&file_path, None).await?;
+        assert_eq!(content, "Hello, edit!");
+
+        let file_path_str = file_path.to_str().unwrap();
+        assert_eq!(handler.next_document_version(file_path_str), None);
+        handler.add_did_open_document(file_path_str);
+        assert_eq!(handler.next_document_version(file_path_str), Some(2));
+        assert_eq!(handler.next_document_version(file_path_str), Some(3));
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_list_files() -> Result<(), Box<dyn Error + Send + Sync>> {
         // Setup temporary directory and files
@@ -404,6 +597,122 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_list_files_incremental() -> Result<(), Box<dyn Error + Send + Sync>> {
+        let dir = tempdir()?;
+        fs::create_dir(dir.path().join("src"))?;
+        fs::write(dir.path().join("src/lib.rs"), "mod a;")?;
+        fs::write(dir.path().join("src/a.rs"), "fn a() {}")?;
+        let (tx, rx) = create_test_watcher_channels();
+        let handler = WorkspaceDocumentsHandler::new(
+            dir.path(),
+            vec!["**/*.rs".to_string()],
+            vec!["**/target".to_string()],
+            rx,
+            DidOpenConfiguration::None,
+        );
+        let send = |path: PathBuf| {
+            tx.send(DebouncedEvent {
+                path,
+                kind: DebouncedEventKind::Any,
+            })
+        };
+        let sorted_files = || async {
+            let mut files = handler.list_files().await;
+            files.sort();
+            files
+        };
+
+        assert_eq!(
+            sorted_files().await,
+            vec![dir.path().join("src/a.rs"), dir.path().join("src/lib.rs")]
+        );
+
+        // A rename is reported as a deletion and a creation
+        fs::rename(dir.path().join("src/a.rs"), dir.path().join("src/b.rs"))?;
+        send(dir.path().join("src/a.rs"))?;
+        send(dir.path().join("src/b.rs"))?;
+        // A new directory is walked, unless it is excluded
+        fs::create_dir_all(dir.path().join("tests/common"))?;
+        fs::write(dir.path().join("tests/common/mod.rs"), "")?;
+        send(dir.path().join("tests"))?;
+        fs::create_dir_all(dir.path().join("target/debug"))?;
+        fs::write(dir.path().join("target/debug/build.rs"), "")?;
+        send(dir.path().join("target"))?;
+        send(dir.path().join("target/debug/build.rs"))?;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        assert_eq!(
+            sorted_files().await,
+            vec![
+                dir.path().join("src/b.rs"),
+                dir.path().join("src/lib.rs"),
+                dir.path().join("tests/common/mod.rs"),
+            ]
+        );
+
+        // Deleting a directory removes its files
+        fs::remove_dir_all(dir.path().join("tests"))?;
+        send(dir.path().join("tests"))?;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        assert_eq!(
+            sorted_files().await,
+            vec![dir.path().join("src/b.rs"), dir.path().join("src/lib.rs")]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cache_invalidation() -> Result<(), Box<dyn Error + Send + Sync>> {
+        let dir = tempdir()?;
+        let changed_path = dir.path().join("changed.txt");
+        let unchanged_path = dir.path().join("unchanged.txt");
+        fs::write(&changed_path, "before")?;
+        fs::write(&unchanged_path, "same")?;
+        let (tx, rx) = create_test_watcher_channels();
+        let handler = WorkspaceDocumentsHandler::new(
+            dir.path(),
+            vec!["*.txt".to_string()],
+            vec![],
+            rx,
+            DidOpenConfiguration::None,
+        );
+
+        handler.list_files().await;
+        handler.read_text_document(&changed_path, None).await?;
+        handler.read_text_document(&unchanged_path, None).await?;
+
+        fs::write(&changed_path, "after")?;
+        tx.send(DebouncedEvent {
+            path: changed_path.clone(),
+            kind: DebouncedEventKind::Any,
+        })?;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        // Only the changed file is read again
+        assert_eq!(
+            handler.read_text_document(&changed_path, None).await?,
+            "after"
+        );
+        assert_eq!(
+            handler.read_text_document(&unchanged_path, None).await?,
+            "same"
+        );
+        assert_eq!(
+            handler.cache_stats().await,
+            DocumentCacheStats {
+                files: 2,
+                cached_documents: 2,
+                hits: 1,
+                misses: 3,
+            }
+        );
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_read_text_document_out_of_bounds() -> Result<(), Box<dyn Error + Send + Sync>> {
         // Setup temporary directory and file
@@ -165,7 +165,7 @@ install_python() {
        echo "Warning: EXTERNALLY-MANAGED file not found at $MANAGED_FILE"
    fi
 
-   pip3 install jedi-language-server ast-grep-cli
+   pip3 install jedi-language-server
 }
 
 # Function to install Node.js
@@ -303,20 +303,6 @@ install_lsproxy() {
     chmod +x /usr/local/bin/lsproxy
 }
 
-# Function to install ast_grep configuration
-install_ast_grep_config() {
-    local config_url="https://github.com/agentic-labs/lsproxy/releases/download/${LSPROXY_VERSION}/lsproxy-${LSPROXY_VERSION}-ast-grep-rules.tar.gz"
-    local dest_dir="/usr/src"
-
-    echo "Downloading ast_grep configuration and rules..."
-    mkdir -p "$dest_dir"
-    curl -L -o /tmp/ast_grep.tar.gz "$config_url"
-
-    echo "Extracting ast_grep configuration and rules..."
-    tar -xzf /tmp/ast_grep.tar.gz -C "$dest_dir" --no-same-owner
-    rm /tmp/ast_grep.tar.gz
-}
-
 # Function to clean up
 cleanup() {
     echo "Cleaning up..."
@@ -342,7 +328,6 @@ main() {
     install_ruby
     install_dotnet
     install_lsproxy
-    install_ast_grep_config
     cleanup
 
     # Capture the new environment and write out only differences dynamically